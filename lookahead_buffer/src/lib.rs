@@ -1,39 +1,112 @@
-pub struct LookaheadBuffer<T: Clone> {
+use std::iter::Fuse;
+use std::vec;
+
+/// A window over a sequence with lookahead. Values are pulled from the source
+/// only when they are peeked at or advanced over, and the ones advanced over
+/// are dropped on `commit`, so only the current slice and the lookahead are
+/// ever held in memory.
+pub struct LookaheadBuffer<T: Clone, S: Iterator<Item = T> = vec::IntoIter<T>> {
+    source: Fuse<S>,
     buf: Vec<T>,
-    start: usize,
     current: usize,
+    // How many values `commit` has dropped, so marks can hold a position in
+    // the whole sequence rather than in `buf`.
+    committed: usize,
 }
 
+/// A position to `reset` back to, taken with `mark`.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Mark(usize);
+
 impl<T: Clone> LookaheadBuffer<T> {
     pub fn new(buffer: Vec<T>) -> Self {
+        LookaheadBuffer::streaming(buffer)
+    }
+}
+
+impl<T: Clone, S: Iterator<Item = T>> LookaheadBuffer<T, S> {
+    pub fn streaming(source: impl IntoIterator<Item = T, IntoIter = S>) -> Self {
         LookaheadBuffer {
-            buf: buffer,
+            source: source.into_iter().fuse(),
+            buf: vec![],
             current: 0,
-            start: 0,
+            committed: 0,
         }
     }
 
-    pub fn peek(&self, n: usize) -> Option<T> {
-        if self.current + n >= self.buf.len() {
+    /// The value `n` ahead of the current position, pulling it from the
+    /// source first. `None` always means the source has ended.
+    pub fn peek(&mut self, n: usize) -> Option<T> {
+        self.peek_ref(n).cloned()
+    }
+
+    /// Like `peek`, but borrows the value instead of cloning it.
+    pub fn peek_ref(&mut self, n: usize) -> Option<&T> {
+        if !self.fill(self.current + n) {
             return None;
         }
 
-        Some(self.buf[self.current + n].clone())
+        Some(&self.buf[self.current + n])
     }
 
     pub fn advance(&mut self) {
-        if self.current == self.buf.len() {
+        if !self.fill(self.current) {
             return;
         }
 
-        self.current = self.current + 1;
+        self.current += 1;
     }
 
     pub fn get_slice(&self) -> Vec<T> {
-        self.buf[self.start..self.current].to_vec()
+        self.slice().to_vec()
+    }
+
+    /// The values advanced over since the last commit, without copying them.
+    pub fn slice(&self) -> &[T] {
+        &self.buf[..self.current]
+    }
+
+    /// Remembers the current position so that a speculative parse can `reset`
+    /// back to it.
+    pub fn mark(&self) -> Mark {
+        Mark(self.committed + self.current)
+    }
+
+    /// Moves back (or forward) to `mark`. The values advanced over since then
+    /// are kept, so they will be peeked at again.
+    ///
+    /// # Panics
+    ///
+    /// When `mark` was taken before the last commit, as the values it points
+    /// at have been dropped.
+    pub fn reset(&mut self, mark: Mark) {
+        assert!(
+            mark.0 >= self.committed,
+            "can't reset to a mark taken before the last commit"
+        );
+        self.current = mark.0 - self.committed;
+    }
+
+    /// How many values have been pulled from the source and not dropped yet.
+    pub fn buffered(&self) -> usize {
+        self.buf.len()
     }
 
     pub fn commit(&mut self) {
-        self.start = self.current;
+        self.buf.drain(..self.current);
+        self.committed += self.current;
+        self.current = 0;
+    }
+
+    // Pulls from the source until `index` is in the buffer, returning false
+    // when the source ends first.
+    fn fill(&mut self, index: usize) -> bool {
+        while self.buf.len() <= index {
+            match self.source.next() {
+                Some(value) => self.buf.push(value),
+                None => return false,
+            }
+        }
+        true
     }
 }
@@ -1,4 +1,5 @@
 use lookahead_buffer::*;
+use std::cell::Cell;
 
 #[cfg(test)]
 mod tests {
@@ -7,7 +8,7 @@ mod tests {
     #[test]
     fn test_can_peek_at_top_values() {
         let input = Vec::from("abcd");
-        let input: LookaheadBuffer<u8> = LookaheadBuffer::new(input);
+        let mut input: LookaheadBuffer<u8> = LookaheadBuffer::new(input);
         assert_eq!(b'a', input.peek(0).unwrap());
         assert_eq!(b'b', input.peek(1).unwrap());
     }
@@ -101,4 +102,105 @@ mod tests {
         let top = input.peek(0);
         assert!(top.is_none())
     }
+
+    // Counts how many values have been pulled from the source.
+    fn counted(len: usize, pulled: &Cell<usize>) -> impl Iterator<Item = usize> + '_ {
+        (0..len).inspect(move |_| pulled.set(pulled.get() + 1))
+    }
+
+    #[test]
+    fn test_streaming_pulls_values_lazily() {
+        let pulled = Cell::new(0);
+        let mut input = LookaheadBuffer::streaming(counted(10, &pulled));
+        assert_eq!(0, pulled.get());
+
+        assert_eq!(Some(2), input.peek(2));
+        assert_eq!(3, pulled.get());
+
+        input.advance();
+        input.advance();
+        assert_eq!(vec![0, 1], input.get_slice());
+        assert_eq!(3, pulled.get());
+
+        assert_eq!(None, input.peek(8));
+        assert_eq!(10, pulled.get());
+    }
+
+    #[test]
+    fn test_streaming_only_keeps_values_from_start() {
+        let mut input = LookaheadBuffer::streaming(0..1_000_000);
+        let mut sum = 0;
+
+        while let Some(value) = input.peek(0) {
+            input.advance();
+            assert_eq!(vec![value], input.get_slice());
+            assert!(input.buffered() <= 2);
+            input.commit();
+            sum += value as u64;
+        }
+
+        assert_eq!(499_999_500_000, sum);
+        assert_eq!(0, input.buffered());
+    }
+
+    #[test]
+    fn test_borrowing_accessors_match_the_cloning_ones() {
+        let mut input = LookaheadBuffer::new(vec![String::from("a"), String::from("b")]);
+        assert_eq!(Some(&String::from("b")), input.peek_ref(1));
+        assert_eq!(None, input.peek_ref(2));
+
+        input.advance();
+        assert_eq!(input.get_slice().as_slice(), input.slice());
+
+        input.commit();
+        let empty: &[String] = &[];
+        assert_eq!(empty, input.slice());
+        assert_eq!(Some(&String::from("b")), input.peek_ref(0));
+    }
+
+    #[test]
+    fn test_can_reset_to_a_mark() {
+        let mut input: LookaheadBuffer<u8> = LookaheadBuffer::new(Vec::from("abcd"));
+        input.advance();
+        let mark = input.mark();
+
+        input.advance();
+        input.advance();
+        assert_eq!(b"abc", input.slice());
+
+        input.reset(mark);
+        assert_eq!(b"a", input.slice());
+        assert_eq!(Some(b'b'), input.peek(0));
+    }
+
+    #[test]
+    fn test_can_reset_to_a_mark_taken_after_commit() {
+        let mut input = LookaheadBuffer::streaming(0..10);
+        input.advance();
+        input.advance();
+        input.commit();
+
+        let start = input.mark();
+        input.advance();
+        let end = input.mark();
+        input.advance();
+
+        input.reset(start);
+        assert_eq!(Some(2), input.peek(0));
+
+        input.reset(end);
+        assert_eq!(vec![2], input.get_slice());
+        assert_eq!(Some(3), input.peek(0));
+    }
+
+    #[test]
+    #[should_panic(expected = "before the last commit")]
+    fn test_resetting_to_a_mark_before_commit_panics() {
+        let mut input: LookaheadBuffer<u8> = LookaheadBuffer::new(Vec::from("abcd"));
+        let mark = input.mark();
+        input.advance();
+        input.commit();
+
+        input.reset(mark);
+    }
 }
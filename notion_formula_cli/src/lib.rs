@@ -0,0 +1,509 @@
+use notion_formula_core::csv;
+use notion_formula_core::formatter;
+use notion_formula_core::notion_api;
+use notion_formula_core::interpreter::{self, Interpreter, Props, RuntimeType};
+use notion_formula_core::lint::{self, LintConfig, Severity};
+use notion_formula_core::metrics::{self, Limits};
+use notion_formula_core::migrate;
+use notion_formula_core::parser::{self, Document, Expression, LocatedStatement, Statement};
+use notion_formula_core::reader;
+use notion_formula_core::tokenizer::{self, Token};
+use notion_formula_core::typechecker::{Schema, TypeChecker};
+use pipeline::{FnHandler, HandlerResult, Pipeline, SimpleError, Timing};
+use std::fs::{self, File};
+use std::io::{BufRead, Write};
+
+pub mod repl;
+
+pub const USAGE: &str = "Usage:
+    notion-formula run [--timings] <file>
+    notion-formula eval <formula> [--props <props.json> | --page <page.json>]
+    notion-formula check [--max <metric>=<n>]... <file>...
+    notion-formula metrics <file>...
+    notion-formula fmt [--check] <file>...
+    notion-formula tokens <file>
+    notion-formula ast <file>
+    notion-formula repl
+    notion-formula csv <data.csv> <formula-file> [--schema <table.notion>] [--column <name>] [--output <file>]
+    notion-formula migrate [--write] [--list <property>]... <formula-file>...
+    notion-formula lint [--config <file>] [--fix] <file>...";
+
+/// Runs the command line tool with `args` (not including the program name)
+/// and returns the exit code: 0 on success, 1 when the command failed and 2
+/// for invalid usage.
+pub fn run(
+    args: &[String],
+    stdin: &mut dyn BufRead,
+    stdout: &mut dyn Write,
+    stderr: &mut dyn Write,
+) -> i32 {
+    let args: Vec<&str> = args.iter().map(|arg| arg.as_str()).collect();
+    let result = match args.as_slice() {
+        ["run", path] => run_file(path, None, stdout),
+        ["run", "--timings", path] | ["run", path, "--timings"] => {
+            run_file(path, Some(stderr), stdout)
+        }
+        ["eval", formula] => eval(formula, None, stdout),
+        ["eval", formula, "--props", path] | ["eval", "--props", path, formula] => {
+            read_props(path).and_then(|props| eval(formula, Some(props), stdout))
+        }
+        ["eval", formula, "--page", path] | ["eval", "--page", path, formula] => {
+            read_file(path)
+                .and_then(|page| notion_api::read_page(&page.into_iter().collect::<String>()))
+                .and_then(|props| eval(formula, Some(props), stdout))
+        }
+        ["check", args @ ..] => match check_options(args) {
+            Some((limits, paths)) => check(&paths, &limits, stderr),
+            None => {
+                let _ = writeln!(stderr, "{}", USAGE);
+                return 2;
+            }
+        },
+        ["metrics", paths @ ..] if !paths.is_empty() => metrics(paths, stdout),
+        ["fmt", "--check", paths @ ..] if !paths.is_empty() => fmt(paths, true, stdout),
+        ["fmt", paths @ ..] if !paths.is_empty() => fmt(paths, false, stdout),
+        ["tokens", path] => tokens(path, stdout),
+        ["ast", path] => ast(path, stdout),
+        ["repl"] => repl::run(stdin, stdout, stderr),
+        ["csv", data, formula, options @ ..] => match csv_options(options) {
+            Some(options) => csv(data, formula, &options, stdout),
+            None => {
+                let _ = writeln!(stderr, "{}", USAGE);
+                return 2;
+            }
+        },
+        ["migrate", args @ ..] => match migrate_options(args) {
+            Some(options) => migrate(&options, stdout),
+            None => {
+                let _ = writeln!(stderr, "{}", USAGE);
+                return 2;
+            }
+        },
+        ["lint", args @ ..] => match lint_options(args) {
+            Some(options) => lint(&options, stdout),
+            None => {
+                let _ = writeln!(stderr, "{}", USAGE);
+                return 2;
+            }
+        },
+        _ => {
+            let _ = writeln!(stderr, "{}", USAGE);
+            return 2;
+        }
+    };
+
+    match result {
+        Ok(code) => code,
+        Err(e) => {
+            let _ = writeln!(stderr, "error: {}", e);
+            1
+        }
+    }
+}
+
+fn open_file(path: &str) -> HandlerResult<File> {
+    File::open(path).map_err(|e| SimpleError::new(format!("Couldn't open {}: {}", path, e)) as _)
+}
+
+fn read_file(path: &str) -> HandlerResult<Vec<char>> {
+    reader::read(&mut open_file(path)?)
+}
+
+// Tokenizes the file while it's read, so its source is never held in
+// memory as a whole.
+fn tokenize_file(file: File) -> HandlerResult<Vec<Token>> {
+    Ok(reader::stream(file, |chars| tokenizer::tokenize_chars(chars))??)
+}
+
+fn read_document(path: &str) -> HandlerResult<Document> {
+    Ok(parser::document_parser(tokenize_file(open_file(path)?)?)?)
+}
+
+fn document_pipeline<'a>() -> Pipeline<'a, Vec<char>, Document> {
+    Pipeline::new()
+        .add(FnHandler::new(tokenizer::tokenizer))
+        .add(FnHandler::new(parser::document_parser))
+}
+
+// With `timings`, the time spent in each stage is written there, even when
+// the run fails.
+fn run_file(
+    path: &str,
+    timings: Option<&mut dyn Write>,
+    stdout: &mut dyn Write,
+) -> HandlerResult<i32> {
+    let timing = Timing::new();
+    let pipeline = Pipeline::new()
+        .add_named("reader", FnHandler::new(open_file))
+        .add_named("tokenizer", FnHandler::new(tokenize_file))
+        .add_named("parser", FnHandler::new(parser::located_document_parser))
+        .add_named("interpreter", FnHandler::new(interpreter::run))
+        .add_middleware(timing.clone());
+
+    let result = pipeline.start(path);
+    if let Some(timings) = timings {
+        write!(timings, "{}", timing.report())?;
+    }
+    for value in result? {
+        writeln!(stdout, "{}", value)?;
+    }
+    Ok(0)
+}
+
+fn eval(formula: &str, props: Option<Props>, stdout: &mut dyn Write) -> HandlerResult<i32> {
+    let pipeline: Pipeline<Vec<char>, Expression> = Pipeline::new()
+        .add(FnHandler::new(tokenizer::tokenizer))
+        .add(FnHandler::new(parser::formula_parser));
+    let ast = pipeline.start(formula.chars().collect())?;
+
+    let interpreter = Interpreter::new();
+    let result = match props {
+        Some(props) => interpreter.evaluate_with_props(&ast, &props)?,
+        None => interpreter.evaluate(&ast)?,
+    };
+    writeln!(stdout, "{}", result)?;
+    Ok(0)
+}
+
+/// Props files are a JSON object mapping property names to text, numbers
+/// or checkboxes, e.g. `{"Name": "Atlas", "Age": 29, "Done": false}`.
+fn read_props(path: &str) -> HandlerResult<Props> {
+    let input: String = read_file(path)?.into_iter().collect();
+    let json: serde_json::Value = serde_json::from_str(&input)?;
+    let object = json
+        .as_object()
+        .ok_or_else(|| SimpleError::new(format!("Expected {} to contain a JSON object", path)))?;
+
+    let mut props = Props::new();
+    for (name, value) in object {
+        let value = match value {
+            serde_json::Value::String(value) => RuntimeType::Str(value.clone()),
+            serde_json::Value::Bool(value) => RuntimeType::Bool(*value),
+            serde_json::Value::Number(value) => match value.as_f64() {
+                Some(value) => RuntimeType::Num(value),
+                None => return Err(SimpleError::new(format!("Invalid number for {}", name))),
+            },
+            _ => {
+                return Err(SimpleError::new(format!(
+                    "Unsupported value {} for property {}",
+                    value, name
+                )))
+            }
+        };
+        props.insert(name.clone(), value);
+    }
+    Ok(props)
+}
+
+fn check_options<'a>(mut args: &[&'a str]) -> Option<(Limits, Vec<&'a str>)> {
+    let mut limits = Limits::default();
+    let mut paths = vec![];
+
+    loop {
+        match args {
+            ["--max", limit, rest @ ..] => {
+                limits.parse_limit(limit).ok()?;
+                args = rest;
+            }
+            [flag, ..] if flag.starts_with("--") => return None,
+            [path, rest @ ..] => {
+                paths.push(*path);
+                args = rest;
+            }
+            [] => break,
+        }
+    }
+
+    if paths.is_empty() {
+        return None;
+    }
+    Some((limits, paths))
+}
+
+/// Typechecks every file and, with `--max`, fails on formulas whose
+/// metrics go over the limits.
+fn check(paths: &[&str], limits: &Limits, stderr: &mut dyn Write) -> HandlerResult<i32> {
+    let mut failed = false;
+
+    for path in paths {
+        let statements = match located_statements(path) {
+            Ok(statements) => statements,
+            Err(e) => {
+                writeln!(stderr, "{}: {}", path, e)?;
+                failed = true;
+                continue;
+            }
+        };
+
+        let mut checker = TypeChecker::new();
+        for located in &statements {
+            if let Err(e) = checker.check_located(located) {
+                match e.span() {
+                    Some(span) => writeln!(stderr, "{}:{}: {}", path, span.start.line, e.message())?,
+                    None => writeln!(stderr, "{}: {}", path, e.message())?,
+                }
+                failed = true;
+            }
+        }
+
+        for formula in metrics::measure_document(&statements) {
+            for message in formula.metrics.exceeded(limits) {
+                writeln!(stderr, "{}:{}: {}: {}", path, formula.line, formula.name, message)?;
+                failed = true;
+            }
+        }
+    }
+
+    Ok(failed as i32)
+}
+
+fn located_statements(path: &str) -> HandlerResult<Vec<LocatedStatement>> {
+    let tokens = tokenize_file(open_file(path)?)?;
+    Ok(parser::located_document_parser(tokens)?)
+}
+
+fn metrics(paths: &[&str], stdout: &mut dyn Write) -> HandlerResult<i32> {
+    for path in paths {
+        for formula in metrics::measure_document(&located_statements(path)?) {
+            writeln!(
+                stdout,
+                "{}:{}: {}: {}",
+                path, formula.line, formula.name, formula.metrics
+            )?;
+        }
+    }
+    Ok(0)
+}
+
+fn fmt(paths: &[&str], check: bool, stdout: &mut dyn Write) -> HandlerResult<i32> {
+    let pipeline = Pipeline::new()
+        .add(FnHandler::new(tokenizer::lossless_tokenizer))
+        .add(FnHandler::new(formatter::format));
+    let mut unformatted = false;
+
+    for path in paths {
+        let input = read_file(path)?;
+        let source: String = input.iter().collect();
+        let result = pipeline.start(input)?;
+
+        if result == source {
+            continue;
+        }
+        if check {
+            writeln!(stdout, "{} is not formatted", path)?;
+            unformatted = true;
+        } else {
+            fs::write(path, result)?;
+        }
+    }
+
+    Ok(unformatted as i32)
+}
+
+fn tokens(path: &str, stdout: &mut dyn Write) -> HandlerResult<i32> {
+    let tokens = tokenize_file(open_file(path)?)?;
+
+    for line in format_tokens(&tokens) {
+        writeln!(stdout, "{}", line)?;
+    }
+    Ok(0)
+}
+
+fn format_tokens(tokens: &[Token]) -> Vec<String> {
+    tokens
+        .iter()
+        .map(|token| format!("{}:{} {:?}", token.line, token.column, token.token_type))
+        .collect()
+}
+
+fn ast(path: &str, stdout: &mut dyn Write) -> HandlerResult<i32> {
+    let document = read_document(path)?;
+
+    writeln!(stdout, "{}", serde_json::to_string_pretty(&document)?)?;
+    Ok(0)
+}
+
+#[derive(Default)]
+struct CsvOptions<'a> {
+    schema: Option<&'a str>,
+    column: Option<&'a str>,
+    output: Option<&'a str>,
+}
+
+fn csv_options<'a>(args: &[&'a str]) -> Option<CsvOptions<'a>> {
+    let mut options = CsvOptions::default();
+
+    for pair in args.chunks(2) {
+        match pair {
+            ["--schema", value] => options.schema = Some(value),
+            ["--column", value] => options.column = Some(value),
+            ["--output", value] => options.output = Some(value),
+            _ => return None,
+        }
+    }
+    Some(options)
+}
+
+/// Appends the result of the formula in `formula_path` to every row of the
+/// CSV file. Column types are inferred from the data unless the table in
+/// `--schema` declares them.
+fn csv(
+    data_path: &str,
+    formula_path: &str,
+    options: &CsvOptions,
+    stdout: &mut dyn Write,
+) -> HandlerResult<i32> {
+    let data: String = read_file(data_path)?.into_iter().collect();
+    let table = csv::read_csv(&data)?;
+
+    let formula = parser::formula_parser(tokenize_file(open_file(formula_path)?)?)?;
+
+    let mut schema = csv::infer_schema(&table);
+    if let Some(path) = options.schema {
+        for declared in read_schema(path)? {
+            match schema.iter_mut().find(|column| column.name == declared.name) {
+                Some(column) => *column = declared,
+                None => schema.push(declared),
+            }
+        }
+    }
+
+    let result = csv::evaluate_csv(&table, &schema, &formula, options.column.unwrap_or("Result"))?;
+    match options.output {
+        Some(path) => fs::write(path, csv::write_csv(&result))?,
+        None => write!(stdout, "{}", csv::write_csv(&result))?,
+    }
+    Ok(0)
+}
+
+fn read_schema(path: &str) -> HandlerResult<Schema> {
+    let document = read_document(path)?;
+    let mut checker = TypeChecker::new();
+
+    for statement in &document.statements {
+        checker.check(statement)?;
+        if let Statement::TableDef(name, _) = statement {
+            return Ok(checker.table(name).cloned().unwrap_or_default());
+        }
+    }
+    Err(SimpleError::new(format!("{} doesn't define a table", path)))
+}
+
+#[derive(Default)]
+struct MigrateOptions<'a> {
+    write: bool,
+    list_properties: Vec<String>,
+    paths: Vec<&'a str>,
+}
+
+fn migrate_options<'a>(mut args: &[&'a str]) -> Option<MigrateOptions<'a>> {
+    let mut options = MigrateOptions::default();
+
+    loop {
+        match args {
+            ["--write", rest @ ..] => {
+                options.write = true;
+                args = rest;
+            }
+            ["--list", name, rest @ ..] => {
+                options.list_properties.push(name.to_string());
+                args = rest;
+            }
+            [flag, ..] if flag.starts_with("--") => return None,
+            [path, rest @ ..] => {
+                options.paths.push(path);
+                args = rest;
+            }
+            [] => break,
+        }
+    }
+
+    if options.paths.is_empty() {
+        return None;
+    }
+    Some(options)
+}
+
+/// Prints a 1.0 to 2.0 migration report for every formula file, and with
+/// `--write` replaces the files with the migrated formulas.
+fn migrate(options: &MigrateOptions, stdout: &mut dyn Write) -> HandlerResult<i32> {
+    let mut formulas = vec![];
+    for path in &options.paths {
+        formulas.push(read_file(path)?.into_iter().collect::<String>());
+    }
+
+    let migrations = migrate::migrate(&formulas, &options.list_properties)?;
+    for (path, migration) in options.paths.iter().zip(&migrations) {
+        write!(stdout, "{}", migration.report(path))?;
+        if options.write && migration.changed() {
+            fs::write(path, &migration.after)?;
+        }
+    }
+    Ok(0)
+}
+
+#[derive(Default)]
+struct LintOptions<'a> {
+    config: Option<&'a str>,
+    fix: bool,
+    paths: Vec<&'a str>,
+}
+
+fn lint_options<'a>(mut args: &[&'a str]) -> Option<LintOptions<'a>> {
+    let mut options = LintOptions::default();
+
+    loop {
+        match args {
+            ["--fix", rest @ ..] => {
+                options.fix = true;
+                args = rest;
+            }
+            ["--config", path, rest @ ..] => {
+                options.config = Some(path);
+                args = rest;
+            }
+            [flag, ..] if flag.starts_with("--") => return None,
+            [path, rest @ ..] => {
+                options.paths.push(path);
+                args = rest;
+            }
+            [] => break,
+        }
+    }
+
+    if options.paths.is_empty() {
+        return None;
+    }
+    Some(options)
+}
+
+/// Prints the lints of every file, after applying the safe fixes with
+/// `--fix`. Fails when any lint is an error.
+fn lint(options: &LintOptions, stdout: &mut dyn Write) -> HandlerResult<i32> {
+    let config = match options.config {
+        Some(path) => LintConfig::parse(&read_file(path)?.into_iter().collect::<String>())?,
+        None => LintConfig::default(),
+    };
+    let mut failed = false;
+
+    for path in &options.paths {
+        let mut source: String = read_file(path)?.into_iter().collect();
+        if options.fix {
+            let fixed = lint::fix(&source, &config)?;
+            if fixed != source {
+                fs::write(path, &fixed)?;
+                source = fixed;
+            }
+        }
+
+        for lint in lint::lint(&source, &config)? {
+            writeln!(stdout, "{}:{}", path, lint)?;
+            if let Some(fix) = &lint.fix {
+                writeln!(stdout, "    fix: {}", fix)?;
+            }
+            failed |= lint.severity == Severity::Error;
+        }
+    }
+
+    Ok(failed as i32)
+}
@@ -0,0 +1,14 @@
+use std::env;
+use std::io;
+use std::process;
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let code = notion_formula_cli::run(
+        &args,
+        &mut io::stdin().lock(),
+        &mut io::stdout(),
+        &mut io::stderr(),
+    );
+    process::exit(code);
+}
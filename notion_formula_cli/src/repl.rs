@@ -0,0 +1,162 @@
+use crate::{document_pipeline, format_tokens, read_document};
+use notion_formula_core::interpreter::Interpreter;
+use notion_formula_core::parser::{self, Document, Expression};
+use notion_formula_core::tokenizer;
+use notion_formula_core::typechecker::TypeChecker;
+use pipeline::{FnHandler, HandlerResult, Pipeline, SimpleError};
+use std::io::{BufRead, Write};
+
+const PROMPT: &str = "> ";
+const CONTINUATION_PROMPT: &str = "... ";
+
+pub const HELP: &str = "Enter a statement or formula to evaluate it.
+    :type <formula>    show the type of a formula
+    :ast <source>      show the syntax tree
+    :tokens <source>   show the tokens
+    :load <file>       run a file, keeping its definitions
+    :help              show this message
+    :quit              exit";
+
+/// Keeps the definitions made by earlier inputs so that `let` bindings,
+/// tables and named formulas can be used by the ones that follow.
+#[derive(Default)]
+pub struct Repl {
+    interpreter: Interpreter,
+    checker: TypeChecker,
+}
+impl Repl {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Handles one complete input and returns the lines to print.
+    pub fn eval(&mut self, input: &str) -> HandlerResult<Vec<String>> {
+        let input = input.trim();
+        let (command, argument) = match input.strip_prefix(':') {
+            Some(command) => match command.find(char::is_whitespace) {
+                Some(index) => (&command[..index], command[index..].trim()),
+                None => (command, ""),
+            },
+            None => return self.execute(document_pipeline().start(input.chars().collect())?),
+        };
+
+        match command {
+            "type" => {
+                let pipeline: Pipeline<Vec<char>, Expression> = Pipeline::new()
+                    .add(FnHandler::new(tokenizer::tokenizer))
+                    .add(FnHandler::new(parser::formula_parser));
+                let ast = pipeline.start(argument.chars().collect())?;
+                Ok(vec![self.checker.infer(&ast)?.to_string()])
+            }
+            "ast" => {
+                let document = document_pipeline().start(argument.chars().collect())?;
+                Ok(vec![serde_json::to_string_pretty(&document)?])
+            }
+            "tokens" => {
+                let tokens = tokenizer::tokenizer(argument.chars().collect())?;
+                Ok(format_tokens(&tokens))
+            }
+            "load" => self.execute(read_document(argument)?),
+            "help" => Ok(vec![HELP.to_string()]),
+            _ => Err(SimpleError::new(format!(
+                "Unknown command :{}, try :help",
+                command
+            ))),
+        }
+    }
+
+    fn execute(&mut self, document: Document) -> HandlerResult<Vec<String>> {
+        let mut output = vec![];
+
+        for statement in document.statements {
+            // The checker only follows along so that `:type` knows about
+            // the definitions, the interpreter has the final say.
+            let _ = self.checker.check(&statement);
+            if let Some(value) = self.interpreter.execute(statement)? {
+                output.push(value.to_string());
+            }
+        }
+        Ok(output)
+    }
+}
+
+/// Reads inputs until the end of `input` or `:quit`. An input spans several
+/// lines while it has unclosed brackets, strings or block comments.
+pub fn run(
+    input: &mut dyn BufRead,
+    stdout: &mut dyn Write,
+    stderr: &mut dyn Write,
+) -> HandlerResult<i32> {
+    let mut repl = Repl::new();
+    let mut source = String::new();
+
+    loop {
+        write!(stdout, "{}", if source.is_empty() { PROMPT } else { CONTINUATION_PROMPT })?;
+        stdout.flush()?;
+
+        let mut line = String::new();
+        if input.read_line(&mut line)? == 0 {
+            if !source.trim().is_empty() {
+                writeln!(stderr, "error: Unexpected end of input")?;
+                return Ok(1);
+            }
+            writeln!(stdout)?;
+            return Ok(0);
+        }
+
+        source.push_str(&line);
+        if !is_complete(&source) {
+            continue;
+        }
+
+        let input = std::mem::take(&mut source);
+        match input.trim() {
+            "" => continue,
+            ":quit" | ":q" => return Ok(0),
+            _ => (),
+        }
+        match repl.eval(&input) {
+            Ok(lines) => {
+                for line in lines {
+                    writeln!(stdout, "{}", line)?;
+                }
+            }
+            Err(e) => writeln!(stderr, "error: {}", e)?,
+        }
+    }
+}
+
+/// Whether every bracket, string and block comment opened in `source` has
+/// been closed.
+pub fn is_complete(source: &str) -> bool {
+    let mut chars = source.chars().peekable();
+    let mut depth = 0;
+
+    while let Some(value) = chars.next() {
+        match value {
+            '(' | '[' | '{' => depth += 1,
+            ')' | ']' | '}' => depth -= 1,
+            '"' if !chars.any(|value| value == '"') => return false,
+            '/' if chars.peek() == Some(&'/') => {
+                chars.find(|value| *value == '\n');
+            }
+            '/' if chars.peek() == Some(&'*') && !skip_block_comment(&mut chars) => {
+                return false
+            }
+            _ => (),
+        }
+    }
+
+    depth <= 0
+}
+
+// Consumes the rest of a block comment, returning whether it was closed.
+fn skip_block_comment(chars: &mut impl Iterator<Item = char>) -> bool {
+    chars.next();
+    let mut previous = ' ';
+    chars.any(|value| {
+        let closed = previous == '*' && value == '/';
+        previous = value;
+        closed
+    })
+}
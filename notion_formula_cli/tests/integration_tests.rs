@@ -0,0 +1,438 @@
+#[cfg(test)]
+mod test {
+    use notion_formula_cli::run;
+    use std::env;
+    use std::fs;
+    use std::path::PathBuf;
+
+    const TEST_FORMULA: &str = "../notion_formula_core/tests/test_formula.notion";
+    const COMPLEX_EXAMPLE: &str = "../notion_formula_core/tests/complex_example.notion";
+
+    fn execute(args: &[&str]) -> (i32, String, String) {
+        execute_with_input(args, "")
+    }
+
+    fn execute_with_input(args: &[&str], input: &str) -> (i32, String, String) {
+        let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+        let mut stdout = vec![];
+        let mut stderr = vec![];
+        let code = run(&args, &mut input.as_bytes(), &mut stdout, &mut stderr);
+
+        (
+            code,
+            String::from_utf8(stdout).unwrap(),
+            String::from_utf8(stderr).unwrap(),
+        )
+    }
+
+    fn temp_file(name: &str, contents: &str) -> PathBuf {
+        let path = env::temp_dir().join(format!("notion_formula_cli_{}", name));
+        fs::write(&path, contents).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_run_prints_output() {
+        let (code, stdout, _) = execute(&["run", COMPLEX_EXAMPLE]);
+
+        assert_eq!(0, code);
+        assert_eq!("Atlas 0\n", stdout);
+    }
+
+    #[test]
+    fn test_run_with_timings() {
+        let (code, stdout, stderr) = execute(&["run", "--timings", COMPLEX_EXAMPLE]);
+
+        assert_eq!(0, code);
+        assert_eq!("Atlas 0\n", stdout);
+        let stages: Vec<_> = stderr
+            .lines()
+            .map(|line| line.split(": 1 calls, 0 failed, ").next().unwrap())
+            .collect();
+        assert_eq!(vec!["reader", "tokenizer", "parser", "interpreter"], stages);
+    }
+
+    #[test]
+    fn test_run_reports_errors() {
+        let path = temp_file("failing.notion", "print 1\nassert 1 == 2\n");
+        let (code, stdout, stderr) = execute(&["run", path.to_str().unwrap()]);
+
+        assert_eq!(1, code);
+        assert_eq!("", stdout);
+        assert_eq!(
+            "error: stage 'interpreter' failed: Assertion failed: 1==2 on line: 2, column: 8\n",
+            stderr
+        );
+
+        let path = temp_file("broken.notion", "print 1 +\n");
+        let (code, _, stderr) = execute(&["run", path.to_str().unwrap()]);
+
+        assert_eq!(1, code);
+        assert_eq!(
+            "error: stage 'parser' failed: Unexpected Token: Eof on line: 2, column: 1\n",
+            stderr
+        );
+
+        let path = temp_file("cycle.notion", "formula f { f + 1 }\nprint f\n");
+        let (code, _, stderr) = execute(&["run", path.to_str().unwrap()]);
+
+        assert_eq!(1, code);
+        assert_eq!(
+            "error: stage 'interpreter' failed: Formula f refers to itself: f -> f on line: 2, column: 7\n",
+            stderr
+        );
+
+        let path = temp_file(
+            "table_cycle.notion",
+            "table T { \"a\": Number, \"f\": formula { T { \"a\": 1 }[\"a\"] } }\nprint T { \"a\": 2 }[\"a\"]\n",
+        );
+        let (code, _, stderr) = execute(&["run", path.to_str().unwrap()]);
+
+        assert_eq!(1, code);
+        assert_eq!(
+            "error: stage 'interpreter' failed: Table T refers to itself: T -> T on line: 2, column: 7\n",
+            stderr
+        );
+    }
+
+    #[test]
+    fn test_run_only_prints_print_statements() {
+        let path = temp_file("expressions.notion", "let x = 1\nx + 41\nprint \"hi\"\n");
+        let (code, stdout, _) = execute(&["run", path.to_str().unwrap()]);
+
+        assert_eq!(0, code);
+        assert_eq!("hi\n", stdout);
+    }
+
+    #[test]
+    fn test_eval_with_props() {
+        let path = temp_file("props.json", "{\"State\": \"🔵\", \"Estimated Completion Date\": \"\"}");
+        let formula = fs::read_to_string(TEST_FORMULA).unwrap();
+        let (code, stdout, _) = execute(&["eval", &formula, "--props", path.to_str().unwrap()]);
+
+        assert_eq!(0, code);
+        assert_eq!("🟩\n", stdout);
+    }
+
+    #[test]
+    fn test_eval_with_page() {
+        let path = temp_file(
+            "page.json",
+            "{\"object\": \"page\", \"properties\": {\
+               \"State\": {\"type\": \"select\", \"select\": {\"name\": \"⚪\"}},\
+               \"Estimated Completion Date\": {\"type\": \"rich_text\", \"rich_text\": []}}}",
+        );
+        let formula = fs::read_to_string(TEST_FORMULA).unwrap();
+        let (code, stdout, _) = execute(&["eval", &formula, "--page", path.to_str().unwrap()]);
+
+        assert_eq!(0, code);
+        assert_eq!("🟨\n", stdout);
+    }
+
+    #[test]
+    fn test_eval_without_props() {
+        let (code, stdout, _) = execute(&["eval", "upper(\"a\") + format(1 + 2)"]);
+
+        assert_eq!(0, code);
+        assert_eq!("A3\n", stdout);
+    }
+
+    #[test]
+    fn test_eval_rejects_unsupported_props() {
+        let path = temp_file("nested_props.json", "{\"State\": [1]}");
+        let (code, _, stderr) = execute(&["eval", "1", "--props", path.to_str().unwrap()]);
+
+        assert_eq!(1, code);
+        assert_eq!("error: Unsupported value [1] for property State\n", stderr);
+    }
+
+    #[test]
+    fn test_check() {
+        let path = temp_file(
+            "invalid.notion",
+            "table T { \"a\": Text }\nlet x = T { \"a\": 1 }\nprint 1 + \"a\"\n",
+        );
+        let (code, _, stderr) = execute(&["check", COMPLEX_EXAMPLE, path.to_str().unwrap()]);
+
+        assert_eq!(1, code);
+        assert_eq!(
+            format!(
+                "{0}:2: Invalid value of type Number for column a of type Text\n\
+                 {0}:3: Invalid types Number and Text for Add\n",
+                path.to_str().unwrap()
+            ),
+            stderr
+        );
+        assert_eq!(0, execute(&["check", COMPLEX_EXAMPLE, TEST_FORMULA]).0);
+    }
+
+    #[test]
+    fn test_check_with_limits() {
+        let (code, _, stderr) = execute(&["check", "--max", "depth=4", "--max", "cost=30", TEST_FORMULA]);
+
+        assert_eq!(1, code);
+        assert_eq!(
+            format!("{}:1: expression: depth is 5, over the limit of 4\n", TEST_FORMULA),
+            stderr
+        );
+        assert_eq!(0, execute(&["check", "--max", "depth=5", TEST_FORMULA]).0);
+        assert_eq!(2, execute(&["check", "--max", "size=5", TEST_FORMULA]).0);
+    }
+
+    #[test]
+    fn test_metrics() {
+        let (code, stdout, _) = execute(&["metrics", TEST_FORMULA]);
+
+        assert_eq!(0, code);
+        assert_eq!(
+            format!(
+                "{}:1: expression: nodes 18, depth 5, props 3, functions 2, branches 4, cost 21\n",
+                TEST_FORMULA
+            ),
+            stdout
+        );
+    }
+
+    #[test]
+    fn test_fmt() {
+        let path = temp_file("unformatted.notion", "let x=1+2 // three\nprint x*2");
+        let path = path.to_str().unwrap();
+
+        let (code, stdout, _) = execute(&["fmt", "--check", path]);
+        assert_eq!(1, code);
+        assert_eq!(format!("{} is not formatted\n", path), stdout);
+
+        assert_eq!(0, execute(&["fmt", path]).0);
+        assert_eq!("let x = 1 + 2 // three\nprint x * 2\n", fs::read_to_string(path).unwrap());
+        assert_eq!(0, execute(&["fmt", "--check", path]).0);
+    }
+
+    #[test]
+    fn test_tokens() {
+        let path = temp_file("tokens.notion", "x + 1");
+        let (code, stdout, _) = execute(&["tokens", path.to_str().unwrap()]);
+
+        assert_eq!(0, code);
+        assert_eq!(
+            "1:1 Identifier(\"x\")\n1:3 Plus\n1:5 NumberLiteral(\"1\")\n1:6 Eof\n",
+            stdout
+        );
+    }
+
+    #[test]
+    fn test_ast() {
+        let path = temp_file("ast.notion", "print x");
+        let (code, stdout, _) = execute(&["ast", path.to_str().unwrap()]);
+
+        assert_eq!(0, code);
+        let json: serde_json::Value = serde_json::from_str(&stdout).unwrap();
+        assert_eq!(
+            serde_json::json!({ "statements": [{ "PrintStatement": { "Identifier": "x" } }] }),
+            json
+        );
+    }
+
+    #[test]
+    fn test_usage() {
+        let (code, _, stderr) = execute(&["frobnicate"]);
+
+        assert_eq!(2, code);
+        assert!(stderr.starts_with("Usage:"));
+    }
+
+    #[test]
+    fn test_repl_keeps_bindings() {
+        let input = "let x = 2\nx * 3\nx == 4\nprint x + 1\n";
+        let (code, stdout, stderr) = execute_with_input(&["repl"], input);
+
+        assert_eq!(0, code);
+        assert_eq!("> > 6\n> false\n> 3\n> \n", stdout);
+        assert_eq!("", stderr);
+    }
+
+    #[test]
+    fn test_repl_reads_multiple_lines() {
+        let input = "table Users {\n  \"name\": Text,\n  \"label\": formula { upper(prop(\"name\")) }\n}\n\
+                     Users { \"name\": \"(\" + \"josh\" }[\"label\"]\nif(\n/* ( */ true,\n1,\n2\n)\n";
+        let (code, stdout, stderr) = execute_with_input(&["repl"], input);
+
+        assert_eq!(0, code);
+        assert_eq!("> ... ... ... > (JOSH\n> ... ... ... ... 1\n> \n", stdout);
+        assert_eq!("", stderr);
+    }
+
+    #[test]
+    fn test_repl_commands() {
+        let input = "let name = \"Atlas\"\n:type length(name)\n:tokens name\n:ast print 1\n:quit\nprint 2\n";
+        let (code, stdout, _) = execute_with_input(&["repl"], input);
+
+        assert_eq!(0, code);
+        assert_eq!(
+            "> > Number\n> 1:1 Identifier(\"name\")\n1:5 Eof\n> {\n  \"statements\": [\n    {\n      \
+             \"PrintStatement\": {\n        \"Number\": \"1\"\n      }\n    }\n  ]\n}\n> ",
+            stdout
+        );
+    }
+
+    #[test]
+    fn test_repl_load() {
+        let path = temp_file(
+            "load.notion",
+            "table Users { \"name\": Text }\nlet u = Users { \"name\": \"Atlas\" }\nprint u[\"name\"]\n",
+        );
+        let input = format!(":load {}\nu[\"name\"] + \"!\"\n:type u\n", path.to_str().unwrap());
+        let (code, stdout, _) = execute_with_input(&["repl"], &input);
+
+        assert_eq!(0, code);
+        assert_eq!("> Atlas\n> Atlas!\n> Users\n> \n", stdout);
+    }
+
+    #[test]
+    fn test_repl_reports_errors_and_continues() {
+        let input = "1 +\nmissing\n:nope\nformula f { f }\nf\n1 + 1\n(1 +\n";
+        let (code, stdout, stderr) = execute_with_input(&["repl"], input);
+
+        assert_eq!(1, code);
+        assert_eq!("> > > > > > 2\n> ... ", stdout);
+        assert_eq!(
+            "error: Unexpected Token: Eof on line: 1, column: 4\n\
+             error: Unknown identifier: missing\n\
+             error: Unknown command :nope, try :help\n\
+             error: Formula f refers to itself: f -> f\n\
+             error: Unexpected end of input\n",
+            stderr
+        );
+    }
+
+    #[test]
+    fn test_csv_appends_formula_results() {
+        let data = temp_file(
+            "tasks.csv",
+            "Name,State,Estimated Completion Date\nDocs,🔵,\nBug,⚪,soon\n",
+        );
+        let (code, stdout, stderr) =
+            execute(&["csv", data.to_str().unwrap(), TEST_FORMULA, "--column", "Status"]);
+
+        assert_eq!("", stderr);
+        assert_eq!(0, code);
+        assert_eq!(
+            "Name,State,Estimated Completion Date,Status\nDocs,🔵,,🟩\nBug,⚪,soon,🟨\n",
+            stdout
+        );
+    }
+
+    #[test]
+    fn test_csv_with_schema_and_output() {
+        let data = temp_file("numbers.csv", "Name,Code\na,007\nb,\n");
+        let formula = temp_file("code.notion", "prop(\"Name\") + prop(\"Code\")");
+        let schema = temp_file("schema.notion", "table Codes { \"Code\": Text }");
+        let output = env::temp_dir().join("notion_formula_cli_numbers_out.csv");
+        let (code, stdout, _) = execute(&[
+            "csv",
+            data.to_str().unwrap(),
+            formula.to_str().unwrap(),
+            "--schema",
+            schema.to_str().unwrap(),
+            "--output",
+            output.to_str().unwrap(),
+        ]);
+
+        assert_eq!(0, code);
+        assert_eq!("", stdout);
+        assert_eq!(
+            "Name,Code,Result\na,007,a007\nb,,b\n",
+            fs::read_to_string(output).unwrap()
+        );
+
+        let (code, _, stderr) = execute(&["csv", data.to_str().unwrap(), formula.to_str().unwrap()]);
+        assert_eq!(1, code);
+        assert_eq!("error: Invalid types Text and Number for Add\n", stderr);
+    }
+
+    #[test]
+    fn test_migrate_prints_a_report() {
+        let legacy = temp_file("legacy.notion", "concat(prop(\"Tags\"), \"!\")\n");
+        let (code, stdout, _) = execute(&[
+            "migrate",
+            "--list",
+            "Tags",
+            legacy.to_str().unwrap(),
+            TEST_FORMULA,
+        ]);
+
+        assert_eq!(0, code);
+        assert_eq!(
+            format!(
+                "--- {}\n\
+                 - concat(prop(\"Tags\"), \"!\")\n\
+                 + prop(\"Tags\") + \"!\"\n  \
+                 rewrote: concat joins lists in 2.0, text is joined with + (concat(prop(\"Tags\"), \"!\") => prop(\"Tags\") + \"!\")\n  \
+                 warning: prop(\"Tags\") is a list in 2.0 instead of comma separated text\n\
+                 --- {}\n  nothing to migrate\n",
+                legacy.display(),
+                TEST_FORMULA
+            ),
+            stdout
+        );
+        assert_eq!(
+            "concat(prop(\"Tags\"), \"!\")\n",
+            fs::read_to_string(&legacy).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_migrate_write() {
+        let legacy = temp_file("legacy_write.notion", "slice(prop(\"Name\"), 1)\n");
+        let (code, _, _) = execute(&["migrate", "--write", legacy.to_str().unwrap()]);
+
+        assert_eq!(0, code);
+        assert_eq!(
+            "substring(prop(\"Name\"), 1)\n",
+            fs::read_to_string(&legacy).unwrap()
+        );
+        assert_eq!(2, execute(&["migrate", "--write"]).0);
+    }
+
+    #[test]
+    fn test_lint() {
+        let source = temp_file(
+            "lint.notion",
+            "let unused = prop(\"Due\") == \"\"\nprint 1 == 1\n",
+        );
+        let path = source.to_str().unwrap();
+        let (code, stdout, _) = execute(&["lint", path]);
+
+        assert_eq!(0, code);
+        assert_eq!(
+            format!(
+                "{0}:1:1: warning [unused-let] unused is never used\n    \
+                 fix: remove the statement\n\
+                 {0}:1:14: warning [empty-comparison] Use empty(prop(\"Due\")) to check for an empty value\n    \
+                 fix: replace with empty(prop(\"Due\"))\n",
+                path
+            ),
+            stdout
+        );
+
+        let config = temp_file("lint.config", "unused-let = error\nempty-comparison = off\n");
+        let (code, stdout, _) = execute(&["lint", "--config", config.to_str().unwrap(), path]);
+        assert_eq!(1, code);
+        assert_eq!(
+            format!(
+                "{}:1:1: error [unused-let] unused is never used\n    fix: remove the statement\n",
+                path
+            ),
+            stdout
+        );
+    }
+
+    #[test]
+    fn test_lint_fix() {
+        let source = temp_file("lint_fix.notion", "let x = true\nprint x == true\n");
+        let (code, stdout, _) = execute(&["lint", "--fix", source.to_str().unwrap()]);
+
+        assert_eq!(0, code);
+        assert_eq!("", stdout);
+        assert_eq!("let x = true\nprint x\n", fs::read_to_string(&source).unwrap());
+    }
+}
@@ -0,0 +1,259 @@
+use pipeline::SimpleError;
+
+/// Documentation for a function or constant that every formula can use.
+#[derive(Debug, PartialEq)]
+pub struct Builtin {
+    pub name: &'static str,
+    pub signature: &'static str,
+    pub description: &'static str,
+}
+
+pub const BUILTINS: &[Builtin] = &[
+    Builtin {
+        name: "prop",
+        signature: "prop(name: Text) -> Any",
+        description: "Returns the value of the named property of the current row.",
+    },
+    Builtin {
+        name: "if",
+        signature: "if(test: Checkbox, accept: T, reject: T) -> T",
+        description: "Returns accept when test is true, otherwise reject.",
+    },
+    Builtin {
+        name: "ifs",
+        signature: "ifs(test: Checkbox, value: T, ..., otherwise: T) -> T",
+        description: "Returns the value after the first test that is true, otherwise the last argument.",
+    },
+    Builtin {
+        name: "empty",
+        signature: "empty(value: Any) -> Checkbox",
+        description: "Returns true for empty text, zero and false.",
+    },
+    Builtin {
+        name: "length",
+        signature: "length(text: Text) -> Number",
+        description: "Returns the number of characters in the text.",
+    },
+    Builtin {
+        name: "format",
+        signature: "format(value: Any) -> Text",
+        description: "Converts the value to text.",
+    },
+    Builtin {
+        name: "toNumber",
+        signature: "toNumber(value: Any) -> Number",
+        description: "Converts text or a checkbox to a number.",
+    },
+    Builtin {
+        name: "concat",
+        signature: "concat(text: Text, ...) -> Text",
+        description: "Joins all of the arguments together.",
+    },
+    Builtin {
+        name: "join",
+        signature: "join(separator: Text, text: Text, ...) -> Text",
+        description: "Joins the remaining arguments with the separator between each.",
+    },
+    Builtin {
+        name: "contains",
+        signature: "contains(text: Text, search: Text) -> Checkbox",
+        description: "Returns true if the search text appears in the text.",
+    },
+    Builtin {
+        name: "replace",
+        signature: "replace(text: Text, search: Text, replacement: Text) -> Text",
+        description: "Replaces the first occurrence of the search text.",
+    },
+    Builtin {
+        name: "replaceAll",
+        signature: "replaceAll(text: Text, search: Text, replacement: Text) -> Text",
+        description: "Replaces every occurrence of the search text.",
+    },
+    Builtin {
+        name: "lower",
+        signature: "lower(text: Text) -> Text",
+        description: "Converts the text to lowercase.",
+    },
+    Builtin {
+        name: "upper",
+        signature: "upper(text: Text) -> Text",
+        description: "Converts the text to uppercase.",
+    },
+    Builtin {
+        name: "slice",
+        signature: "slice(text: Text, start: Number, end: Number?) -> Text",
+        description: "Returns the characters from start up to, but not including, end.",
+    },
+    Builtin {
+        name: "substring",
+        signature: "substring(text: Text, start: Number, end: Number?) -> Text",
+        description: "The Notion 2.0 name for slice on text.",
+    },
+    Builtin {
+        name: "abs",
+        signature: "abs(value: Number) -> Number",
+        description: "Returns the absolute value of the number.",
+    },
+    Builtin {
+        name: "ceil",
+        signature: "ceil(value: Number) -> Number",
+        description: "Rounds the number up to the nearest integer.",
+    },
+    Builtin {
+        name: "floor",
+        signature: "floor(value: Number) -> Number",
+        description: "Rounds the number down to the nearest integer.",
+    },
+    Builtin {
+        name: "round",
+        signature: "round(value: Number) -> Number",
+        description: "Rounds the number to the nearest integer.",
+    },
+    Builtin {
+        name: "sqrt",
+        signature: "sqrt(value: Number) -> Number",
+        description: "Returns the square root of the number.",
+    },
+    Builtin {
+        name: "cbrt",
+        signature: "cbrt(value: Number) -> Number",
+        description: "Returns the cube root of the number.",
+    },
+    Builtin {
+        name: "exp",
+        signature: "exp(value: Number) -> Number",
+        description: "Returns e raised to the power of the number.",
+    },
+    Builtin {
+        name: "ln",
+        signature: "ln(value: Number) -> Number",
+        description: "Returns the natural logarithm of the number.",
+    },
+    Builtin {
+        name: "log10",
+        signature: "log10(value: Number) -> Number",
+        description: "Returns the base 10 logarithm of the number.",
+    },
+    Builtin {
+        name: "log2",
+        signature: "log2(value: Number) -> Number",
+        description: "Returns the base 2 logarithm of the number.",
+    },
+    Builtin {
+        name: "sign",
+        signature: "sign(value: Number) -> Number",
+        description: "Returns 1, -1 or 0 depending on the sign of the number.",
+    },
+    Builtin {
+        name: "pow",
+        signature: "pow(base: Number, exponent: Number) -> Number",
+        description: "Raises the base to the exponent.",
+    },
+    Builtin {
+        name: "min",
+        signature: "min(value: Number, ...) -> Number",
+        description: "Returns the smallest of the arguments.",
+    },
+    Builtin {
+        name: "max",
+        signature: "max(value: Number, ...) -> Number",
+        description: "Returns the largest of the arguments.",
+    },
+    Builtin {
+        name: "now",
+        signature: "now() -> Text",
+        description: "Returns the current date and time.",
+    },
+    Builtin {
+        name: "dateAdd",
+        signature: "dateAdd(date: Text, amount: Number, unit: Text) -> Text",
+        description: "Adds the amount of years, months, weeks, days, hours, minutes or seconds to the date.",
+    },
+    Builtin {
+        name: "dateSubtract",
+        signature: "dateSubtract(date: Text, amount: Number, unit: Text) -> Text",
+        description: "Subtracts the amount of years, months, weeks, days, hours, minutes or seconds from the date.",
+    },
+    Builtin {
+        name: "dateBetween",
+        signature: "dateBetween(end: Text, start: Text, unit: Text) -> Number",
+        description: "Returns the whole number of units from start to end.",
+    },
+    Builtin {
+        name: "formatDate",
+        signature: "formatDate(date: Text, format: Text) -> Text",
+        description: "Writes the date with a format such as \"MMMM D, YYYY\".",
+    },
+    Builtin {
+        name: "parseDate",
+        signature: "parseDate(text: Text) -> Text",
+        description: "Reads an ISO 8601 date and writes it the way date functions return dates.",
+    },
+    Builtin {
+        name: "year",
+        signature: "year(date: Text) -> Number",
+        description: "Returns the year of the date.",
+    },
+    Builtin {
+        name: "month",
+        signature: "month(date: Text) -> Number",
+        description: "Returns the month of the date, from 1 for January to 12.",
+    },
+    Builtin {
+        name: "date",
+        signature: "date(date: Text) -> Number",
+        description: "Returns the day of the month of the date.",
+    },
+    Builtin {
+        name: "day",
+        signature: "day(date: Text) -> Number",
+        description: "Returns the day of the week of the date, from 1 for Monday to 7 for Sunday.",
+    },
+    Builtin {
+        name: "hour",
+        signature: "hour(date: Text) -> Number",
+        description: "Returns the hour of the date, from 0 to 23.",
+    },
+    Builtin {
+        name: "minute",
+        signature: "minute(date: Text) -> Number",
+        description: "Returns the minute of the date.",
+    },
+    Builtin {
+        name: "timestamp",
+        signature: "timestamp(date: Text) -> Number",
+        description: "Returns the milliseconds since 1970-01-01 of the date.",
+    },
+    Builtin {
+        name: "fromTimestamp",
+        signature: "fromTimestamp(milliseconds: Number) -> Text",
+        description: "Returns the date the given milliseconds after 1970-01-01.",
+    },
+    Builtin {
+        name: "e",
+        signature: "e: Number",
+        description: "The base of the natural logarithm.",
+    },
+    Builtin {
+        name: "pi",
+        signature: "pi: Number",
+        description: "The ratio of a circle's circumference to its diameter.",
+    },
+];
+
+/// Notion's date functions that aren't builtins yet. Dates are text, see
+/// `date::Date`, which can't hold the ranges the last three work on.
+pub const DATE_FUNCTIONS: &[&str] = &["today", "week", "dateRange", "dateStart", "dateEnd"];
+
+pub fn lookup(name: &str) -> Option<&'static Builtin> {
+    BUILTINS.iter().find(|builtin| builtin.name == name)
+}
+
+/// The error for calling a function that isn't a builtin, which says so when
+/// it is one of Notion's that isn't supported yet.
+pub fn unknown_function(name: &str) -> Box<SimpleError> {
+    if DATE_FUNCTIONS.contains(&name) {
+        return SimpleError::new(format!("{} isn't supported yet", name));
+    }
+    SimpleError::new(format!("Unknown function: {}", name))
+}
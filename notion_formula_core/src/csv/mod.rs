@@ -0,0 +1,230 @@
+use crate::interpreter::{Interpreter, Props, RuntimeType};
+use crate::parser::Expression;
+use crate::typechecker::{Column, Schema, StaticType, TypeChecker};
+use pipeline::{HandlerResult, SimpleError};
+
+/// The rows of a CSV file, such as a database exported from Notion. Cells
+/// are kept as text until they are read with a schema.
+#[derive(Debug, PartialEq, Clone)]
+pub struct CsvTable {
+    pub headers: Vec<String>,
+    pub rows: Vec<Vec<String>>,
+}
+
+/// Reads CSV text where the first record holds the column names. Quoted
+/// fields may contain commas, doubled quotes and line breaks.
+pub fn read_csv(input: &str) -> HandlerResult<CsvTable> {
+    let input = input.strip_prefix('\u{feff}').unwrap_or(input);
+    let mut records = records(input)?.into_iter();
+
+    let headers = records
+        .next()
+        .ok_or_else(|| SimpleError::new("The CSV file has no header row".into()))?;
+    let mut rows = vec![];
+    for (i, record) in records.enumerate() {
+        if record.len() != headers.len() {
+            return Err(SimpleError::new(format!(
+                "Row {} has {} fields, expected {}",
+                i + 1,
+                record.len(),
+                headers.len()
+            )));
+        }
+        rows.push(record);
+    }
+
+    Ok(CsvTable { headers, rows })
+}
+
+pub fn write_csv(table: &CsvTable) -> String {
+    let mut output = String::new();
+
+    for record in std::iter::once(&table.headers).chain(table.rows.iter()) {
+        let fields: Vec<String> = record.iter().map(|field| quote(field)).collect();
+        output.push_str(&fields.join(","));
+        output.push('\n');
+    }
+    output
+}
+
+/// Picks the narrowest type that fits every non-empty cell of each column:
+/// Number, then Checkbox (`Yes`/`No` as Notion exports them, or
+/// `true`/`false`), falling back to Text.
+pub fn infer_schema(table: &CsvTable) -> Schema {
+    table
+        .headers
+        .iter()
+        .enumerate()
+        .map(|(i, name)| {
+            let cells: Vec<&str> = table
+                .rows
+                .iter()
+                .map(|row| row[i].trim())
+                .filter(|cell| !cell.is_empty())
+                .collect();
+
+            let static_type = if cells.is_empty() {
+                StaticType::Str
+            } else if cells.iter().all(|cell| parse_number(cell).is_some()) {
+                StaticType::Num
+            } else if cells.iter().all(|cell| parse_checkbox(cell).is_some()) {
+                StaticType::Bool
+            } else {
+                StaticType::Str
+            };
+
+            Column {
+                name: name.clone(),
+                static_type,
+                formula: false,
+            }
+        })
+        .collect()
+}
+
+/// Binds each column of `row` to a property. Empty cells of Number columns
+/// read as 0, the same as Notion treats them in arithmetic.
+pub fn row_props(table: &CsvTable, schema: &Schema, row: usize) -> HandlerResult<Props> {
+    let mut props = Props::new();
+
+    for (i, name) in table.headers.iter().enumerate() {
+        let cell = &table.rows[row][i];
+        let static_type = schema
+            .iter()
+            .find(|column| &column.name == name)
+            .map_or(&StaticType::Str, |column| &column.static_type);
+
+        let value = match static_type {
+            StaticType::Num if cell.trim().is_empty() => RuntimeType::Num(0.0),
+            StaticType::Num => match parse_number(cell.trim()) {
+                Some(value) => RuntimeType::Num(value),
+                None => return Err(invalid_cell(row, name, cell, static_type)),
+            },
+            StaticType::Bool => match parse_checkbox(cell.trim()) {
+                Some(value) => RuntimeType::Bool(value),
+                None if cell.trim().is_empty() => RuntimeType::Bool(false),
+                None => return Err(invalid_cell(row, name, cell, static_type)),
+            },
+            _ => RuntimeType::Str(cell.clone()),
+        };
+        props.insert(name.clone(), value);
+    }
+
+    Ok(props)
+}
+
+/// Evaluates `formula` against every row and returns the table with the
+/// results appended as a new column named `column`. The formula is
+/// typechecked against the schema first, so an unknown property fails
+/// before any row is evaluated.
+pub fn evaluate_csv(
+    table: &CsvTable,
+    schema: &Schema,
+    formula: &Expression,
+    column: &str,
+) -> HandlerResult<CsvTable> {
+    if table.headers.iter().any(|header| header == column) {
+        return Err(SimpleError::new(format!("Column {} already exists", column)));
+    }
+    TypeChecker::new().infer_with_schema(formula, schema)?;
+
+    let interpreter = Interpreter::new();
+    let mut result = table.clone();
+    result.headers.push(column.to_string());
+
+    for (i, row) in result.rows.iter_mut().enumerate() {
+        let props = row_props(table, schema, i)?;
+        let value = interpreter
+            .evaluate_with_props(formula, &props)
+            .map_err(|e| SimpleError::new(format!("Row {}: {}", i + 1, e)))?;
+        row.push(value.to_string());
+    }
+
+    Ok(result)
+}
+
+// Lines without a single character, like the one after the final line
+// break, are skipped. Anything else is a record, even a lone `""`.
+fn records(input: &str) -> HandlerResult<Vec<Vec<String>>> {
+    let mut records = vec![];
+    let mut record = vec![];
+    let mut field = String::new();
+    let mut started = false;
+    let mut chars = input.chars().peekable();
+
+    while let Some(value) = chars.next() {
+        match value {
+            '"' if field.is_empty() => loop {
+                started = true;
+                match chars.next() {
+                    Some('"') if chars.peek() == Some(&'"') => {
+                        chars.next();
+                        field.push('"');
+                    }
+                    Some('"') => break,
+                    Some(value) => field.push(value),
+                    None => {
+                        return Err(SimpleError::new(
+                            "Couldn't find the end of a quoted field, missing '\"'".into(),
+                        ))
+                    }
+                }
+            },
+            ',' => {
+                started = true;
+                record.push(std::mem::take(&mut field));
+            }
+            '\r' if chars.peek() == Some(&'\n') => (),
+            '\n' if !started => (),
+            '\n' => {
+                record.push(std::mem::take(&mut field));
+                records.push(std::mem::take(&mut record));
+                started = false;
+            }
+            _ => {
+                started = true;
+                field.push(value);
+            }
+        }
+    }
+
+    if started {
+        record.push(field);
+        records.push(record);
+    }
+    Ok(records)
+}
+
+fn quote(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+// Rust also reads `inf` and `NaN` as numbers, which Notion never exports.
+fn parse_number(cell: &str) -> Option<f64> {
+    cell.parse::<f64>().ok().filter(|value| value.is_finite())
+}
+
+fn parse_checkbox(cell: &str) -> Option<bool> {
+    match cell.to_lowercase().as_str() {
+        "yes" | "true" => Some(true),
+        "no" | "false" => Some(false),
+        _ => None,
+    }
+}
+
+fn invalid_cell(row: usize, name: &str, cell: &str, static_type: &StaticType) -> Box<SimpleError> {
+    SimpleError::new(format!(
+        "Row {}: invalid value {:?} for column {} of type {}",
+        row + 1,
+        cell,
+        name,
+        static_type
+    ))
+}
+
+#[cfg(test)]
+mod test;
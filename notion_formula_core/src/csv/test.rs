@@ -0,0 +1,152 @@
+use super::*;
+use crate::parser::formula_parser;
+use crate::tokenizer::tokenizer;
+
+const EXPORT: &str = "\u{feff}Name,Estimate,Done,Notes\r\n\
+                      Write docs,3,Yes,\"Short, sweet\"\r\n\
+                      Fix bug,,No,\"Says \"\"hi\"\"\nthen leaves\"\r\n";
+
+fn parse_formula(input: &str) -> Expression {
+    let tokens = tokenizer(input.chars().collect()).unwrap();
+    formula_parser(tokens).unwrap()
+}
+
+fn column(name: &str, static_type: StaticType) -> Column {
+    Column {
+        name: name.into(),
+        static_type,
+        formula: false,
+    }
+}
+
+#[test]
+fn test_reads_notion_exports() {
+    let result = read_csv(EXPORT).unwrap();
+
+    assert_eq!(
+        CsvTable {
+            headers: vec!["Name".into(), "Estimate".into(), "Done".into(), "Notes".into()],
+            rows: vec![
+                vec!["Write docs".into(), "3".into(), "Yes".into(), "Short, sweet".into()],
+                vec![
+                    "Fix bug".into(),
+                    "".into(),
+                    "No".into(),
+                    "Says \"hi\"\nthen leaves".into()
+                ],
+            ],
+        },
+        result
+    );
+}
+
+#[test]
+fn test_keeps_rows_of_empty_values() {
+    let result = read_csv("Name\nA\n\"\"\nC\n").unwrap();
+
+    assert_eq!(
+        vec![vec!["A".to_string()], vec!["".into()], vec!["C".into()]],
+        result.rows
+    );
+    assert_eq!(3, read_csv("Name\r\nA\r\n\"\"\r\nC").unwrap().rows.len());
+}
+
+#[test]
+fn test_read_errors() {
+    assert_eq!(
+        "Row 2 has 1 fields, expected 2",
+        read_csv("a,b\n1,2\n3\n").unwrap_err().to_string()
+    );
+    assert_eq!(
+        "Couldn't find the end of a quoted field, missing '\"'",
+        read_csv("a\n\"open\n").unwrap_err().to_string()
+    );
+    assert_eq!(
+        "The CSV file has no header row",
+        read_csv("").unwrap_err().to_string()
+    );
+}
+
+#[test]
+fn test_write_round_trips() {
+    let table = read_csv(EXPORT).unwrap();
+    let result = write_csv(&table);
+
+    assert_eq!(
+        "Name,Estimate,Done,Notes\nWrite docs,3,Yes,\"Short, sweet\"\nFix bug,,No,\"Says \"\"hi\"\"\nthen leaves\"\n",
+        result
+    );
+    assert_eq!(table, read_csv(&result).unwrap());
+}
+
+#[test]
+fn test_infers_schema() {
+    let table = read_csv("a,b,c,d\n1.5,yes,x,\n-2,False,3,\n").unwrap();
+
+    assert_eq!(
+        vec![
+            column("a", StaticType::Num),
+            column("b", StaticType::Bool),
+            column("c", StaticType::Str),
+            column("d", StaticType::Str),
+        ],
+        infer_schema(&table)
+    );
+
+    let table = read_csv("a,b\ninf,1\nNaN,infinity\n").unwrap();
+    assert_eq!(
+        vec![column("a", StaticType::Str), column("b", StaticType::Str)],
+        infer_schema(&table)
+    );
+}
+
+#[test]
+fn test_row_props() {
+    let table = read_csv(EXPORT).unwrap();
+    let schema = infer_schema(&table);
+    let result = row_props(&table, &schema, 1).unwrap();
+
+    assert_eq!(Some(&RuntimeType::Num(0.0)), result.get("Estimate"));
+    assert_eq!(Some(&RuntimeType::Bool(false)), result.get("Done"));
+    assert_eq!(Some(&RuntimeType::Str("Fix bug".into())), result.get("Name"));
+
+    let schema = vec![column("Name", StaticType::Num)];
+    assert_eq!(
+        "Row 1: invalid value \"Write docs\" for column Name of type Number",
+        row_props(&table, &schema, 0).unwrap_err().to_string()
+    );
+}
+
+#[test]
+fn test_evaluates_formula_for_each_row() {
+    let table = read_csv(EXPORT).unwrap();
+    let schema = infer_schema(&table);
+    let formula = parse_formula("prop(\"Done\") ? \"✅\" : prop(\"Name\") + \" (\" + format(prop(\"Estimate\") * 2) + \")\"");
+    let result = evaluate_csv(&table, &schema, &formula, "Status").unwrap();
+
+    assert_eq!(
+        vec!["Name", "Estimate", "Done", "Notes", "Status"],
+        result.headers
+    );
+    assert_eq!("✅", result.rows[0][4]);
+    assert_eq!("Fix bug (0)", result.rows[1][4]);
+}
+
+#[test]
+fn test_evaluate_errors() {
+    let table = read_csv(EXPORT).unwrap();
+    let schema = infer_schema(&table);
+
+    let result = evaluate_csv(&table, &schema, &parse_formula("prop(\"Owner\")"), "Result");
+    assert_eq!("Unknown property: Owner", result.unwrap_err().to_string());
+
+    let result = evaluate_csv(&table, &schema, &parse_formula("1"), "Name");
+    assert_eq!("Column Name already exists", result.unwrap_err().to_string());
+
+    let formula = parse_formula("toNumber(prop(\"Notes\"))");
+    let result = evaluate_csv(&table, &schema, &formula, "Result");
+    assert_eq!(
+        "Row 1: invalid float literal",
+        result.unwrap_err().to_string()
+    );
+}
@@ -0,0 +1,444 @@
+use pipeline::{HandlerResult, SimpleError};
+use std::fmt;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// The format date functions write dates in, such as `2021-05-01T09:30:00`.
+pub const DATE_FORMAT: &str = "YYYY-MM-DD[T]HH:mm:ss";
+
+pub(crate) const MONTHS: [&str; 12] = [
+    "January",
+    "February",
+    "March",
+    "April",
+    "May",
+    "June",
+    "July",
+    "August",
+    "September",
+    "October",
+    "November",
+    "December",
+];
+
+pub(crate) const WEEKDAYS: [&str; 7] = [
+    "Monday",
+    "Tuesday",
+    "Wednesday",
+    "Thursday",
+    "Friday",
+    "Saturday",
+    "Sunday",
+];
+
+/// A point in time, to the second, in UTC. Formulas have no date type, so
+/// dates are text in ISO 8601 form, like Notion exports them: `2021-05-01`,
+/// or with a time such as `2021-05-01T09:30`, seconds, fractions of a second
+/// and a `Z` or `+02:00` offset. Fractions of a second are dropped.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+pub struct Date {
+    seconds: i64,
+}
+impl Date {
+    pub fn parse(text: &str) -> HandlerResult<Date> {
+        match parse(text.trim()) {
+            Some(date) => Ok(date),
+            None => Err(SimpleError::new(format!("Invalid date: {}", text))),
+        }
+    }
+
+    pub fn now() -> Date {
+        let seconds = match SystemTime::now().duration_since(UNIX_EPOCH) {
+            Ok(elapsed) => elapsed.as_secs() as i64,
+            Err(before) => -(before.duration().as_secs() as i64),
+        };
+        Date { seconds }
+    }
+
+    /// Milliseconds since 1970-01-01, as Notion's `timestamp` returns them.
+    pub fn from_timestamp(milliseconds: f64) -> Date {
+        Date {
+            seconds: (milliseconds / 1000.0).floor() as i64,
+        }
+    }
+
+    pub fn timestamp(&self) -> f64 {
+        self.seconds as f64 * 1000.0
+    }
+
+    /// Adds `amount` of `unit`, rounded toward zero. Adding months or years
+    /// stops at the end of shorter months, so a month after January 31st is
+    /// the last day of February.
+    pub fn add(&self, amount: f64, unit: DateUnit) -> Date {
+        let amount = amount.trunc() as i64;
+        let months = match (unit, unit.seconds()) {
+            (_, Some(length)) => {
+                return Date {
+                    seconds: self.seconds + amount * length,
+                }
+            }
+            (DateUnit::Years, None) => amount * 12,
+            (_, None) => amount,
+        };
+
+        let (year, month, day) = civil_from_days(self.days());
+        let index = year * 12 + (month as i64 - 1) + months;
+        let (year, month) = (index.div_euclid(12), index.rem_euclid(12) as u32 + 1);
+        let day = day.min(days_in_month(year, month));
+        Date {
+            seconds: days_from_civil(year, month, day) * 86400 + self.time_of_day(),
+        }
+    }
+
+    /// The whole number of `unit` from `start` to this date, rounded toward
+    /// zero and negative when `start` is later.
+    pub fn since(&self, start: &Date, unit: DateUnit) -> f64 {
+        match (unit, unit.seconds()) {
+            (_, Some(length)) => ((self.seconds - start.seconds) / length) as f64,
+            (DateUnit::Years, None) => (self.months_since(start) / 12) as f64,
+            (_, None) => self.months_since(start) as f64,
+        }
+    }
+
+    pub fn field(&self, field: DateField) -> f64 {
+        let (year, month, day) = civil_from_days(self.days());
+        let value = match field {
+            DateField::Year => year,
+            DateField::Month => month as i64,
+            DateField::Date => day as i64,
+            DateField::Day => self.weekday() as i64,
+            DateField::Hour => self.time_of_day() / 3600,
+            DateField::Minute => self.time_of_day() / 60 % 60,
+        };
+        value as f64
+    }
+
+    pub fn format(&self, format: &[FormatToken]) -> String {
+        let (year, month, day) = civil_from_days(self.days());
+        let hour = self.time_of_day() / 3600;
+        let hour12 = (hour + 11) % 12 + 1;
+
+        let mut output = String::new();
+        for token in format {
+            let text = match token {
+                FormatToken::Text(text) => text.clone(),
+                FormatToken::Year => format!("{:04}", year),
+                FormatToken::ShortYear => format!("{:02}", year.rem_euclid(100)),
+                FormatToken::MonthName => MONTHS[month as usize - 1].into(),
+                FormatToken::ShortMonthName => MONTHS[month as usize - 1][..3].into(),
+                FormatToken::Month => month.to_string(),
+                FormatToken::PaddedMonth => format!("{:02}", month),
+                FormatToken::Date => day.to_string(),
+                FormatToken::PaddedDate => format!("{:02}", day),
+                FormatToken::DayName => WEEKDAYS[self.weekday() as usize - 1].into(),
+                FormatToken::ShortDayName => WEEKDAYS[self.weekday() as usize - 1][..3].into(),
+                FormatToken::Hour => hour.to_string(),
+                FormatToken::PaddedHour => format!("{:02}", hour),
+                FormatToken::Hour12 => hour12.to_string(),
+                FormatToken::PaddedHour12 => format!("{:02}", hour12),
+                FormatToken::Minute => format!("{:02}", self.time_of_day() / 60 % 60),
+                FormatToken::Second => format!("{:02}", self.time_of_day() % 60),
+                FormatToken::Meridiem if hour < 12 => "AM".into(),
+                FormatToken::Meridiem => "PM".into(),
+            };
+            output.push_str(&text);
+        }
+        output
+    }
+
+    fn days(&self) -> i64 {
+        self.seconds.div_euclid(86400)
+    }
+
+    fn time_of_day(&self) -> i64 {
+        self.seconds.rem_euclid(86400)
+    }
+
+    // From 1 for Monday to 7 for Sunday. 1970-01-01 was a Thursday.
+    fn weekday(&self) -> u32 {
+        ((self.days() + 3).rem_euclid(7) + 1) as u32
+    }
+
+    // Whole months count once the day and time of `start` have come around
+    // again, so January 31st to February 29th is still 0 months.
+    fn months_since(&self, start: &Date) -> i64 {
+        let (year, month, day) = civil_from_days(self.days());
+        let (start_year, start_month, start_day) = civil_from_days(start.days());
+        let months = (year - start_year) * 12 + month as i64 - start_month as i64;
+
+        let rest = (day, self.time_of_day());
+        let start_rest = (start_day, start.time_of_day());
+        if self >= start && rest < start_rest {
+            months - 1
+        } else if self < start && rest > start_rest {
+            months + 1
+        } else {
+            months
+        }
+    }
+}
+impl fmt::Display for Date {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.format(&parse_format(DATE_FORMAT)))
+    }
+}
+
+/// The units `dateAdd`, `dateSubtract` and `dateBetween` take, named in the
+/// plural as in Notion.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum DateUnit {
+    Years,
+    Months,
+    Weeks,
+    Days,
+    Hours,
+    Minutes,
+    Seconds,
+}
+impl DateUnit {
+    pub fn parse(name: &str) -> HandlerResult<DateUnit> {
+        match name {
+            "years" => Ok(DateUnit::Years),
+            "months" => Ok(DateUnit::Months),
+            "weeks" => Ok(DateUnit::Weeks),
+            "days" => Ok(DateUnit::Days),
+            "hours" => Ok(DateUnit::Hours),
+            "minutes" => Ok(DateUnit::Minutes),
+            "seconds" => Ok(DateUnit::Seconds),
+            _ => Err(SimpleError::new(format!(
+                "Unknown date unit: {}, expected years, months, weeks, days, hours, minutes or seconds",
+                name
+            ))),
+        }
+    }
+
+    /// The length of the unit, or `None` for months and years, which vary.
+    pub fn seconds(&self) -> Option<i64> {
+        match self {
+            DateUnit::Weeks => Some(604800),
+            DateUnit::Days => Some(86400),
+            DateUnit::Hours => Some(3600),
+            DateUnit::Minutes => Some(60),
+            DateUnit::Seconds => Some(1),
+            DateUnit::Years | DateUnit::Months => None,
+        }
+    }
+}
+
+/// The parts of a date that Notion's functions of the same name return.
+/// `Month` counts from 1 for January and `Day` from 1 for Monday.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum DateField {
+    Year,
+    Month,
+    Date,
+    Day,
+    Hour,
+    Minute,
+}
+impl DateField {
+    pub fn of_function(name: &str) -> Option<DateField> {
+        match name {
+            "year" => Some(DateField::Year),
+            "month" => Some(DateField::Month),
+            "date" => Some(DateField::Date),
+            "day" => Some(DateField::Day),
+            "hour" => Some(DateField::Hour),
+            "minute" => Some(DateField::Minute),
+            _ => None,
+        }
+    }
+}
+
+/// A piece of a `formatDate` format, which uses the same tokens as Notion,
+/// such as `MMMM D, YYYY` or `HH:mm`. Text in square brackets is kept as it
+/// is.
+#[derive(Debug, PartialEq, Clone)]
+pub enum FormatToken {
+    Text(String),
+    /// `YYYY`
+    Year,
+    /// `YY`
+    ShortYear,
+    /// `MMMM`, e.g. January
+    MonthName,
+    /// `MMM`, e.g. Jan
+    ShortMonthName,
+    /// `M`
+    Month,
+    /// `MM`
+    PaddedMonth,
+    /// `D`, the day of the month
+    Date,
+    /// `DD`
+    PaddedDate,
+    /// `dddd`, e.g. Monday
+    DayName,
+    /// `ddd`, e.g. Mon
+    ShortDayName,
+    /// `H`, from 0 to 23
+    Hour,
+    /// `HH`
+    PaddedHour,
+    /// `h`, from 1 to 12
+    Hour12,
+    /// `hh`
+    PaddedHour12,
+    /// `mm`
+    Minute,
+    /// `ss`
+    Second,
+    /// `A`, AM or PM
+    Meridiem,
+}
+
+// Longer tokens come first so that `MMMM` isn't read as `MM` twice.
+const TOKENS: &[(&str, FormatToken)] = &[
+    ("YYYY", FormatToken::Year),
+    ("YY", FormatToken::ShortYear),
+    ("MMMM", FormatToken::MonthName),
+    ("MMM", FormatToken::ShortMonthName),
+    ("MM", FormatToken::PaddedMonth),
+    ("M", FormatToken::Month),
+    ("DD", FormatToken::PaddedDate),
+    ("D", FormatToken::Date),
+    ("dddd", FormatToken::DayName),
+    ("ddd", FormatToken::ShortDayName),
+    ("HH", FormatToken::PaddedHour),
+    ("H", FormatToken::Hour),
+    ("hh", FormatToken::PaddedHour12),
+    ("h", FormatToken::Hour12),
+    ("mm", FormatToken::Minute),
+    ("ss", FormatToken::Second),
+    ("A", FormatToken::Meridiem),
+];
+
+pub fn parse_format(format: &str) -> Vec<FormatToken> {
+    let mut tokens = vec![];
+    let mut rest = format;
+
+    while let Some(c) = rest.chars().next() {
+        if c == '[' {
+            if let Some(end) = rest.find(']') {
+                push_text(&mut tokens, &rest[1..end]);
+                rest = &rest[end + 1..];
+                continue;
+            }
+        }
+
+        match TOKENS.iter().find(|(token, _)| rest.starts_with(token)) {
+            Some((token, format_token)) => {
+                tokens.push(format_token.clone());
+                rest = &rest[token.len()..];
+            }
+            None => {
+                push_text(&mut tokens, &rest[..c.len_utf8()]);
+                rest = &rest[c.len_utf8()..];
+            }
+        }
+    }
+    tokens
+}
+
+fn push_text(tokens: &mut Vec<FormatToken>, text: &str) {
+    match tokens.last_mut() {
+        Some(FormatToken::Text(last)) => last.push_str(text),
+        _ => tokens.push(FormatToken::Text(text.into())),
+    }
+}
+
+fn parse(text: &str) -> Option<Date> {
+    let mut rest = text;
+    let year = digits(&mut rest, 4)?;
+    let month = separated(&mut rest, '-', 2)?;
+    let day = separated(&mut rest, '-', 2)?;
+    if !(1..=12).contains(&month) || day < 1 || day > days_in_month(year, month as u32) as i64 {
+        return None;
+    }
+    let mut seconds = days_from_civil(year, month as u32, day as u32) * 86400;
+
+    if let Some(time) = rest.strip_prefix('T').or_else(|| rest.strip_prefix(' ')) {
+        rest = time;
+        let hour = digits(&mut rest, 2)?;
+        let minute = separated(&mut rest, ':', 2)?;
+        let second = separated(&mut rest, ':', 2).unwrap_or(0);
+        if let Some(fraction) = rest.strip_prefix('.') {
+            rest = fraction.trim_start_matches(|c: char| c.is_ascii_digit());
+        }
+        if hour > 23 || minute > 59 || second > 59 {
+            return None;
+        }
+        seconds += hour * 3600 + minute * 60 + second;
+
+        if let Some(utc) = rest.strip_prefix('Z') {
+            rest = utc;
+        } else if let Some(sign) = rest.chars().next().filter(|c| *c == '+' || *c == '-') {
+            rest = &rest[1..];
+            let offset = digits(&mut rest, 2)? * 3600 + separated(&mut rest, ':', 2)? * 60;
+            seconds -= if sign == '+' { offset } else { -offset };
+        }
+    }
+
+    match rest {
+        "" => Some(Date { seconds }),
+        _ => None,
+    }
+}
+
+fn digits(text: &mut &str, count: usize) -> Option<i64> {
+    let value = text.get(..count)?;
+    if !value.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    *text = &text[count..];
+    value.parse().ok()
+}
+
+fn separated(text: &mut &str, separator: char, count: usize) -> Option<i64> {
+    let mut rest = text.strip_prefix(separator)?;
+    let value = digits(&mut rest, count)?;
+    *text = rest;
+    Some(value)
+}
+
+fn days_in_month(year: i64, month: u32) -> u32 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+// Days since 1970-01-01 of a date in the proleptic Gregorian calendar, and
+// back, after Howard Hinnant's `days_from_civil` and `civil_from_days`.
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year.rem_euclid(400);
+    let month = month as i64;
+    let day_of_year =
+        (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + day as i64 - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
+
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let days = days + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days.rem_euclid(146097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * shifted_month + 2) / 5 + 1) as u32;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    } as u32;
+    let year = year_of_era + era * 400;
+    (if month <= 2 { year + 1 } else { year }, month, day)
+}
+
+#[cfg(test)]
+mod test;
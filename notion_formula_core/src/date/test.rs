@@ -0,0 +1,161 @@
+use super::*;
+
+fn date(text: &str) -> Date {
+    Date::parse(text).unwrap()
+}
+
+#[test]
+fn test_parses_dates_with_and_without_times() {
+    let cases = vec![
+        ("2021-05-01", "2021-05-01T00:00:00"),
+        ("2021-05-01T09:30", "2021-05-01T09:30:00"),
+        ("2021-05-01 09:30:15", "2021-05-01T09:30:15"),
+        ("2021-05-01T09:30:15.250Z", "2021-05-01T09:30:15"),
+        ("2021-05-01T01:30:00+02:00", "2021-04-30T23:30:00"),
+        ("2021-05-01T22:30:00-02:00", "2021-05-02T00:30:00"),
+        ("1969-12-31T23:59:59", "1969-12-31T23:59:59"),
+        ("2024-02-29", "2024-02-29T00:00:00"),
+    ];
+
+    for (input, expected) in cases {
+        assert_eq!(expected, date(input).to_string(), "{}", input);
+    }
+}
+
+#[test]
+fn test_rejects_invalid_dates() {
+    for input in &[
+        "",
+        "May 1, 2021",
+        "2021-5-1",
+        "2021-02-29",
+        "2021-13-01",
+        "2021-05-01T24:00",
+        "2021-05-01T09:30x",
+    ] {
+        assert_eq!(
+            format!("Invalid date: {}", input),
+            Date::parse(input).unwrap_err().to_string()
+        );
+    }
+}
+
+#[test]
+fn test_adds_units_and_stops_at_the_end_of_shorter_months() {
+    let cases = vec![
+        ("2021-01-31", 1.0, DateUnit::Months, "2021-02-28T00:00:00"),
+        (
+            "2024-01-31T10:00",
+            1.0,
+            DateUnit::Months,
+            "2024-02-29T10:00:00",
+        ),
+        ("2024-02-29", 1.0, DateUnit::Years, "2025-02-28T00:00:00"),
+        ("2021-03-31", -1.0, DateUnit::Months, "2021-02-28T00:00:00"),
+        ("2021-12-15", 1.0, DateUnit::Months, "2022-01-15T00:00:00"),
+        ("2021-05-01", 2.0, DateUnit::Weeks, "2021-05-15T00:00:00"),
+        ("2021-05-01", -1.0, DateUnit::Days, "2021-04-30T00:00:00"),
+        ("2021-05-01", 1.9, DateUnit::Days, "2021-05-02T00:00:00"),
+        (
+            "2021-05-01T23:30",
+            45.0,
+            DateUnit::Minutes,
+            "2021-05-02T00:15:00",
+        ),
+        ("2021-05-01", 90.0, DateUnit::Seconds, "2021-05-01T00:01:30"),
+    ];
+
+    for (input, amount, unit, expected) in cases {
+        assert_eq!(
+            expected,
+            date(input).add(amount, unit).to_string(),
+            "{} {:?}",
+            input,
+            unit
+        );
+    }
+}
+
+#[test]
+fn test_counts_whole_units_between_dates() {
+    let cases = vec![
+        ("2021-05-03", "2021-05-01", DateUnit::Days, 2.0),
+        ("2021-05-01", "2021-05-03", DateUnit::Days, -2.0),
+        ("2021-05-02T11:00", "2021-05-01T12:00", DateUnit::Days, 0.0),
+        ("2021-05-01T12:00", "2021-05-02T11:00", DateUnit::Days, 0.0),
+        ("2021-05-01T10:30", "2021-05-01T08:45", DateUnit::Hours, 1.0),
+        ("2021-05-22", "2021-05-01", DateUnit::Weeks, 3.0),
+        ("2024-02-29", "2024-01-31", DateUnit::Months, 0.0),
+        ("2024-03-31", "2024-01-31", DateUnit::Months, 2.0),
+        ("2024-01-31", "2024-03-31", DateUnit::Months, -2.0),
+        ("2024-01-30", "2024-03-31", DateUnit::Months, -2.0),
+        ("2024-01-31", "2021-02-01", DateUnit::Years, 2.0),
+        ("2021-02-01", "2024-01-31", DateUnit::Years, -2.0),
+    ];
+
+    for (end, start, unit, expected) in cases {
+        assert_eq!(
+            expected,
+            date(end).since(&date(start), unit),
+            "{} {} {:?}",
+            end,
+            start,
+            unit
+        );
+    }
+}
+
+#[test]
+fn test_reads_fields_and_timestamps() {
+    let sunday = date("2021-05-02T21:07:09");
+    let fields: Vec<f64> = [
+        DateField::Year,
+        DateField::Month,
+        DateField::Date,
+        DateField::Day,
+        DateField::Hour,
+        DateField::Minute,
+    ]
+    .iter()
+    .map(|field| sunday.field(*field))
+    .collect();
+
+    assert_eq!(vec![2021.0, 5.0, 2.0, 7.0, 21.0, 7.0], fields);
+    assert_eq!(1619989629000.0, sunday.timestamp());
+    assert_eq!(sunday, Date::from_timestamp(1619989629999.0));
+    assert_eq!(
+        "1969-12-31T23:59:59",
+        Date::from_timestamp(-1.0).to_string()
+    );
+}
+
+#[test]
+fn test_formats_with_notion_tokens() {
+    let cases = vec![
+        ("MMMM D, YYYY", "May 2, 2021"),
+        ("ddd, MMM DD YY", "Sun, May 02 21"),
+        ("dddd [at] h:mm A", "Sunday at 9:07 PM"),
+        ("M/D/YYYY HH:mm:ss", "5/2/2021 21:07:09"),
+        ("[YYYY] hh", "YYYY 09"),
+        ("Q [unclosed", "Q [unclosed"),
+    ];
+
+    for (format, expected) in cases {
+        assert_eq!(
+            expected,
+            date("2021-05-02T21:07:09").format(&parse_format(format)),
+            "{}",
+            format
+        );
+    }
+    assert_eq!("12 AM", date("2021-05-02").format(&parse_format("h A")));
+}
+
+#[test]
+fn test_rejects_unknown_units() {
+    assert_eq!(DateUnit::Weeks, DateUnit::parse("weeks").unwrap());
+    assert_eq!(
+        "Unknown date unit: day, expected years, months, weeks, days, hours, minutes or seconds",
+        DateUnit::parse("day").unwrap_err().to_string()
+    );
+}
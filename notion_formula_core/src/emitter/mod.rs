@@ -0,0 +1,167 @@
+use crate::parser::BooleanOperator;
+use crate::parser::ComparisonOperator;
+use crate::parser::Expression;
+use crate::parser::MathOperator;
+use crate::parser::UnaryOperator;
+use pipeline::HandlerResult;
+
+// Binding strength of each production in the parser, loosest first.
+const TERNARY: u8 = 0;
+const OR: u8 = 1;
+const AND: u8 = 2;
+const NOT: u8 = 3;
+const EQUALITY: u8 = 4;
+const RELATIONAL: u8 = 5;
+const ADDITIVE: u8 = 6;
+const MULTIPLICATIVE: u8 = 7;
+const PREFIX: u8 = 8;
+const EXPONENTIAL: u8 = 9;
+const ATOMIC: u8 = 10;
+
+/// Renders an expression back to source on a single line, using only the
+/// parentheses required by the parser's precedence rules.
+pub fn minify(input: Expression) -> HandlerResult<String> {
+    let mut output = String::new();
+    emit(&input, TERNARY, &mut output);
+    Ok(output)
+}
+
+pub(crate) fn precedence(input: &Expression) -> u8 {
+    use ComparisonOperator::*;
+    use MathOperator::*;
+
+    match input {
+        Expression::TernaryOp(_, _, _) => TERNARY,
+        Expression::BooleanOp(_, BooleanOperator::Or, _) => OR,
+        Expression::BooleanOp(_, BooleanOperator::And, _) => AND,
+        Expression::UnaryOp(UnaryOperator::Not, _) => NOT,
+        Expression::Comparison(_, Equals, _) | Expression::Comparison(_, NotEquals, _) => EQUALITY,
+        Expression::Comparison(_, _, _) => RELATIONAL,
+        Expression::BinaryOp(_, Add, _) | Expression::BinaryOp(_, Subtract, _) => ADDITIVE,
+        Expression::BinaryOp(_, Exponent, _) => EXPONENTIAL,
+        Expression::BinaryOp(_, _, _) => MULTIPLICATIVE,
+        Expression::UnaryOp(_, _) => PREFIX,
+        _ => ATOMIC,
+    }
+}
+
+fn emit(input: &Expression, minimum: u8, output: &mut String) {
+    let wrap = precedence(input) < minimum;
+    if wrap {
+        output.push('(');
+    }
+
+    match input {
+        Expression::TernaryOp(test, accept, reject) => {
+            emit(test, OR, output);
+            output.push('?');
+            emit(accept, OR, output);
+            output.push(':');
+            emit(reject, TERNARY, output);
+        }
+        Expression::BooleanOp(lhs, op, rhs) => {
+            let (word, left, right) = match op {
+                BooleanOperator::Or => ("or", OR, AND),
+                BooleanOperator::And => ("and", AND, NOT),
+            };
+            emit(lhs, left, output);
+            push_word(word, output);
+            emit(rhs, right, output);
+        }
+        Expression::UnaryOp(UnaryOperator::Not, operand) => {
+            push_word("not", output);
+            emit(operand, NOT, output);
+        }
+        Expression::UnaryOp(op, operand) => {
+            output.push(match op {
+                UnaryOperator::USub => '-',
+                _ => '+',
+            });
+            emit(operand, PREFIX, output);
+        }
+        Expression::Comparison(lhs, op, rhs) => {
+            let (symbol, left, right) = match op {
+                ComparisonOperator::Equals => ("==", EQUALITY, RELATIONAL),
+                ComparisonOperator::NotEquals => ("!=", EQUALITY, RELATIONAL),
+                ComparisonOperator::LessThan => ("<", ADDITIVE, ADDITIVE),
+                ComparisonOperator::LessThanEq => ("<=", ADDITIVE, ADDITIVE),
+                ComparisonOperator::GreaterThan => (">", ADDITIVE, ADDITIVE),
+                ComparisonOperator::GreaterThanEq => (">=", ADDITIVE, ADDITIVE),
+            };
+            emit(lhs, left, output);
+            output.push_str(symbol);
+            emit(rhs, right, output);
+        }
+        Expression::BinaryOp(lhs, op, rhs) => {
+            let (symbol, left, right) = match op {
+                MathOperator::Add => ('+', ADDITIVE, MULTIPLICATIVE),
+                MathOperator::Subtract => ('-', ADDITIVE, MULTIPLICATIVE),
+                MathOperator::Multiply => ('*', MULTIPLICATIVE, PREFIX),
+                MathOperator::Divide => ('/', MULTIPLICATIVE, PREFIX),
+                MathOperator::Mod => ('%', MULTIPLICATIVE, PREFIX),
+                MathOperator::Exponent => ('^', ATOMIC, EXPONENTIAL),
+            };
+            emit(lhs, left, output);
+            output.push(symbol);
+            emit(rhs, right, output);
+        }
+        Expression::Call(callee, args) => {
+            emit(callee, ATOMIC, output);
+            output.push('(');
+            for (i, arg) in args.iter().enumerate() {
+                if i > 0 {
+                    output.push(',');
+                }
+                emit(arg, TERNARY, output);
+            }
+            output.push(')');
+        }
+        Expression::Access(target, index) => {
+            emit(target, ATOMIC, output);
+            output.push('[');
+            emit(index, TERNARY, output);
+            output.push(']');
+        }
+        Expression::TableInstance(name, pairs) => {
+            push_word(name, output);
+            output.push('{');
+            for (i, pair) in pairs.iter().enumerate() {
+                if i > 0 {
+                    output.push(',');
+                }
+                output.push_str(&pair.key);
+                output.push(':');
+                emit(&pair.value, TERNARY, output);
+            }
+            output.push('}');
+        }
+        Expression::Identifier(value) | Expression::Number(value) => push_word(value, output),
+        Expression::Str(value) => output.push_str(value),
+        Expression::Bool(value) => push_word(if *value { "true" } else { "false" }, output),
+    }
+
+    if wrap {
+        output.push(')');
+    }
+}
+
+// Words only need a separating space when they would otherwise run into the
+// previous identifier, keyword or number.
+fn push_word(word: &str, output: &mut String) {
+    let needs_space = match (output.chars().last(), word.chars().next()) {
+        (Some(last), Some(first)) => is_word_char(last) && is_word_char(first),
+        _ => false,
+    };
+
+    if needs_space {
+        output.push(' ');
+    }
+    output.push_str(word);
+}
+
+fn is_word_char(value: char) -> bool {
+    value.is_ascii_alphanumeric() || value == '.'
+}
+
+#[cfg(test)]
+mod test;
@@ -0,0 +1,91 @@
+use super::*;
+use crate::parser::formula_parser;
+use crate::tokenizer::tokenizer;
+
+fn minify_source(input: &str) -> String {
+    let tokens = tokenizer(input.chars().collect()).unwrap();
+    let ast = formula_parser(tokens).unwrap();
+    minify(ast).unwrap()
+}
+
+#[test]
+fn test_strips_whitespace_and_comments() {
+    let result = minify_source("  1 +\n  2 // trailing\n /* block */ * foo ");
+
+    assert_eq!("1+2*foo", result);
+}
+
+#[test]
+fn test_removes_redundant_parentheses() {
+    let result = minify_source("((a * b)) + (c)");
+
+    assert_eq!("a*b+c", result);
+}
+
+#[test]
+fn test_keeps_parentheses_required_by_precedence() {
+    let result = minify_source("(a + b) * (c - d)");
+
+    assert_eq!("(a+b)*(c-d)", result);
+}
+
+#[test]
+fn test_keeps_parentheses_for_right_operand_of_left_associative_operators() {
+    let result = minify_source("a - (b - c) - d / (e * f)");
+
+    assert_eq!("a-(b-c)-d/(e*f)", result);
+}
+
+#[test]
+fn test_exponent_is_right_associative() {
+    assert_eq!("2^3^x", minify_source("2 ^ (3 ^ x)"));
+    assert_eq!("(2^3)^x", minify_source("(2 ^ 3) ^ x"));
+}
+
+#[test]
+fn test_prefix_operators_around_exponent() {
+    assert_eq!("-2^2", minify_source("-(2 ^ 2)"));
+    assert_eq!("(-2)^2", minify_source("(-2) ^ 2"));
+    assert_eq!("2^(-x)", minify_source("2 ^ (-x)"));
+}
+
+#[test]
+fn test_relational_operators_are_not_chained() {
+    let result = minify_source("(a < b) < c");
+
+    assert_eq!("(a<b)<c", result);
+}
+
+#[test]
+fn test_boolean_keywords_are_spaced() {
+    let result = minify_source("not (a) and (b or c) or not d");
+
+    assert_eq!("not a and(b or c)or not d", result);
+}
+
+#[test]
+fn test_ternary_expressions() {
+    assert_eq!("a?b:c?d:e", minify_source("a ? b : (c ? d : e)"));
+    assert_eq!("(a?b:c)?d:e", minify_source("(a ? b : c) ? d : e"));
+    assert_eq!("a or b?(c?d:e):f", minify_source("a or b ? (c ? d : e) : f"));
+}
+
+#[test]
+fn test_function_calls() {
+    let result = minify_source("if( prop(\"State\") == \"Done\", 1 + 2, (3) )");
+
+    assert_eq!("if(prop(\"State\")==\"Done\",1+2,3)", result);
+}
+
+#[test]
+fn test_minified_output_parses_to_the_same_tree() {
+    let source = "-(a + 1) * 2 ^ (-b) - (not c == d ? e : f) % 3";
+    let tokens = tokenizer(source.chars().collect()).unwrap();
+    let expected = formula_parser(tokens).unwrap();
+
+    let minified = minify_source(source);
+    let tokens = tokenizer(minified.chars().collect()).unwrap();
+    let result = formula_parser(tokens).unwrap();
+
+    assert_eq!(expected, result);
+}
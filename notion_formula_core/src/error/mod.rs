@@ -0,0 +1,165 @@
+use crate::tokenizer::Token;
+use std::error::Error;
+use std::fmt;
+
+/// A line and column in the source, both starting from 1 and counted in
+/// characters like the positions on `Token`.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+pub struct Position {
+    pub line: u32,
+    pub column: u32,
+}
+impl Position {
+    pub fn new(line: u32, column: u32) -> Self {
+        Position { line, column }
+    }
+
+    /// The position right after `text` when it starts here.
+    pub fn advance(self, text: &str) -> Self {
+        text.chars().fold(self, |position, c| match c {
+            '\n' => Position::new(position.line + 1, 1),
+            _ => Position::new(position.line, position.column + 1),
+        })
+    }
+}
+
+/// The part of the source from `start` up to, but not including, `end`.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Span {
+    pub start: Position,
+    pub end: Position,
+}
+impl Span {
+    pub fn new(start: Position, end: Position) -> Self {
+        Span { start, end }
+    }
+
+    pub fn of_text(start: Position, text: &str) -> Self {
+        Span::new(start, start.advance(text))
+    }
+
+    pub fn of_token(token: &Token) -> Self {
+        Span::of_text(Position::new(token.line, token.column), &token.token_type.lexeme())
+    }
+
+    /// From the start of the first token to the end of the last one.
+    pub fn of_tokens(tokens: &[Token]) -> Option<Self> {
+        match (tokens.first(), tokens.last()) {
+            (Some(first), Some(last)) => {
+                Some(Span::new(Span::of_token(first).start, Span::of_token(last).end))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// An error from any stage of handling a formula. Errors from the tokenizer
+/// and parser always know where they happened, while type and runtime
+/// errors only do when they come from a located statement, where they point
+/// at the expression that failed.
+#[derive(Debug, PartialEq, Clone)]
+pub enum FormulaError {
+    Lex { message: String, span: Option<Span> },
+    Parse { message: String, span: Option<Span> },
+    Type { message: String, span: Option<Span> },
+    Runtime { message: String, span: Option<Span> },
+}
+impl FormulaError {
+    pub fn lex(message: String, span: Span) -> Self {
+        FormulaError::Lex {
+            message,
+            span: Some(span),
+        }
+    }
+
+    pub fn parse(message: String, span: Span) -> Self {
+        FormulaError::Parse {
+            message,
+            span: Some(span),
+        }
+    }
+
+    /// Wraps an error from the typechecker, keeping the span of one it
+    /// already located.
+    pub fn type_error(error: Box<dyn Error>) -> Self {
+        match error.downcast::<FormulaError>() {
+            Ok(error) if matches!(*error, FormulaError::Type { .. }) => *error,
+            Ok(error) => FormulaError::Type {
+                message: error.to_string(),
+                span: None,
+            },
+            Err(error) => FormulaError::Type {
+                message: error.to_string(),
+                span: None,
+            },
+        }
+    }
+
+    /// Wraps an error from the interpreter, keeping the span of one it
+    /// already located.
+    pub fn runtime(error: Box<dyn Error>) -> Self {
+        match error.downcast::<FormulaError>() {
+            Ok(error) if matches!(*error, FormulaError::Runtime { .. }) => *error,
+            Ok(error) => FormulaError::Runtime {
+                message: error.to_string(),
+                span: None,
+            },
+            Err(error) => FormulaError::Runtime {
+                message: error.to_string(),
+                span: None,
+            },
+        }
+    }
+
+    /// The message without the position.
+    pub fn message(&self) -> &str {
+        match self {
+            FormulaError::Lex { message, .. }
+            | FormulaError::Parse { message, .. }
+            | FormulaError::Type { message, .. }
+            | FormulaError::Runtime { message, .. } => message,
+        }
+    }
+
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            FormulaError::Lex { span, .. }
+            | FormulaError::Parse { span, .. }
+            | FormulaError::Type { span, .. }
+            | FormulaError::Runtime { span, .. } => *span,
+        }
+    }
+
+    /// Points the error at `located` unless it already has a span.
+    pub fn or_span(mut self, located: Option<Span>) -> Self {
+        match &mut self {
+            FormulaError::Lex { span, .. }
+            | FormulaError::Parse { span, .. }
+            | FormulaError::Type { span, .. }
+            | FormulaError::Runtime { span, .. } => {
+                if span.is_none() {
+                    *span = located;
+                }
+            }
+        }
+        self
+    }
+}
+impl fmt::Display for FormulaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.span() {
+            Some(span) => write!(
+                f,
+                "{} on line: {}, column: {}",
+                self.message(),
+                span.start.line,
+                span.start.column
+            ),
+            None => write!(f, "{}", self.message()),
+        }
+    }
+}
+impl Error for FormulaError {}
+
+#[cfg(test)]
+mod test;
@@ -0,0 +1,82 @@
+use super::*;
+use crate::interpreter::run;
+use crate::parser::{formula_parser, located_document_parser};
+use crate::tokenizer::tokenizer;
+use crate::typechecker::TypeChecker;
+
+fn parse(input: &str) -> Result<crate::parser::Expression, FormulaError> {
+    formula_parser(tokenizer(input.chars().collect())?)
+}
+
+fn span(start: (u32, u32), end: (u32, u32)) -> Option<Span> {
+    Some(Span::new(
+        Position::new(start.0, start.1),
+        Position::new(end.0, end.1),
+    ))
+}
+
+#[test]
+fn test_lex_errors_point_at_the_lexeme() {
+    let error = tokenizer("1 +\n  # 2".chars().collect()).unwrap_err();
+
+    assert_eq!(
+        FormulaError::Lex {
+            message: "Unknown character found #".into(),
+            span: span((2, 3), (2, 4)),
+        },
+        error
+    );
+    assert_eq!("Unknown character found # on line: 2, column: 3", error.to_string());
+}
+
+#[test]
+fn test_unterminated_lexemes_span_to_the_end_of_the_input() {
+    let string = tokenizer("x + \"ab\nc".chars().collect()).unwrap_err();
+    let comment = tokenizer("1 /* two".chars().collect()).unwrap_err();
+
+    assert_eq!(span((1, 5), (2, 2)), string.span());
+    assert_eq!("Couldn't find the end of the string, missing '\"'", string.message());
+    assert_eq!(span((1, 3), (1, 9)), comment.span());
+}
+
+#[test]
+fn test_parse_errors_point_at_the_token() {
+    let error = parse("if(true, \"a\" \"b\")").unwrap_err();
+
+    match error {
+        FormulaError::Parse { message, span: Some(span) } => {
+            assert_eq!("Expected a closing parentheses in function call", message);
+            assert_eq!(Span::new(Position::new(1, 14), Position::new(1, 17)), span);
+        }
+        other => panic!("Expected a parse error, found {:?}", other),
+    }
+}
+
+#[test]
+fn test_type_and_runtime_errors() {
+    let mut checker = TypeChecker::new();
+    let tokens = tokenizer("print 1;\nprint 1 + true;".chars().collect()).unwrap();
+    let statements = located_document_parser(tokens).unwrap();
+
+    assert!(checker.check(&statements[0].statement).is_ok());
+    let error = checker.check_located(&statements[1]).unwrap_err();
+    assert!(matches!(error, FormulaError::Type { .. }));
+    assert_eq!(span((2, 7), (2, 15)), error.span());
+
+    let tokens = tokenizer("assert 1 == 2".chars().collect()).unwrap();
+    let error = run(located_document_parser(tokens).unwrap()).unwrap_err();
+    assert_eq!(
+        FormulaError::Runtime {
+            message: "Assertion failed: 1==2".into(),
+            span: span((1, 8), (1, 14)),
+        },
+        error
+    );
+}
+
+#[test]
+fn test_or_span_keeps_an_existing_span() {
+    let error = parse("1 +").unwrap_err();
+
+    assert_eq!(error.span(), error.clone().or_span(span((9, 9), (9, 10))).span());
+}
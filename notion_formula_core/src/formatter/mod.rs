@@ -0,0 +1,176 @@
+use crate::emitter::minify;
+use crate::parser::Expression;
+use crate::tokenizer::{lossless_tokenizer, LosslessToken, TokenType, Trivia};
+use pipeline::HandlerResult;
+
+const INDENT: &str = "    ";
+
+struct Frame {
+    indent: usize,
+    ternaries: usize,
+}
+
+/// Re-lays out source code with canonical spacing and indentation. Line
+/// breaks chosen by the author are kept, up to a single blank line, and so
+/// are comments, so the result always tokenizes exactly like the input.
+pub fn format(input: Vec<LosslessToken>) -> HandlerResult<String> {
+    let mut output = String::new();
+    let mut frames = vec![Frame {
+        indent: 0,
+        ternaries: 0,
+    }];
+    let mut line_indent = 0;
+    let mut previous: Option<TokenType> = None;
+    let mut previous_unary = false;
+    let mut trailing: Vec<Trivia> = vec![];
+
+    for token in input {
+        let mut newlines = 0;
+        let mut force_newline = false;
+
+        for trivia in trailing.iter().chain(token.leading.iter()) {
+            match trivia {
+                Trivia::Whitespace(text) => newlines += text.matches('\n').count(),
+                Trivia::Comment(text) => {
+                    if !output.is_empty() {
+                        if newlines > 0 || force_newline {
+                            push_newlines(&mut output, newlines);
+                            line_indent = frames.last().map_or(0, |frame| frame.indent);
+                            output.push_str(&INDENT.repeat(line_indent));
+                        } else {
+                            output.push(' ');
+                        }
+                    }
+                    output.push_str(text);
+
+                    newlines = 0;
+                    force_newline = text.starts_with("//");
+                }
+            }
+        }
+
+        let token_type = token.token.token_type;
+        if token_type == TokenType::Eof {
+            if !output.is_empty() {
+                output.push('\n');
+            }
+            break;
+        }
+
+        let closing = is_closing(&token_type);
+        if !output.is_empty() {
+            if newlines > 0 || force_newline {
+                push_newlines(&mut output, newlines);
+                let frame_indent = frames.last().map_or(0, |frame| frame.indent);
+                line_indent = if closing && frames.len() > 1 {
+                    frame_indent - 1
+                } else {
+                    frame_indent
+                };
+                output.push_str(&INDENT.repeat(line_indent));
+            } else if needs_space(previous.as_ref(), previous_unary, &token_type, &frames) {
+                output.push(' ');
+            }
+        }
+        output.push_str(&token.text);
+
+        match token_type {
+            TokenType::LeftParen | TokenType::LeftSquareBracket | TokenType::LeftBracket => {
+                frames.push(Frame {
+                    indent: line_indent + 1,
+                    ternaries: 0,
+                })
+            }
+            TokenType::RightParen | TokenType::RightSquareBracket | TokenType::RightBracket
+                if frames.len() > 1 =>
+            {
+                frames.pop();
+            }
+            TokenType::QuestionMark => {
+                if let Some(frame) = frames.last_mut() {
+                    frame.ternaries += 1;
+                }
+            }
+            TokenType::Colon => {
+                if let Some(frame) = frames.last_mut() {
+                    frame.ternaries = frame.ternaries.saturating_sub(1);
+                }
+            }
+            _ => (),
+        }
+
+        previous_unary = matches!(token_type, TokenType::Plus | TokenType::Minus)
+            && !previous.as_ref().is_some_and(ends_operand);
+        trailing = token.trailing;
+        previous = Some(token_type);
+    }
+
+    Ok(output)
+}
+
+/// Renders an expression as formatted source on a single line, for tools
+/// that rewrite the syntax tree.
+pub fn format_expression(input: &Expression) -> HandlerResult<String> {
+    let source = minify(input.clone())?;
+    let result = format(lossless_tokenizer(source.chars().collect())?)?;
+    Ok(result.trim_end().to_string())
+}
+
+fn push_newlines(output: &mut String, newlines: usize) {
+    output.push('\n');
+    if newlines > 1 {
+        output.push('\n');
+    }
+}
+
+// `previous_unary` marks a `+` or `-` used as a prefix operator, which sticks
+// to its operand.
+fn needs_space(
+    previous: Option<&TokenType>,
+    previous_unary: bool,
+    current: &TokenType,
+    frames: &[Frame],
+) -> bool {
+    use TokenType::*;
+
+    let previous = match previous {
+        Some(previous) => previous,
+        None => return false,
+    };
+
+    match (previous, current) {
+        (_, RightParen) | (_, RightSquareBracket) | (_, Comma) | (_, SemiColon) => false,
+        (LeftParen, _) | (LeftSquareBracket, _) => false,
+        (LeftBracket, RightBracket) => false,
+        (Identifier(_), LeftParen) => false,
+        (_, LeftSquareBracket) => !ends_operand(previous),
+        (_, Colon) => frames.last().is_some_and(|frame| frame.ternaries > 0),
+        (Minus, _) | (Plus, _) => !previous_unary,
+        _ => true,
+    }
+}
+
+fn ends_operand(token_type: &TokenType) -> bool {
+    use TokenType::*;
+    matches!(
+        token_type,
+        Identifier(_)
+            | StringLiteral(_)
+            | NumberLiteral(_)
+            | True
+            | False
+            | RightParen
+            | RightSquareBracket
+            | RightBracket
+    )
+}
+
+fn is_closing(token_type: &TokenType) -> bool {
+    matches!(
+        token_type,
+        TokenType::RightParen | TokenType::RightSquareBracket | TokenType::RightBracket
+    )
+}
+
+#[cfg(test)]
+mod test;
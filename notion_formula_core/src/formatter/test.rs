@@ -0,0 +1,71 @@
+use super::*;
+use crate::tokenizer::lossless_tokenizer;
+
+fn format_source(input: &str) -> String {
+    let tokens = lossless_tokenizer(input.chars().collect()).unwrap();
+    format(tokens).unwrap()
+}
+
+#[test]
+fn test_normalizes_spacing() {
+    let result = format_source("1+2*-3");
+
+    assert_eq!("1 + 2 * -3\n", result);
+}
+
+#[test]
+fn test_normalizes_calls_and_access() {
+    let result = format_source("if( prop( \"A\" )==\"\" ,1,2)  +  a [ \"b\" ]");
+
+    assert_eq!("if(prop(\"A\") == \"\", 1, 2) + a[\"b\"]\n", result);
+}
+
+#[test]
+fn test_keeps_ternary_colons_apart_from_pair_colons() {
+    let result = format_source("x?Users{\"a\":1}:z");
+
+    assert_eq!("x ? Users { \"a\": 1 } : z\n", result);
+}
+
+#[test]
+fn test_indents_nested_lines() {
+    let result = format_source("if(\nprop(\"State\") == \"🔵\",\nif(\ntrue,\n1,\n2\n),\n3\n)");
+
+    assert_eq!(
+        "if(\n    prop(\"State\") == \"🔵\",\n    if(\n        true,\n        1,\n        2\n    ),\n    3\n)\n",
+        result
+    );
+}
+
+#[test]
+fn test_keeps_comments() {
+    let result = format_source("// heading\n1+2 // trailing\n\n\n\n/* block */3");
+
+    assert_eq!("// heading\n1 + 2 // trailing\n\n/* block */ 3\n", result);
+}
+
+#[test]
+fn test_formatted_examples_are_unchanged() {
+    let sources = vec![
+        include_str!("../../tests/test_formula.notion"),
+        include_str!("../../tests/complex_example.notion"),
+    ];
+
+    for source in sources {
+        let result = format_source(source);
+
+        assert_eq!(source.trim_end(), result.trim_end());
+        assert_eq!(result, format_source(&result));
+    }
+}
+
+#[test]
+fn test_formats_expressions() {
+    let tokens = crate::tokenizer::tokenizer("if(a,-1,(2+3)*4)".chars().collect()).unwrap();
+    let expression = crate::parser::formula_parser(tokens).unwrap();
+
+    assert_eq!(
+        "if(a, -1, (2 + 3) * 4)",
+        format_expression(&expression).unwrap()
+    );
+}
@@ -0,0 +1,146 @@
+use super::RuntimeType;
+use super::RuntimeType::*;
+use crate::date::{parse_format, Date, DateField, DateUnit};
+use pipeline::{HandlerResult, SimpleError};
+use std::f64::consts;
+
+pub fn constant(name: &str) -> HandlerResult<RuntimeType> {
+    match name {
+        "e" => Ok(Num(consts::E)),
+        "pi" => Ok(Num(consts::PI)),
+        _ => Err(SimpleError::new(format!("Unknown identifier: {}", name))),
+    }
+}
+
+pub fn call(name: &str, args: Vec<RuntimeType>) -> HandlerResult<RuntimeType> {
+    match (name, args.as_slice()) {
+        ("empty", [value]) => Ok(Bool(match value {
+            Num(value) => *value == 0.0,
+            Str(value) => value.is_empty(),
+            Bool(value) => !value,
+            Record(_, _) => false,
+        })),
+        ("format", [value]) => Ok(Str(value.to_string())),
+        ("toNumber", [value]) => match value {
+            Num(value) => Ok(Num(*value)),
+            Str(value) => Ok(Num(value.trim().parse::<f64>()?)),
+            Bool(value) => Ok(Num(*value as u8 as f64)),
+            _ => Err(invalid_arguments(name, &args)),
+        },
+        ("length", [Str(value)]) => Ok(Num(value.chars().count() as f64)),
+        ("lower", [Str(value)]) => Ok(Str(value.to_lowercase())),
+        ("upper", [Str(value)]) => Ok(Str(value.to_uppercase())),
+        ("contains", [Str(value), Str(search)]) => Ok(Bool(value.contains(search.as_str()))),
+        ("replace", [Str(value), Str(search), Str(replacement)]) => {
+            Ok(Str(value.replacen(search.as_str(), replacement, 1)))
+        }
+        ("replaceAll", [Str(value), Str(search), Str(replacement)]) => {
+            Ok(Str(value.replace(search.as_str(), replacement)))
+        }
+        ("slice", [Str(value), Num(start)]) | ("substring", [Str(value), Num(start)]) => {
+            Ok(Str(slice(value, *start, None)))
+        }
+        ("slice", [Str(value), Num(start), Num(end)])
+        | ("substring", [Str(value), Num(start), Num(end)]) => {
+            Ok(Str(slice(value, *start, Some(*end))))
+        }
+        ("concat", _) if !args.is_empty() => {
+            let mut result = String::new();
+            for arg in &args {
+                match arg {
+                    Str(value) => result.push_str(value),
+                    _ => return Err(invalid_arguments(name, &args)),
+                }
+            }
+            Ok(Str(result))
+        }
+        ("join", [Str(separator), rest @ ..]) => {
+            let mut values = vec![];
+            for arg in rest {
+                match arg {
+                    Str(value) => values.push(value.as_str()),
+                    _ => return Err(invalid_arguments(name, &args)),
+                }
+            }
+            Ok(Str(values.join(separator)))
+        }
+        ("min", _) | ("max", _) if !args.is_empty() => {
+            let mut result = match args[0] {
+                Num(value) => value,
+                _ => return Err(invalid_arguments(name, &args)),
+            };
+            for arg in &args[1..] {
+                match (name, arg) {
+                    ("min", Num(value)) => result = result.min(*value),
+                    ("max", Num(value)) => result = result.max(*value),
+                    _ => return Err(invalid_arguments(name, &args)),
+                }
+            }
+            Ok(Num(result))
+        }
+        ("pow", [Num(base), Num(exponent)]) => Ok(Num(base.powf(*exponent))),
+        ("now", []) => Ok(Str(Date::now().to_string())),
+        ("dateAdd", [Str(date), Num(amount), Str(unit)]) => Ok(Str(Date::parse(date)?
+            .add(*amount, DateUnit::parse(unit)?)
+            .to_string())),
+        ("dateSubtract", [Str(date), Num(amount), Str(unit)]) => Ok(Str(Date::parse(date)?
+            .add(-amount, DateUnit::parse(unit)?)
+            .to_string())),
+        ("dateBetween", [Str(end), Str(start), Str(unit)]) => Ok(Num(
+            Date::parse(end)?.since(&Date::parse(start)?, DateUnit::parse(unit)?)
+        )),
+        ("formatDate", [Str(date), Str(format)]) => {
+            Ok(Str(Date::parse(date)?.format(&parse_format(format))))
+        }
+        ("parseDate", [Str(date)]) => Ok(Str(Date::parse(date)?.to_string())),
+        ("timestamp", [Str(date)]) => Ok(Num(Date::parse(date)?.timestamp())),
+        ("fromTimestamp", [Num(milliseconds)]) => {
+            Ok(Str(Date::from_timestamp(*milliseconds).to_string()))
+        }
+        (_, [Str(date)]) => match DateField::of_function(name) {
+            Some(field) => Ok(Num(Date::parse(date)?.field(field))),
+            None => Err(invalid_arguments(name, &args)),
+        },
+        (_, [Num(value)]) => {
+            let result = match name {
+                "abs" => value.abs(),
+                "ceil" => value.ceil(),
+                "floor" => value.floor(),
+                "round" => value.round(),
+                "sqrt" => value.sqrt(),
+                "cbrt" => value.cbrt(),
+                "exp" => value.exp(),
+                "ln" => value.ln(),
+                "log10" => value.log10(),
+                "log2" => value.log2(),
+                "sign" if *value == 0.0 => 0.0,
+                "sign" => value.signum(),
+                _ => return Err(invalid_arguments(name, &args)),
+            };
+            Ok(Num(result))
+        }
+        _ => Err(invalid_arguments(name, &args)),
+    }
+}
+
+fn slice(value: &str, start: f64, end: Option<f64>) -> String {
+    let length = value.chars().count();
+    let start = (start.max(0.0) as usize).min(length);
+    let end = end.map_or(length, |end| (end.max(0.0) as usize).min(length));
+
+    value
+        .chars()
+        .skip(start)
+        .take(end.saturating_sub(start))
+        .collect()
+}
+
+fn invalid_arguments(name: &str, args: &[RuntimeType]) -> Box<SimpleError> {
+    match crate::builtins::lookup(name) {
+        Some(builtin) => SimpleError::new(format!(
+            "Invalid arguments {:?} for {}, expected {}",
+            args, name, builtin.signature
+        )),
+        None => crate::builtins::unknown_function(name),
+    }
+}
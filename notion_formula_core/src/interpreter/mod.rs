@@ -1,181 +1,590 @@
+mod builtins;
+
+use crate::emitter::minify;
+use crate::error::FormulaError;
 use crate::parser::BooleanOperator;
 use crate::parser::ComparisonOperator;
 use crate::parser::Expression;
+use crate::parser::LocatedStatement;
 use crate::parser::MathOperator;
+use crate::parser::Pair;
+use crate::parser::Statement;
+use crate::parser::Type;
 use crate::parser::UnaryOperator;
 use pipeline::HandlerResult;
 use pipeline::SimpleError;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
 
-#[derive(Debug, PartialEq, PartialOrd)]
+/// With the `serde` feature, values serialize as `{"Num":1.0}`,
+/// `{"Str":"hello"}`, `{"Bool":true}` or
+/// `{"Record":["Users",[["name",{"Str":"Atlas"}]]]}`.
+#[derive(Debug, PartialEq, PartialOrd, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum RuntimeType {
     Num(f64),
     Str(String),
     Bool(bool),
+    Record(String, Vec<(String, RuntimeType)>),
 }
-
-fn visit_expression(input: Expression) -> HandlerResult<RuntimeType> {
-    use BooleanOperator::*;
-    use ComparisonOperator::*;
-    use MathOperator::*;
-    use UnaryOperator::*;
-    use RuntimeType::*;
-
-    match input {
-        Expression::BinaryOp(lhs, op, rhs) => {
-            let left_result = visit_expression(*lhs)?;
-            let right_result = visit_expression(*rhs)?;
-            let pair = (left_result, right_result);
-
-            match pair {
-                (Num(left_value), Num(right_value)) => {
-                    let result = match op {
-                        Add => left_value + right_value,
-                        Subtract => left_value - right_value,
-                        Mod => left_value % right_value,
-                        Multiply => left_value * right_value,
-                        Divide => left_value / right_value,
-                        Exponent => left_value.powf(right_value),
-                    };
-                    Ok(Num(result))
-                }
-                (Str(left_value), Str(right_value)) => {
-                    let result = match op {
-                        Add => format!("{}{}", left_value, right_value),
-                        _ => {
-                            return Err(SimpleError::new(format!(
-                                "Invalid value {:?}, for binary operation",
-                                left_value
-                            )))
-                        }
-                    };
-                    Ok(Str(result))
+impl fmt::Display for RuntimeType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RuntimeType::Num(value) => write!(f, "{}", value),
+            RuntimeType::Str(value) => write!(f, "{}", value),
+            RuntimeType::Bool(value) => write!(f, "{}", value),
+            RuntimeType::Record(name, values) => {
+                write!(f, "{} {{", name)?;
+                for (i, (key, value)) in values.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ",")?;
+                    }
+                    match value {
+                        RuntimeType::Str(value) => write!(f, " \"{}\": \"{}\"", key, value)?,
+                        _ => write!(f, " \"{}\": {}", key, value)?,
+                    }
                 }
-                _ => Err(SimpleError::new(format!(
-                    "Invalid values {:?}, {:?}, for binary operation",
-                    pair.0, pair.1
-                ))),
+                write!(f, " }}")
             }
         }
-        Expression::Comparison(lhs, op, rhs) => {
-            let left_result = visit_expression(*lhs)?;
-            let right_result = visit_expression(*rhs)?;
+    }
+}
 
-            if !is_same_type(&left_result, &right_result) {
-                return Err(SimpleError::new(format!(
-                    "Can't compare two values of diferent types: {:?} and {:?}",
-                    left_result, right_result
-                )));
+/// Property values of the row a formula is evaluated against, keyed by
+/// property name without quotes.
+pub type Props = HashMap<String, RuntimeType>;
+
+/// Executes statements while keeping table definitions, named formulas and
+/// `let` bindings alive between them.
+#[derive(Default)]
+pub struct Interpreter {
+    tables: HashMap<String, Vec<Pair<Type>>>,
+    formulas: HashMap<String, Expression>,
+    variables: HashMap<String, RuntimeType>,
+}
+impl Interpreter {
+    pub fn new() -> Self {
+        Interpreter::default()
+    }
+
+    /// Runs a single statement, returning the value of `print` statements
+    /// and bare expressions.
+    pub fn execute(&mut self, statement: Statement) -> Result<Option<RuntimeType>, FormulaError> {
+        self.execute_statement(&statement, None)
+    }
+
+    /// Like `execute`, but errors point at the expression that failed, or at
+    /// the statement when no expression did.
+    pub fn execute_located(
+        &mut self,
+        located: &LocatedStatement,
+    ) -> Result<Option<RuntimeType>, FormulaError> {
+        self.execute_statement(&located.statement, Some(located))
+            .map_err(|e| e.or_span(located.span()))
+    }
+
+    fn execute_statement(
+        &mut self,
+        statement: &Statement,
+        located: Option<&LocatedStatement>,
+    ) -> Result<Option<RuntimeType>, FormulaError> {
+        match statement {
+            Statement::TableDef(name, columns) => {
+                self.tables.insert(name.clone(), columns.clone());
+                Ok(None)
+            }
+            Statement::FormulaDef(name, expr) => {
+                self.formulas.insert(name.clone(), expr.clone());
+                Ok(None)
+            }
+            Statement::Assignment(name, expr) => {
+                let value = self.evaluate_located(expr, located)?;
+                self.variables.insert(name.clone(), value);
+                Ok(None)
             }
+            Statement::PrintStatement(expr) | Statement::ExpressionStatement(expr) => {
+                Ok(Some(self.evaluate_located(expr, located)?))
+            }
+            Statement::AssertStatement(expr) => match self.evaluate_located(expr, located)? {
+                RuntimeType::Bool(true) => Ok(None),
+                RuntimeType::Bool(false) => Err(FormulaError::Runtime {
+                    message: format!(
+                        "Assertion failed: {}",
+                        minify(expr.clone()).map_err(FormulaError::runtime)?
+                    ),
+                    span: located.and_then(|located| located.span_of(expr)),
+                }),
+                result => Err(FormulaError::Runtime {
+                    message: format!("Assertion needs to be a boolean: {:?}", result),
+                    span: located.and_then(|located| located.span_of(expr)),
+                }),
+            },
+        }
+    }
 
-            let result = match op {
-                Equals => left_result == right_result,
-                NotEquals => left_result != right_result,
-                LessThan => left_result < right_result,
-                LessThanEq => left_result <= right_result,
-                GreaterThan => left_result > right_result,
-                GreaterThanEq => left_result >= right_result,
-            };
+    pub fn evaluate(&self, input: &Expression) -> Result<RuntimeType, FormulaError> {
+        self.evaluate_located(input, None)
+    }
+
+    pub fn evaluate_with_props(
+        &self,
+        input: &Expression,
+        props: &Props,
+    ) -> Result<RuntimeType, FormulaError> {
+        let scope = Scope {
+            interpreter: self,
+            props: Some(props),
+            formulas: vec![],
+            tables: vec![],
+            located: None,
+        };
+        scope.visit_expression(input).map_err(FormulaError::runtime)
+    }
 
-            Ok(Bool(result))
+    pub fn variable(&self, name: &str) -> Option<&RuntimeType> {
+        self.variables.get(name)
+    }
+
+    fn evaluate_located(
+        &self,
+        input: &Expression,
+        located: Option<&LocatedStatement>,
+    ) -> Result<RuntimeType, FormulaError> {
+        let scope = Scope {
+            interpreter: self,
+            props: None,
+            formulas: vec![],
+            tables: vec![],
+            located,
+        };
+        scope.visit_expression(input).map_err(FormulaError::runtime)
+    }
+}
+
+struct Scope<'a> {
+    interpreter: &'a Interpreter,
+    props: Option<&'a Props>,
+    // Named formulas being evaluated, outermost first, so that a formula
+    // which refers back to itself fails instead of recursing forever.
+    formulas: Vec<&'a str>,
+    // Likewise for tables whose formula columns are being evaluated.
+    tables: Vec<&'a str>,
+    // The statement being executed. Formulas it uses are evaluated in
+    // scopes without one, so their errors point at where they are used.
+    located: Option<&'a LocatedStatement>,
+}
+impl<'a> Scope<'a> {
+    fn visit_expression(&self, input: &Expression) -> HandlerResult<RuntimeType> {
+        let result = self.evaluate_expression(input);
+        match (result, self.located) {
+            (Err(e), Some(located)) => {
+                Err(located.locate(FormulaError::runtime(e), input).into())
+            }
+            (result, _) => result,
         }
-        Expression::BooleanOp(lhs, op, rhs) => {
-            let left_result = visit_expression(*lhs)?;
-            let right_result = visit_expression(*rhs)?;
-            let pair = (left_result, right_result);
-
-            match pair {
-                (Bool(left_value), Bool(right_value)) => {
-                    let result = match op {
-                        And => left_value && right_value,
-                        Or => left_value || right_value,
-                    };
-                    Ok(Bool(result))
+    }
+
+    fn evaluate_expression(&self, input: &Expression) -> HandlerResult<RuntimeType> {
+        use BooleanOperator::*;
+        use ComparisonOperator::*;
+        use MathOperator::*;
+        use RuntimeType::*;
+        use UnaryOperator::*;
+
+        match input {
+            Expression::BinaryOp(lhs, op, rhs) => {
+                let left_result = self.visit_expression(lhs)?;
+                let right_result = self.visit_expression(rhs)?;
+                let pair = (left_result, right_result);
+
+                match pair {
+                    (Num(left_value), Num(right_value)) => {
+                        let result = match op {
+                            Add => left_value + right_value,
+                            Subtract => left_value - right_value,
+                            Mod => left_value % right_value,
+                            Multiply => left_value * right_value,
+                            Divide => left_value / right_value,
+                            Exponent => left_value.powf(right_value),
+                        };
+                        Ok(Num(result))
+                    }
+                    (Str(left_value), Str(right_value)) => {
+                        let result = match op {
+                            Add => format!("{}{}", left_value, right_value),
+                            _ => {
+                                return Err(SimpleError::new(format!(
+                                    "Invalid value {:?}, for binary operation",
+                                    left_value
+                                )))
+                            }
+                        };
+                        Ok(Str(result))
+                    }
+                    _ => Err(SimpleError::new(format!(
+                        "Invalid values {:?}, {:?}, for binary operation",
+                        pair.0, pair.1
+                    ))),
                 }
-                _ => Err(SimpleError::new(format!(
-                    "Boolean operations only accept booleans: {:?}, {:?}",
-                    pair.0, pair.1
-                ))),
             }
-        }
-        Expression::UnaryOp(op, rhs) => {
-            let result = visit_expression(*rhs)?;
-            match op {
-                UAdd => {
-                    match result {
+            Expression::Comparison(lhs, op, rhs) => {
+                let left_result = self.visit_expression(lhs)?;
+                let right_result = self.visit_expression(rhs)?;
+
+                if !is_same_type(&left_result, &right_result) {
+                    return Err(SimpleError::new(format!(
+                        "Can't compare two values of diferent types: {:?} and {:?}",
+                        left_result, right_result
+                    )));
+                }
+
+                let result = match op {
+                    Equals => left_result == right_result,
+                    NotEquals => left_result != right_result,
+                    LessThan => left_result < right_result,
+                    LessThanEq => left_result <= right_result,
+                    GreaterThan => left_result > right_result,
+                    GreaterThanEq => left_result >= right_result,
+                };
+
+                Ok(Bool(result))
+            }
+            Expression::BooleanOp(lhs, op, rhs) => {
+                let left_result = self.visit_expression(lhs)?;
+                let right_result = self.visit_expression(rhs)?;
+                let pair = (left_result, right_result);
+
+                match pair {
+                    (Bool(left_value), Bool(right_value)) => {
+                        let result = match op {
+                            And => left_value && right_value,
+                            Or => left_value || right_value,
+                        };
+                        Ok(Bool(result))
+                    }
+                    _ => Err(SimpleError::new(format!(
+                        "Boolean operations only accept booleans: {:?}, {:?}",
+                        pair.0, pair.1
+                    ))),
+                }
+            }
+            Expression::UnaryOp(op, rhs) => {
+                let result = self.visit_expression(rhs)?;
+                match op {
+                    UAdd => match result {
                         Str(value) => {
                             let result = value.parse::<f64>()?;
                             Ok(Num(result))
-                        },
+                        }
                         Bool(value) => Ok(Num(value as u8 as f64)),
-                        _ => Ok(result)
-                    }
-                }
-                USub => {
-                    match result {
+                        _ => Ok(result),
+                    },
+                    USub => match result {
                         Num(value) => Ok(Num(-value)),
                         _ => Err(SimpleError::new(format!(
                             "Can't use unary minus on non number values: {:?}",
                             result
-                        )))
-                    }
-                }
-                Not => {
-                    match result {
+                        ))),
+                    },
+                    Not => match result {
                         Bool(value) => Ok(Bool(!value)),
                         _ => Err(SimpleError::new(format!(
                             "Can't perform boolean operations on non boolean values: {:?}",
                             result
+                        ))),
+                    },
+                }
+            }
+            Expression::TernaryOp(test, accept, reject) => self.condition(test, accept, reject),
+            Expression::Call(callee, args) => {
+                let name = match callee.as_ref() {
+                    Expression::Identifier(name) => name,
+                    _ => {
+                        return Err(SimpleError::new(format!(
+                            "Only named functions can be called: {:?}",
+                            callee
                         )))
                     }
+                };
+
+                match (name.as_str(), args.as_slice()) {
+                    ("if", [test, accept, reject]) => self.condition(test, accept, reject),
+                    ("ifs", [_, _, _, ..]) if args.len() % 2 == 1 => self.conditions(args),
+                    ("prop", [name]) => match self.visit_expression(name)? {
+                        Str(name) => self.prop(&name),
+                        result => Err(SimpleError::new(format!(
+                            "Property names need to be text: {:?}",
+                            result
+                        ))),
+                    },
+                    _ => {
+                        let mut values = vec![];
+                        for arg in args {
+                            values.push(self.visit_expression(arg)?);
+                        }
+                        builtins::call(name, values)
+                    }
                 }
             }
+            Expression::Identifier(name) => {
+                if let Some(value) = self.interpreter.variables.get(name) {
+                    return Ok(value.clone());
+                }
+
+                if let Some((name, formula)) = self.interpreter.formulas.get_key_value(name) {
+                    return self.formula(name, formula);
+                }
+
+                builtins::constant(name)
+            }
+            Expression::Access(target, index) => {
+                let target = self.visit_expression(target)?;
+                let index = self.visit_expression(index)?;
+
+                match (target, index) {
+                    (Record(name, values), Str(key)) => values
+                        .into_iter()
+                        .find(|(column, _)| *column == key)
+                        .map(|(_, value)| value)
+                        .ok_or_else(|| {
+                            SimpleError::new(format!("{} has no column named {}", name, key)).into()
+                        }),
+                    pair => Err(SimpleError::new(format!(
+                        "Can only access records with text: {:?}, {:?}",
+                        pair.0, pair.1
+                    ))),
+                }
+            }
+            Expression::TableInstance(name, pairs) => self.table_instance(name, pairs),
+            Expression::Str(value) => Ok(Str(unquote(value))),
+            Expression::Number(value) => Ok(Num(value.parse::<f64>()?)),
+            Expression::Bool(value) => Ok(Bool(*value)),
         }
-        Expression::TernaryOp(test, accept, reject) => {
-            let test_result = visit_expression(*test)?;
-            let accept_result = visit_expression(*accept)?;
-            let reject_result = visit_expression(*reject)?;
-
-            match test_result {
-                Bool(test_value) => {
-                    if !is_same_type(&accept_result, &reject_result) {
-                        Err(SimpleError::new(format!(
-                            "Each branch of a condition must be the same type: {:?} and {:?}",
-                            accept_result, reject_result
-                        )))
-                    } else if test_value {
-                        Ok(accept_result)
-                    } else {
-                        Ok(reject_result)
-                    }
+    }
+
+    fn formula(&self, name: &'a str, formula: &Expression) -> HandlerResult<RuntimeType> {
+        check_cycle("Formula", &self.formulas, name)?;
+
+        let mut formulas = self.formulas.clone();
+        formulas.push(name);
+        let scope = Scope {
+            interpreter: self.interpreter,
+            props: self.props,
+            formulas,
+            tables: self.tables.clone(),
+            located: None,
+        };
+        scope.visit_expression(formula)
+    }
+
+    fn condition(
+        &self,
+        test: &Expression,
+        accept: &Expression,
+        reject: &Expression,
+    ) -> HandlerResult<RuntimeType> {
+        let test_result = self.visit_expression(test)?;
+        let accept_result = self.visit_expression(accept)?;
+        let reject_result = self.visit_expression(reject)?;
+
+        match test_result {
+            RuntimeType::Bool(test_value) => {
+                if !is_same_type(&accept_result, &reject_result) {
+                    Err(SimpleError::new(format!(
+                        "Each branch of a condition must be the same type: {:?} and {:?}",
+                        accept_result, reject_result
+                    )))
+                } else if test_value {
+                    Ok(accept_result)
+                } else {
+                    Ok(reject_result)
                 }
-                _ => Err(SimpleError::new(format!(
-                    "Result of test needs to be a boolean: {:?}",
-                    test_result
+            }
+            _ => Err(SimpleError::new(format!(
+                "Result of test needs to be a boolean: {:?}",
+                test_result
+            ))),
+        }
+    }
+
+    // Like `condition`, every argument is evaluated and has to typecheck.
+    fn conditions(&self, args: &[Expression]) -> HandlerResult<RuntimeType> {
+        let mut values = vec![];
+        for arg in args {
+            values.push(self.visit_expression(arg)?);
+        }
+
+        let otherwise = values.pop().unwrap();
+        let mut result = None;
+        for pair in values.chunks(2) {
+            let (test, value) = (&pair[0], &pair[1]);
+            if !is_same_type(value, &otherwise) {
+                return Err(SimpleError::new(format!(
+                    "Each branch of a condition must be the same type: {:?} and {:?}",
+                    value, otherwise
+                )));
+            }
+            match test {
+                RuntimeType::Bool(true) if result.is_none() => result = Some(value.clone()),
+                RuntimeType::Bool(_) => (),
+                _ => {
+                    return Err(SimpleError::new(format!(
+                        "Result of test needs to be a boolean: {:?}",
+                        test
+                    )))
+                }
+            }
+        }
+        Ok(result.unwrap_or(otherwise))
+    }
+
+    fn prop(&self, name: &str) -> HandlerResult<RuntimeType> {
+        let props = match self.props {
+            Some(props) => props,
+            None => {
+                return Err(SimpleError::new(format!(
+                    "Can't read property {} outside of a row",
+                    name
                 )))
             }
+        };
+
+        match props.get(name) {
+            Some(value) => Ok(value.clone()),
+            None => Err(SimpleError::new(format!("Unknown property: {}", name))),
         }
-        Expression::Call(_, _) => {
-            unimplemented!()
+    }
+
+    // Builds a row of the named table. Plain columns come from the given
+    // values and formula columns are then evaluated, in order, against them.
+    fn table_instance(&self, name: &str, pairs: &[Pair<Expression>]) -> HandlerResult<RuntimeType> {
+        let (name, columns) = match self.interpreter.tables.get_key_value(name) {
+            Some((name, columns)) => (name.as_str(), columns),
+            None => return Err(SimpleError::new(format!("Unknown table: {}", name))),
+        };
+        check_cycle("Table", &self.tables, name)?;
+
+        for pair in pairs {
+            let key = unquote(&pair.key);
+            if !columns.iter().any(|column| unquote(&column.key) == key) {
+                return Err(SimpleError::new(format!(
+                    "{} has no column named {}",
+                    name, key
+                )));
+            }
         }
-        Expression::Identifier(_) => {
-            unimplemented!()
+
+        let mut tables = self.tables.clone();
+        tables.push(name);
+        let mut props = Props::new();
+        let mut values = vec![];
+        for column in columns {
+            let key = unquote(&column.key);
+            let value = match &column.value {
+                Type::Formula(formula) => {
+                    let scope = Scope {
+                        interpreter: self.interpreter,
+                        props: Some(&props),
+                        formulas: self.formulas.clone(),
+                        tables: tables.clone(),
+                        located: None,
+                    };
+                    scope.visit_expression(formula)?
+                }
+                column_type => {
+                    let value = match pairs.iter().find(|pair| unquote(&pair.key) == key) {
+                        Some(pair) => self.visit_expression(&pair.value)?,
+                        None => {
+                            return Err(SimpleError::new(format!(
+                                "Missing value for column {} of {}",
+                                key, name
+                            )))
+                        }
+                    };
+                    check_column_type(&key, column_type, value)?
+                }
+            };
+
+            props.insert(key.clone(), value.clone());
+            values.push((key, value));
         }
-        Expression::Str(value) => Ok(Str(value)),
-        Expression::Number(value) => Ok(Num(value.parse::<f64>()?)),
-        Expression::Bool(value) => Ok(Bool(value)),
+
+        Ok(RuntimeType::Record(name.into(), values))
+    }
+}
+
+// Fails when `name` is already in `outer`, the formulas or tables being
+// evaluated, outermost first.
+fn check_cycle(kind: &str, outer: &[&str], name: &str) -> HandlerResult<()> {
+    match outer.iter().position(|outer| *outer == name) {
+        Some(start) => {
+            let mut cycle = outer[start..].to_vec();
+            cycle.push(name);
+            Err(SimpleError::new(format!(
+                "{} {} refers to itself: {}",
+                kind,
+                name,
+                cycle.join(" -> ")
+            )))
+        }
+        None => Ok(()),
+    }
+}
+
+fn check_column_type(key: &str, column_type: &Type, value: RuntimeType) -> HandlerResult<RuntimeType> {
+    match (column_type, &value) {
+        (Type::Str, RuntimeType::Str(_))
+        | (Type::Number, RuntimeType::Num(_))
+        | (Type::Bool, RuntimeType::Bool(_)) => Ok(value),
+        _ => Err(SimpleError::new(format!(
+            "Invalid value {:?} for column {} of type {:?}",
+            value, key, column_type
+        ))),
     }
 }
 
 fn is_same_type(a: &RuntimeType, b: &RuntimeType) -> bool {
     use RuntimeType::*;
     match (a, b) {
-        (Num(_), Num(_)) | (Str(_), Str(_)) | (Bool(_), Bool(_)) => true,
-        _ => false,
+        (Record(a, _), Record(b, _)) => a == b,
+        _ => matches!(
+            (a, b),
+            (Num(_), Num(_)) | (Str(_), Str(_)) | (Bool(_), Bool(_))
+        ),
+    }
+}
+
+/// Strips the surrounding quotes that string literals keep from the source.
+pub fn unquote(value: &str) -> String {
+    if value.len() >= 2 && value.starts_with('"') && value.ends_with('"') {
+        value[1..value.len() - 1].into()
+    } else {
+        value.into()
     }
 }
 
-pub fn interpret(input: Expression) -> HandlerResult<RuntimeType> {
-    visit_expression(input)
+pub fn interpret(input: Expression) -> Result<RuntimeType, FormulaError> {
+    Interpreter::new().evaluate(&input)
+}
+
+/// Executes every statement of the document, returning the printed values.
+/// Bare expressions are evaluated but, unlike in the REPL, not printed.
+/// Errors point at the expression that failed.
+pub fn run(input: Vec<LocatedStatement>) -> Result<Vec<RuntimeType>, FormulaError> {
+    let mut interpreter = Interpreter::new();
+    let mut output = vec![];
+
+    for located in input {
+        let printed = matches!(located.statement, Statement::PrintStatement(_));
+        match interpreter.execute_located(&located)? {
+            Some(value) if printed => output.push(value),
+            _ => (),
+        }
+    }
+
+    Ok(output)
 }
 
 #[cfg(test)]
@@ -171,3 +171,303 @@ fn test_ternary_operation() {
 
     assert_eq!(RuntimeType::Str("Beans".into()), result);
 }
+
+fn parse_statements(input: &str) -> Vec<LocatedStatement> {
+    let tokens = crate::tokenizer::tokenizer(input.chars().collect()).unwrap();
+    crate::parser::located_document_parser(tokens).unwrap()
+}
+
+fn parse_formula(input: &str) -> Expression {
+    let tokens = crate::tokenizer::tokenizer(input.chars().collect()).unwrap();
+    crate::parser::formula_parser(tokens).unwrap()
+}
+
+#[test]
+fn test_string_literals_are_unquoted() {
+    let result = interpret(parse_formula("\"hello\" + \", world\"")).unwrap();
+
+    assert_eq!(RuntimeType::Str("hello, world".into()), result);
+}
+
+#[test]
+fn test_builtin_functions() {
+    let cases = vec![
+        ("if(1 > 2, \"a\", \"b\")", RuntimeType::Str("b".into())),
+        ("ifs(1 > 2, \"a\", 2 > 1, \"b\", \"c\")", RuntimeType::Str("b".into())),
+        ("ifs(false, 1, false, 2, 3)", RuntimeType::Num(3.0)),
+        ("empty(\"\")", RuntimeType::Bool(true)),
+        ("length(\"hello\")", RuntimeType::Num(5.0)),
+        ("format(1.5) + \"!\"", RuntimeType::Str("1.5!".into())),
+        ("toNumber(\"42\")", RuntimeType::Num(42.0)),
+        ("concat(\"a\", \"b\", \"c\")", RuntimeType::Str("abc".into())),
+        ("join(\"-\", \"a\", \"b\")", RuntimeType::Str("a-b".into())),
+        ("contains(\"hello\", \"ell\")", RuntimeType::Bool(true)),
+        ("replaceAll(\"a.b.c\", \".\", \"/\")", RuntimeType::Str("a/b/c".into())),
+        ("upper(slice(\"hello\", 1, 3))", RuntimeType::Str("EL".into())),
+        ("substring(\"hello\", 3)", RuntimeType::Str("lo".into())),
+        ("max(1, 5, 3) - min(4, 2)", RuntimeType::Num(3.0)),
+        ("round(2.5) + abs(-1)", RuntimeType::Num(4.0)),
+        ("sign(0)", RuntimeType::Num(0.0)),
+        ("floor(pi)", RuntimeType::Num(3.0)),
+    ];
+
+    for (source, expected) in cases {
+        assert_eq!(expected, interpret(parse_formula(source)).unwrap(), "{}", source);
+    }
+}
+
+#[test]
+fn test_date_functions_read_and_write_text() {
+    let cases = vec![
+        (
+            "dateAdd(\"2021-01-31\", 1, \"months\")",
+            RuntimeType::Str("2021-02-28T00:00:00".into()),
+        ),
+        (
+            "dateSubtract(\"2021-05-01T09:30\", 90, \"minutes\")",
+            RuntimeType::Str("2021-05-01T08:00:00".into()),
+        ),
+        (
+            "dateBetween(\"2021-05-03\", \"2021-05-01T12:00\", \"days\")",
+            RuntimeType::Num(1.0),
+        ),
+        (
+            "formatDate(\"2021-05-02T21:07\", \"dddd, MMMM D [at] h:mm A\")",
+            RuntimeType::Str("Sunday, May 2 at 9:07 PM".into()),
+        ),
+        (
+            "parseDate(\"2021-05-02 09:30:00.5+00:00\")",
+            RuntimeType::Str("2021-05-02T09:30:00".into()),
+        ),
+        ("year(\"2021-05-02\") + month(\"2021-05-02\")", RuntimeType::Num(2026.0)),
+        ("date(\"2021-05-02\") + day(\"2021-05-02\")", RuntimeType::Num(9.0)),
+        ("hour(\"2021-05-02T21:07\") + minute(\"2021-05-02T21:07\")", RuntimeType::Num(28.0)),
+        (
+            "fromTimestamp(timestamp(\"2021-05-02T21:07\") + 1000)",
+            RuntimeType::Str("2021-05-02T21:07:01".into()),
+        ),
+        (
+            "dateBetween(now(), now(), \"seconds\") <= 1",
+            RuntimeType::Bool(true),
+        ),
+    ];
+
+    for (source, expected) in cases {
+        assert_eq!(expected, interpret(parse_formula(source)).unwrap(), "{}", source);
+    }
+
+    let cases = vec![
+        ("year(\"May 2\")", "Invalid date: May 2"),
+        (
+            "dateAdd(\"2021-05-02\", 1, \"day\")",
+            "Unknown date unit: day, expected years, months, weeks, days, hours, minutes or seconds",
+        ),
+    ];
+    for (source, expected) in cases {
+        let result = interpret(parse_formula(source)).unwrap_err();
+        assert_eq!(expected, result.to_string(), "{}", source);
+    }
+}
+
+#[test]
+fn test_builtin_functions_reject_invalid_arguments() {
+    let result = format!("{}", interpret(parse_formula("length(1)")).unwrap_err());
+
+    assert_eq!(
+        "Invalid arguments [Num(1.0)] for length, expected length(text: Text) -> Number",
+        result
+    );
+}
+
+#[test]
+fn test_prop_reads_from_props() {
+    let mut props = Props::new();
+    props.insert("Name".into(), RuntimeType::Str("Atlas".into()));
+
+    let input = parse_formula("\"Hi \" + prop(\"Name\")");
+    let result = Interpreter::new().evaluate_with_props(&input, &props).unwrap();
+
+    assert_eq!(RuntimeType::Str("Hi Atlas".into()), result);
+}
+
+#[test]
+fn test_prop_outside_of_a_row_fails() {
+    let result = interpret(parse_formula("prop(\"Name\")"));
+
+    assert!(result.is_err());
+}
+
+#[test]
+fn test_run_document() {
+    let document = parse_statements(
+        "
+        table Users {
+            \"name\": Text,
+            \"age\": Number,
+            \"test\": formula {
+                prop(\"name\") + \" \" + format(prop(\"age\"))
+            }
+        }
+
+        formula Adult { prop(\"age\") >= 18 }
+
+        let u0 = Users { \"name\": \"Atlas\", \"age\": 0 };
+        let u1 = Users { \"name\": \"Josh\", \"age\": 29 };
+
+        assert u1[\"age\"] > u0[\"age\"]
+        print u0[\"test\"]
+        print u1
+        ",
+    );
+    let result = run(document).unwrap();
+
+    assert_eq!(
+        vec![
+            RuntimeType::Str("Atlas 0".into()),
+            RuntimeType::Record(
+                "Users".into(),
+                vec![
+                    ("name".into(), RuntimeType::Str("Josh".into())),
+                    ("age".into(), RuntimeType::Num(29.0)),
+                    ("test".into(), RuntimeType::Str("Josh 29".into())),
+                ]
+            )
+        ],
+        result
+    );
+    assert_eq!(
+        "Users { \"name\": \"Josh\", \"age\": 29, \"test\": \"Josh 29\" }",
+        result[1].to_string()
+    );
+}
+
+#[test]
+fn test_named_formulas_use_the_current_row() {
+    let document = parse_statements(
+        "
+        formula Greeting { \"Hi \" + prop(\"name\") }
+        table Users { \"name\": Text, \"greeting\": formula { Greeting } }
+        print Users { \"name\": \"Atlas\" }[\"greeting\"]
+        ",
+    );
+    let result = run(document).unwrap();
+
+    assert_eq!(vec![RuntimeType::Str("Hi Atlas".into())], result);
+}
+
+#[test]
+fn test_failed_assertion() {
+    let result = run(parse_statements("let x = 1\nassert x + 1 == 3"));
+
+    assert_eq!(
+        "Assertion failed: x+1==3 on line: 2, column: 8",
+        format!("{}", result.unwrap_err())
+    );
+}
+
+#[test]
+fn test_run_errors_point_at_the_failing_expression() {
+    let cases = vec![
+        (
+            "let x = 1\nprint 2 * (x + \"a\")",
+            "Invalid values Num(1.0), Str(\"a\"), for binary operation on line: 2, column: 12",
+        ),
+        (
+            "formula f { 1 + true }\nlet y = 2\nprint y + f",
+            "Invalid values Num(1.0), Bool(true), for binary operation on line: 3, column: 11",
+        ),
+        (
+            "print upper(dateAdd(\"2021-05-01\", 1, \"day\"))",
+            "Unknown date unit: day, expected years, months, weeks, days, hours, minutes or seconds \
+             on line: 1, column: 13",
+        ),
+    ];
+
+    for (source, expected) in cases {
+        let result = run(parse_statements(source));
+        assert_eq!(expected, result.unwrap_err().to_string(), "{}", source);
+    }
+}
+
+#[test]
+fn test_formulas_referring_to_themselves_fail() {
+    let cases = vec![
+        (
+            "formula f { f + 1 }\nprint f",
+            "Formula f refers to itself: f -> f on line: 2, column: 7",
+        ),
+        (
+            "formula a { b }\nformula b { c * 2 }\nformula c { a }\nformula d { a }\nprint d",
+            "Formula a refers to itself: a -> b -> c -> a on line: 5, column: 7",
+        ),
+    ];
+
+    for (source, expected) in cases {
+        let result = run(parse_statements(source));
+        assert_eq!(expected, format!("{}", result.unwrap_err()));
+    }
+
+    let result = run(parse_statements("formula a { 1 }\nformula b { a + a }\nprint b"));
+    assert_eq!(vec![RuntimeType::Num(2.0)], result.unwrap());
+}
+
+#[test]
+fn test_tables_creating_rows_of_themselves_fail() {
+    let result = run(parse_statements(
+        "table T { \"a\": Number, \"f\": formula { T { \"a\": 1 }[\"a\"] } }\nprint T { \"a\": 2 }[\"a\"]",
+    ));
+
+    assert_eq!(
+        "Table T refers to itself: T -> T on line: 2, column: 7",
+        result.unwrap_err().to_string()
+    );
+
+    let result = run(parse_statements(
+        "table U { \"b\": Number }\ntable T { \"a\": Number, \"f\": formula { U { \"b\": 1 }[\"b\"] } }\nprint T { \"a\": 2 }[\"f\"]",
+    ));
+    assert_eq!(vec![RuntimeType::Num(1.0)], result.unwrap());
+}
+
+#[test]
+fn test_run_only_returns_printed_values() {
+    let result = run(parse_statements("let x = 1\nx + 41\nprint \"hi\""));
+
+    assert_eq!(vec![RuntimeType::Str("hi".into())], result.unwrap());
+
+    let mut interpreter = Interpreter::new();
+    let statement = parse_statements("1 + 41").remove(0).statement;
+    assert_eq!(Some(RuntimeType::Num(42.0)), interpreter.execute(statement).unwrap());
+}
+
+#[test]
+fn test_table_instances_are_validated() {
+    let table = "table T { \"a\": Number, \"b\": formula { prop(\"a\") } }\n";
+
+    let cases = vec![
+        ("print T { \"a\": \"x\" }", "Invalid value Str(\"x\") for column a of type Number"),
+        ("print T { }", "Missing value for column a of T"),
+        ("print T { \"a\": 1, \"c\": 2 }", "T has no column named c"),
+        ("print U { }", "Unknown table: U"),
+    ];
+
+    for (source, expected) in cases {
+        let result = run(parse_statements(&format!("{}{}", table, source)));
+        assert_eq!(
+            format!("{} on line: 2, column: 7", expected),
+            result.unwrap_err().to_string()
+        );
+    }
+}
+
+#[test]
+fn test_interpreter_keeps_bindings_between_statements() {
+    let mut interpreter = Interpreter::new();
+    for located in parse_statements("let x = 2\nlet y = x * 3") {
+        interpreter.execute(located.statement).unwrap();
+    }
+
+    let result = interpreter.evaluate(&parse_formula("x + y")).unwrap();
+
+    assert_eq!(RuntimeType::Num(8.0), result);
+    assert_eq!(Some(&RuntimeType::Num(6.0)), interpreter.variable("y"));
+}
@@ -0,0 +1,237 @@
+mod runtime;
+
+use crate::interpreter::unquote;
+use crate::parser::BooleanOperator;
+use crate::parser::ComparisonOperator;
+use crate::parser::Expression;
+use crate::parser::MathOperator;
+use crate::parser::UnaryOperator;
+use crate::typechecker::TypeChecker;
+use pipeline::HandlerResult;
+use pipeline::SimpleError;
+use std::cell::RefCell;
+
+/// Compiles a formula into a self-contained JavaScript function
+/// `(props) => value`, where `props` maps property names to numbers, strings
+/// and booleans. The formula is typechecked first, with every property typed
+/// `Any`, so calls with the wrong arguments fail here rather than in the
+/// browser. The function carries the part of the runtime it needs, so type
+/// errors in property values are thrown the same way the interpreter reports
+/// them.
+///
+/// Like the interpreter, date properties are passed in as text and date
+/// functions work on it in UTC, see `date::Date`.
+pub fn compile(input: &Expression) -> HandlerResult<String> {
+    TypeChecker::new().infer(input)?;
+
+    let compiler = Compiler {
+        helpers: RefCell::new(vec![]),
+    };
+    let body = compiler.visit_expression(input)?;
+    let helpers = compiler.helpers.into_inner();
+
+    let mut output = String::from("(props) => {\n    const $ = {\n");
+    for helper in runtime::HELPERS {
+        if helpers.contains(&helper.name) {
+            for line in helper.source.lines() {
+                output.push_str("        ");
+                output.push_str(line);
+                output.push('\n');
+            }
+            output.truncate(output.len() - 1);
+            output.push_str(",\n");
+        }
+    }
+    output.push_str("    };\n    return ");
+    output.push_str(&body);
+    output.push_str(";\n};\n");
+    Ok(output)
+}
+
+struct Compiler {
+    helpers: RefCell<Vec<&'static str>>,
+}
+impl Compiler {
+    fn visit_expression(&self, input: &Expression) -> HandlerResult<String> {
+        match input {
+            // `1e400` reads as infinity, which would print as `inf`.
+            Expression::Number(value) => match value.parse::<f64>() {
+                Ok(number) if number.is_finite() => Ok(format!("{}", number)),
+                _ => Err(SimpleError::new(format!("Invalid number: {}", value))),
+            },
+            Expression::Str(value) => Ok(string_literal(&unquote(value))),
+            Expression::Bool(value) => Ok(value.to_string()),
+            Expression::Identifier(name) => match name.as_str() {
+                "pi" => Ok("Math.PI".into()),
+                "e" => Ok("Math.E".into()),
+                _ => Err(SimpleError::new(format!("Unknown identifier: {}", name))),
+            },
+            Expression::BinaryOp(lhs, op, rhs) => {
+                let (left, right) = (self.visit_expression(lhs)?, self.visit_expression(rhs)?);
+                match op {
+                    MathOperator::Add => Ok(self.helper("add", &[left, right])),
+                    MathOperator::Subtract => Ok(self.arithmetic(&left, "-", &right)),
+                    MathOperator::Multiply => Ok(self.arithmetic(&left, "*", &right)),
+                    MathOperator::Divide => Ok(self.arithmetic(&left, "/", &right)),
+                    MathOperator::Mod => Ok(self.arithmetic(&left, "%", &right)),
+                    MathOperator::Exponent => Ok(format!(
+                        "Math.pow({}, {})",
+                        self.number(&left, "^"),
+                        self.number(&right, "^")
+                    )),
+                }
+            }
+            Expression::Comparison(lhs, op, rhs) => {
+                let name = match op {
+                    ComparisonOperator::Equals => "eq",
+                    ComparisonOperator::NotEquals => "ne",
+                    ComparisonOperator::LessThan => "lt",
+                    ComparisonOperator::GreaterThan => "gt",
+                    ComparisonOperator::LessThanEq => "le",
+                    ComparisonOperator::GreaterThanEq => "ge",
+                };
+                let args = [self.visit_expression(lhs)?, self.visit_expression(rhs)?];
+                Ok(self.helper(name, &args))
+            }
+            // Notion evaluates both sides, so a type error on the right isn't
+            // hidden by short-circuiting.
+            Expression::BooleanOp(lhs, op, rhs) => {
+                let name = match op {
+                    BooleanOperator::And => "and",
+                    BooleanOperator::Or => "or",
+                };
+                let args = [self.visit_expression(lhs)?, self.visit_expression(rhs)?];
+                Ok(self.helper(name, &args))
+            }
+            Expression::UnaryOp(op, operand) => {
+                let operand = self.visit_expression(operand)?;
+                match op {
+                    UnaryOperator::Not => Ok(self.helper("not", &[operand])),
+                    // `--5` would be a decrement, so negative operands are
+                    // wrapped in parentheses.
+                    UnaryOperator::USub => match self.number(&operand, "-") {
+                        operand if operand.starts_with('-') => Ok(format!("-({})", operand)),
+                        operand => Ok(format!("-{}", operand)),
+                    },
+                    UnaryOperator::UAdd => Ok(self.helper("plus", &[operand])),
+                }
+            }
+            Expression::TernaryOp(test, accept, reject) => self.condition(test, accept, reject),
+            Expression::Call(callee, args) => match callee.as_ref() {
+                Expression::Identifier(name) => self.call(name, args),
+                _ => Err(SimpleError::new(format!("Can't call {:?}", callee))),
+            },
+            Expression::Access(_, _) | Expression::TableInstance(_, _) => Err(SimpleError::new(
+                "Table rows can't be compiled to JavaScript".into(),
+            )),
+        }
+    }
+
+    fn condition(
+        &self,
+        test: &Expression,
+        accept: &Expression,
+        reject: &Expression,
+    ) -> HandlerResult<String> {
+        let args = [
+            self.visit_expression(test)?,
+            self.visit_expression(accept)?,
+            self.visit_expression(reject)?,
+        ];
+        Ok(self.helper("cond", &args))
+    }
+
+    fn call(&self, name: &str, args: &[Expression]) -> HandlerResult<String> {
+        match (name, args) {
+            ("if", [test, accept, reject]) => return self.condition(test, accept, reject),
+            ("ifs", [test, accept, rest @ ..]) if rest.len() % 2 == 1 => {
+                let reject = match rest {
+                    [otherwise] => otherwise.clone(),
+                    _ => Expression::Call(
+                        Box::new(Expression::Identifier("ifs".into())),
+                        rest.to_vec(),
+                    ),
+                };
+                return self.condition(test, accept, &reject);
+            }
+            ("prop", [key]) => {
+                let key = self.visit_expression(key)?;
+                return Ok(self.helper("prop", &["props".into(), key]));
+            }
+            _ => (),
+        }
+
+        let name = match (crate::builtins::lookup(name), runtime::lookup(name)) {
+            (Some(_), Some(helper)) if !["if", "ifs", "prop"].contains(&name) => helper.name,
+            (Some(builtin), _) => {
+                return Err(SimpleError::new(format!(
+                    "Invalid arguments for {}, expected {}",
+                    name, builtin.signature
+                )))
+            }
+            (None, _) => return Err(crate::builtins::unknown_function(name)),
+        };
+        let args = args
+            .iter()
+            .map(|arg| self.visit_expression(arg))
+            .collect::<HandlerResult<Vec<String>>>()?;
+        Ok(self.helper(name, &args))
+    }
+
+    fn arithmetic(&self, left: &str, op: &str, right: &str) -> String {
+        format!(
+            "({} {} {})",
+            self.number(left, op),
+            op,
+            self.number(right, op)
+        )
+    }
+
+    // Number literals don't need checking at runtime.
+    fn number(&self, value: &str, name: &str) -> String {
+        if value.parse::<f64>().is_ok() {
+            return value.into();
+        }
+        self.helper("num", &[value.into(), string_literal(name)])
+    }
+
+    /// Calls a runtime helper, marking it and its dependencies as used.
+    fn helper(&self, name: &'static str, args: &[String]) -> String {
+        self.require(name);
+        format!("$.{}({})", name, args.join(", "))
+    }
+
+    fn require(&self, name: &'static str) {
+        if self.helpers.borrow().contains(&name) {
+            return;
+        }
+        self.helpers.borrow_mut().push(name);
+        if let Some(helper) = runtime::lookup(name) {
+            for dependency in helper.dependencies {
+                self.require(dependency);
+            }
+        }
+    }
+}
+
+fn string_literal(value: &str) -> String {
+    let mut output = String::from("\"");
+    for c in value.chars() {
+        match c {
+            '"' => output.push_str("\\\""),
+            '\\' => output.push_str("\\\\"),
+            '\n' => output.push_str("\\n"),
+            '\r' => output.push_str("\\r"),
+            '\t' => output.push_str("\\t"),
+            '\u{2028}' => output.push_str("\\u2028"),
+            '\u{2029}' => output.push_str("\\u2029"),
+            c if c.is_control() => output.push_str(&format!("\\u{:04x}", c as u32)),
+            c => output.push(c),
+        }
+    }
+    output.push('"');
+    output
+}
+
+#[cfg(test)]
+mod test;
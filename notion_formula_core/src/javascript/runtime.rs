@@ -0,0 +1,335 @@
+/// A method of the runtime object `$` that generated code calls into. Only
+/// the helpers a formula uses, and the ones those depend on, are emitted.
+pub struct Helper {
+    pub name: &'static str,
+    pub dependencies: &'static [&'static str],
+    pub source: &'static str,
+}
+
+const fn helper(
+    name: &'static str,
+    dependencies: &'static [&'static str],
+    source: &'static str,
+) -> Helper {
+    Helper {
+        name,
+        dependencies,
+        source,
+    }
+}
+
+// Errors mirror the interpreter's so previews fail the same way.
+pub const HELPERS: &[Helper] = &[
+    helper("fail", &[], "fail(message) {
+    throw new Error(message);
+}"),
+    helper("typeName", &[], "typeName(value) {
+    switch (typeof value) {
+        case \"number\": return \"Number\";
+        case \"string\": return \"Text\";
+        case \"boolean\": return \"Checkbox\";
+        default: return String(value);
+    }
+}"),
+    helper("expect", &["fail", "typeName"], "expect(type, value, name) {
+    if (this.typeName(value) !== type) {
+        this.fail(\"Invalid argument of type \" + this.typeName(value) + \" for \" + name + \", expected \" + type);
+    }
+    return value;
+}"),
+    helper("num", &["expect"], "num(value, name) {
+    return this.expect(\"Number\", value, name);
+}"),
+    helper("text", &["expect"], "text(value, name) {
+    return this.expect(\"Text\", value, name);
+}"),
+    helper("bool", &["expect"], "bool(value, name) {
+    return this.expect(\"Checkbox\", value, name);
+}"),
+    helper("same", &["fail", "typeName"], "same(a, b) {
+    if (typeof a !== typeof b) {
+        this.fail(\"Can't compare \" + this.typeName(a) + \" with \" + this.typeName(b));
+    }
+}"),
+    helper("prop", &["fail"], "prop(props, name) {
+    if (!Object.prototype.hasOwnProperty.call(props, name)) {
+        this.fail(\"Unknown property: \" + name);
+    }
+    return props[name];
+}"),
+    helper("add", &["fail", "typeName"], "add(a, b) {
+    if (typeof a === typeof b && (typeof a === \"number\" || typeof a === \"string\")) {
+        return a + b;
+    }
+    this.fail(\"Invalid types \" + this.typeName(a) + \" and \" + this.typeName(b) + \" for Add\");
+}"),
+    helper("plus", &["toNumber"], "plus(value) {
+    return this.toNumber(value);
+}"),
+    helper("cond", &["bool", "fail", "typeName"], "cond(test, accept, reject) {
+    this.bool(test, \"if\");
+    if (typeof accept !== typeof reject) {
+        this.fail(\"Each branch of a condition must be the same type: \" + this.typeName(accept) + \" and \" + this.typeName(reject));
+    }
+    return test ? accept : reject;
+}"),
+    helper("and", &["bool"], "and(a, b) {
+    const left = this.bool(a, \"and\");
+    const right = this.bool(b, \"and\");
+    return left && right;
+}"),
+    helper("or", &["bool"], "or(a, b) {
+    const left = this.bool(a, \"or\");
+    const right = this.bool(b, \"or\");
+    return left || right;
+}"),
+    helper("not", &["bool"], "not(value) {
+    return !this.bool(value, \"not\");
+}"),
+    helper("eq", &["same"], "eq(a, b) {
+    this.same(a, b);
+    return a === b;
+}"),
+    helper("ne", &["same"], "ne(a, b) {
+    this.same(a, b);
+    return a !== b;
+}"),
+    helper("lt", &["same"], "lt(a, b) {
+    this.same(a, b);
+    return a < b;
+}"),
+    helper("le", &["same"], "le(a, b) {
+    this.same(a, b);
+    return a <= b;
+}"),
+    helper("gt", &["same"], "gt(a, b) {
+    this.same(a, b);
+    return a > b;
+}"),
+    helper("ge", &["same"], "ge(a, b) {
+    this.same(a, b);
+    return a >= b;
+}"),
+    helper("empty", &[], "empty(value) {
+    return value === 0 || value === \"\" || value === false;
+}"),
+    helper("length", &["text"], "length(value) {
+    return Array.from(this.text(value, \"length\")).length;
+}"),
+    helper("format", &[], "format(value) {
+    return String(value);
+}"),
+    helper("toNumber", &["fail", "typeName"], "toNumber(value) {
+    switch (typeof value) {
+        case \"number\": return value;
+        case \"boolean\": return value ? 1 : 0;
+        case \"string\": {
+            const result = Number(value.trim());
+            if (value.trim() === \"\" || Number.isNaN(result)) {
+                this.fail(\"Invalid number: \" + value);
+            }
+            return result;
+        }
+        default: this.fail(\"Invalid argument of type \" + this.typeName(value) + \" for toNumber\");
+    }
+}"),
+    helper("concat", &["text"], "concat(...values) {
+    return values.map((value) => this.text(value, \"concat\")).join(\"\");
+}"),
+    helper("join", &["text"], "join(separator, ...values) {
+    return values.map((value) => this.text(value, \"join\")).join(this.text(separator, \"join\"));
+}"),
+    helper("contains", &["text"], "contains(value, search) {
+    return this.text(value, \"contains\").includes(this.text(search, \"contains\"));
+}"),
+    helper("replace", &["text"], "replace(value, search, replacement) {
+    this.text(replacement, \"replace\");
+    return this.text(value, \"replace\").replace(this.text(search, \"replace\"), () => replacement);
+}"),
+    helper("replaceAll", &["text"], "replaceAll(value, search, replacement) {
+    return this.text(value, \"replaceAll\").split(this.text(search, \"replaceAll\")).join(this.text(replacement, \"replaceAll\"));
+}"),
+    helper("lower", &["text"], "lower(value) {
+    return this.text(value, \"lower\").toLowerCase();
+}"),
+    helper("upper", &["text"], "upper(value) {
+    return this.text(value, \"upper\").toUpperCase();
+}"),
+    helper("slice", &["text", "num"], "slice(value, start, end) {
+    const chars = Array.from(this.text(value, \"slice\"));
+    const from = Math.max(0, Math.trunc(this.num(start, \"slice\")));
+    const to = end === undefined ? chars.length : Math.max(0, Math.trunc(this.num(end, \"slice\")));
+    return chars.slice(from, to).join(\"\");
+}"),
+    helper("substring", &["slice"], "substring(value, start, end) {
+    return this.slice(value, start, end);
+}"),
+    helper("abs", &["num"], "abs(value) {
+    return Math.abs(this.num(value, \"abs\"));
+}"),
+    helper("ceil", &["num"], "ceil(value) {
+    return Math.ceil(this.num(value, \"ceil\"));
+}"),
+    helper("floor", &["num"], "floor(value) {
+    return Math.floor(this.num(value, \"floor\"));
+}"),
+    // Math.round rounds halves up, Notion rounds them away from zero.
+    helper("round", &["num"], "round(value) {
+    return Math.sign(this.num(value, \"round\")) * Math.round(Math.abs(value));
+}"),
+    helper("sqrt", &["num"], "sqrt(value) {
+    return Math.sqrt(this.num(value, \"sqrt\"));
+}"),
+    helper("cbrt", &["num"], "cbrt(value) {
+    return Math.cbrt(this.num(value, \"cbrt\"));
+}"),
+    helper("exp", &["num"], "exp(value) {
+    return Math.exp(this.num(value, \"exp\"));
+}"),
+    helper("ln", &["num"], "ln(value) {
+    return Math.log(this.num(value, \"ln\"));
+}"),
+    helper("log10", &["num"], "log10(value) {
+    return Math.log10(this.num(value, \"log10\"));
+}"),
+    helper("log2", &["num"], "log2(value) {
+    return Math.log2(this.num(value, \"log2\"));
+}"),
+    helper("sign", &["num"], "sign(value) {
+    return Math.sign(this.num(value, \"sign\"));
+}"),
+    helper("pow", &["num"], "pow(base, exponent) {
+    return Math.pow(this.num(base, \"pow\"), this.num(exponent, \"pow\"));
+}"),
+    helper("min", &["num"], "min(...values) {
+    return Math.min(...values.map((value) => this.num(value, \"min\")));
+}"),
+    helper("max", &["num"], "max(...values) {
+    return Math.max(...values.map((value) => this.num(value, \"max\")));
+}"),
+    // Dates are text, see `date::Date`, and are handled as milliseconds
+    // since 1970-01-01 in between, always to the second.
+    helper("toDate", &["text", "fail"], "toDate(value, name) {
+    const text = this.text(value, name).trim();
+    const match = /^(\\d{4})-(\\d{2})-(\\d{2})(?:[T ](\\d{2}):(\\d{2})(?::(\\d{2}))?(?:\\.\\d*)?(Z|[+-]\\d{2}:\\d{2})?)?$/.exec(text);
+    if (!match) {
+        this.fail(\"Invalid date: \" + value);
+    }
+    const [year, month, day, hour, minute, second] = match.slice(1, 7).map((part) => Number(part || 0));
+    const date = new Date(0);
+    date.setUTCFullYear(year, month - 1, day);
+    date.setUTCHours(hour, minute, second);
+    if (date.getUTCMonth() !== month - 1 || date.getUTCDate() !== day || hour > 23 || minute > 59 || second > 59) {
+        this.fail(\"Invalid date: \" + value);
+    }
+    const offset = match[7] && match[7] !== \"Z\"
+        ? (match[7][0] === \"-\" ? -1 : 1) * (Number(match[7].slice(1, 3)) * 60 + Number(match[7].slice(4))) * 60000
+        : 0;
+    return date.getTime() - offset;
+}"),
+    helper("dateText", &[], "dateText(time) {
+    return new Date(time).toISOString().slice(0, 19);
+}"),
+    helper("dateUnit", &["text", "fail"], "dateUnit(unit, name) {
+    if (![\"years\", \"months\", \"weeks\", \"days\", \"hours\", \"minutes\", \"seconds\"].includes(this.text(unit, name))) {
+        this.fail(\"Unknown date unit: \" + unit + \", expected years, months, weeks, days, hours, minutes or seconds\");
+    }
+    return { weeks: 604800000, days: 86400000, hours: 3600000, minutes: 60000, seconds: 1000 }[unit] || unit;
+}"),
+    // Like `date::Date::add`, months and years stop at the end of shorter
+    // months.
+    helper("shift", &[], "shift(time, count, unit) {
+    if (typeof unit === \"number\") {
+        return time + count * unit;
+    }
+    const date = new Date(time);
+    const index = date.getUTCFullYear() * 12 + date.getUTCMonth() + (unit === \"years\" ? count * 12 : count);
+    const year = Math.floor(index / 12);
+    const last = new Date(0);
+    last.setUTCFullYear(year, index - year * 12 + 1, 0);
+    date.setUTCFullYear(year, index - year * 12, Math.min(date.getUTCDate(), last.getUTCDate()));
+    return date.getTime();
+}"),
+    helper("monthsBetween", &[], "monthsBetween(end, start) {
+    const [to, from] = [new Date(end), new Date(start)];
+    const months = (to.getUTCFullYear() - from.getUTCFullYear()) * 12 + to.getUTCMonth() - from.getUTCMonth();
+    const rest = (date) => date.getUTCDate() * 86400000 + (((date.getTime() % 86400000) + 86400000) % 86400000);
+    if (end >= start && rest(to) < rest(from)) {
+        return months - 1;
+    }
+    if (end < start && rest(to) > rest(from)) {
+        return months + 1;
+    }
+    return months;
+}"),
+    helper("now", &["dateText"], "now() {
+    return this.dateText(Date.now());
+}"),
+    helper("dateAdd", &["toDate", "num", "dateUnit", "shift", "dateText"], "dateAdd(value, amount, unit) {
+    const time = this.toDate(value, \"dateAdd\");
+    const count = Math.trunc(this.num(amount, \"dateAdd\"));
+    return this.dateText(this.shift(time, count, this.dateUnit(unit, \"dateAdd\")));
+}"),
+    helper("dateSubtract", &["toDate", "num", "dateUnit", "shift", "dateText"], "dateSubtract(value, amount, unit) {
+    const time = this.toDate(value, \"dateSubtract\");
+    const count = Math.trunc(this.num(amount, \"dateSubtract\"));
+    return this.dateText(this.shift(time, -count, this.dateUnit(unit, \"dateSubtract\")));
+}"),
+    helper("dateBetween", &["toDate", "dateUnit", "monthsBetween"], "dateBetween(end, start, unit) {
+    const [to, from] = [this.toDate(end, \"dateBetween\"), this.toDate(start, \"dateBetween\")];
+    const length = this.dateUnit(unit, \"dateBetween\");
+    if (typeof length === \"number\") {
+        return Math.trunc((to - from) / length);
+    }
+    const months = this.monthsBetween(to, from);
+    return length === \"years\" ? Math.trunc(months / 12) : months;
+}"),
+    helper("formatDate", &["toDate", "text"], "formatDate(value, format) {
+    const date = new Date(this.toDate(value, \"formatDate\"));
+    const months = [\"January\", \"February\", \"March\", \"April\", \"May\", \"June\", \"July\", \"August\", \"September\", \"October\", \"November\", \"December\"];
+    const days = [\"Monday\", \"Tuesday\", \"Wednesday\", \"Thursday\", \"Friday\", \"Saturday\", \"Sunday\"];
+    const pad = (number, length) => String(number).padStart(length, \"0\");
+    const [year, month, day, hour] = [date.getUTCFullYear(), date.getUTCMonth(), date.getUTCDate(), date.getUTCHours()];
+    const weekday = (date.getUTCDay() + 6) % 7;
+    const tokens = {
+        YYYY: pad(year, 4), YY: pad(((year % 100) + 100) % 100, 2),
+        MMMM: months[month], MMM: months[month].slice(0, 3), MM: pad(month + 1, 2), M: String(month + 1),
+        DD: pad(day, 2), D: String(day), dddd: days[weekday], ddd: days[weekday].slice(0, 3),
+        HH: pad(hour, 2), H: String(hour), hh: pad((hour + 11) % 12 + 1, 2), h: String((hour + 11) % 12 + 1),
+        mm: pad(date.getUTCMinutes(), 2), ss: pad(date.getUTCSeconds(), 2), A: hour < 12 ? \"AM\" : \"PM\",
+    };
+    const pattern = /\\[([^\\]]*)\\]|YYYY|YY|MMMM|MMM|MM|M|DD|D|dddd|ddd|HH|H|hh|h|mm|ss|A/g;
+    return this.text(format, \"formatDate\").replace(pattern, (token, text) => text === undefined ? tokens[token] : text);
+}"),
+    helper("parseDate", &["toDate", "dateText"], "parseDate(value) {
+    return this.dateText(this.toDate(value, \"parseDate\"));
+}"),
+    helper("year", &["toDate"], "year(value) {
+    return new Date(this.toDate(value, \"year\")).getUTCFullYear();
+}"),
+    helper("month", &["toDate"], "month(value) {
+    return new Date(this.toDate(value, \"month\")).getUTCMonth() + 1;
+}"),
+    helper("date", &["toDate"], "date(value) {
+    return new Date(this.toDate(value, \"date\")).getUTCDate();
+}"),
+    helper("day", &["toDate"], "day(value) {
+    return (new Date(this.toDate(value, \"day\")).getUTCDay() + 6) % 7 + 1;
+}"),
+    helper("hour", &["toDate"], "hour(value) {
+    return new Date(this.toDate(value, \"hour\")).getUTCHours();
+}"),
+    helper("minute", &["toDate"], "minute(value) {
+    return new Date(this.toDate(value, \"minute\")).getUTCMinutes();
+}"),
+    helper("timestamp", &["toDate"], "timestamp(value) {
+    return this.toDate(value, \"timestamp\");
+}"),
+    helper("fromTimestamp", &["num", "dateText"], "fromTimestamp(value) {
+    return this.dateText(this.num(value, \"fromTimestamp\"));
+}"),
+];
+
+pub fn lookup(name: &str) -> Option<&'static Helper> {
+    HELPERS.iter().find(|helper| helper.name == name)
+}
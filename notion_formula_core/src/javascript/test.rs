@@ -0,0 +1,165 @@
+use super::*;
+use crate::builtins::BUILTINS;
+use crate::parser::formula_parser;
+use crate::tokenizer::tokenizer;
+use std::env;
+use std::fs;
+use std::path::Path;
+
+fn compile_source(input: &str) -> HandlerResult<String> {
+    let tokens = tokenizer(input.chars().collect()).unwrap();
+    let ast = formula_parser(tokens).unwrap();
+    compile(&ast)
+}
+
+/// Compares the output with `tests/golden/<name>.js`. Run with
+/// `UPDATE_GOLDEN=1` to rewrite the snapshots after an intended change.
+fn assert_golden(name: &str, input: &str) {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/golden")
+        .join(format!("{}.js", name));
+    let actual = compile_source(input).unwrap();
+
+    if env::var_os("UPDATE_GOLDEN").is_some() {
+        fs::write(&path, &actual).unwrap();
+    }
+    let expected = fs::read_to_string(&path).unwrap();
+    assert_eq!(expected, actual, "{}", path.display());
+}
+
+#[test]
+fn test_compiles_the_example_formula() {
+    assert_golden(
+        "test_formula",
+        include_str!("../../tests/test_formula.notion"),
+    );
+}
+
+#[test]
+fn test_compiles_arithmetic() {
+    assert_golden(
+        "arithmetic",
+        "-prop(\"Estimate\") * 2 ^ 3 + round(prop(\"Hours\") / 8) % 5",
+    );
+}
+
+#[test]
+fn test_compiles_text() {
+    assert_golden(
+        "text",
+        "prop(\"Name\") + \": \" + upper(slice(prop(\"State\"), 0, 1)) + format(+\"3\" > 2)",
+    );
+}
+
+#[test]
+fn test_compiles_dates() {
+    assert_golden(
+        "dates",
+        "formatDate(dateAdd(prop(\"Due\"), 1, \"months\"), \"MMM D, YYYY\") + \" in \" + format(dateBetween(prop(\"Due\"), now(), \"days\"))",
+    );
+}
+
+#[test]
+fn test_compiles_booleans() {
+    assert_golden(
+        "booleans",
+        "prop(\"Done\") ? \"✅\" : (not empty(prop(\"Tags\")) and contains(prop(\"Tags\"), \"urgent\") ? \"🔥\" : \"\")",
+    );
+}
+
+#[test]
+fn test_compiles_ifs_as_nested_conditions() {
+    let output = compile_source("ifs(prop(\"A\"), 1, prop(\"B\"), 2, 3)").unwrap();
+    assert!(
+        output.contains(
+            "return $.cond($.prop(props, \"A\"), 1, $.cond($.prop(props, \"B\"), 2, 3));"
+        ),
+        "{}",
+        output
+    );
+}
+
+#[test]
+fn test_escapes_strings() {
+    let output = compile_source("\"a\\b\" + \"\u{2028}\"").unwrap();
+    assert!(
+        output.contains("$.add(\"a\\\\b\", \"\\u2028\")"),
+        "{}",
+        output
+    );
+}
+
+#[test]
+fn test_normalizes_numbers() {
+    let output = compile_source("007 + 1.50").unwrap();
+    assert!(output.contains("$.add(7, 1.5)"), "{}", output);
+}
+
+#[test]
+fn test_separates_nested_unary_minus() {
+    let cases = vec![
+        ("- -5", "return -(-5);"),
+        ("-(-5)", "return -(-5);"),
+        ("- - -5", "return -$.num(-(-5), \"-\");"),
+        (
+            "- -prop(\"A\")",
+            "return -$.num(-$.num($.prop(props, \"A\"), \"-\"), \"-\");",
+        ),
+    ];
+
+    for (input, expected) in cases {
+        let output = compile_source(input).unwrap();
+        assert!(output.contains(expected), "{}", output);
+    }
+}
+
+#[test]
+fn test_reports_unsupported_expressions() {
+    let cases = vec![
+        ("nope(1)", "Unknown function: nope"),
+        ("x + 1", "Unknown identifier: x"),
+        ("fail(\"x\")", "Unknown function: fail"),
+        ("today()", "today isn't supported yet"),
+        (
+            "prop()",
+            "Wrong number of arguments for prop, expected prop(name: Text) -> Any",
+        ),
+        (
+            "abs(1, 2)",
+            "Wrong number of arguments for abs, expected abs(value: Number) -> Number",
+        ),
+        (
+            "length(1)",
+            "Invalid argument of type Number for length, expected Text",
+        ),
+        ("1e400", "Invalid number: 1e400"),
+        (
+            "Users { \"name\": \"a\" }[\"name\"]",
+            "Unknown table: Users",
+        ),
+    ];
+
+    for (input, expected) in cases {
+        let tokens = tokenizer(input.chars().collect()).unwrap();
+        let ast = formula_parser(tokens).unwrap();
+        assert_eq!(
+            expected,
+            compile(&ast).unwrap_err().to_string(),
+            "{}",
+            input
+        );
+    }
+}
+
+#[test]
+fn test_every_builtin_function_has_a_helper() {
+    for builtin in BUILTINS {
+        let constant = !builtin.signature.contains('(');
+        let special = ["if", "ifs", "prop"].contains(&builtin.name);
+        assert!(
+            constant || special || runtime::lookup(builtin.name).is_some(),
+            "{}",
+            builtin.name
+        );
+    }
+}
@@ -1,4 +1,19 @@
 pub mod reader;
 pub mod parser;
+pub mod error;
 pub mod tokenizer;
 pub mod interpreter;
+pub mod emitter;
+pub mod refactor;
+pub mod builtins;
+pub mod typechecker;
+pub mod formatter;
+pub mod csv;
+pub mod sql;
+pub mod javascript;
+pub mod migrate;
+pub mod lint;
+pub mod metrics;
+pub mod date;
+#[cfg(feature = "notion-api")]
+pub mod notion_api;
@@ -0,0 +1,64 @@
+use super::{lookup, Rule, Severity};
+use pipeline::{HandlerResult, SimpleError};
+use std::collections::HashMap;
+
+/// Which rules run and how severe their findings are. The config file has
+/// one `rule = level` line per rule to change, where the level is `off`,
+/// `info`, `warning` or `error`, and `#` starts a comment:
+///
+/// ```text
+/// # Nested ifs are fine in this project.
+/// nested-if = off
+/// unused-let = error
+/// ```
+#[derive(Debug, Default, PartialEq)]
+pub struct LintConfig {
+    levels: HashMap<&'static str, Option<Severity>>,
+}
+impl LintConfig {
+    pub fn parse(input: &str) -> HandlerResult<LintConfig> {
+        let mut config = LintConfig::default();
+
+        for (index, line) in input.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            let (id, level) = line.split_once('=').ok_or_else(|| {
+                SimpleError::new(format!(
+                    "Line {}: expected <rule> = <off|info|warning|error>",
+                    index + 1
+                ))
+            })?;
+            let (id, level) = (id.trim(), level.trim().trim_matches('"'));
+
+            let rule = lookup(id).ok_or_else(|| {
+                SimpleError::new(format!("Line {}: unknown rule {}", index + 1, id))
+            })?;
+            let level = match level {
+                "off" => None,
+                "info" => Some(Severity::Info),
+                "warning" => Some(Severity::Warning),
+                "error" => Some(Severity::Error),
+                _ => {
+                    return Err(SimpleError::new(format!(
+                        "Line {}: unknown level {}",
+                        index + 1,
+                        level
+                    )))
+                }
+            };
+            config.levels.insert(rule.id(), level);
+        }
+        Ok(config)
+    }
+
+    /// The severity of the rule's findings, or `None` when it's turned off.
+    pub fn severity(&self, rule: &dyn Rule) -> Option<Severity> {
+        match self.levels.get(rule.id()) {
+            Some(level) => *level,
+            None => Some(rule.default_severity()),
+        }
+    }
+}
@@ -0,0 +1,354 @@
+mod config;
+mod rules;
+
+pub use config::*;
+pub use rules::*;
+
+use crate::emitter::precedence;
+use crate::formatter::format_expression;
+use crate::interpreter::unquote;
+use crate::parser::{
+    cst_document_parser, lower_document, walk_expression, walk_statement, Expression, Statement,
+    Visitor,
+};
+use crate::tokenizer::{lossless_tokenizer, LosslessToken, Trivia};
+use pipeline::HandlerResult;
+use std::fmt;
+
+// Fixes can uncover new problems, e.g. removing the only use of a variable,
+// so fixing repeats until nothing changes, up to this many times.
+const MAX_FIX_PASSES: usize = 10;
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+pub enum Severity {
+    Info,
+    Warning,
+    Error,
+}
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Info => write!(f, "info"),
+            Severity::Warning => write!(f, "warning"),
+            Severity::Error => write!(f, "error"),
+        }
+    }
+}
+
+/// A problem found by a rule, with the change that fixes it if there is a
+/// safe one.
+pub struct Finding {
+    pub message: String,
+    pub fix: Option<Fix>,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Fix {
+    /// Replaces the expression the finding was reported for.
+    Replace(Expression),
+    /// Removes the statement the finding was reported for.
+    Remove,
+}
+
+/// A finding of an enabled rule in a document. Positions point at the start
+/// of the formula or statement containing the problem.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Lint {
+    pub rule: &'static str,
+    pub severity: Severity,
+    pub message: String,
+    pub line: u32,
+    pub column: u32,
+    /// Describes the fix, if the rule has one.
+    pub fix: Option<String>,
+    edit: Option<Edit>,
+}
+impl fmt::Display for Lint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}: {} [{}] {}",
+            self.line, self.column, self.severity, self.rule, self.message
+        )
+    }
+}
+
+/// Replaces the tokens `start..end` with `text`, or removes them along with
+/// their whitespace when there's no text. Edits that would drop comments
+/// aren't safe and are only suggested.
+#[derive(Debug, PartialEq, Clone)]
+struct Edit {
+    start: usize,
+    end: usize,
+    text: Option<String>,
+    safe: bool,
+}
+
+/// Runs every enabled rule over a document. Rules can be silenced for a
+/// statement with a `// lint-ignore` comment above or inside it, optionally
+/// followed by the rule IDs to ignore, e.g. `// lint-ignore nested-if`.
+pub fn lint(source: &str, config: &LintConfig) -> HandlerResult<Vec<Lint>> {
+    let tokens = lossless_tokenizer(source.chars().collect())?;
+    let lowered = lower_document(&cst_document_parser(tokens.clone())?);
+    let statements: Vec<Statement> = lowered.iter().map(|s| s.statement.clone()).collect();
+    let rules: Vec<(&dyn Rule, Severity)> = RULES
+        .iter()
+        .filter_map(|rule| config.severity(*rule).map(|severity| (*rule, severity)))
+        .collect();
+    let mut lints = vec![];
+
+    for (rule, severity) in &rules {
+        for (index, finding) in rule.check_document(&statements) {
+            let (start, end) = lowered[index].range;
+            let edit = match finding.fix {
+                Some(Fix::Remove) => Some(Edit {
+                    start,
+                    end,
+                    text: None,
+                    safe: !has_comments(&tokens[start..end], true),
+                }),
+                _ => None,
+            };
+            let lint = Lint {
+                rule: rule.id(),
+                severity: *severity,
+                message: finding.message,
+                line: tokens[start].token.line,
+                column: tokens[start].token.column,
+                fix: edit.as_ref().map(|_| "remove the statement".to_string()),
+                edit,
+            };
+            lints.push((index, lint));
+        }
+    }
+
+    for (index, statement) in lowered.iter().enumerate() {
+        let mut checker = Checker {
+            rules: &rules,
+            tokens: &tokens,
+            nodes: &statement.nodes,
+            columns: None,
+            parents: vec![],
+            next: 0,
+            unit: 0,
+            lints: vec![],
+        };
+        checker.visit_statement(&statement.statement);
+        lints.extend(checker.lints.into_iter().map(|lint| (index, lint)));
+    }
+
+    let mut lints: Vec<Lint> = lints
+        .into_iter()
+        .filter(|(index, lint)| {
+            let (start, end) = lowered[*index].range;
+            !is_ignored(&tokens[start..end], lint.rule)
+        })
+        .map(|(_, lint)| lint)
+        .collect();
+    lints.sort_by_key(|lint| (lint.line, lint.column));
+    Ok(lints)
+}
+
+/// Applies every safe fix and returns the new source.
+pub fn fix(source: &str, config: &LintConfig) -> HandlerResult<String> {
+    let mut source = source.to_string();
+
+    for _ in 0..MAX_FIX_PASSES {
+        let lints = lint(&source, config)?;
+        let mut edits: Vec<&Edit> = lints
+            .iter()
+            .filter_map(|lint| lint.edit.as_ref())
+            .filter(|edit| edit.safe)
+            .collect();
+        edits.sort_by_key(|edit| edit.start);
+
+        // Overlapping edits wait for the next pass.
+        let mut chosen: Vec<&Edit> = vec![];
+        for edit in edits {
+            if chosen
+                .last()
+                .is_none_or(|previous| previous.end <= edit.start)
+            {
+                chosen.push(edit);
+            }
+        }
+        if chosen.is_empty() {
+            break;
+        }
+
+        let tokens = lossless_tokenizer(source.chars().collect())?;
+        let result = apply(&tokens, &chosen);
+        source = match source.starts_with(char::is_whitespace) {
+            true => result,
+            false => result.trim_start().to_string(),
+        };
+    }
+    Ok(source)
+}
+
+/// Runs the expression rules over every node of a statement, finding each
+/// node's tokens in the ranges recorded while the statement was built from
+/// the concrete syntax tree.
+struct Checker<'a> {
+    rules: &'a [(&'a dyn Rule, Severity)],
+    tokens: &'a [LosslessToken],
+    nodes: &'a [(usize, usize)],
+    columns: Option<Vec<String>>,
+    parents: Vec<Expression>,
+    next: usize,
+    // The first token of the formula being checked.
+    unit: usize,
+    lints: Vec<Lint>,
+}
+impl Visitor for Checker<'_> {
+    fn visit_statement(&mut self, statement: &Statement) {
+        if let Statement::TableDef(_, columns) = statement {
+            self.columns = Some(columns.iter().map(|column| unquote(&column.key)).collect());
+        }
+        walk_statement(self, statement);
+    }
+
+    fn visit_expression(&mut self, expr: &Expression) {
+        let range = self.nodes[self.next];
+        self.next += 1;
+        if self.parents.is_empty() {
+            self.unit = range.0;
+        }
+
+        let context = Context {
+            columns: self.columns.as_deref(),
+            parent: self.parents.last(),
+        };
+        for (rule, severity) in self.rules {
+            if let Some(finding) = rule.check_expression(expr, &context) {
+                self.lints.push(expression_lint(
+                    *rule,
+                    *severity,
+                    finding,
+                    expr,
+                    range,
+                    self.unit,
+                    self.tokens,
+                ));
+            }
+        }
+
+        self.parents.push(expr.clone());
+        walk_expression(self, expr);
+        self.parents.pop();
+    }
+}
+
+// Replacements only cover the tokens of the node they replace, and are
+// parenthesized when they bind looser than it.
+fn expression_lint(
+    rule: &dyn Rule,
+    severity: Severity,
+    finding: Finding,
+    input: &Expression,
+    (start, end): (usize, usize),
+    unit: usize,
+    tokens: &[LosslessToken],
+) -> Lint {
+    let (fix, edit) = match finding.fix {
+        Some(Fix::Replace(replacement)) => match format_expression(&replacement) {
+            Ok(source) => {
+                let text = match precedence(&replacement) < precedence(input) {
+                    true => format!("({})", source),
+                    false => source.clone(),
+                };
+                let edit = Edit {
+                    start,
+                    end,
+                    text: Some(text),
+                    safe: !has_comments(&tokens[start..end], false),
+                };
+                (Some(format!("replace with {}", source)), Some(edit))
+            }
+            Err(_) => (None, None),
+        },
+        _ => (None, None),
+    };
+
+    Lint {
+        rule: rule.id(),
+        severity,
+        message: finding.message,
+        line: tokens[unit].token.line,
+        column: tokens[unit].token.column,
+        fix,
+        edit,
+    }
+}
+
+fn comments(tokens: &[LosslessToken]) -> impl Iterator<Item = &str> {
+    tokens
+        .iter()
+        .flat_map(|token| token.leading.iter().chain(token.trailing.iter()))
+        .filter_map(|trivia| match trivia {
+            Trivia::Comment(text) => Some(text.as_str()),
+            Trivia::Whitespace(_) => None,
+        })
+}
+
+// With `outer`, comments before the first and after the last token count.
+fn has_comments(tokens: &[LosslessToken], outer: bool) -> bool {
+    if outer {
+        return comments(tokens).next().is_some();
+    }
+
+    let last = tokens.len().saturating_sub(1);
+    let trailing = tokens[..last]
+        .iter()
+        .flat_map(|token| token.trailing.iter());
+    let leading = tokens.iter().skip(1).flat_map(|token| token.leading.iter());
+    trailing
+        .chain(leading)
+        .any(|trivia| matches!(trivia, Trivia::Comment(_)))
+}
+
+fn is_ignored(tokens: &[LosslessToken], rule: &str) -> bool {
+    comments(tokens).any(|comment| {
+        let text = comment
+            .trim_start_matches("//")
+            .trim_start_matches("/*")
+            .trim_end_matches("*/")
+            .trim();
+        match text.strip_prefix("lint-ignore") {
+            Some(rules) if rules.trim().is_empty() => true,
+            Some(rules) => rules
+                .trim_start_matches(':')
+                .split(|c: char| c == ',' || c.is_whitespace())
+                .any(|id| id == rule),
+            None => false,
+        }
+    })
+}
+
+fn apply(tokens: &[LosslessToken], edits: &[&Edit]) -> String {
+    let mut output = String::new();
+    let mut next = 0;
+
+    for edit in edits {
+        for token in &tokens[next..edit.start] {
+            output.push_str(&token.to_string());
+        }
+        if let Some(text) = &edit.text {
+            for trivia in &tokens[edit.start].leading {
+                output.push_str(&trivia.to_string());
+            }
+            output.push_str(text);
+            for trivia in &tokens[edit.end - 1].trailing {
+                output.push_str(&trivia.to_string());
+            }
+        }
+        next = edit.end;
+    }
+    for token in &tokens[next..] {
+        output.push_str(&token.to_string());
+    }
+    output
+}
+
+#[cfg(test)]
+mod test;
@@ -0,0 +1,298 @@
+use super::{Finding, Fix, Severity};
+use crate::formatter::format_expression;
+use crate::interpreter::unquote;
+use crate::parser::{ComparisonOperator, Expression, Statement, UnaryOperator, Visitor};
+
+/// What a rule knows about where an expression appears.
+pub struct Context<'a> {
+    /// The columns of the table, for formula columns.
+    pub columns: Option<&'a [String]>,
+    pub parent: Option<&'a Expression>,
+}
+
+pub trait Rule {
+    fn id(&self) -> &'static str;
+
+    fn default_severity(&self) -> Severity;
+
+    /// Checks a single node, called for every node of every expression.
+    fn check_expression(&self, _input: &Expression, _context: &Context) -> Option<Finding> {
+        None
+    }
+
+    /// Checks the document as a whole, returning findings along with the
+    /// index of the statement they belong to.
+    fn check_document(&self, _statements: &[Statement]) -> Vec<(usize, Finding)> {
+        vec![]
+    }
+}
+
+pub const RULES: &[&dyn Rule] = &[
+    &EmptyComparison,
+    &NestedIf,
+    &UnusedLet,
+    &UnknownProp,
+    &RedundantBooleanComparison,
+];
+
+pub fn lookup(id: &str) -> Option<&'static dyn Rule> {
+    RULES.iter().find(|rule| rule.id() == id).copied()
+}
+
+/// `prop("Date") == ""` is only true for empty text; `empty()` also works
+/// for dates and numbers.
+pub struct EmptyComparison;
+impl Rule for EmptyComparison {
+    fn id(&self) -> &'static str {
+        "empty-comparison"
+    }
+
+    fn default_severity(&self) -> Severity {
+        Severity::Warning
+    }
+
+    fn check_expression(&self, input: &Expression, _context: &Context) -> Option<Finding> {
+        let (lhs, op, rhs) = match input {
+            Expression::Comparison(lhs, op, rhs) => (lhs.as_ref(), op, rhs.as_ref()),
+            _ => return None,
+        };
+        let operand = match (lhs, rhs) {
+            (Expression::Str(value), operand) | (operand, Expression::Str(value))
+                if value == "\"\"" =>
+            {
+                operand
+            }
+            _ => return None,
+        };
+
+        let empty = call("empty", vec![operand.clone()]);
+        let replacement = match op {
+            ComparisonOperator::Equals => empty,
+            ComparisonOperator::NotEquals => {
+                Expression::UnaryOp(UnaryOperator::Not, Box::new(empty))
+            }
+            _ => return None,
+        };
+        Some(Finding {
+            message: format!("Use {} to check for an empty value", render(&replacement)),
+            fix: Some(Fix::Replace(replacement)),
+        })
+    }
+}
+
+/// Chains of three or more conditions read better as a single `ifs`.
+pub struct NestedIf;
+impl NestedIf {
+    const MINIMUM: usize = 3;
+}
+impl Rule for NestedIf {
+    fn id(&self) -> &'static str {
+        "nested-if"
+    }
+
+    fn default_severity(&self) -> Severity {
+        Severity::Warning
+    }
+
+    fn check_expression(&self, input: &Expression, context: &Context) -> Option<Finding> {
+        // Only the head of the chain is reported.
+        if let Some(parent) = context.parent {
+            if matches!(condition(parent), Some((_, _, reject)) if reject == input) {
+                return None;
+            }
+        }
+
+        let mut args = vec![];
+        let mut current = input;
+        while let Some((test, accept, reject)) = condition(current) {
+            args.push(test.clone());
+            args.push(accept.clone());
+            current = reject;
+        }
+        if args.len() / 2 < Self::MINIMUM {
+            return None;
+        }
+        args.push(current.clone());
+
+        Some(Finding {
+            message: format!(
+                "{} nested conditions can be written with ifs",
+                args.len() / 2
+            ),
+            fix: Some(Fix::Replace(call("ifs", args))),
+        })
+    }
+}
+
+/// A `let` whose variable is never read.
+pub struct UnusedLet;
+impl Rule for UnusedLet {
+    fn id(&self) -> &'static str {
+        "unused-let"
+    }
+
+    fn default_severity(&self) -> Severity {
+        Severity::Warning
+    }
+
+    fn check_document(&self, statements: &[Statement]) -> Vec<(usize, Finding)> {
+        let mut findings = vec![];
+
+        for (index, statement) in statements.iter().enumerate() {
+            let name = match statement {
+                Statement::Assignment(name, _) => name,
+                _ => continue,
+            };
+
+            let mut used = false;
+            for later in &statements[index + 1..] {
+                let mut references = References { name, found: false };
+                references.visit_statement(later);
+                if references.found {
+                    used = true;
+                    break;
+                }
+                if matches!(later, Statement::Assignment(other, _) if other == name) {
+                    break;
+                }
+            }
+
+            if !used {
+                findings.push((
+                    index,
+                    Finding {
+                        message: format!("{} is never used", name),
+                        fix: Some(Fix::Remove),
+                    },
+                ));
+            }
+        }
+        findings
+    }
+}
+
+/// `prop("...")` in a formula column naming a column the table doesn't have.
+pub struct UnknownProp;
+impl Rule for UnknownProp {
+    fn id(&self) -> &'static str {
+        "unknown-prop"
+    }
+
+    fn default_severity(&self) -> Severity {
+        Severity::Error
+    }
+
+    fn check_expression(&self, input: &Expression, context: &Context) -> Option<Finding> {
+        let columns = context.columns?;
+        let name = match input {
+            Expression::Call(callee, args) => match (callee.as_ref(), args.as_slice()) {
+                (Expression::Identifier(callee), [Expression::Str(name)]) if callee == "prop" => {
+                    unquote(name)
+                }
+                _ => return None,
+            },
+            _ => return None,
+        };
+        if columns.contains(&name) {
+            return None;
+        }
+
+        let closest = columns
+            .iter()
+            .map(|column| (distance(&name, column), column))
+            .filter(|(distance, _)| *distance <= 2)
+            .min_by_key(|(distance, _)| *distance);
+        let message = match closest {
+            Some((_, column)) => format!("Unknown property {:?}, did you mean {:?}?", name, column),
+            None => format!("Unknown property {:?}", name),
+        };
+        Some(Finding { message, fix: None })
+    }
+}
+
+/// `x == true` is just `x`.
+pub struct RedundantBooleanComparison;
+impl Rule for RedundantBooleanComparison {
+    fn id(&self) -> &'static str {
+        "redundant-boolean-comparison"
+    }
+
+    fn default_severity(&self) -> Severity {
+        Severity::Warning
+    }
+
+    fn check_expression(&self, input: &Expression, _context: &Context) -> Option<Finding> {
+        let (lhs, op, rhs) = match input {
+            Expression::Comparison(lhs, op, rhs) => (lhs.as_ref(), op, rhs.as_ref()),
+            _ => return None,
+        };
+        let (operand, value) = match (lhs, rhs) {
+            (operand, Expression::Bool(value)) | (Expression::Bool(value), operand) => {
+                (operand, *value)
+            }
+            _ => return None,
+        };
+
+        let keep = match op {
+            ComparisonOperator::Equals => value,
+            ComparisonOperator::NotEquals => !value,
+            _ => return None,
+        };
+        let replacement = match keep {
+            true => operand.clone(),
+            false => Expression::UnaryOp(UnaryOperator::Not, Box::new(operand.clone())),
+        };
+        Some(Finding {
+            message: format!("Comparing with {} is redundant", value),
+            fix: Some(Fix::Replace(replacement)),
+        })
+    }
+}
+
+fn call(name: &str, args: Vec<Expression>) -> Expression {
+    Expression::Call(Box::new(Expression::Identifier(name.into())), args)
+}
+
+fn render(input: &Expression) -> String {
+    format_expression(input).unwrap_or_default()
+}
+
+fn condition(input: &Expression) -> Option<(&Expression, &Expression, &Expression)> {
+    match input {
+        Expression::TernaryOp(test, accept, reject) => Some((test, accept, reject)),
+        Expression::Call(callee, args) => match (callee.as_ref(), args.as_slice()) {
+            (Expression::Identifier(name), [test, accept, reject]) if name == "if" => {
+                Some((test, accept, reject))
+            }
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Looks for an identifier with the given name.
+struct References<'a> {
+    name: &'a str,
+    found: bool,
+}
+impl Visitor for References<'_> {
+    fn visit_identifier(&mut self, name: &str) {
+        self.found |= name == self.name;
+    }
+}
+
+// Levenshtein distance over characters.
+fn distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+
+    for (i, x) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, y) in b.iter().enumerate() {
+            let substitution = previous[j] + (x != *y) as usize;
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
@@ -0,0 +1,202 @@
+use super::*;
+
+fn lint_source(source: &str) -> Vec<String> {
+    lint(source, &LintConfig::default())
+        .unwrap()
+        .iter()
+        .map(|lint| lint.to_string())
+        .collect()
+}
+
+#[test]
+fn test_reports_each_rule() {
+    let source = "table Tasks {
+    \"Name\": Text,
+    \"Due\": Text,
+    \"Label\": formula { prop(\"Nmae\") + prop(\"Owner\") }
+}
+let unused = 1
+let used = 2
+formula Late { prop(\"Due\") == \"\" }
+formula Done { prop(\"Done\") == true }
+print if(used > 3, \"a\", if(used > 2, \"b\", if(used > 1, \"c\", \"d\")))";
+
+    assert_eq!(
+        vec![
+            "4:24: error [unknown-prop] Unknown property \"Nmae\", did you mean \"Name\"?",
+            "4:24: error [unknown-prop] Unknown property \"Owner\"",
+            "6:1: warning [unused-let] unused is never used",
+            "8:16: warning [empty-comparison] Use empty(prop(\"Due\")) to check for an empty value",
+            "9:16: warning [redundant-boolean-comparison] Comparing with true is redundant",
+            "10:7: warning [nested-if] 3 nested conditions can be written with ifs",
+        ],
+        lint_source(source)
+    );
+}
+
+#[test]
+fn test_describes_fixes() {
+    let lints = lint(
+        "print prop(\"A\") != false and \"\" != prop(\"B\")",
+        &LintConfig::default(),
+    )
+    .unwrap();
+    let fixes: Vec<Option<&str>> = lints.iter().map(|lint| lint.fix.as_deref()).collect();
+
+    assert_eq!(
+        vec![
+            Some("replace with prop(\"A\")"),
+            Some("replace with not empty(prop(\"B\"))"),
+        ],
+        fixes
+    );
+}
+
+#[test]
+fn test_fixes_a_document() {
+    let source = "let unused = 1
+// Status of a task
+formula Status {
+    prop(\"Done\") == true ? \"done\" : prop(\"Due\") == \"\" ? \"someday\" : prop(\"Late\") ? \"late\" : \"soon\"
+}
+print 1 // one
+";
+
+    assert_eq!(
+        "// Status of a task
+formula Status {
+    ifs(prop(\"Done\"), \"done\", empty(prop(\"Due\")), \"someday\", prop(\"Late\"), \"late\", \"soon\")
+}
+print 1 // one
+",
+        fix(source, &LintConfig::default()).unwrap()
+    );
+}
+
+#[test]
+fn test_removing_the_first_statement() {
+    assert_eq!(
+        "print 1",
+        fix("let x = 1\nprint 1", &LintConfig::default()).unwrap()
+    );
+}
+
+#[test]
+fn test_fixes_that_drop_comments_are_not_applied() {
+    let source = "print prop(\"A\") == /* never empty */ \"\"\nlet x = 1 // keep";
+    let lints = lint(source, &LintConfig::default()).unwrap();
+
+    assert_eq!(2, lints.len());
+    assert!(lints.iter().all(|lint| lint.fix.is_some()));
+    assert_eq!(source, fix(source, &LintConfig::default()).unwrap());
+}
+
+#[test]
+fn test_suppression_comments() {
+    let source = "// lint-ignore unused-let
+let a = 1
+let b = prop(\"X\") == true // lint-ignore
+/* lint-ignore: empty-comparison, unused-let */
+let c = prop(\"Y\") == \"\"
+let d = prop(\"Z\") == \"\"";
+
+    assert_eq!(
+        vec![
+            "6:1: warning [unused-let] d is never used",
+            "6:9: warning [empty-comparison] Use empty(prop(\"Z\")) to check for an empty value",
+        ],
+        lint_source(source)
+    );
+}
+
+#[test]
+fn test_config_changes_severities() {
+    let config = LintConfig::parse(
+        "# project settings\nunused-let = off\n\nempty-comparison = \"error\" # stricter\n",
+    )
+    .unwrap();
+    let lints: Vec<String> = lint("let a = prop(\"A\") == \"\"", &config)
+        .unwrap()
+        .iter()
+        .map(|lint| lint.to_string())
+        .collect();
+
+    assert_eq!(
+        vec!["1:9: error [empty-comparison] Use empty(prop(\"A\")) to check for an empty value"],
+        lints
+    );
+    assert_eq!(None, config.severity(&UnusedLet));
+    assert_eq!(Some(Severity::Error), config.severity(&UnknownProp));
+}
+
+#[test]
+fn test_config_errors() {
+    let cases = vec![
+        (
+            "nested-if",
+            "Line 1: expected <rule> = <off|info|warning|error>",
+        ),
+        ("\nno-such-rule = off", "Line 2: unknown rule no-such-rule"),
+        ("nested-if = loud", "Line 1: unknown level loud"),
+    ];
+
+    for (input, expected) in cases {
+        assert_eq!(expected, LintConfig::parse(input).unwrap_err().to_string());
+    }
+}
+
+#[test]
+fn test_nested_if_only_reports_the_head_of_a_chain() {
+    let source = "print true ? 1 : false ? 2 : true ? 3 : false ? 4 : 5";
+
+    assert_eq!(
+        vec!["1:7: warning [nested-if] 4 nested conditions can be written with ifs"],
+        lint_source(source)
+    );
+    assert_eq!(
+        "print ifs(true, 1, false, 2, true, 3, false, 4, 5)",
+        fix(source, &LintConfig::default()).unwrap()
+    );
+}
+
+#[test]
+fn test_every_rule_can_be_configured() {
+    for rule in RULES {
+        let config = LintConfig::parse(&format!("{} = off", rule.id())).unwrap();
+        assert_eq!(None, config.severity(*rule));
+    }
+}
+
+#[test]
+fn test_fixes_only_replace_the_reported_node() {
+    let source = "formula Status {
+    prop(\"Done\") == true
+        ? \"done\"   // finished
+        : prop(\"Due\") == \"\"
+}
+print (prop(\"A\") or prop(\"B\")) == true and prop(\"C\")";
+
+    assert_eq!(
+        "formula Status {
+    prop(\"Done\")
+        ? \"done\"   // finished
+        : empty(prop(\"Due\"))
+}
+print (prop(\"A\") or prop(\"B\")) and prop(\"C\")",
+        fix(source, &LintConfig::default()).unwrap()
+    );
+}
+
+#[test]
+fn test_lints_every_sample_file() {
+    let samples = [
+        include_str!("../../tests/test_formula.notion"),
+        include_str!("../../tests/complex_example.notion"),
+    ];
+
+    for source in samples {
+        assert!(lint(source, &LintConfig::default()).is_ok());
+        let fixed = fix(source, &LintConfig::default()).unwrap();
+        assert!(lint(&fixed, &LintConfig::default()).is_ok());
+    }
+}
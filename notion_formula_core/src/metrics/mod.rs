@@ -0,0 +1,211 @@
+use crate::interpreter::unquote;
+use crate::parser::{walk_expression, Expression, LocatedStatement, Statement, Type, Visitor};
+use pipeline::{HandlerResult, SimpleError};
+use std::fmt;
+
+/// Size and complexity of a single formula.
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct Metrics {
+    pub nodes: usize,
+    /// The longest path from the root to a leaf, counting both.
+    pub depth: usize,
+    pub props: usize,
+    /// Distinct functions called, in order of first use.
+    pub functions: Vec<String>,
+    /// One plus the number of decisions, like cyclomatic complexity: every
+    /// condition of `if`, `ifs` or a ternary and every `and` or `or`.
+    pub branches: usize,
+    /// A rough relative estimate of the work to evaluate the formula. Every
+    /// node is counted, since both sides of every branch are evaluated.
+    pub cost: usize,
+}
+impl Metrics {
+    /// Describes every metric that goes over its limit.
+    pub fn exceeded(&self, limits: &Limits) -> Vec<String> {
+        let values = [
+            ("nodes", self.nodes, limits.nodes),
+            ("depth", self.depth, limits.depth),
+            ("props", self.props, limits.props),
+            ("functions", self.functions.len(), limits.functions),
+            ("branches", self.branches, limits.branches),
+            ("cost", self.cost, limits.cost),
+        ];
+
+        values
+            .iter()
+            .filter_map(|(name, value, limit)| match limit {
+                Some(limit) if value > limit => Some(format!(
+                    "{} is {}, over the limit of {}",
+                    name, value, limit
+                )),
+                _ => None,
+            })
+            .collect()
+    }
+}
+impl fmt::Display for Metrics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "nodes {}, depth {}, props {}, functions {}, branches {}, cost {}",
+            self.nodes,
+            self.depth,
+            self.props,
+            self.functions.len(),
+            self.branches,
+            self.cost
+        )
+    }
+}
+
+/// The largest value allowed for each metric. Metrics without a limit
+/// aren't checked.
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct Limits {
+    pub nodes: Option<usize>,
+    pub depth: Option<usize>,
+    pub props: Option<usize>,
+    pub functions: Option<usize>,
+    pub branches: Option<usize>,
+    pub cost: Option<usize>,
+}
+impl Limits {
+    /// Sets a limit from a `metric=value` argument, e.g. `depth=10`.
+    pub fn parse_limit(&mut self, input: &str) -> HandlerResult<()> {
+        let invalid = || {
+            SimpleError::new(format!(
+                "Invalid limit {}, expected <metric>=<number>",
+                input
+            ))
+        };
+        let (name, value) = input.split_once('=').ok_or_else(invalid)?;
+        let value = value.trim().parse::<usize>().map_err(|_| invalid())?;
+
+        let limit = match name.trim() {
+            "nodes" => &mut self.nodes,
+            "depth" => &mut self.depth,
+            "props" => &mut self.props,
+            "functions" => &mut self.functions,
+            "branches" => &mut self.branches,
+            "cost" => &mut self.cost,
+            name => return Err(SimpleError::new(format!("Unknown metric {}", name))),
+        };
+        *limit = Some(value);
+        Ok(())
+    }
+}
+
+/// Metrics of one formula in a document.
+#[derive(Debug, PartialEq, Clone)]
+pub struct FormulaMetrics {
+    pub name: String,
+    pub line: u32,
+    pub metrics: Metrics,
+}
+
+pub fn measure(input: &Expression) -> Metrics {
+    let mut collector = Collector {
+        metrics: Metrics {
+            branches: 1,
+            ..Metrics::default()
+        },
+        depth: 0,
+    };
+    collector.visit_expression(input);
+    collector.metrics
+}
+
+/// Measures every formula of a document: formula definitions, formula
+/// columns of tables and the expressions of the other statements.
+pub fn measure_document(statements: &[LocatedStatement]) -> Vec<FormulaMetrics> {
+    let mut result = vec![];
+
+    for located in statements {
+        let line = located.tokens.first().map_or(0, |token| token.line);
+        let mut push = |name: String, input: &Expression| {
+            result.push(FormulaMetrics {
+                name,
+                line,
+                metrics: measure(input),
+            })
+        };
+
+        match &located.statement {
+            Statement::TableDef(table, columns) => {
+                for column in columns {
+                    if let Type::Formula(input) = &column.value {
+                        push(format!("{}.{}", table, unquote(&column.key)), input);
+                    }
+                }
+            }
+            Statement::FormulaDef(name, input) => push(name.clone(), input),
+            Statement::Assignment(name, input) => push(format!("let {}", name), input),
+            Statement::PrintStatement(input) => push("print".into(), input),
+            Statement::AssertStatement(input) => push("assert".into(), input),
+            Statement::ExpressionStatement(input) => push("expression".into(), input),
+        }
+    }
+    result
+}
+
+/// Adds up the metrics of every node below the expression it visits.
+struct Collector {
+    metrics: Metrics,
+    // The depth of the node being visited.
+    depth: usize,
+}
+impl Visitor for Collector {
+    fn visit_expression(&mut self, input: &Expression) {
+        let metrics = &mut self.metrics;
+        self.depth += 1;
+        metrics.depth = metrics.depth.max(self.depth);
+        metrics.nodes += 1;
+        metrics.cost += match input {
+            Expression::Call(callee, args) => match callee.as_ref() {
+                Expression::Identifier(name) => {
+                    if !metrics.functions.contains(name) {
+                        metrics.functions.push(name.clone());
+                    }
+                    match name.as_str() {
+                        "prop" => metrics.props += 1,
+                        "if" => metrics.branches += 1,
+                        "ifs" => metrics.branches += args.len() / 2,
+                        _ => (),
+                    }
+                    call_cost(name)
+                }
+                _ => 1,
+            },
+            Expression::TernaryOp(_, _, _) | Expression::BooleanOp(_, _, _) => {
+                metrics.branches += 1;
+                1
+            }
+            Expression::Access(_, _) | Expression::TableInstance(_, _) => 2,
+            _ => 1,
+        };
+
+        walk_expression(self, input);
+        self.depth -= 1;
+    }
+
+    // The callee is measured as part of the call.
+    fn visit_call(&mut self, _callee: &Expression, args: &[Expression]) {
+        for arg in args {
+            self.visit_expression(arg);
+        }
+    }
+}
+
+// Functions that scan or build text cost more than arithmetic.
+fn call_cost(name: &str) -> usize {
+    match name {
+        "replaceAll" => 4,
+        "replace" | "contains" => 3,
+        "prop" | "concat" | "join" | "format" | "toNumber" | "slice" | "substring" | "lower"
+        | "upper" | "length" => 2,
+        _ => 1,
+    }
+}
+
+#[cfg(test)]
+mod test;
@@ -0,0 +1,101 @@
+use super::*;
+use crate::parser::{formula_parser, located_document_parser};
+use crate::tokenizer::tokenizer;
+
+fn measure_source(input: &str) -> Metrics {
+    let tokens = tokenizer(input.chars().collect()).unwrap();
+    measure(&formula_parser(tokens).unwrap())
+}
+
+#[test]
+fn test_measures_the_example_formula() {
+    let metrics = measure_source(include_str!("../../tests/test_formula.notion"));
+
+    assert_eq!(
+        Metrics {
+            nodes: 18,
+            depth: 5,
+            props: 3,
+            functions: vec!["if".into(), "prop".into()],
+            branches: 4,
+            cost: 21,
+        },
+        metrics
+    );
+}
+
+#[test]
+fn test_measures_simple_formulas() {
+    let cases = vec![
+        ("1", (1, 1, 0, 0, 1, 1)),
+        ("1 + 2 * 3", (5, 3, 0, 0, 1, 5)),
+        ("replaceAll(prop(\"A\"), \"a\", \"b\")", (5, 3, 1, 2, 1, 9)),
+        ("ifs(true, 1, false, 2, 3) and x or y", (10, 4, 0, 1, 5, 10)),
+        ("Users { \"a\": 1 }[\"a\"]", (4, 3, 0, 0, 1, 6)),
+    ];
+
+    for (input, (nodes, depth, props, functions, branches, cost)) in cases {
+        let metrics = measure_source(input);
+        assert_eq!(
+            (nodes, depth, props, functions, branches, cost),
+            (
+                metrics.nodes,
+                metrics.depth,
+                metrics.props,
+                metrics.functions.len(),
+                metrics.branches,
+                metrics.cost
+            ),
+            "{}",
+            input
+        );
+    }
+}
+
+#[test]
+fn test_measures_every_formula_of_a_document() {
+    let source = "table Tasks { \"Name\": Text, \"Label\": formula { upper(prop(\"Name\")) } }
+formula Double { 2 * 2 }
+let x = 1
+print x";
+    let statements = located_document_parser(tokenizer(source.chars().collect()).unwrap()).unwrap();
+    let names: Vec<(String, u32, usize)> = measure_document(&statements)
+        .into_iter()
+        .map(|formula| (formula.name, formula.line, formula.metrics.nodes))
+        .collect();
+
+    assert_eq!(
+        vec![
+            ("Tasks.Label".to_string(), 1, 3),
+            ("Double".to_string(), 2, 3),
+            ("let x".to_string(), 3, 1),
+            ("print".to_string(), 4, 1),
+        ],
+        names
+    );
+}
+
+#[test]
+fn test_limits() {
+    let mut limits = Limits::default();
+    limits.parse_limit("depth=2").unwrap();
+    limits.parse_limit("cost = 100").unwrap();
+    let metrics = measure_source("1 + 2 * 3");
+
+    assert_eq!(
+        vec!["depth is 3, over the limit of 2"],
+        metrics.exceeded(&limits)
+    );
+    assert_eq!(
+        "nodes 5, depth 3, props 0, functions 0, branches 1, cost 5",
+        metrics.to_string()
+    );
+    assert_eq!(
+        "Unknown metric size",
+        limits.parse_limit("size=1").unwrap_err().to_string()
+    );
+    assert_eq!(
+        "Invalid limit depth, expected <metric>=<number>",
+        limits.parse_limit("depth").unwrap_err().to_string()
+    );
+}
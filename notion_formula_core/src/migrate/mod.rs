@@ -0,0 +1,247 @@
+use crate::formatter::format_expression;
+use crate::interpreter::unquote;
+use crate::parser::{
+    formula_parser, walk_expression_mut, ComparisonOperator, Expression, MathOperator,
+    UnaryOperator, VisitorMut,
+};
+use crate::tokenizer::{lossless_tokenizer, tokenizer, Trivia};
+use crate::typechecker::{StaticType, TypeChecker};
+use pipeline::HandlerResult;
+use std::fmt::Write;
+
+/// One automatic rewrite, with the formatted source of the expression before
+/// and after it.
+#[derive(Debug, PartialEq)]
+pub struct Rewrite {
+    pub reason: String,
+    pub before: String,
+    pub after: String,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Migration {
+    pub before: String,
+    pub after: String,
+    pub rewrites: Vec<Rewrite>,
+    /// Behavior changes in 2.0 that need a person to look at the formula.
+    pub warnings: Vec<String>,
+}
+impl Migration {
+    pub fn changed(&self) -> bool {
+        !self.rewrites.is_empty()
+    }
+
+    /// Renders a diff-like report of the migration, headed by `name`.
+    pub fn report(&self, name: &str) -> String {
+        let mut output = String::new();
+        let _ = writeln!(output, "--- {}", name);
+
+        if self.changed() {
+            for line in self.before.trim_end().lines() {
+                let _ = writeln!(output, "- {}", line);
+            }
+            for line in self.after.trim_end().lines() {
+                let _ = writeln!(output, "+ {}", line);
+            }
+        } else if self.warnings.is_empty() {
+            output.push_str("  nothing to migrate\n");
+        }
+
+        for rewrite in &self.rewrites {
+            let _ = writeln!(
+                output,
+                "  rewrote: {} ({} => {})",
+                rewrite.reason, rewrite.before, rewrite.after
+            );
+        }
+        for warning in &self.warnings {
+            let _ = writeln!(output, "  warning: {}", warning);
+        }
+        output
+    }
+}
+
+/// Rewrites Notion formula 1.0 constructs into their 2.0 equivalents:
+///
+/// - `slice(text, ...)` becomes `substring(text, ...)`, since 2.0's `slice`
+///   works on lists.
+/// - `concat(a, b)` becomes `a + b`, since 2.0's `concat` joins lists.
+/// - `x == ""` and `x != ""` become `empty(x)` and `not empty(x)`, which
+///   also work for dates and lists. As `empty` is true for 0 and false too,
+///   this is flagged unless `x` is known to be text.
+/// - `+x` becomes `toNumber(x)`.
+///
+/// Behavior changes that can't be rewritten, like `join` taking a list or
+/// properties in `list_properties` turning from comma separated text into
+/// lists, are reported as warnings. Formulas without rewrites are returned
+/// unchanged; rewritten ones are reformatted, and lose their comments.
+pub fn migrate(formulas: &[String], list_properties: &[String]) -> HandlerResult<Vec<Migration>> {
+    formulas
+        .iter()
+        .map(|source| migrate_formula(source, list_properties))
+        .collect()
+}
+
+fn migrate_formula(source: &str, list_properties: &[String]) -> HandlerResult<Migration> {
+    let mut expression = formula_parser(tokenizer(source.chars().collect())?)?;
+    let mut migrator = Migrator {
+        list_properties,
+        rewrites: vec![],
+        warnings: vec![],
+    };
+    migrator.visit_expression_mut(&mut expression);
+
+    let mut warnings = migrator.warnings;
+    let after = if migrator.rewrites.is_empty() {
+        source.to_string()
+    } else {
+        if has_comments(source)? {
+            warnings.push("Comments were removed from the rewritten formula".into());
+        }
+        let mut after = render(&expression);
+        if source.ends_with('\n') {
+            after.push('\n');
+        }
+        after
+    };
+
+    Ok(Migration {
+        before: source.to_string(),
+        after,
+        rewrites: migrator.rewrites,
+        warnings,
+    })
+}
+
+fn has_comments(source: &str) -> HandlerResult<bool> {
+    let tokens = lossless_tokenizer(source.chars().collect())?;
+    Ok(tokens.iter().any(|token| {
+        token
+            .leading
+            .iter()
+            .chain(token.trailing.iter())
+            .any(|trivia| matches!(trivia, Trivia::Comment(_)))
+    }))
+}
+
+fn render(input: &Expression) -> String {
+    format_expression(input).unwrap_or_default()
+}
+
+struct Migrator<'a> {
+    list_properties: &'a [String],
+    rewrites: Vec<Rewrite>,
+    warnings: Vec<String>,
+}
+impl Migrator<'_> {
+    fn rewrite(&mut self, expr: &mut Expression, reason: &str, after: Expression) {
+        self.rewrites.push(Rewrite {
+            reason: reason.into(),
+            before: render(expr),
+            after: render(&after),
+        });
+        *expr = after;
+    }
+
+    // Comparing with "" only matched empty text, while `empty` also matches
+    // 0 and false, so the rewrite needs checking unless `operand` is text.
+    fn check_text(&mut self, operand: &Expression) {
+        if let Ok(StaticType::Str) = TypeChecker::new().infer(operand) {
+            return;
+        }
+        self.warn(format!(
+            "empty({}) is also true for 0 and false, check that it is only used with text or dates",
+            render(operand)
+        ));
+    }
+
+    fn warn(&mut self, warning: String) {
+        if !self.warnings.contains(&warning) {
+            self.warnings.push(warning);
+        }
+    }
+}
+impl VisitorMut for Migrator<'_> {
+    fn visit_expression_mut(&mut self, expr: &mut Expression) {
+        walk_expression_mut(self, expr);
+
+        let rewrite = match &*expr {
+            Expression::Call(callee, args) => match (callee.as_ref(), args.as_slice()) {
+                (Expression::Identifier(name), [..]) if name == "slice" => Some((
+                    "slice on text is substring in 2.0",
+                    call("substring", args.clone()),
+                )),
+                (Expression::Identifier(name), [first, rest @ ..]) if name == "concat" => Some((
+                    "concat joins lists in 2.0, text is joined with +",
+                    rest.iter().cloned().fold(first.clone(), |lhs, rhs| {
+                        Expression::BinaryOp(Box::new(lhs), MathOperator::Add, Box::new(rhs))
+                    }),
+                )),
+                (Expression::Identifier(name), [_, _, ..]) if name == "join" => {
+                    self.warn(format!(
+                        "join takes a list in 2.0, {} needs to be rewritten by hand",
+                        render(expr)
+                    ));
+                    None
+                }
+                (Expression::Identifier(name), [Expression::Str(key)]) if name == "prop" => {
+                    let key = unquote(key);
+                    if self.list_properties.contains(&key) {
+                        self.warn(format!(
+                            "prop(\"{}\") is a list in 2.0 instead of comma separated text",
+                            key
+                        ));
+                    }
+                    None
+                }
+                _ => None,
+            },
+            Expression::Comparison(lhs, op, rhs) => {
+                let operand = match (lhs.as_ref(), rhs.as_ref()) {
+                    (operand, Expression::Str(value)) | (Expression::Str(value), operand)
+                        if value == "\"\"" =>
+                    {
+                        Some(operand.clone())
+                    }
+                    _ => None,
+                };
+                match (op, operand) {
+                    (ComparisonOperator::Equals, Some(operand)) => {
+                        self.check_text(&operand);
+                        Some((
+                            "comparing with \"\" is empty() in 2.0",
+                            call("empty", vec![operand]),
+                        ))
+                    }
+                    (ComparisonOperator::NotEquals, Some(operand)) => {
+                        self.check_text(&operand);
+                        Some((
+                            "comparing with \"\" is empty() in 2.0",
+                            Expression::UnaryOp(
+                                UnaryOperator::Not,
+                                Box::new(call("empty", vec![operand])),
+                            ),
+                        ))
+                    }
+                    _ => None,
+                }
+            }
+            Expression::UnaryOp(UnaryOperator::UAdd, operand) => Some((
+                "unary + is toNumber() in 2.0",
+                call("toNumber", vec![operand.as_ref().clone()]),
+            )),
+            _ => None,
+        };
+
+        if let Some((reason, after)) = rewrite {
+            self.rewrite(expr, reason, after);
+        }
+    }
+}
+
+fn call(name: &str, args: Vec<Expression>) -> Expression {
+    Expression::Call(Box::new(Expression::Identifier(name.into())), args)
+}
+
+#[cfg(test)]
+mod test;
@@ -0,0 +1,126 @@
+use super::*;
+
+fn migrate_one(source: &str, list_properties: &[&str]) -> Migration {
+    let list_properties: Vec<String> = list_properties
+        .iter()
+        .map(|name| name.to_string())
+        .collect();
+    migrate(&[source.to_string()], &list_properties)
+        .unwrap()
+        .remove(0)
+}
+
+#[test]
+fn test_rewrites_legacy_functions() {
+    let cases = vec![
+        (
+            "slice(prop(\"Name\"), 0, 3)",
+            "substring(prop(\"Name\"), 0, 3)",
+        ),
+        (
+            "concat(prop(\"First\"), \" \", prop(\"Last\"))",
+            "prop(\"First\") + \" \" + prop(\"Last\")",
+        ),
+        ("concat(\"a\")", "\"a\""),
+        ("upper(concat(\"a\", \"b\"))", "upper(\"a\" + \"b\")"),
+        ("format(prop(\"Count\")) == \"\"", "empty(format(prop(\"Count\")))"),
+        ("\"\" != lower(prop(\"Name\"))", "not empty(lower(prop(\"Name\")))"),
+        ("+prop(\"Count\") * 2", "toNumber(prop(\"Count\")) * 2"),
+        (
+            "concat(slice(\"abc\", 1), \"d\")",
+            "substring(\"abc\", 1) + \"d\"",
+        ),
+    ];
+
+    for (before, after) in cases {
+        let migration = migrate_one(before, &[]);
+        assert_eq!(after, migration.after, "{}", before);
+        assert!(migration.changed());
+        assert!(migration.warnings.is_empty(), "{:?}", migration.warnings);
+    }
+}
+
+#[test]
+fn test_flags_empty_rewrites_of_values_that_may_not_be_text() {
+    let cases = vec![
+        ("prop(\"Date\") == \"\"", "empty(prop(\"Date\"))"),
+        ("\"\" != prop(\"Date\")", "not empty(prop(\"Date\"))"),
+    ];
+
+    for (before, after) in cases {
+        let migration = migrate_one(before, &[]);
+        assert_eq!(after, migration.after, "{}", before);
+        assert_eq!(
+            vec![
+                "empty(prop(\"Date\")) is also true for 0 and false, check that it is only used with text or dates"
+            ],
+            migration.warnings
+        );
+    }
+}
+
+#[test]
+fn test_leaves_current_formulas_alone() {
+    let source = "if(\n    prop(\"Done\"), // finished\n    \"✅\",\n    \"\"\n)";
+    let migration = migrate_one(source, &[]);
+
+    assert_eq!(source, migration.after);
+    assert!(!migration.changed());
+    assert_eq!("--- a\n  nothing to migrate\n", migration.report("a"));
+}
+
+#[test]
+fn test_warns_about_changes_it_cant_rewrite() {
+    let migration = migrate_one(
+        "join(\", \", prop(\"Tags\"), \"x\") + format(length(prop(\"Tags\")))",
+        &["Tags"],
+    );
+
+    assert!(!migration.changed());
+    assert_eq!(
+        vec![
+            "prop(\"Tags\") is a list in 2.0 instead of comma separated text",
+            "join takes a list in 2.0, join(\", \", prop(\"Tags\"), \"x\") needs to be rewritten by hand",
+        ],
+        migration.warnings
+    );
+}
+
+#[test]
+fn test_reports_rewrites_as_a_diff() {
+    let migration = migrate_one(
+        "if(\n    prop(\"Due\") == \"\", /* no date */\n    slice(prop(\"Name\"), 0, 1),\n    \"\"\n)",
+        &[],
+    );
+
+    assert_eq!(
+        "--- Initial\n\
+         - if(\n\
+         -     prop(\"Due\") == \"\", /* no date */\n\
+         -     slice(prop(\"Name\"), 0, 1),\n\
+         -     \"\"\n\
+         - )\n\
+         + if(empty(prop(\"Due\")), substring(prop(\"Name\"), 0, 1), \"\")\n  \
+         rewrote: comparing with \"\" is empty() in 2.0 (prop(\"Due\") == \"\" => empty(prop(\"Due\")))\n  \
+         rewrote: slice on text is substring in 2.0 (slice(prop(\"Name\"), 0, 1) => substring(prop(\"Name\"), 0, 1))\n  \
+         warning: empty(prop(\"Due\")) is also true for 0 and false, check that it is only used with text or dates\n  \
+         warning: Comments were removed from the rewritten formula\n",
+        migration.report("Initial")
+    );
+}
+
+#[test]
+fn test_migrated_formulas_still_evaluate() {
+    use crate::interpreter::interpret;
+
+    let migration = migrate_one("concat(slice(\"hello\", 1, 3), format(+\"2\" == 2))", &[]);
+    let tokens = tokenizer(migration.after.chars().collect()).unwrap();
+    let result = interpret(formula_parser(tokens).unwrap()).unwrap();
+
+    assert_eq!("eltrue", result.to_string());
+}
+
+#[test]
+fn test_reports_parse_errors() {
+    assert!(migrate(&["slice(".to_string()], &[]).is_err());
+}
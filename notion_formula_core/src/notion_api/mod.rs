@@ -0,0 +1,142 @@
+use crate::interpreter::{Props, RuntimeType};
+use pipeline::{HandlerResult, SimpleError};
+use serde_json::Value;
+
+/// Reads a page object as returned by the Notion API, e.g. from
+/// `GET /v1/pages/{id}` or one of the results of a database query.
+pub fn read_page(input: &str) -> HandlerResult<Props> {
+    let page: Value = serde_json::from_str(input)?;
+    page_props(&page)
+}
+
+/// Converts the `properties` of a page into the values `prop()` reads.
+/// Either the whole page object or just its `properties` can be passed.
+///
+/// Text-like properties become Text, with lists such as multi-selects,
+/// people, relations and files joined by ", ". Dates are their start,
+/// followed by " → " and the end for ranges. Empty numbers read as 0 and
+/// empty selects and dates as empty text.
+///
+/// Properties without a value a formula could use, such as buttons or
+/// rollups Notion reports as `incomplete` or `unsupported`, are left out,
+/// along with any type this module doesn't know yet.
+pub fn page_props(page: &Value) -> HandlerResult<Props> {
+    let properties = match page.get("properties") {
+        Some(properties) => properties,
+        None => page,
+    };
+    let properties = properties
+        .as_object()
+        .ok_or_else(|| SimpleError::new("Expected the page properties to be an object".into()))?;
+
+    let mut props = Props::new();
+    for (name, property) in properties {
+        let value = convert(property)
+            .map_err(|e| SimpleError::new(format!("{} for property {}", e, name)))?;
+        if let Some(value) = value {
+            props.insert(name.clone(), value);
+        }
+    }
+    Ok(props)
+}
+
+/// Converts a single property value object, which carries its `type`
+/// alongside a field of the same name holding the value.
+pub fn property_value(property: &Value) -> HandlerResult<RuntimeType> {
+    match convert(property)? {
+        Some(value) => Ok(value),
+        None => Err(SimpleError::new(format!(
+            "Unsupported property type {}",
+            property["type"].as_str().unwrap_or_default()
+        ))),
+    }
+}
+
+// Like `property_value`, with `None` for unsupported types.
+fn convert(property: &Value) -> HandlerResult<Option<RuntimeType>> {
+    let property_type = property["type"]
+        .as_str()
+        .ok_or_else(|| SimpleError::new("Missing property type".into()))?;
+    let value = &property[property_type];
+
+    let result = match property_type {
+        "title" | "rich_text" => RuntimeType::Str(rich_text(value)),
+        "number" => RuntimeType::Num(value.as_f64().unwrap_or(0.0)),
+        "checkbox" => RuntimeType::Bool(value.as_bool().unwrap_or(false)),
+        "select" | "status" => RuntimeType::Str(field(value, "name")),
+        "multi_select" => RuntimeType::Str(join(value, |option| field(option, "name"))),
+        "date" => RuntimeType::Str(date(value)),
+        "people" => RuntimeType::Str(join(value, person)),
+        "created_by" | "last_edited_by" => RuntimeType::Str(person(value)),
+        "relation" => RuntimeType::Str(join(value, |page| field(page, "id"))),
+        "files" => RuntimeType::Str(join(value, |file| field(file, "name"))),
+        "unique_id" => RuntimeType::Str(unique_id(value)),
+        "verification" => RuntimeType::Str(field(value, "state")),
+        "url" | "email" | "phone_number" | "created_time" | "last_edited_time" | "string" => {
+            RuntimeType::Str(value.as_str().unwrap_or_default().to_string())
+        }
+        "boolean" => RuntimeType::Bool(value.as_bool().unwrap_or(false)),
+        "formula" => return convert(value),
+        "rollup" => match value["type"].as_str() {
+            Some("array") => {
+                let items = value["array"].as_array().map(Vec::as_slice).unwrap_or_default();
+                let mut texts = vec![];
+                for item in items {
+                    if let Some(value) = convert(item)? {
+                        texts.push(value.to_string());
+                    }
+                }
+                RuntimeType::Str(texts.join(", "))
+            }
+            _ => return convert(value),
+        },
+        _ => return Ok(None),
+    };
+    Ok(Some(result))
+}
+
+fn rich_text(value: &Value) -> String {
+    value
+        .as_array()
+        .into_iter()
+        .flatten()
+        .map(|text| text["plain_text"].as_str().unwrap_or_default())
+        .collect()
+}
+
+fn field(value: &Value, name: &str) -> String {
+    value[name].as_str().unwrap_or_default().to_string()
+}
+
+fn join(value: &Value, f: impl Fn(&Value) -> String) -> String {
+    let items: Vec<String> = value.as_array().into_iter().flatten().map(f).collect();
+    items.join(", ")
+}
+
+// Users shared with the integration only partially have no name.
+fn person(user: &Value) -> String {
+    match user["name"].as_str() {
+        Some(name) => name.to_string(),
+        None => field(user, "id"),
+    }
+}
+
+// IDs show as "TASK-12" when the database sets a prefix.
+fn unique_id(value: &Value) -> String {
+    let number = value["number"].as_i64().map(|n| n.to_string()).unwrap_or_default();
+    match value["prefix"].as_str() {
+        Some(prefix) => format!("{}-{}", prefix, number),
+        None => number,
+    }
+}
+
+fn date(value: &Value) -> String {
+    match (value["start"].as_str(), value["end"].as_str()) {
+        (Some(start), Some(end)) => format!("{} → {}", start, end),
+        (Some(start), None) => start.to_string(),
+        _ => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod test;
@@ -0,0 +1,174 @@
+use super::*;
+use crate::interpreter::Interpreter;
+use crate::parser::formula_parser;
+use crate::tokenizer::tokenizer;
+use serde_json::json;
+
+fn page() -> Value {
+    json!({
+        "object": "page",
+        "id": "59833787-2cf9-4fdf-8782-e53db20768a5",
+        "properties": {
+            "Name": {
+                "id": "title",
+                "type": "title",
+                "title": [
+                    { "type": "text", "text": { "content": "Write " }, "plain_text": "Write " },
+                    { "type": "text", "text": { "content": "docs" }, "plain_text": "docs" }
+                ]
+            },
+            "Notes": { "id": "a", "type": "rich_text", "rich_text": [] },
+            "Estimate": { "id": "b", "type": "number", "number": 3 },
+            "Budget": { "id": "c", "type": "number", "number": null },
+            "Done": { "id": "d", "type": "checkbox", "checkbox": true },
+            "State": { "id": "e", "type": "select", "select": { "id": "x", "name": "🔵", "color": "blue" } },
+            "Priority": { "id": "f", "type": "select", "select": null },
+            "Tags": {
+                "id": "g",
+                "type": "multi_select",
+                "multi_select": [{ "name": "docs" }, { "name": "urgent" }]
+            },
+            "Due": {
+                "id": "h",
+                "type": "date",
+                "date": { "start": "2021-05-01", "end": "2021-05-03", "time_zone": null }
+            },
+            "Started": { "id": "i", "type": "date", "date": { "start": "2021-04-30", "end": null } },
+            "Assignees": {
+                "id": "j",
+                "type": "people",
+                "people": [{ "object": "user", "id": "u1", "name": "Josh" }, { "object": "user", "id": "u2" }]
+            },
+            "Project": { "id": "k", "type": "relation", "relation": [{ "id": "p1" }, { "id": "p2" }] },
+            "Label": {
+                "id": "l",
+                "type": "formula",
+                "formula": { "type": "string", "string": "Write docs (3)" }
+            },
+            "Overdue": { "id": "m", "type": "formula", "formula": { "type": "boolean", "boolean": false } },
+            "Total": {
+                "id": "n",
+                "type": "rollup",
+                "rollup": { "type": "number", "number": 12.5, "function": "sum" }
+            },
+            "Project Names": {
+                "id": "o",
+                "type": "rollup",
+                "rollup": {
+                    "type": "array",
+                    "array": [
+                        { "type": "title", "title": [{ "plain_text": "Website" }] },
+                        { "type": "title", "title": [{ "plain_text": "Docs" }] }
+                    ],
+                    "function": "show_original"
+                }
+            }
+        }
+    })
+}
+
+#[test]
+fn test_converts_every_property_type() {
+    let props = page_props(&page()).unwrap();
+    let expected = vec![
+        ("Name", RuntimeType::Str("Write docs".into())),
+        ("Notes", RuntimeType::Str("".into())),
+        ("Estimate", RuntimeType::Num(3.0)),
+        ("Budget", RuntimeType::Num(0.0)),
+        ("Done", RuntimeType::Bool(true)),
+        ("State", RuntimeType::Str("🔵".into())),
+        ("Priority", RuntimeType::Str("".into())),
+        ("Tags", RuntimeType::Str("docs, urgent".into())),
+        ("Due", RuntimeType::Str("2021-05-01 → 2021-05-03".into())),
+        ("Started", RuntimeType::Str("2021-04-30".into())),
+        ("Assignees", RuntimeType::Str("Josh, u2".into())),
+        ("Project", RuntimeType::Str("p1, p2".into())),
+        ("Label", RuntimeType::Str("Write docs (3)".into())),
+        ("Overdue", RuntimeType::Bool(false)),
+        ("Total", RuntimeType::Num(12.5)),
+        ("Project Names", RuntimeType::Str("Website, Docs".into())),
+    ];
+
+    assert_eq!(expected.len(), props.len());
+    for (name, value) in expected {
+        assert_eq!(Some(&value), props.get(name), "{}", name);
+    }
+}
+
+#[test]
+fn test_accepts_properties_object() {
+    let page = page();
+
+    assert_eq!(
+        page_props(&page).unwrap(),
+        page_props(&page["properties"]).unwrap()
+    );
+}
+
+#[test]
+fn test_converts_files_creators_and_ids() {
+    let page = json!({ "properties": {
+        "Files": {
+            "type": "files",
+            "files": [
+                { "name": "spec.pdf", "type": "file", "file": { "url": "https://example.com/spec.pdf" } },
+                { "name": "logo.png", "type": "external", "external": { "url": "https://example.com/logo.png" } }
+            ]
+        },
+        "Created by": { "type": "created_by", "created_by": { "object": "user", "id": "u1", "name": "Josh" } },
+        "Last edited by": { "type": "last_edited_by", "last_edited_by": { "object": "user", "id": "u2" } },
+        "ID": { "type": "unique_id", "unique_id": { "prefix": "TASK", "number": 12 } },
+        "Number": { "type": "unique_id", "unique_id": { "prefix": null, "number": 3 } },
+        "Verified": { "type": "verification", "verification": { "state": "verified", "date": null } }
+    } });
+    let props = page_props(&page).unwrap();
+
+    let expected = vec![
+        ("Files", "spec.pdf, logo.png"),
+        ("Created by", "Josh"),
+        ("Last edited by", "u2"),
+        ("ID", "TASK-12"),
+        ("Number", "3"),
+        ("Verified", "verified"),
+    ];
+    assert_eq!(expected.len(), props.len());
+    for (name, value) in expected {
+        assert_eq!(Some(&RuntimeType::Str(value.into())), props.get(name), "{}", name);
+    }
+}
+
+#[test]
+fn test_skips_unsupported_properties() {
+    let mut cached = page();
+    let properties = cached["properties"].as_object_mut().unwrap();
+    properties.insert("Archive".into(), json!({ "type": "button", "button": {} }));
+    properties.insert(
+        "Pending".into(),
+        json!({ "type": "rollup", "rollup": { "type": "incomplete", "incomplete": {}, "function": "sum" } }),
+    );
+    properties.insert(
+        "Other".into(),
+        json!({ "type": "rollup", "rollup": { "type": "unsupported", "unsupported": {}, "function": "sum" } }),
+    );
+    properties.insert("Future".into(), json!({ "type": "something_new", "something_new": 1 }));
+
+    let props = page_props(&cached).unwrap();
+
+    assert_eq!(page_props(&page()).unwrap(), props);
+    assert_eq!(
+        "Unsupported property type button",
+        property_value(&cached["properties"]["Archive"]).unwrap_err().to_string()
+    );
+    assert!(read_page("[]").is_err());
+}
+
+#[test]
+fn test_evaluates_formula_against_page() {
+    let props = read_page(&page().to_string()).unwrap();
+    let formula = formula_parser(
+        tokenizer("if(prop(\"Done\"), prop(\"Name\") + \" ✓\", \"\")".chars().collect()).unwrap(),
+    )
+    .unwrap();
+    let result = Interpreter::new().evaluate_with_props(&formula, &props).unwrap();
+    assert_eq!(RuntimeType::Str("Write docs ✓".into()), result);
+}
@@ -0,0 +1,518 @@
+use crate::tokenizer::{LosslessToken, TokenType};
+use lookahead_buffer::LookaheadBuffer;
+use crate::error::{FormulaError, Span};
+use std::fmt;
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum SyntaxKind {
+    Root,
+    Document,
+    TableDef,
+    Column,
+    FormulaDef,
+    Assignment,
+    Print,
+    Assert,
+    ExpressionStatement,
+    Ternary,
+    BooleanOp,
+    Not,
+    Comparison,
+    BinaryOp,
+    Prefix,
+    Parenthesized,
+    Call,
+    Access,
+    TableInstance,
+    Field,
+}
+
+/// A node of the concrete syntax tree. Unlike `Expression` it keeps every
+/// token, including parentheses, commas and surrounding trivia, so printing
+/// it reproduces the original source byte for byte.
+#[derive(Debug, PartialEq, Clone)]
+pub enum SyntaxNode {
+    Token(LosslessToken),
+    Node(SyntaxKind, Vec<SyntaxNode>),
+}
+impl SyntaxNode {
+    pub fn kind(&self) -> Option<&SyntaxKind> {
+        match self {
+            SyntaxNode::Node(kind, _) => Some(kind),
+            SyntaxNode::Token(_) => None,
+        }
+    }
+
+    pub fn children(&self) -> &[SyntaxNode] {
+        match self {
+            SyntaxNode::Node(_, children) => children,
+            SyntaxNode::Token(_) => &[],
+        }
+    }
+
+    pub fn tokens(&self) -> Vec<&LosslessToken> {
+        let mut result = vec![];
+        self.collect_tokens(&mut result);
+        result
+    }
+
+    pub fn tokens_mut(&mut self) -> Vec<&mut LosslessToken> {
+        match self {
+            SyntaxNode::Token(token) => vec![token],
+            SyntaxNode::Node(_, children) => children
+                .iter_mut()
+                .flat_map(|child| child.tokens_mut())
+                .collect(),
+        }
+    }
+
+    fn collect_tokens<'a>(&'a self, result: &mut Vec<&'a LosslessToken>) {
+        match self {
+            SyntaxNode::Token(token) => result.push(token),
+            SyntaxNode::Node(_, children) => {
+                for child in children {
+                    child.collect_tokens(result);
+                }
+            }
+        }
+    }
+}
+impl fmt::Display for SyntaxNode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for token in self.tokens() {
+            write!(f, "{}", token)?;
+        }
+        Ok(())
+    }
+}
+
+type ParseResult<T> = Result<T, FormulaError>;
+
+type ParseFn = fn(&mut LookaheadBuffer<LosslessToken>) -> ParseResult<SyntaxNode>;
+
+pub fn cst_parser(input: Vec<LosslessToken>) -> ParseResult<SyntaxNode> {
+    let mut buffer = LookaheadBuffer::new(input);
+    let expr = expression(&mut buffer)?;
+
+    match &current_token(&mut buffer)?.token.token_type {
+        TokenType::Eof => Ok(SyntaxNode::Node(
+            SyntaxKind::Root,
+            vec![expr, next_token(&mut buffer)?],
+        )),
+        _ => Err(unexpected_token(current_token(&mut buffer)?)),
+    }
+}
+
+/// Parses a whole document. Statements and the semicolons between them are
+/// children of the `Document` node, which ends with the `Eof` token.
+/// `document_parser` builds its statements from this tree, so both always
+/// follow the same grammar.
+pub fn cst_document_parser(input: Vec<LosslessToken>) -> ParseResult<SyntaxNode> {
+    let mut buffer = LookaheadBuffer::new(input);
+    let mut children = vec![];
+
+    loop {
+        match &current_token(&mut buffer)?.token.token_type {
+            TokenType::Eof => {
+                children.push(next_token(&mut buffer)?);
+                break;
+            }
+            TokenType::SemiColon => children.push(next_token(&mut buffer)?),
+            _ => children.push(statement(&mut buffer)?),
+        }
+    }
+
+    Ok(SyntaxNode::Node(SyntaxKind::Document, children))
+}
+
+fn statement(buffer: &mut LookaheadBuffer<LosslessToken>) -> ParseResult<SyntaxNode> {
+    let (kind, mut children) = match &current_token(buffer)?.token.token_type {
+        TokenType::Table => {
+            let table = next_token(buffer)?;
+            return table_definition(buffer, table);
+        }
+        TokenType::Formula => {
+            let mut children = vec![next_token(buffer)?, expect_identifier(buffer)?];
+            children.extend(braced_expression(buffer)?);
+            return Ok(SyntaxNode::Node(SyntaxKind::FormulaDef, children));
+        }
+        TokenType::Let => {
+            let token = next_token(buffer)?;
+            let name = expect_identifier(buffer)?;
+            let equal = expect(buffer, TokenType::Equal, "'='")?;
+            (SyntaxKind::Assignment, vec![token, name, equal])
+        }
+        TokenType::Print => (SyntaxKind::Print, vec![next_token(buffer)?]),
+        TokenType::Assert => (SyntaxKind::Assert, vec![next_token(buffer)?]),
+        _ => (SyntaxKind::ExpressionStatement, vec![]),
+    };
+
+    children.push(expression(buffer)?);
+    Ok(SyntaxNode::Node(kind, children))
+}
+
+// Each column is a `Column` node of its name, colon and type, with the commas
+// between columns kept as direct children of the table.
+fn table_definition(
+    buffer: &mut LookaheadBuffer<LosslessToken>,
+    table: SyntaxNode,
+) -> ParseResult<SyntaxNode> {
+    let name = expect_identifier(buffer)?;
+    let open = expect(buffer, TokenType::LeftBracket, "'{'")?;
+    let mut children = vec![table, name, open];
+
+    loop {
+        match &current_token(buffer)?.token.token_type {
+            TokenType::RightBracket => {
+                children.push(next_token(buffer)?);
+                break;
+            }
+            TokenType::StringLiteral(_) => {
+                let mut column = vec![next_token(buffer)?];
+                column.push(expect(buffer, TokenType::Colon, "':'")?);
+                column.extend(column_type(buffer)?);
+                children.push(SyntaxNode::Node(SyntaxKind::Column, column));
+
+                match &current_token(buffer)?.token.token_type {
+                    TokenType::Comma => children.push(next_token(buffer)?),
+                    TokenType::RightBracket => (),
+                    _ => return Err(expected("',' or '}'", current_token(buffer)?)),
+                }
+            }
+            _ => return Err(expected("a column name", current_token(buffer)?)),
+        }
+    }
+
+    Ok(SyntaxNode::Node(SyntaxKind::TableDef, children))
+}
+
+fn column_type(buffer: &mut LookaheadBuffer<LosslessToken>) -> ParseResult<Vec<SyntaxNode>> {
+    let token = current_token(buffer)?;
+    match &token.token.token_type {
+        TokenType::Formula => {
+            let mut children = vec![next_token(buffer)?];
+            children.extend(braced_expression(buffer)?);
+            Ok(children)
+        }
+        TokenType::Identifier(name) => match name.as_str() {
+            "Text" | "Number" | "Checkbox" => Ok(vec![next_token(buffer)?]),
+            _ => Err(FormulaError::parse(
+                format!("Unknown column type: {}", name),
+                Span::of_token(&token.token),
+            )),
+        },
+        _ => Err(expected("a column type", token)),
+    }
+}
+
+fn braced_expression(
+    buffer: &mut LookaheadBuffer<LosslessToken>,
+) -> ParseResult<Vec<SyntaxNode>> {
+    let open = expect(buffer, TokenType::LeftBracket, "'{'")?;
+    let expr = expression(buffer)?;
+    let close = expect(buffer, TokenType::RightBracket, "'}'")?;
+    Ok(vec![open, expr, close])
+}
+
+fn expression(buffer: &mut LookaheadBuffer<LosslessToken>) -> ParseResult<SyntaxNode> {
+    ternary_expression(buffer)
+}
+
+fn ternary_expression(buffer: &mut LookaheadBuffer<LosslessToken>) -> ParseResult<SyntaxNode> {
+    let test = or_expression(buffer)?;
+
+    if current_token(buffer)?.token.token_type != TokenType::QuestionMark {
+        return Ok(test);
+    }
+    let question = next_token(buffer)?;
+
+    let accept = or_expression(buffer)?;
+    let colon = current_token(buffer)?;
+    if colon.token.token_type != TokenType::Colon {
+        return Err(missing("Expected colon in ternary expression", colon));
+    }
+    let colon = next_token(buffer)?;
+
+    let reject = expression(buffer)?;
+    Ok(SyntaxNode::Node(
+        SyntaxKind::Ternary,
+        vec![test, question, accept, colon, reject],
+    ))
+}
+
+fn or_expression(buffer: &mut LookaheadBuffer<LosslessToken>) -> ParseResult<SyntaxNode> {
+    left_associative(buffer, SyntaxKind::BooleanOp, &[TokenType::Or], and_expression)
+}
+
+fn and_expression(buffer: &mut LookaheadBuffer<LosslessToken>) -> ParseResult<SyntaxNode> {
+    left_associative(buffer, SyntaxKind::BooleanOp, &[TokenType::And], not_expression)
+}
+
+fn not_expression(buffer: &mut LookaheadBuffer<LosslessToken>) -> ParseResult<SyntaxNode> {
+    match current_token(buffer)?.token.token_type {
+        TokenType::Not => {
+            let token = next_token(buffer)?;
+            let operand = not_expression(buffer)?;
+            Ok(SyntaxNode::Node(SyntaxKind::Not, vec![token, operand]))
+        }
+        _ => equality_expression(buffer),
+    }
+}
+
+fn equality_expression(buffer: &mut LookaheadBuffer<LosslessToken>) -> ParseResult<SyntaxNode> {
+    left_associative(
+        buffer,
+        SyntaxKind::Comparison,
+        &[TokenType::EqualEqual, TokenType::BangEqual],
+        relational_expression,
+    )
+}
+
+fn relational_expression(
+    buffer: &mut LookaheadBuffer<LosslessToken>,
+) -> ParseResult<SyntaxNode> {
+    let left = additive_expression(buffer)?;
+
+    match current_token(buffer)?.token.token_type {
+        TokenType::LessEqual | TokenType::Less | TokenType::GreaterEqual | TokenType::Greater => {
+            let token = next_token(buffer)?;
+            let right = additive_expression(buffer)?;
+            Ok(SyntaxNode::Node(SyntaxKind::Comparison, vec![left, token, right]))
+        }
+        _ => Ok(left),
+    }
+}
+
+fn additive_expression(buffer: &mut LookaheadBuffer<LosslessToken>) -> ParseResult<SyntaxNode> {
+    left_associative(
+        buffer,
+        SyntaxKind::BinaryOp,
+        &[TokenType::Plus, TokenType::Minus],
+        multiplicative_expression,
+    )
+}
+
+fn multiplicative_expression(
+    buffer: &mut LookaheadBuffer<LosslessToken>,
+) -> ParseResult<SyntaxNode> {
+    left_associative(
+        buffer,
+        SyntaxKind::BinaryOp,
+        &[TokenType::Star, TokenType::Slash, TokenType::Percent],
+        prefix_expression,
+    )
+}
+
+fn prefix_expression(buffer: &mut LookaheadBuffer<LosslessToken>) -> ParseResult<SyntaxNode> {
+    match current_token(buffer)?.token.token_type {
+        TokenType::Minus | TokenType::Plus => {
+            let token = next_token(buffer)?;
+            let operand = prefix_expression(buffer)?;
+            Ok(SyntaxNode::Node(SyntaxKind::Prefix, vec![token, operand]))
+        }
+        _ => exponential_expression(buffer),
+    }
+}
+
+fn exponential_expression(
+    buffer: &mut LookaheadBuffer<LosslessToken>,
+) -> ParseResult<SyntaxNode> {
+    let left = atomic_expression(buffer)?;
+
+    match current_token(buffer)?.token.token_type {
+        TokenType::Caret => {
+            let token = next_token(buffer)?;
+            let right = exponential_expression(buffer)?;
+            Ok(SyntaxNode::Node(SyntaxKind::BinaryOp, vec![left, token, right]))
+        }
+        _ => Ok(left),
+    }
+}
+
+fn atomic_expression(buffer: &mut LookaheadBuffer<LosslessToken>) -> ParseResult<SyntaxNode> {
+    let mut expr = primary_expression(buffer)?;
+
+    while current_token(buffer)?.token.token_type == TokenType::LeftSquareBracket {
+        let open = next_token(buffer)?;
+        let index = expression(buffer)?;
+        let close = expect(buffer, TokenType::RightSquareBracket, "']'")?;
+        expr = SyntaxNode::Node(SyntaxKind::Access, vec![expr, open, index, close]);
+    }
+
+    Ok(expr)
+}
+
+fn primary_expression(buffer: &mut LookaheadBuffer<LosslessToken>) -> ParseResult<SyntaxNode> {
+    match &current_token(buffer)?.token.token_type {
+        TokenType::Identifier(_) => {
+            let id = next_token(buffer)?;
+            match current_token(buffer)?.token.token_type {
+                TokenType::LeftParen => {
+                    let open = next_token(buffer)?;
+                    function_call_expression(buffer, id, open)
+                }
+                TokenType::LeftBracket => {
+                    let open = next_token(buffer)?;
+                    table_instance_expression(buffer, id, open)
+                }
+                _ => Ok(id),
+            }
+        }
+        TokenType::LeftParen => {
+            let open = next_token(buffer)?;
+            let expr = expression(buffer)?;
+
+            let close = current_token(buffer)?;
+            match close.token.token_type {
+                TokenType::RightParen => Ok(SyntaxNode::Node(
+                    SyntaxKind::Parenthesized,
+                    vec![open, expr, next_token(buffer)?],
+                )),
+                _ => Err(missing("Expected a closing parentheses", close)),
+            }
+        }
+        TokenType::NumberLiteral(_)
+        | TokenType::StringLiteral(_)
+        | TokenType::True
+        | TokenType::False => next_token(buffer),
+        _ => Err(unexpected_token(current_token(buffer)?)),
+    }
+}
+
+// Like table columns, each value is a `Field` node of its key, colon and
+// expression, with the commas between them kept as direct children.
+fn table_instance_expression(
+    buffer: &mut LookaheadBuffer<LosslessToken>,
+    name: SyntaxNode,
+    open: SyntaxNode,
+) -> ParseResult<SyntaxNode> {
+    let mut children = vec![name, open];
+
+    loop {
+        match &current_token(buffer)?.token.token_type {
+            TokenType::RightBracket => {
+                children.push(next_token(buffer)?);
+                break;
+            }
+            TokenType::StringLiteral(_) => {
+                let key = next_token(buffer)?;
+                let colon = expect(buffer, TokenType::Colon, "':'")?;
+                let value = expression(buffer)?;
+                children.push(SyntaxNode::Node(SyntaxKind::Field, vec![key, colon, value]));
+
+                match &current_token(buffer)?.token.token_type {
+                    TokenType::Comma => children.push(next_token(buffer)?),
+                    TokenType::RightBracket => (),
+                    _ => return Err(expected("',' or '}'", current_token(buffer)?)),
+                }
+            }
+            _ => return Err(expected("a column name", current_token(buffer)?)),
+        }
+    }
+
+    Ok(SyntaxNode::Node(SyntaxKind::TableInstance, children))
+}
+
+// Arguments and the commas separating them are kept as direct children of the
+// call, between the opening and closing parentheses.
+fn function_call_expression(
+    buffer: &mut LookaheadBuffer<LosslessToken>,
+    id: SyntaxNode,
+    open: SyntaxNode,
+) -> ParseResult<SyntaxNode> {
+    let mut children = vec![id, open];
+
+    if current_token(buffer)?.token.token_type != TokenType::RightParen {
+        children.push(expression(buffer)?);
+
+        while current_token(buffer)?.token.token_type == TokenType::Comma {
+            children.push(next_token(buffer)?);
+            children.push(expression(buffer)?);
+        }
+    }
+
+    let close = current_token(buffer)?;
+    match close.token.token_type {
+        TokenType::RightParen => {
+            children.push(next_token(buffer)?);
+            Ok(SyntaxNode::Node(SyntaxKind::Call, children))
+        }
+        _ => Err(missing(
+            "Expected a closing parentheses in function call",
+            close,
+        )),
+    }
+}
+
+fn left_associative(
+    buffer: &mut LookaheadBuffer<LosslessToken>,
+    kind: SyntaxKind,
+    operators: &[TokenType],
+    operand: ParseFn,
+) -> ParseResult<SyntaxNode> {
+    let mut left = operand(buffer)?;
+
+    while operators.contains(&current_token(buffer)?.token.token_type) {
+        let token = next_token(buffer)?;
+        let right = operand(buffer)?;
+        left = SyntaxNode::Node(kind.clone(), vec![left, token, right]);
+    }
+
+    Ok(left)
+}
+
+fn expect(
+    buffer: &mut LookaheadBuffer<LosslessToken>,
+    token_type: TokenType,
+    description: &str,
+) -> ParseResult<SyntaxNode> {
+    let token = current_token(buffer)?;
+    if token.token.token_type != token_type {
+        return Err(expected(description, token));
+    }
+
+    next_token(buffer)
+}
+
+fn expect_identifier(buffer: &mut LookaheadBuffer<LosslessToken>) -> ParseResult<SyntaxNode> {
+    match current_token(buffer)?.token.token_type {
+        TokenType::Identifier(_) => next_token(buffer),
+        _ => Err(expected("an identifier", current_token(buffer)?)),
+    }
+}
+
+fn expected(description: &str, token: &LosslessToken) -> FormulaError {
+    FormulaError::parse(
+        format!("Expected {} but found {:?}", description, token.token.token_type),
+        Span::of_token(&token.token),
+    )
+}
+
+fn unexpected_token(token: &LosslessToken) -> FormulaError {
+    FormulaError::parse(
+        format!("Unexpected Token: {:?}", token.token.token_type),
+        Span::of_token(&token.token),
+    )
+}
+
+fn missing(message: &str, token: &LosslessToken) -> FormulaError {
+    FormulaError::parse(message.into(), Span::of_token(&token.token))
+}
+
+// Lookahead borrows the token, so only the tokens that end up in the tree are
+// copied, by `next_token`.
+fn current_token(buffer: &mut LookaheadBuffer<LosslessToken>) -> ParseResult<&LosslessToken> {
+    match buffer.peek_ref(0) {
+        Some(token) => Ok(token),
+        None => Err(FormulaError::Parse {
+            message: "No EOF token found...".into(),
+            span: None,
+        }),
+    }
+}
+
+fn next_token(buffer: &mut LookaheadBuffer<LosslessToken>) -> ParseResult<SyntaxNode> {
+    let token = current_token(buffer)?.clone();
+    buffer.advance();
+    Ok(SyntaxNode::Token(token))
+}
@@ -0,0 +1,275 @@
+use super::*;
+
+use crate::tokenizer::{LosslessToken, TokenType};
+
+/// A statement built from the concrete syntax tree, along with the token
+/// range of the statement and of each node of its expressions in the order
+/// `Visitor` visits them. Ranges index the tokens of the whole document.
+#[derive(Debug, PartialEq, Clone)]
+pub struct LoweredStatement {
+    pub statement: Statement,
+    pub tokens: Vec<Token>,
+    pub range: (usize, usize),
+    pub nodes: Vec<(usize, usize)>,
+}
+
+/// Builds the syntax tree of a `Root` node from `cst_parser`.
+pub fn lower_formula(root: &SyntaxNode) -> Expression {
+    Lowering::default().expression(&root.children()[0])
+}
+
+/// Builds the statements of a `Document` node from `cst_document_parser`.
+pub fn lower_document(document: &SyntaxNode) -> Vec<LoweredStatement> {
+    let mut lowering = Lowering::default();
+    let mut statements = vec![];
+
+    for child in document.children() {
+        match child {
+            SyntaxNode::Token(_) => lowering.skip(child),
+            SyntaxNode::Node(..) => {
+                let start = lowering.next;
+                let statement = lowering.statement(child);
+                statements.push(LoweredStatement {
+                    statement,
+                    tokens: child.tokens().into_iter().map(|t| t.token.clone()).collect(),
+                    range: (start, lowering.next),
+                    nodes: std::mem::take(&mut lowering.nodes),
+                });
+            }
+        }
+    }
+    statements
+}
+
+// The concrete syntax tree only holds trees `cst` parsed successfully, so
+// each kind of node always has the children matched below.
+#[derive(Default)]
+struct Lowering {
+    // The index of the first token of the node being lowered.
+    next: usize,
+    nodes: Vec<(usize, usize)>,
+}
+impl Lowering {
+    fn skip(&mut self, node: &SyntaxNode) {
+        self.next += node.tokens().len();
+    }
+
+    fn statement(&mut self, node: &SyntaxNode) -> Statement {
+        let children = node.children();
+        match node.kind() {
+            Some(SyntaxKind::TableDef) => {
+                self.skip(&children[0]);
+                let name = self.identifier(&children[1]);
+                let mut columns = vec![];
+                for child in &children[2..] {
+                    match child.kind() {
+                        Some(SyntaxKind::Column) => columns.push(self.column(child)),
+                        _ => self.skip(child),
+                    }
+                }
+                Statement::TableDef(name, columns)
+            }
+            Some(SyntaxKind::FormulaDef) => {
+                self.skip(&children[0]);
+                let name = self.identifier(&children[1]);
+                Statement::FormulaDef(name, self.braced_expression(&children[2..]))
+            }
+            Some(SyntaxKind::Assignment) => {
+                self.skip(&children[0]);
+                let name = self.identifier(&children[1]);
+                self.skip(&children[2]);
+                Statement::Assignment(name, self.expression(&children[3]))
+            }
+            Some(SyntaxKind::Print) => {
+                self.skip(&children[0]);
+                Statement::PrintStatement(self.expression(&children[1]))
+            }
+            Some(SyntaxKind::Assert) => {
+                self.skip(&children[0]);
+                Statement::AssertStatement(self.expression(&children[1]))
+            }
+            _ => Statement::ExpressionStatement(self.expression(&children[0])),
+        }
+    }
+
+    fn column(&mut self, node: &SyntaxNode) -> Pair<Type> {
+        let children = node.children();
+        let key = self.string(&children[0]);
+        self.skip(&children[1]);
+
+        let value = match token_type(&children[2]) {
+            Some(TokenType::Formula) => {
+                self.skip(&children[2]);
+                Type::Formula(self.braced_expression(&children[3..]))
+            }
+            Some(TokenType::Identifier(name)) => {
+                self.skip(&children[2]);
+                match name.as_str() {
+                    "Text" => Type::Str,
+                    "Number" => Type::Number,
+                    _ => Type::Bool,
+                }
+            }
+            _ => unreachable!("column types are formulas or identifiers"),
+        };
+        Pair { key, value }
+    }
+
+    fn braced_expression(&mut self, children: &[SyntaxNode]) -> Expression {
+        self.skip(&children[0]);
+        let expr = self.expression(&children[1]);
+        self.skip(&children[2]);
+        expr
+    }
+
+    // Parentheses have no node of their own in the syntax tree, so only the
+    // expression inside them gets a range.
+    fn expression(&mut self, node: &SyntaxNode) -> Expression {
+        let children = node.children();
+        if let Some(SyntaxKind::Parenthesized) = node.kind() {
+            return self.braced_expression(children);
+        }
+
+        let index = self.nodes.len();
+        let start = self.next;
+        self.nodes.push((start, start));
+
+        let expr = match node.kind() {
+            None => self.atom(node),
+            Some(SyntaxKind::Ternary) => {
+                let test = self.expression(&children[0]);
+                self.skip(&children[1]);
+                let accept = self.expression(&children[2]);
+                self.skip(&children[3]);
+                let reject = self.expression(&children[4]);
+                Expression::TernaryOp(Box::new(test), Box::new(accept), Box::new(reject))
+            }
+            Some(SyntaxKind::BooleanOp) => {
+                let (lhs, op, rhs) = self.binary(children);
+                let op = match op {
+                    Some(TokenType::And) => BooleanOperator::And,
+                    _ => BooleanOperator::Or,
+                };
+                Expression::BooleanOp(lhs, op, rhs)
+            }
+            Some(SyntaxKind::Comparison) => {
+                let (lhs, op, rhs) = self.binary(children);
+                let op = match op {
+                    Some(TokenType::EqualEqual) => ComparisonOperator::Equals,
+                    Some(TokenType::BangEqual) => ComparisonOperator::NotEquals,
+                    Some(TokenType::Less) => ComparisonOperator::LessThan,
+                    Some(TokenType::Greater) => ComparisonOperator::GreaterThan,
+                    Some(TokenType::LessEqual) => ComparisonOperator::LessThanEq,
+                    _ => ComparisonOperator::GreaterThanEq,
+                };
+                Expression::Comparison(lhs, op, rhs)
+            }
+            Some(SyntaxKind::BinaryOp) => {
+                let (lhs, op, rhs) = self.binary(children);
+                let op = match op {
+                    Some(TokenType::Plus) => MathOperator::Add,
+                    Some(TokenType::Minus) => MathOperator::Subtract,
+                    Some(TokenType::Star) => MathOperator::Multiply,
+                    Some(TokenType::Slash) => MathOperator::Divide,
+                    Some(TokenType::Percent) => MathOperator::Mod,
+                    _ => MathOperator::Exponent,
+                };
+                Expression::BinaryOp(lhs, op, rhs)
+            }
+            Some(SyntaxKind::Not) | Some(SyntaxKind::Prefix) => {
+                let op = match token_type(&children[0]) {
+                    Some(TokenType::Not) => UnaryOperator::Not,
+                    Some(TokenType::Minus) => UnaryOperator::USub,
+                    _ => UnaryOperator::UAdd,
+                };
+                self.skip(&children[0]);
+                Expression::UnaryOp(op, Box::new(self.expression(&children[1])))
+            }
+            Some(SyntaxKind::Call) => {
+                let callee = self.expression(&children[0]);
+                self.skip(&children[1]);
+                let mut args = vec![];
+                for (index, child) in children.iter().enumerate().skip(2) {
+                    match index % 2 == 0 && index < children.len() - 1 {
+                        true => args.push(self.expression(child)),
+                        false => self.skip(child),
+                    }
+                }
+                Expression::Call(Box::new(callee), args)
+            }
+            Some(SyntaxKind::Access) => {
+                let target = self.expression(&children[0]);
+                self.skip(&children[1]);
+                let index = self.expression(&children[2]);
+                self.skip(&children[3]);
+                Expression::Access(Box::new(target), Box::new(index))
+            }
+            Some(SyntaxKind::TableInstance) => {
+                let name = self.identifier(&children[0]);
+                let mut pairs = vec![];
+                for child in &children[1..] {
+                    match child.kind() {
+                        Some(SyntaxKind::Field) => {
+                            let fields = child.children();
+                            let key = self.string(&fields[0]);
+                            self.skip(&fields[1]);
+                            let value = self.expression(&fields[2]);
+                            pairs.push(Pair { key, value });
+                        }
+                        _ => self.skip(child),
+                    }
+                }
+                Expression::TableInstance(name, pairs)
+            }
+            Some(kind) => unreachable!("{:?} is not an expression", kind),
+        };
+
+        self.nodes[index].1 = self.next;
+        expr
+    }
+
+    fn binary<'n>(
+        &mut self,
+        children: &'n [SyntaxNode],
+    ) -> (Box<Expression>, Option<&'n TokenType>, Box<Expression>) {
+        let lhs = self.expression(&children[0]);
+        self.skip(&children[1]);
+        let rhs = self.expression(&children[2]);
+        (Box::new(lhs), token_type(&children[1]), Box::new(rhs))
+    }
+
+    fn atom(&mut self, node: &SyntaxNode) -> Expression {
+        self.skip(node);
+        match token_type(node) {
+            Some(TokenType::Identifier(name)) => Expression::Identifier(name.clone()),
+            Some(TokenType::NumberLiteral(value)) => Expression::Number(value.clone()),
+            Some(TokenType::StringLiteral(value)) => Expression::Str(value.clone()),
+            Some(TokenType::True) => Expression::Bool(true),
+            Some(TokenType::False) => Expression::Bool(false),
+            other => unreachable!("{:?} is not an expression", other),
+        }
+    }
+
+    fn identifier(&mut self, node: &SyntaxNode) -> String {
+        self.skip(node);
+        match token_type(node) {
+            Some(TokenType::Identifier(name)) => name.clone(),
+            other => unreachable!("expected an identifier, found {:?}", other),
+        }
+    }
+
+    fn string(&mut self, node: &SyntaxNode) -> String {
+        self.skip(node);
+        match token_type(node) {
+            Some(TokenType::StringLiteral(value)) => value.clone(),
+            other => unreachable!("expected a string, found {:?}", other),
+        }
+    }
+}
+
+fn token_type(node: &SyntaxNode) -> Option<&TokenType> {
+    match node {
+        SyntaxNode::Token(LosslessToken { token, .. }) => Some(&token.token_type),
+        SyntaxNode::Node(..) => None,
+    }
+}
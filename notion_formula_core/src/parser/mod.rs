@@ -1,23 +1,37 @@
-mod util;
+mod cst;
+mod lower;
+mod visitor;
 
-use crate::tokenizer::Token;
-use lookahead_buffer::LookaheadBuffer;
-use pipeline::HandlerResult;
+pub use cst::*;
+pub use lower::*;
+pub use visitor::*;
+use crate::error::{FormulaError, Span};
+use crate::tokenizer::{LosslessToken, Token};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, PartialEq)]
-struct Document {
-    statements: Vec<Statement>
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Document {
+    pub statements: Vec<Statement>
 }
 
-#[derive(Debug, PartialEq)]
+/// With the `serde` feature, statements serialize the same way as
+/// expressions, e.g. `let x = 1` is `{"Assignment":["x",{"Number":"1"}]}`
+/// and table columns are `{"key":"\"name\"","value":"Str"}` pairs.
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum Statement {
     TableDef(String, Vec<Pair<Type>>),
     FormulaDef(String, Expression),
     Assignment(String, Expression),
     PrintStatement(Expression),
-    AssertStatement(Expression)
+    AssertStatement(Expression),
+    ExpressionStatement(Expression),
 }
 
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum Type {
     Str,
     Number,
@@ -25,7 +39,12 @@ pub enum Type {
     Formula(Expression)
 }
 
-#[derive(Debug, PartialEq)]
+/// With the `serde` feature, expressions serialize externally tagged by
+/// variant name with tuple variants as arrays, so `1 + x` is
+/// `{"BinaryOp":[{"Number":"1"},"Add",{"Identifier":"x"}]}`. Operators are
+/// plain strings and string literals keep their surrounding quotes.
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum Expression {
     BinaryOp(Box<Expression>, MathOperator, Box<Expression>),
     Comparison(Box<Expression>, ComparisonOperator, Box<Expression>),
@@ -41,12 +60,15 @@ pub enum Expression {
     Bool(bool),
 }
 
-struct Pair<T> {
-    key: String,
-    value: T
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Pair<T> {
+    pub key: String,
+    pub value: T
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum MathOperator {
     Add,
     Subtract,
@@ -56,7 +78,8 @@ pub enum MathOperator {
     Exponent,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum ComparisonOperator {
     Equals,
     NotEquals,
@@ -66,22 +89,123 @@ pub enum ComparisonOperator {
     GreaterThanEq,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum BooleanOperator {
     And,
     Or,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum UnaryOperator {
     UAdd,
     USub,
     Not,
 }
 
-pub fn formula_parser(input: Vec<Token>) -> HandlerResult<Expression> {
-    let mut buffer = LookaheadBuffer::new(input);
-    util::expression(&mut buffer)
+/// A statement along with the tokens it was parsed from, for tools that
+/// need to point back into the source. `nodes` holds the span of each
+/// expression in the order `Visitor` visits them.
+#[derive(Debug, PartialEq, Clone)]
+pub struct LocatedStatement {
+    pub statement: Statement,
+    pub tokens: Vec<Token>,
+    pub nodes: Vec<Span>,
+}
+impl LocatedStatement {
+    pub fn span(&self) -> Option<Span> {
+        Span::of_tokens(&self.tokens)
+    }
+
+    /// The span of `expr`, which must be borrowed from `self.statement`:
+    /// nodes are told apart by address, since equal expressions can appear
+    /// more than once.
+    pub fn span_of(&self, expr: &Expression) -> Option<Span> {
+        let mut finder = NodeFinder {
+            target: expr,
+            next: 0,
+            found: None,
+        };
+        finder.visit_statement(&self.statement);
+        finder.found.and_then(|index| self.nodes.get(index).copied())
+    }
+
+    /// Points `error` at `expr` unless it already has a span, or `expr`
+    /// isn't part of this statement.
+    pub fn locate(&self, error: FormulaError, expr: &Expression) -> FormulaError {
+        match error.span() {
+            Some(_) => error,
+            None => error.or_span(self.span_of(expr)),
+        }
+    }
+}
+
+struct NodeFinder<'a> {
+    target: &'a Expression,
+    next: usize,
+    found: Option<usize>,
+}
+impl Visitor for NodeFinder<'_> {
+    fn visit_expression(&mut self, expr: &Expression) {
+        if self.found.is_some() {
+            return;
+        }
+        if std::ptr::eq(expr, self.target) {
+            self.found = Some(self.next);
+        }
+        self.next += 1;
+        walk_expression(self, expr);
+    }
+}
+
+pub fn formula_parser(input: Vec<Token>) -> Result<Expression, FormulaError> {
+    Ok(lower_formula(&cst_parser(lossless(input))?))
+}
+
+pub fn document_parser(input: Vec<Token>) -> Result<Document, FormulaError> {
+    let statements = located_document_parser(input)?
+        .into_iter()
+        .map(|located| located.statement)
+        .collect();
+
+    Ok(Document { statements })
+}
+
+pub fn located_document_parser(
+    input: Vec<Token>,
+) -> Result<Vec<LocatedStatement>, FormulaError> {
+    let document = cst_document_parser(lossless(input))?;
+    let statements = lower_document(&document)
+        .into_iter()
+        .map(|lowered| {
+            let start = lowered.range.0;
+            let nodes = lowered
+                .nodes
+                .iter()
+                .filter_map(|(from, to)| {
+                    Span::of_tokens(&lowered.tokens[from - start..to - start])
+                })
+                .collect();
+            LocatedStatement {
+                statement: lowered.statement,
+                tokens: lowered.tokens,
+                nodes,
+            }
+        })
+        .collect();
+
+    Ok(statements)
+}
+
+// The grammar lives in `cst`, and the syntax tree is built from the concrete
+// one. Building it doesn't need the source text, so tokens without it are
+// parsed with empty text.
+fn lossless(input: Vec<Token>) -> Vec<LosslessToken> {
+    input
+        .into_iter()
+        .map(|token| LosslessToken::new(token, String::new()))
+        .collect()
 }
 
 #[cfg(test)]
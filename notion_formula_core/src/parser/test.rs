@@ -383,3 +383,436 @@ fn test_function_calls() {
         result
     )
 }
+
+fn parse_cst(input: &str) -> SyntaxNode {
+    let tokens = crate::tokenizer::lossless_tokenizer(input.chars().collect()).unwrap();
+    cst_parser(tokens).unwrap()
+}
+
+#[test]
+fn test_cst_prints_source_byte_for_byte() {
+    let source = "/* status */\nif(\n    prop(\"State\") == \"Done\" or not  (a),  // finished\n    -2 ^ 3,\n    x ? y : z\n)\n";
+    let result = parse_cst(source);
+
+    assert_eq!(source, result.to_string());
+}
+
+#[test]
+fn test_cst_keeps_parentheses_and_operators() {
+    let result = parse_cst("(a + b) * c");
+    let root = result.children();
+
+    assert_eq!(Some(&SyntaxKind::Root), result.kind());
+    assert_eq!(Some(&SyntaxKind::BinaryOp), root[0].kind());
+
+    let operands = root[0].children();
+    assert_eq!(Some(&SyntaxKind::Parenthesized), operands[0].kind());
+    assert_eq!("(a + b) ", operands[0].to_string());
+    assert_eq!("* ", operands[1].to_string());
+    assert_eq!("c", operands[2].to_string());
+}
+
+#[test]
+fn test_cst_function_call_children() {
+    let result = parse_cst("foo(bar, 1)");
+    let call = &result.children()[0];
+
+    let texts: Vec<String> = call.children().iter().map(|c| c.to_string()).collect();
+    assert_eq!(Some(&SyntaxKind::Call), call.kind());
+    assert_eq!(vec!["foo", "(", "bar", ", ", "1", ")"], texts);
+}
+
+#[test]
+fn test_cst_tokens_can_be_rewritten_in_place() {
+    let mut result = parse_cst("prop(\"Old\") + 1 // keep me");
+    for token in result.tokens_mut() {
+        if token.text == "\"Old\"" {
+            token.text = "\"New\"".into();
+        }
+    }
+
+    assert_eq!("prop(\"New\") + 1 // keep me", result.to_string());
+}
+
+#[test]
+fn test_cst_errors_on_trailing_tokens() {
+    let tokens = crate::tokenizer::lossless_tokenizer("a b".chars().collect()).unwrap();
+    let result = cst_parser(tokens);
+
+    assert!(result.is_err());
+}
+
+fn parse_cst_document(input: &str) -> SyntaxNode {
+    let tokens = crate::tokenizer::lossless_tokenizer(input.chars().collect()).unwrap();
+    cst_document_parser(tokens).unwrap()
+}
+
+#[test]
+fn test_cst_parses_every_sample_file() {
+    let formula = include_str!("../../tests/test_formula.notion");
+    assert_eq!(formula, parse_cst(formula).to_string());
+
+    let document = include_str!("../../tests/complex_example.notion");
+    assert_eq!(document, parse_cst_document(document).to_string());
+}
+
+#[test]
+fn test_cst_document_statements() {
+    let source = "table T { \"a\": Text, \"f\": formula { 1 } }\nformula g { 2 };\nlet x = T { \"a\": \"b\" };\nprint x[\"a\"]\nassert true\n3";
+    let result = parse_cst_document(source);
+    let kinds: Vec<Option<&SyntaxKind>> = result.children().iter().map(|c| c.kind()).collect();
+
+    assert_eq!(source, result.to_string());
+    assert_eq!(Some(&SyntaxKind::Document), result.kind());
+    assert_eq!(
+        vec![
+            Some(&SyntaxKind::TableDef),
+            Some(&SyntaxKind::FormulaDef),
+            None,
+            Some(&SyntaxKind::Assignment),
+            None,
+            Some(&SyntaxKind::Print),
+            Some(&SyntaxKind::Assert),
+            Some(&SyntaxKind::ExpressionStatement),
+            None,
+        ],
+        kinds
+    );
+
+    let columns = result.children()[0].children();
+    assert_eq!(Some(&SyntaxKind::Column), columns[3].kind());
+    assert_eq!("\"f\": formula { 1 } ", columns[5].to_string());
+}
+
+#[test]
+fn test_cst_access_and_table_instances() {
+    let result = parse_cst("u[\"a\"][0] + T{\"a\": 1, \"b\": prop(\"B\")}");
+    let operands = result.children()[0].children();
+
+    assert_eq!(Some(&SyntaxKind::Access), operands[0].kind());
+    assert_eq!(Some(&SyntaxKind::Access), operands[0].children()[0].kind());
+    assert_eq!(Some(&SyntaxKind::TableInstance), operands[2].kind());
+
+    let texts: Vec<String> = operands[2].children().iter().map(|c| c.to_string()).collect();
+    assert_eq!(vec!["T", "{", "\"a\": 1", ", ", "\"b\": prop(\"B\")", "}"], texts);
+    assert_eq!(Some(&SyntaxKind::Field), operands[2].children()[2].kind());
+}
+
+#[test]
+fn test_cst_document_errors_match_the_parser() {
+    let source = "let = 1";
+    let tokens = crate::tokenizer::lossless_tokenizer(source.chars().collect()).unwrap();
+    let lossless = cst_document_parser(tokens).unwrap_err();
+
+    let tokens = crate::tokenizer::tokenizer(source.chars().collect()).unwrap();
+    let expected = document_parser(tokens).unwrap_err();
+
+    assert_eq!(expected, lossless);
+}
+
+#[test]
+fn test_lowering_records_ranges_in_visit_order() {
+    let source = "let x = (a + 1) * f(b)\nprint x";
+    let tokens = crate::tokenizer::lossless_tokenizer(source.chars().collect()).unwrap();
+    let result = lower_document(&cst_document_parser(tokens.clone()).unwrap());
+
+    let text = |(start, end): (usize, usize)| -> String {
+        tokens[start..end].iter().map(|t| t.to_string()).collect()
+    };
+    let nodes: Vec<String> = result[0].nodes.iter().map(|range| text(*range)).collect();
+
+    assert_eq!(
+        vec!["(a + 1) * f(b)", "a + 1", "a ", "1", "f(b)", "f", "b"],
+        nodes
+    );
+    assert_eq!("\nprint x", text(result[1].range));
+    assert_eq!(
+        Statement::PrintStatement(Expression::Identifier("x".into())),
+        result[1].statement
+    );
+}
+
+fn parse_source(input: &str) -> Expression {
+    let tokens = crate::tokenizer::tokenizer(input.chars().collect()).unwrap();
+    formula_parser(tokens).unwrap()
+}
+
+#[test]
+fn test_visitor_collects_function_names() {
+    struct FunctionNames(Vec<String>);
+    impl Visitor for FunctionNames {
+        fn visit_call(&mut self, callee: &Expression, args: &[Expression]) {
+            if let Identifier(name) = callee {
+                self.0.push(name.clone());
+            }
+            walk_call(self, callee, args);
+        }
+    }
+
+    let input = parse_source("if(empty(prop(\"A\")), 1, -abs(prop(\"B\")) ^ 2)");
+    let mut visitor = FunctionNames(vec![]);
+    visitor.visit_expression(&input);
+
+    assert_eq!(vec!["if", "empty", "prop", "abs", "prop"], visitor.0);
+}
+
+#[test]
+fn test_visitor_walks_statements() {
+    struct Identifiers(Vec<String>);
+    impl Visitor for Identifiers {
+        fn visit_identifier(&mut self, name: &str) {
+            self.0.push(name.into());
+        }
+    }
+
+    let statements = vec![
+        Statement::TableDef(
+            "Users".into(),
+            vec![
+                Pair {
+                    key: "\"name\"".into(),
+                    value: Type::Str,
+                },
+                Pair {
+                    key: "\"test\"".into(),
+                    value: Type::Formula(parse_source("a + 1")),
+                },
+            ],
+        ),
+        Statement::Assignment(
+            "u0".into(),
+            TableInstance(
+                "Users".into(),
+                vec![Pair {
+                    key: "\"name\"".into(),
+                    value: parse_source("b"),
+                }],
+            ),
+        ),
+        Statement::PrintStatement(Access(
+            Box::new(Identifier("c".into())),
+            Box::new(Str("\"test\"".into())),
+        )),
+    ];
+    let mut visitor = Identifiers(vec![]);
+    for statement in &statements {
+        visitor.visit_statement(statement);
+    }
+
+    assert_eq!(vec!["a", "b", "c"], visitor.0);
+}
+
+#[test]
+fn test_visitor_mut_replaces_identifiers() {
+    struct Replace;
+    impl VisitorMut for Replace {
+        fn visit_expression_mut(&mut self, expr: &mut Expression) {
+            if *expr == Identifier("x".into()) {
+                *expr = Number("2".into());
+            } else {
+                walk_expression_mut(self, expr);
+            }
+        }
+    }
+
+    let mut input = parse_source("x * (y + x)");
+    Replace.visit_expression_mut(&mut input);
+
+    assert_eq!(parse_source("2 * (y + 2)"), input);
+}
+
+#[test]
+fn test_visitor_mut_edits_operators() {
+    struct Flip;
+    impl VisitorMut for Flip {
+        fn visit_boolean_op_mut(
+            &mut self,
+            lhs: &mut Expression,
+            op: &mut BooleanOperator,
+            rhs: &mut Expression,
+        ) {
+            *op = match op {
+                And => Or,
+                Or => And,
+            };
+            walk_boolean_op_mut(self, lhs, op, rhs);
+        }
+    }
+
+    let mut input = parse_source("a and (b or c)");
+    Flip.visit_expression_mut(&mut input);
+
+    assert_eq!(parse_source("a or (b and c)"), input);
+}
+
+fn parse_document(input: &str) -> Document {
+    let tokens = crate::tokenizer::tokenizer(input.chars().collect()).unwrap();
+    document_parser(tokens).unwrap()
+}
+
+#[test]
+fn test_formula_parser_rejects_trailing_tokens() {
+    let input = vec![
+        Token::new(TokenType::Identifier("a".into()), 1, 1),
+        Token::new(TokenType::Identifier("b".into()), 1, 3),
+        Token::new(TokenType::Eof, 1, 4),
+    ];
+    let result = format!("{}", formula_parser(input).unwrap_err());
+
+    assert_eq!(
+        "Unexpected Token: Identifier(\"b\") on line: 1, column: 3",
+        result
+    );
+}
+
+#[test]
+fn test_access_expressions() {
+    let result = parse_source("u0[\"a\"][\"b\"]");
+
+    assert_eq!(
+        Access(
+            Box::new(Access(
+                Box::new(Identifier("u0".into())),
+                Box::new(Str("\"a\"".into()))
+            )),
+            Box::new(Str("\"b\"".into()))
+        ),
+        result
+    )
+}
+
+#[test]
+fn test_table_instance_expressions() {
+    let result = parse_source("Users { \"name\": \"Atlas\", \"age\": 1 + 2 }");
+
+    assert_eq!(
+        TableInstance(
+            "Users".into(),
+            vec![
+                Pair {
+                    key: "\"name\"".into(),
+                    value: Str("\"Atlas\"".into())
+                },
+                Pair {
+                    key: "\"age\"".into(),
+                    value: BinaryOp(
+                        Box::new(Number("1".into())),
+                        Add,
+                        Box::new(Number("2".into()))
+                    )
+                },
+            ]
+        ),
+        result
+    )
+}
+
+#[test]
+fn test_document_statements() {
+    let result = parse_document(
+        "table Users { \"name\": Text, \"age\": Number, \"done\": Checkbox, \"test\": formula { prop(\"age\") } }
+        formula Double { prop(\"age\") * 2 }
+        let x = 1;
+        print x;
+        assert x == 1
+        x",
+    );
+
+    assert_eq!(
+        Document {
+            statements: vec![
+                Statement::TableDef(
+                    "Users".into(),
+                    vec![
+                        Pair {
+                            key: "\"name\"".into(),
+                            value: Type::Str
+                        },
+                        Pair {
+                            key: "\"age\"".into(),
+                            value: Type::Number
+                        },
+                        Pair {
+                            key: "\"done\"".into(),
+                            value: Type::Bool
+                        },
+                        Pair {
+                            key: "\"test\"".into(),
+                            value: Type::Formula(parse_source("prop(\"age\")"))
+                        },
+                    ]
+                ),
+                Statement::FormulaDef("Double".into(), parse_source("prop(\"age\") * 2")),
+                Statement::Assignment("x".into(), Number("1".into())),
+                Statement::PrintStatement(Identifier("x".into())),
+                Statement::AssertStatement(parse_source("x == 1")),
+                Statement::ExpressionStatement(Identifier("x".into())),
+            ]
+        },
+        result
+    )
+}
+
+#[test]
+fn test_document_errors_report_position() {
+    let tokens = crate::tokenizer::tokenizer("let = 1".chars().collect()).unwrap();
+    let result = format!("{}", document_parser(tokens).unwrap_err());
+
+    assert_eq!(
+        "Expected an identifier but found Equal on line: 1, column: 5",
+        result
+    );
+}
+
+#[test]
+fn test_document_rejects_unknown_column_types() {
+    let tokens =
+        crate::tokenizer::tokenizer("table T { \"a\": Date }".chars().collect()).unwrap();
+    let result = format!("{}", document_parser(tokens).unwrap_err());
+
+    assert_eq!("Unknown column type: Date on line: 1, column: 16", result);
+}
+
+#[test]
+fn test_located_document_keeps_statement_tokens() {
+    let tokens = crate::tokenizer::tokenizer("let x = 1;\nprint x".chars().collect()).unwrap();
+    let result = located_document_parser(tokens).unwrap();
+
+    assert_eq!(2, result.len());
+    assert_eq!(
+        Statement::Assignment("x".into(), Number("1".into())),
+        result[0].statement
+    );
+    assert_eq!(
+        vec![(1, 1), (1, 5), (1, 7), (1, 9)],
+        result[0]
+            .tokens
+            .iter()
+            .map(|token| (token.line, token.column))
+            .collect::<Vec<_>>()
+    );
+    assert_eq!(
+        vec![
+            crate::tokenizer::Token::new(TokenType::Print, 2, 1),
+            crate::tokenizer::Token::new(TokenType::Identifier("x".into()), 2, 7),
+        ],
+        result[1].tokens
+    );
+}
+
+#[test]
+fn test_parses_each_source_once_behind_a_cache() {
+    use pipeline::{Cache, FnHandler, Handler, Pipeline};
+
+    let parse =
+        FnHandler::new(crate::tokenizer::tokenizer).and_then(FnHandler::new(formula_parser));
+    let cache = Cache::new(parse, 16);
+    let pipe = Pipeline::<Vec<char>, _, crate::error::FormulaError>::default().add(&cache);
+
+    for source in &["1 + 2", "prop(\"A\")", "1 + 2", "1 + 2", "1 +"] {
+        let _ = pipe.start(source.chars().collect());
+    }
+
+    let stats = cache.stats();
+    assert_eq!((2, 3, 2), (stats.hits, stats.misses, stats.len));
+}
@@ -0,0 +1,349 @@
+use super::*;
+
+/// Read-only traversal of the syntax tree. Every method defaults to the
+/// matching `walk_*` function, which visits the node's children; override the
+/// methods for the nodes you care about and call `walk_*` to keep descending.
+pub trait Visitor {
+    fn visit_statement(&mut self, statement: &Statement) {
+        walk_statement(self, statement)
+    }
+
+    fn visit_expression(&mut self, expr: &Expression) {
+        walk_expression(self, expr)
+    }
+
+    fn visit_binary_op(&mut self, lhs: &Expression, op: &MathOperator, rhs: &Expression) {
+        walk_binary_op(self, lhs, op, rhs)
+    }
+
+    fn visit_comparison(&mut self, lhs: &Expression, op: &ComparisonOperator, rhs: &Expression) {
+        walk_comparison(self, lhs, op, rhs)
+    }
+
+    fn visit_boolean_op(&mut self, lhs: &Expression, op: &BooleanOperator, rhs: &Expression) {
+        walk_boolean_op(self, lhs, op, rhs)
+    }
+
+    fn visit_unary_op(&mut self, op: &UnaryOperator, operand: &Expression) {
+        walk_unary_op(self, op, operand)
+    }
+
+    fn visit_ternary_op(&mut self, test: &Expression, accept: &Expression, reject: &Expression) {
+        walk_ternary_op(self, test, accept, reject)
+    }
+
+    fn visit_call(&mut self, callee: &Expression, args: &[Expression]) {
+        walk_call(self, callee, args)
+    }
+
+    fn visit_access(&mut self, target: &Expression, index: &Expression) {
+        walk_access(self, target, index)
+    }
+
+    fn visit_table_instance(&mut self, name: &str, pairs: &[Pair<Expression>]) {
+        walk_table_instance(self, name, pairs)
+    }
+
+    fn visit_identifier(&mut self, _name: &str) {}
+
+    fn visit_str(&mut self, _value: &str) {}
+
+    fn visit_number(&mut self, _value: &str) {}
+
+    fn visit_bool(&mut self, _value: bool) {}
+}
+
+pub fn walk_statement<V: Visitor + ?Sized>(visitor: &mut V, statement: &Statement) {
+    match statement {
+        Statement::TableDef(_, columns) => {
+            for column in columns {
+                if let Type::Formula(expr) = &column.value {
+                    visitor.visit_expression(expr);
+                }
+            }
+        }
+        Statement::FormulaDef(_, expr)
+        | Statement::Assignment(_, expr)
+        | Statement::PrintStatement(expr)
+        | Statement::AssertStatement(expr)
+        | Statement::ExpressionStatement(expr) => visitor.visit_expression(expr),
+    }
+}
+
+pub fn walk_expression<V: Visitor + ?Sized>(visitor: &mut V, expr: &Expression) {
+    match expr {
+        Expression::BinaryOp(lhs, op, rhs) => visitor.visit_binary_op(lhs, op, rhs),
+        Expression::Comparison(lhs, op, rhs) => visitor.visit_comparison(lhs, op, rhs),
+        Expression::BooleanOp(lhs, op, rhs) => visitor.visit_boolean_op(lhs, op, rhs),
+        Expression::UnaryOp(op, operand) => visitor.visit_unary_op(op, operand),
+        Expression::TernaryOp(test, accept, reject) => {
+            visitor.visit_ternary_op(test, accept, reject)
+        }
+        Expression::Call(callee, args) => visitor.visit_call(callee, args),
+        Expression::Identifier(name) => visitor.visit_identifier(name),
+        Expression::Access(target, index) => visitor.visit_access(target, index),
+        Expression::TableInstance(name, pairs) => visitor.visit_table_instance(name, pairs),
+        Expression::Str(value) => visitor.visit_str(value),
+        Expression::Number(value) => visitor.visit_number(value),
+        Expression::Bool(value) => visitor.visit_bool(*value),
+    }
+}
+
+pub fn walk_binary_op<V: Visitor + ?Sized>(
+    visitor: &mut V,
+    lhs: &Expression,
+    _op: &MathOperator,
+    rhs: &Expression,
+) {
+    visitor.visit_expression(lhs);
+    visitor.visit_expression(rhs);
+}
+
+pub fn walk_comparison<V: Visitor + ?Sized>(
+    visitor: &mut V,
+    lhs: &Expression,
+    _op: &ComparisonOperator,
+    rhs: &Expression,
+) {
+    visitor.visit_expression(lhs);
+    visitor.visit_expression(rhs);
+}
+
+pub fn walk_boolean_op<V: Visitor + ?Sized>(
+    visitor: &mut V,
+    lhs: &Expression,
+    _op: &BooleanOperator,
+    rhs: &Expression,
+) {
+    visitor.visit_expression(lhs);
+    visitor.visit_expression(rhs);
+}
+
+pub fn walk_unary_op<V: Visitor + ?Sized>(
+    visitor: &mut V,
+    _op: &UnaryOperator,
+    operand: &Expression,
+) {
+    visitor.visit_expression(operand);
+}
+
+pub fn walk_ternary_op<V: Visitor + ?Sized>(
+    visitor: &mut V,
+    test: &Expression,
+    accept: &Expression,
+    reject: &Expression,
+) {
+    visitor.visit_expression(test);
+    visitor.visit_expression(accept);
+    visitor.visit_expression(reject);
+}
+
+pub fn walk_call<V: Visitor + ?Sized>(visitor: &mut V, callee: &Expression, args: &[Expression]) {
+    visitor.visit_expression(callee);
+    for arg in args {
+        visitor.visit_expression(arg);
+    }
+}
+
+pub fn walk_access<V: Visitor + ?Sized>(visitor: &mut V, target: &Expression, index: &Expression) {
+    visitor.visit_expression(target);
+    visitor.visit_expression(index);
+}
+
+pub fn walk_table_instance<V: Visitor + ?Sized>(
+    visitor: &mut V,
+    _name: &str,
+    pairs: &[Pair<Expression>],
+) {
+    for pair in pairs {
+        visitor.visit_expression(&pair.value);
+    }
+}
+
+/// In-place rewriting of the syntax tree. Works like `Visitor`, but every
+/// method receives mutable references, so nodes can be edited or replaced
+/// outright from `visit_expression_mut`.
+pub trait VisitorMut {
+    fn visit_statement_mut(&mut self, statement: &mut Statement) {
+        walk_statement_mut(self, statement)
+    }
+
+    fn visit_expression_mut(&mut self, expr: &mut Expression) {
+        walk_expression_mut(self, expr)
+    }
+
+    fn visit_binary_op_mut(
+        &mut self,
+        lhs: &mut Expression,
+        op: &mut MathOperator,
+        rhs: &mut Expression,
+    ) {
+        walk_binary_op_mut(self, lhs, op, rhs)
+    }
+
+    fn visit_comparison_mut(
+        &mut self,
+        lhs: &mut Expression,
+        op: &mut ComparisonOperator,
+        rhs: &mut Expression,
+    ) {
+        walk_comparison_mut(self, lhs, op, rhs)
+    }
+
+    fn visit_boolean_op_mut(
+        &mut self,
+        lhs: &mut Expression,
+        op: &mut BooleanOperator,
+        rhs: &mut Expression,
+    ) {
+        walk_boolean_op_mut(self, lhs, op, rhs)
+    }
+
+    fn visit_unary_op_mut(&mut self, op: &mut UnaryOperator, operand: &mut Expression) {
+        walk_unary_op_mut(self, op, operand)
+    }
+
+    fn visit_ternary_op_mut(
+        &mut self,
+        test: &mut Expression,
+        accept: &mut Expression,
+        reject: &mut Expression,
+    ) {
+        walk_ternary_op_mut(self, test, accept, reject)
+    }
+
+    fn visit_call_mut(&mut self, callee: &mut Expression, args: &mut Vec<Expression>) {
+        walk_call_mut(self, callee, args)
+    }
+
+    fn visit_access_mut(&mut self, target: &mut Expression, index: &mut Expression) {
+        walk_access_mut(self, target, index)
+    }
+
+    fn visit_table_instance_mut(&mut self, name: &mut String, pairs: &mut Vec<Pair<Expression>>) {
+        walk_table_instance_mut(self, name, pairs)
+    }
+
+    fn visit_identifier_mut(&mut self, _name: &mut String) {}
+
+    fn visit_str_mut(&mut self, _value: &mut String) {}
+
+    fn visit_number_mut(&mut self, _value: &mut String) {}
+
+    fn visit_bool_mut(&mut self, _value: &mut bool) {}
+}
+
+pub fn walk_statement_mut<V: VisitorMut + ?Sized>(visitor: &mut V, statement: &mut Statement) {
+    match statement {
+        Statement::TableDef(_, columns) => {
+            for column in columns {
+                if let Type::Formula(expr) = &mut column.value {
+                    visitor.visit_expression_mut(expr);
+                }
+            }
+        }
+        Statement::FormulaDef(_, expr)
+        | Statement::Assignment(_, expr)
+        | Statement::PrintStatement(expr)
+        | Statement::AssertStatement(expr)
+        | Statement::ExpressionStatement(expr) => visitor.visit_expression_mut(expr),
+    }
+}
+
+pub fn walk_expression_mut<V: VisitorMut + ?Sized>(visitor: &mut V, expr: &mut Expression) {
+    match expr {
+        Expression::BinaryOp(lhs, op, rhs) => visitor.visit_binary_op_mut(lhs, op, rhs),
+        Expression::Comparison(lhs, op, rhs) => visitor.visit_comparison_mut(lhs, op, rhs),
+        Expression::BooleanOp(lhs, op, rhs) => visitor.visit_boolean_op_mut(lhs, op, rhs),
+        Expression::UnaryOp(op, operand) => visitor.visit_unary_op_mut(op, operand),
+        Expression::TernaryOp(test, accept, reject) => {
+            visitor.visit_ternary_op_mut(test, accept, reject)
+        }
+        Expression::Call(callee, args) => visitor.visit_call_mut(callee, args),
+        Expression::Identifier(name) => visitor.visit_identifier_mut(name),
+        Expression::Access(target, index) => visitor.visit_access_mut(target, index),
+        Expression::TableInstance(name, pairs) => visitor.visit_table_instance_mut(name, pairs),
+        Expression::Str(value) => visitor.visit_str_mut(value),
+        Expression::Number(value) => visitor.visit_number_mut(value),
+        Expression::Bool(value) => visitor.visit_bool_mut(value),
+    }
+}
+
+pub fn walk_binary_op_mut<V: VisitorMut + ?Sized>(
+    visitor: &mut V,
+    lhs: &mut Expression,
+    _op: &mut MathOperator,
+    rhs: &mut Expression,
+) {
+    visitor.visit_expression_mut(lhs);
+    visitor.visit_expression_mut(rhs);
+}
+
+pub fn walk_comparison_mut<V: VisitorMut + ?Sized>(
+    visitor: &mut V,
+    lhs: &mut Expression,
+    _op: &mut ComparisonOperator,
+    rhs: &mut Expression,
+) {
+    visitor.visit_expression_mut(lhs);
+    visitor.visit_expression_mut(rhs);
+}
+
+pub fn walk_boolean_op_mut<V: VisitorMut + ?Sized>(
+    visitor: &mut V,
+    lhs: &mut Expression,
+    _op: &mut BooleanOperator,
+    rhs: &mut Expression,
+) {
+    visitor.visit_expression_mut(lhs);
+    visitor.visit_expression_mut(rhs);
+}
+
+pub fn walk_unary_op_mut<V: VisitorMut + ?Sized>(
+    visitor: &mut V,
+    _op: &mut UnaryOperator,
+    operand: &mut Expression,
+) {
+    visitor.visit_expression_mut(operand);
+}
+
+pub fn walk_ternary_op_mut<V: VisitorMut + ?Sized>(
+    visitor: &mut V,
+    test: &mut Expression,
+    accept: &mut Expression,
+    reject: &mut Expression,
+) {
+    visitor.visit_expression_mut(test);
+    visitor.visit_expression_mut(accept);
+    visitor.visit_expression_mut(reject);
+}
+
+pub fn walk_call_mut<V: VisitorMut + ?Sized>(
+    visitor: &mut V,
+    callee: &mut Expression,
+    args: &mut Vec<Expression>,
+) {
+    visitor.visit_expression_mut(callee);
+    for arg in args {
+        visitor.visit_expression_mut(arg);
+    }
+}
+
+pub fn walk_access_mut<V: VisitorMut + ?Sized>(
+    visitor: &mut V,
+    target: &mut Expression,
+    index: &mut Expression,
+) {
+    visitor.visit_expression_mut(target);
+    visitor.visit_expression_mut(index);
+}
+
+pub fn walk_table_instance_mut<V: VisitorMut + ?Sized>(
+    visitor: &mut V,
+    _name: &mut String,
+    pairs: &mut Vec<Pair<Expression>>,
+) {
+    for pair in pairs {
+        visitor.visit_expression_mut(&mut pair.value);
+    }
+}
@@ -1,22 +1,90 @@
 use std::io::Read;
+use std::vec;
 use pipeline::HandlerResult;
 
 const BUFFER_SIZE: usize = 1000; // 1kb
 
 pub fn read(input: &mut impl Read) -> HandlerResult<Vec<char>> {
-    let mut result = vec![];
-    let mut buffer: [u8; BUFFER_SIZE] = [0; BUFFER_SIZE];
+    stream(input, |chars| chars.collect())
+}
+
+/// Decodes the characters of `input` one chunk at a time.
+pub fn chars<R: Read>(input: R) -> Chars<R> {
+    Chars {
+        input,
+        bytes: vec![],
+        decoded: vec![].into_iter(),
+        done: false,
+    }
+}
 
-    let mut bytes_read = input.read(&mut buffer)?;
-    while bytes_read > 0 {
-        for i in 0..bytes_read {
-            result.push(buffer[i]);
+/// Hands the characters of `input` to `consume` as they are read, so the
+/// source is never held in memory as a whole. Reading stops at the first
+/// error, which is returned instead of what `consume` made of the characters
+/// before it.
+pub fn stream<T>(
+    input: impl Read,
+    consume: impl FnOnce(&mut dyn Iterator<Item = char>) -> T,
+) -> HandlerResult<T> {
+    let mut chars = chars(input);
+    let mut error = None;
+    let mut valid = chars.by_ref().map_while(|c| match c {
+        Ok(c) => Some(c),
+        Err(e) => {
+            error = Some(e);
+            None
         }
-        bytes_read = input.read(&mut buffer)?;
+    });
+
+    let result = consume(&mut valid);
+    match error {
+        Some(e) => Err(e),
+        None => Ok(result),
+    }
+}
+
+pub struct Chars<R> {
+    input: R,
+    // Bytes read but not decoded yet, such as the start of a character split
+    // between two reads.
+    bytes: Vec<u8>,
+    decoded: vec::IntoIter<char>,
+    done: bool,
+}
+impl<R: Read> Chars<R> {
+    fn fill(&mut self) -> HandlerResult<()> {
+        let mut buffer: [u8; BUFFER_SIZE] = [0; BUFFER_SIZE];
+        let bytes_read = self.input.read(&mut buffer)?;
+        self.bytes.extend_from_slice(&buffer[..bytes_read]);
+        self.done = bytes_read == 0;
+
+        let valid = match std::str::from_utf8(&self.bytes) {
+            Ok(text) => text.len(),
+            Err(e) if e.error_len().is_none() && !self.done => e.valid_up_to(),
+            Err(e) => return Err(Box::new(e)),
+        };
+        let text = String::from_utf8(self.bytes.drain(..valid).collect())?;
+        self.decoded = text.chars().collect::<Vec<_>>().into_iter();
+        Ok(())
     }
+}
+impl<R: Read> Iterator for Chars<R> {
+    type Item = HandlerResult<char>;
 
-    let result_string: String = String::from_utf8(result)?;
-    Ok(result_string.chars().collect())
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(c) = self.decoded.next() {
+                return Some(Ok(c));
+            }
+            if self.done {
+                return None;
+            }
+            if let Err(e) = self.fill() {
+                self.done = true;
+                return Some(Err(e));
+            }
+        }
+    }
 }
 
 #[cfg(test)]
@@ -33,4 +101,43 @@ mod tests {
             result
         )
     }
+
+    // Hands out one byte per read, splitting every multi-byte character.
+    struct OneByte<'a>(&'a [u8]);
+    impl Read for OneByte<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            match self.0.split_first() {
+                Some((byte, rest)) if !buf.is_empty() => {
+                    buf[0] = *byte;
+                    self.0 = rest;
+                    Ok(1)
+                }
+                _ => Ok(0),
+            }
+        }
+    }
+
+    #[test]
+    fn test_decodes_characters_split_between_reads() {
+        let result: HandlerResult<String> = chars(OneByte("é+→".as_bytes())).collect();
+
+        assert_eq!("é+→", result.unwrap())
+    }
+
+    #[test]
+    fn test_fails_on_truncated_input() {
+        let bytes = "→".as_bytes();
+        let result: HandlerResult<String> = chars(&bytes[..2]).collect();
+
+        assert!(result.is_err())
+    }
+
+    #[test]
+    fn test_streams_characters_until_an_error() {
+        let result = stream("1+2".as_bytes(), |chars| chars.count());
+        assert_eq!(3, result.unwrap());
+
+        let bytes = [b'1', 0xff, b'2'];
+        assert!(stream(&bytes[..], |chars| chars.count()).is_err());
+    }
 }
@@ -0,0 +1,147 @@
+use crate::parser::{cst_document_parser, SyntaxKind, SyntaxNode};
+use crate::tokenizer::{lossless_tokenizer, LosslessToken, TokenType};
+use pipeline::{HandlerResult, SimpleError};
+use std::collections::HashMap;
+
+#[derive(Debug, PartialEq)]
+pub struct PropertyRename {
+    pub formula: usize,
+    pub line: u32,
+    pub column: u32,
+    pub from: String,
+    pub to: String,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct RenameResult {
+    pub sources: Vec<String>,
+    pub occurrences: Vec<PropertyRename>,
+}
+
+/// Rewrites every `prop("Old Name")` in the given formulas according to the
+/// rename map. Only the string literals inside `prop(...)` change; comments,
+/// whitespace and everything else are left exactly as they were. Each source
+/// may be a single formula or a whole document.
+pub fn rename_properties(
+    formulas: &[String],
+    renames: &HashMap<String, String>,
+) -> HandlerResult<RenameResult> {
+    check_names(renames)?;
+
+    let mut sources = vec![];
+    let mut occurrences = vec![];
+
+    for (formula, source) in formulas.iter().enumerate() {
+        sources.push(rename_source(formula, source, renames, &mut occurrences)?);
+    }
+
+    Ok(RenameResult {
+        sources,
+        occurrences,
+    })
+}
+
+/// Like `rename_properties` for the source of a single document, covering
+/// `prop(...)` in every statement, table column and formula definition.
+pub fn rename_document_properties(
+    document: &str,
+    renames: &HashMap<String, String>,
+) -> HandlerResult<RenameResult> {
+    check_names(renames)?;
+
+    let mut occurrences = vec![];
+    let source = rename_source(0, document, renames, &mut occurrences)?;
+
+    Ok(RenameResult {
+        sources: vec![source],
+        occurrences,
+    })
+}
+
+fn check_names(renames: &HashMap<String, String>) -> HandlerResult<()> {
+    for name in renames.values() {
+        if name.contains('"') {
+            return Err(SimpleError::new(format!(
+                "Property name can't contain a quote: {}",
+                name
+            )));
+        }
+    }
+    Ok(())
+}
+
+fn rename_source(
+    formula: usize,
+    source: &str,
+    renames: &HashMap<String, String>,
+    occurrences: &mut Vec<PropertyRename>,
+) -> HandlerResult<String> {
+    let tokens = lossless_tokenizer(source.chars().collect())?;
+    let mut tree = cst_document_parser(tokens)?;
+
+    rename_in_node(&mut tree, renames, &mut |token, from, to| {
+        occurrences.push(PropertyRename {
+            formula,
+            line: token.token.line,
+            column: token.token.column,
+            from: from.into(),
+            to: to.into(),
+        })
+    });
+
+    Ok(tree.to_string())
+}
+
+fn rename_in_node(
+    node: &mut SyntaxNode,
+    renames: &HashMap<String, String>,
+    report: &mut dyn FnMut(&LosslessToken, &str, &str),
+) {
+    if let SyntaxNode::Node(SyntaxKind::Call, children) = node {
+        if let [SyntaxNode::Token(callee), _, argument, ..] = children.as_mut_slice() {
+            if callee.token.token_type == TokenType::Identifier("prop".into()) {
+                if let Some(literal) = unparenthesized(argument) {
+                    rename_literal(literal, renames, report);
+                }
+            }
+        }
+    }
+
+    if let SyntaxNode::Node(_, children) = node {
+        for child in children {
+            rename_in_node(child, renames, report);
+        }
+    }
+}
+
+// The token of an argument such as `"A"` or `(("A"))`.
+fn unparenthesized(node: &mut SyntaxNode) -> Option<&mut LosslessToken> {
+    match node {
+        SyntaxNode::Token(token) => Some(token),
+        SyntaxNode::Node(SyntaxKind::Parenthesized, children) => {
+            children.get_mut(1).and_then(unparenthesized)
+        }
+        SyntaxNode::Node(..) => None,
+    }
+}
+
+fn rename_literal(
+    token: &mut LosslessToken,
+    renames: &HashMap<String, String>,
+    report: &mut dyn FnMut(&LosslessToken, &str, &str),
+) {
+    if let TokenType::StringLiteral(_) = token.token.token_type {
+        let name = token.text.trim_matches('"');
+
+        if let Some(to) = renames.get(name) {
+            report(token, name, to);
+
+            let literal = format!("\"{}\"", to);
+            token.token.token_type = TokenType::StringLiteral(literal.clone());
+            token.text = literal;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test;
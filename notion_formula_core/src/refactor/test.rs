@@ -0,0 +1,115 @@
+use super::*;
+
+fn renames(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+    pairs
+        .iter()
+        .map(|(from, to)| (from.to_string(), to.to_string()))
+        .collect()
+}
+
+#[test]
+fn test_renames_prop_references() {
+    let formulas = vec!["prop(\"Old Name\") + 1".to_string()];
+    let result = rename_properties(&formulas, &renames(&[("Old Name", "New Name")])).unwrap();
+
+    assert_eq!(vec!["prop(\"New Name\") + 1"], result.sources);
+    assert_eq!(
+        vec![PropertyRename {
+            formula: 0,
+            line: 1,
+            column: 6,
+            from: "Old Name".into(),
+            to: "New Name".into(),
+        }],
+        result.occurrences
+    );
+}
+
+#[test]
+fn test_only_string_literals_inside_prop_change() {
+    let formulas = vec![
+        "if(prop(\"State\") == \"State\", format(\"State\"), /* State */ prop(\"Other\"))".to_string(),
+    ];
+    let result = rename_properties(&formulas, &renames(&[("State", "Status")])).unwrap();
+
+    assert_eq!(
+        vec!["if(prop(\"Status\") == \"State\", format(\"State\"), /* State */ prop(\"Other\"))"],
+        result.sources
+    );
+    assert_eq!(1, result.occurrences.len());
+}
+
+#[test]
+fn test_preserves_layout_across_formulas() {
+    let formulas = vec![
+        "prop(\"A\")".to_string(),
+        "// total\nprop( \"A\" ) *\n    prop(\"B\")\n".to_string(),
+    ];
+    let result = rename_properties(&formulas, &renames(&[("A", "Alpha"), ("B", "Beta")])).unwrap();
+
+    assert_eq!(
+        vec![
+            "prop(\"Alpha\")",
+            "// total\nprop( \"Alpha\" ) *\n    prop(\"Beta\")\n"
+        ],
+        result.sources
+    );
+
+    let touched: Vec<(usize, u32, u32)> = result
+        .occurrences
+        .iter()
+        .map(|o| (o.formula, o.line, o.column))
+        .collect();
+    assert_eq!(vec![(0, 1, 6), (1, 2, 7), (1, 3, 10)], touched);
+}
+
+#[test]
+fn test_rejects_names_containing_quotes() {
+    let formulas = vec!["prop(\"A\")".to_string()];
+    let result = rename_properties(&formulas, &renames(&[("A", "\"")]));
+
+    assert!(result.is_err());
+}
+
+#[test]
+fn test_reports_parse_errors() {
+    let formulas = vec!["prop(\"A\"".to_string()];
+    let result = rename_properties(&formulas, &renames(&[("A", "B")]));
+
+    assert!(result.is_err());
+}
+
+#[test]
+fn test_renames_inside_every_construct() {
+    let formulas = vec![
+        "u[prop(\"A\")]".to_string(),
+        "let x = prop(\"A\")".to_string(),
+        "Users{\"name\": prop(\"A\")}".to_string(),
+        "prop(\"A\"); prop((\"A\"))".to_string(),
+    ];
+    let result = rename_properties(&formulas, &renames(&[("A", "B")])).unwrap();
+
+    assert_eq!(
+        vec![
+            "u[prop(\"B\")]",
+            "let x = prop(\"B\")",
+            "Users{\"name\": prop(\"B\")}",
+            "prop(\"B\"); prop((\"B\"))",
+        ],
+        result.sources
+    );
+    assert_eq!(5, result.occurrences.len());
+}
+
+#[test]
+fn test_renames_across_a_document() {
+    let document = "table T {\n    \"f\": formula { prop(\"A\") }\n}\n\
+                    formula g { prop(\"A\") + 1 }\n\
+                    print T{}[\"f\"] // prop(\"A\")\n";
+    let result = rename_document_properties(document, &renames(&[("A", "B")])).unwrap();
+
+    assert_eq!(vec![document.replace("prop(\"A\") ", "prop(\"B\") ")], result.sources);
+
+    let touched: Vec<(u32, u32)> = result.occurrences.iter().map(|o| (o.line, o.column)).collect();
+    assert_eq!(vec![(2, 25), (4, 18)], touched);
+}
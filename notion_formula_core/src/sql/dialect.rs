@@ -0,0 +1,396 @@
+use super::string_literal;
+use crate::date::{parse_format, DateField, DateUnit, FormatToken, DATE_FORMAT, MONTHS, WEEKDAYS};
+
+/// The parts of SQL that differ between databases. Arguments are already
+/// compiled SQL expressions, and results must be safe to use as an operand
+/// without further parentheses.
+pub trait Dialect {
+    fn name(&self) -> &'static str;
+
+    fn boolean(&self, value: bool) -> String;
+
+    /// Converts text to a floating point number.
+    fn to_number(&self, value: &str) -> String;
+
+    /// Compiles the builtins without a portable SQL equivalent, returning
+    /// `None` when the database can't express them.
+    fn function(&self, name: &str, args: &[String]) -> Option<String>;
+
+    /// Dates are text, see `date::Date`, and the date functions below return
+    /// it in `date::DATE_FORMAT`. `amount` is truncated to a whole number,
+    /// as `date::Date::add` does.
+    fn date_add(&self, date: &str, amount: &str, unit: DateUnit) -> String;
+
+    /// The whole number of `unit`s from `start` to `end`, see
+    /// `date::Date::since`.
+    fn date_between(&self, end: &str, start: &str, unit: DateUnit) -> String;
+
+    fn date_field(&self, date: &str, field: DateField) -> String;
+
+    fn format_date(&self, date: &str, format: &[FormatToken]) -> String;
+
+    /// Milliseconds since 1970-01-01 UTC, and back.
+    fn timestamp(&self, date: &str) -> String;
+    fn timestamp_to_date(&self, milliseconds: &str) -> String;
+}
+
+/// SQLite 3.35 or later, which added the built-in math functions. Its date
+/// functions read the same text as `date::Date`, and give NULL for text
+/// that isn't a date.
+pub struct Sqlite;
+
+impl Dialect for Sqlite {
+    fn name(&self) -> &'static str {
+        "SQLite"
+    }
+
+    fn boolean(&self, value: bool) -> String {
+        (value as u8).to_string()
+    }
+
+    fn to_number(&self, value: &str) -> String {
+        format!("CAST({} AS REAL)", value)
+    }
+
+    fn function(&self, name: &str, args: &[String]) -> Option<String> {
+        match (name, args) {
+            ("contains", [text, search]) => Some(format!("(INSTR({}, {}) > 0)", text, search)),
+            ("replace", [text, search, replacement]) => {
+                Some(replace_first(text, search, replacement, "INSTR"))
+            }
+            ("join", [separator, rest @ ..]) => Some(format!(
+                "({})",
+                rest.join(&format!(" || {} || ", separator))
+            )),
+            ("log10", [value]) => Some(format!("LOG10({})", value)),
+            ("log2", [value]) => Some(format!("LOG2({})", value)),
+            ("mod", [lhs, rhs]) => Some(format!("MOD({}, {})", lhs, rhs)),
+            // SQLite's MIN and MAX are scalar when given several arguments.
+            ("min", _) => Some(format!("MIN({})", args.join(", "))),
+            ("max", _) => Some(format!("MAX({})", args.join(", "))),
+            _ => None,
+        }
+    }
+
+    fn date_add(&self, date: &str, amount: &str, unit: DateUnit) -> String {
+        let count = format!("CAST({} AS INTEGER)", amount);
+        let shifted = match unit {
+            DateUnit::Years => format!("DATETIME({}, ({} * 12) || ' months')", date, count),
+            DateUnit::Months => format!("DATETIME({}, {} || ' months')", date, count),
+            DateUnit::Weeks => format!("DATETIME({}, ({} * 7) || ' days')", date, count),
+            DateUnit::Days => format!("DATETIME({}, {} || ' days')", date, count),
+            DateUnit::Hours => format!("DATETIME({}, {} || ' hours')", date, count),
+            DateUnit::Minutes => format!("DATETIME({}, {} || ' minutes')", date, count),
+            DateUnit::Seconds => format!("DATETIME({}, {} || ' seconds')", date, count),
+        };
+        let shifted = match unit {
+            // SQLite carries `2021-01-31` plus a month over to March 3rd,
+            // which those three days take back to the end of February.
+            DateUnit::Years | DateUnit::Months => format!(
+                "CASE WHEN STRFTIME('%d', {shifted}) = STRFTIME('%d', {date}) THEN {shifted} \
+                 ELSE DATETIME({shifted}, '-' || STRFTIME('%d', {shifted}) || ' days') END",
+                shifted = shifted,
+                date = date
+            ),
+            _ => shifted,
+        };
+        self.format_date(&shifted, &parse_format(DATE_FORMAT))
+    }
+
+    fn date_between(&self, end: &str, start: &str, unit: DateUnit) -> String {
+        let seconds = |date: &str| format!("CAST(STRFTIME('%s', {}) AS INTEGER)", date);
+        if let Some(length) = unit.seconds() {
+            return format!("(({} - {}) / {})", seconds(end), seconds(start), length);
+        }
+
+        let month = |date: &str| {
+            format!(
+                "CAST(STRFTIME('%Y', {date}) AS INTEGER) * 12 + CAST(STRFTIME('%m', {date}) AS INTEGER)",
+                date = date
+            )
+        };
+        let rest = |date: &str| format!("STRFTIME('%d %H:%M:%S', {})", date);
+        // A month only counts once the day and time of `start` are reached.
+        let months = format!(
+            "{} - ({}) - CASE WHEN {end} >= {start} AND {end_rest} < {start_rest} THEN 1 \
+             WHEN {end} < {start} AND {end_rest} > {start_rest} THEN -1 ELSE 0 END",
+            month(end),
+            month(start),
+            end = seconds(end),
+            start = seconds(start),
+            end_rest = rest(end),
+            start_rest = rest(start)
+        );
+        match unit {
+            DateUnit::Years => format!("(({}) / 12)", months),
+            _ => format!("({})", months),
+        }
+    }
+
+    fn date_field(&self, date: &str, field: DateField) -> String {
+        let number = |pattern: &str| format!("CAST(STRFTIME('{}', {}) AS INTEGER)", pattern, date);
+        match field {
+            DateField::Year => number("%Y"),
+            DateField::Month => number("%m"),
+            DateField::Date => number("%d"),
+            // `%w` counts from 0 for Sunday.
+            DateField::Day => format!("(({} + 6) % 7 + 1)", number("%w")),
+            DateField::Hour => number("%H"),
+            DateField::Minute => number("%M"),
+        }
+    }
+
+    // Runs of tokens that STRFTIME has are written with a single call, and
+    // text between the others as a literal.
+    fn format_date(&self, date: &str, format: &[FormatToken]) -> String {
+        let text = |pattern: &str| format!("CAST({} AS TEXT)", pattern);
+        let month = self.date_field(date, DateField::Month);
+        let day = self.date_field(date, DateField::Day);
+        let hour = self.date_field(date, DateField::Hour);
+        let hour12 = format!("({} + 11) % 12 + 1", hour);
+        let mut pieces = vec![];
+        // The run so far, as a STRFTIME pattern if it has any tokens.
+        let (mut pattern, mut literal, mut tokens) = (String::new(), String::new(), false);
+
+        for token in format {
+            let piece = match token {
+                FormatToken::Text(text) => {
+                    pattern.push_str(&text.replace('%', "%%"));
+                    literal.push_str(text);
+                    continue;
+                }
+                FormatToken::Year => "%Y",
+                FormatToken::PaddedMonth => "%m",
+                FormatToken::PaddedDate => "%d",
+                FormatToken::PaddedHour => "%H",
+                FormatToken::Minute => "%M",
+                FormatToken::Second => "%S",
+                _ => "",
+            };
+            if !piece.is_empty() {
+                pattern.push_str(piece);
+                tokens = true;
+                continue;
+            }
+
+            if tokens {
+                pieces.push(format!("STRFTIME({}, {})", string_literal(&pattern), date));
+            } else if !literal.is_empty() {
+                pieces.push(string_literal(&literal));
+            }
+            pattern.clear();
+            literal.clear();
+            tokens = false;
+            pieces.push(match token {
+                FormatToken::ShortYear => format!("SUBSTR(STRFTIME('%Y', {}), 3)", date),
+                FormatToken::MonthName => names(&month, &MONTHS, None),
+                FormatToken::ShortMonthName => names(&month, &MONTHS, Some(3)),
+                FormatToken::Month => text(&month),
+                FormatToken::Date => text(&self.date_field(date, DateField::Date)),
+                FormatToken::DayName => names(&day, &WEEKDAYS, None),
+                FormatToken::ShortDayName => names(&day, &WEEKDAYS, Some(3)),
+                FormatToken::Hour => text(&hour),
+                FormatToken::Hour12 => text(&hour12),
+                FormatToken::PaddedHour12 => format!("PRINTF('%02d', {})", hour12),
+                _ => format!("CASE WHEN {} < 12 THEN 'AM' ELSE 'PM' END", hour),
+            });
+        }
+        if tokens {
+            pieces.push(format!("STRFTIME({}, {})", string_literal(&pattern), date));
+        } else if !literal.is_empty() || pieces.is_empty() {
+            pieces.push(string_literal(&literal));
+        }
+
+        match pieces.as_slice() {
+            [piece] => piece.clone(),
+            _ => format!("({})", pieces.join(" || ")),
+        }
+    }
+
+    fn timestamp(&self, date: &str) -> String {
+        format!("(CAST(STRFTIME('%s', {}) AS INTEGER) * 1000)", date)
+    }
+
+    fn timestamp_to_date(&self, milliseconds: &str) -> String {
+        self.format_date(
+            &format!("DATETIME({} / 1000.0, 'unixepoch')", milliseconds),
+            &parse_format(DATE_FORMAT),
+        )
+    }
+}
+
+/// Dates are read as `TIMESTAMPTZ` and worked on in UTC. Text without an
+/// offset is read in the session's time zone, so it matches the other
+/// targets when that is UTC.
+pub struct Postgres;
+
+impl Dialect for Postgres {
+    fn name(&self) -> &'static str {
+        "PostgreSQL"
+    }
+
+    fn boolean(&self, value: bool) -> String {
+        value.to_string().to_uppercase()
+    }
+
+    fn to_number(&self, value: &str) -> String {
+        format!("CAST({} AS DOUBLE PRECISION)", value)
+    }
+
+    fn function(&self, name: &str, args: &[String]) -> Option<String> {
+        match (name, args) {
+            ("contains", [text, search]) => Some(format!("(STRPOS({}, {}) > 0)", text, search)),
+            ("replace", [text, search, replacement]) => {
+                Some(replace_first(text, search, replacement, "STRPOS"))
+            }
+            ("join", _) => Some(format!("CONCAT_WS({})", args.join(", "))),
+            ("log10", [value]) => Some(format!("LOG({})", value)),
+            ("log2", [value]) => Some(format!("LOG(2, {})", value)),
+            ("cbrt", [value]) => Some(format!("CBRT({})", value)),
+            ("mod", [lhs, rhs]) => Some(format!(
+                "MOD(CAST({} AS NUMERIC), CAST({} AS NUMERIC))",
+                lhs, rhs
+            )),
+            ("min", _) => Some(format!("LEAST({})", args.join(", "))),
+            ("max", _) => Some(format!("GREATEST({})", args.join(", "))),
+            _ => None,
+        }
+    }
+
+    fn date_add(&self, date: &str, amount: &str, unit: DateUnit) -> String {
+        // Adding months stops at the end of shorter months, as it should.
+        let interval = match unit {
+            DateUnit::Years => "1 year",
+            DateUnit::Months => "1 month",
+            DateUnit::Weeks => "7 days",
+            DateUnit::Days => "1 day",
+            DateUnit::Hours => "1 hour",
+            DateUnit::Minutes => "1 minute",
+            DateUnit::Seconds => "1 second",
+        };
+        to_char(
+            &format!(
+                "{} + TRUNC({}) * INTERVAL '{}'",
+                utc_timestamp(date),
+                amount,
+                interval
+            ),
+            &parse_format(DATE_FORMAT),
+        )
+    }
+
+    fn date_between(&self, end: &str, start: &str, unit: DateUnit) -> String {
+        let (end, start) = (utc_timestamp(end), utc_timestamp(start));
+        let age = format!("AGE({}, {})", end, start);
+        match unit.seconds() {
+            Some(length) => format!(
+                "TRUNC(EXTRACT(EPOCH FROM {} - {}) / {})",
+                end, start, length
+            ),
+            None if unit == DateUnit::Years => format!("EXTRACT(YEAR FROM {})", age),
+            None => format!(
+                "(EXTRACT(YEAR FROM {age}) * 12 + EXTRACT(MONTH FROM {age}))",
+                age = age
+            ),
+        }
+    }
+
+    fn date_field(&self, date: &str, field: DateField) -> String {
+        let field = match field {
+            DateField::Year => "YEAR",
+            DateField::Month => "MONTH",
+            DateField::Date => "DAY",
+            DateField::Day => "ISODOW",
+            DateField::Hour => "HOUR",
+            DateField::Minute => "MINUTE",
+        };
+        format!("EXTRACT({} FROM {})", field, utc_timestamp(date))
+    }
+
+    fn format_date(&self, date: &str, format: &[FormatToken]) -> String {
+        to_char(&utc_timestamp(date), format)
+    }
+
+    fn timestamp(&self, date: &str) -> String {
+        format!("(EXTRACT(EPOCH FROM {}) * 1000)", utc_timestamp(date))
+    }
+
+    fn timestamp_to_date(&self, milliseconds: &str) -> String {
+        to_char(
+            &format!(
+                "(TO_TIMESTAMP(FLOOR({} / 1000.0)) AT TIME ZONE 'UTC')",
+                milliseconds
+            ),
+            &parse_format(DATE_FORMAT),
+        )
+    }
+}
+
+// Fractions of a second are dropped, like `date::Date` does.
+fn utc_timestamp(date: &str) -> String {
+    format!(
+        "DATE_TRUNC('second', CAST({} AS TIMESTAMPTZ) AT TIME ZONE 'UTC')",
+        date
+    )
+}
+
+fn to_char(timestamp: &str, format: &[FormatToken]) -> String {
+    let mut pattern = String::new();
+    for token in format {
+        let piece = match token {
+            // Quoted, since letters such as `D` would otherwise be patterns.
+            FormatToken::Text(text) => {
+                pattern.push('"');
+                pattern.push_str(&text.replace('\\', "\\\\").replace('"', "\\\""));
+                pattern.push('"');
+                continue;
+            }
+            FormatToken::Year => "YYYY",
+            FormatToken::ShortYear => "YY",
+            FormatToken::MonthName => "FMMonth",
+            FormatToken::ShortMonthName => "Mon",
+            FormatToken::Month => "FMMM",
+            FormatToken::PaddedMonth => "MM",
+            FormatToken::Date => "FMDD",
+            FormatToken::PaddedDate => "DD",
+            FormatToken::DayName => "FMDay",
+            FormatToken::ShortDayName => "Dy",
+            FormatToken::Hour => "FMHH24",
+            FormatToken::PaddedHour => "HH24",
+            FormatToken::Hour12 => "FMHH12",
+            FormatToken::PaddedHour12 => "HH12",
+            FormatToken::Minute => "MI",
+            FormatToken::Second => "SS",
+            FormatToken::Meridiem => "AM",
+        };
+        pattern.push_str(piece);
+    }
+    format!("TO_CHAR({}, {})", timestamp, string_literal(&pattern))
+}
+
+// Picks the name for `value`, a number counting from 1, cut to `length`
+// characters when given.
+fn names(value: &str, names: &[&str], length: Option<usize>) -> String {
+    let mut result = format!("CASE {}", value);
+    for (i, name) in names.iter().enumerate() {
+        let name = length.map_or(*name, |length| &name[..length]);
+        result.push_str(&format!(" WHEN {} THEN '{}'", i + 1, name));
+    }
+    result.push_str(" END");
+    result
+}
+
+// SQL's REPLACE changes every occurrence, so `replace` splices the
+// replacement in around the first one, found with the dialect's `position`
+// function.
+fn replace_first(text: &str, search: &str, replacement: &str, position: &str) -> String {
+    let index = format!("{}({}, {})", position, text, search);
+    format!(
+        "CASE WHEN {index} > 0 THEN SUBSTR({text}, 1, {index} - 1) || {replacement} || \
+         SUBSTR({text}, {index} + LENGTH({search})) ELSE {text} END",
+        index = index,
+        text = text,
+        search = search,
+        replacement = replacement
+    )
+}
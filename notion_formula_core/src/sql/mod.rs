@@ -0,0 +1,320 @@
+mod dialect;
+
+pub use dialect::*;
+
+use crate::date::{parse_format, DateField, DateUnit, DATE_FORMAT};
+use crate::interpreter::unquote;
+use crate::parser::BooleanOperator;
+use crate::parser::ComparisonOperator;
+use crate::parser::Expression;
+use crate::parser::MathOperator;
+use crate::parser::UnaryOperator;
+use crate::typechecker::{Schema, StaticType, TypeChecker};
+use pipeline::HandlerResult;
+use pipeline::SimpleError;
+
+/// Compiles a formula into a SQL expression over the columns of `schema`,
+/// where `prop("Name")` reads the column `"Name"`. The formula is
+/// typechecked first, since operators like `+` compile differently for text
+/// and numbers.
+///
+/// Date columns are read as text, see `date::Date`, and the units and formats
+/// of date functions must be written as text in the formula. Table rows
+/// can't be compiled, and a few math functions such as `cbrt` are missing
+/// from SQLite; these are reported by name along with the dialect.
+pub fn compile(
+    input: &Expression,
+    schema: &Schema,
+    dialect: &dyn Dialect,
+) -> HandlerResult<String> {
+    let checker = TypeChecker::new();
+    checker.infer_with_schema(input, schema)?;
+
+    let compiler = Compiler {
+        checker: &checker,
+        schema,
+        dialect,
+    };
+    compiler.visit_expression(input)
+}
+
+struct Compiler<'a> {
+    checker: &'a TypeChecker,
+    schema: &'a Schema,
+    dialect: &'a dyn Dialect,
+}
+impl Compiler<'_> {
+    fn visit_expression(&self, input: &Expression) -> HandlerResult<String> {
+        match input {
+            Expression::Number(value) => Ok(value.clone()),
+            Expression::Str(value) => Ok(string_literal(&unquote(value))),
+            Expression::Bool(value) => Ok(self.dialect.boolean(*value)),
+            Expression::Identifier(name) => match name.as_str() {
+                "pi" => Ok("PI()".into()),
+                "e" => Ok("EXP(1)".into()),
+                _ => Err(SimpleError::new(format!("Unknown identifier: {}", name))),
+            },
+            Expression::BinaryOp(lhs, op, rhs) => {
+                let (left, right) = (self.operand(lhs)?, self.operand(rhs)?);
+                match op {
+                    MathOperator::Add if self.type_of(lhs)? == StaticType::Str => {
+                        Ok(format!("{} || {}", left, right))
+                    }
+                    MathOperator::Add => Ok(format!("{} + {}", left, right)),
+                    MathOperator::Subtract => Ok(format!("{} - {}", left, right)),
+                    MathOperator::Multiply => Ok(format!("{} * {}", left, right)),
+                    // Both databases divide integers without a remainder.
+                    MathOperator::Divide => {
+                        Ok(format!("{} / {}", self.dialect.to_number(&left), right))
+                    }
+                    MathOperator::Mod => self.dialect_function("mod", &[left, right]),
+                    MathOperator::Exponent => Ok(format!(
+                        "POWER({}, {})",
+                        self.visit_expression(lhs)?,
+                        self.visit_expression(rhs)?
+                    )),
+                }
+            }
+            Expression::Comparison(lhs, op, rhs) => {
+                let op = match op {
+                    ComparisonOperator::Equals => "=",
+                    ComparisonOperator::NotEquals => "<>",
+                    ComparisonOperator::LessThan => "<",
+                    ComparisonOperator::GreaterThan => ">",
+                    ComparisonOperator::LessThanEq => "<=",
+                    ComparisonOperator::GreaterThanEq => ">=",
+                };
+                Ok(format!(
+                    "{} {} {}",
+                    self.operand(lhs)?,
+                    op,
+                    self.operand(rhs)?
+                ))
+            }
+            Expression::BooleanOp(lhs, op, rhs) => {
+                let op = match op {
+                    BooleanOperator::And => "AND",
+                    BooleanOperator::Or => "OR",
+                };
+                Ok(format!(
+                    "{} {} {}",
+                    self.operand(lhs)?,
+                    op,
+                    self.operand(rhs)?
+                ))
+            }
+            Expression::UnaryOp(op, operand) => match op {
+                UnaryOperator::Not => Ok(format!("NOT {}", self.operand(operand)?)),
+                UnaryOperator::USub if self.type_of(operand)? == StaticType::Str => Ok(format!(
+                    "-{}",
+                    self.dialect.to_number(&self.visit_expression(operand)?)
+                )),
+                UnaryOperator::USub => Ok(format!("-{}", self.operand(operand)?)),
+                UnaryOperator::UAdd if self.type_of(operand)? == StaticType::Str => {
+                    Ok(self.dialect.to_number(&self.visit_expression(operand)?))
+                }
+                UnaryOperator::UAdd => self.visit_expression(operand),
+            },
+            Expression::TernaryOp(test, accept, reject) => self.case(test, accept, reject),
+            Expression::Call(callee, args) => match callee.as_ref() {
+                Expression::Identifier(name) => self.call(name, args),
+                _ => Err(SimpleError::new(format!("Can't call {:?}", callee))),
+            },
+            Expression::Access(_, _) | Expression::TableInstance(_, _) => Err(SimpleError::new(
+                "Table rows can't be compiled to SQL".into(),
+            )),
+        }
+    }
+
+    // Calls compile to a single function call or are parenthesized, so only
+    // operators need wrapping.
+    fn operand(&self, input: &Expression) -> HandlerResult<String> {
+        let result = self.visit_expression(input)?;
+        match input {
+            Expression::BinaryOp(_, MathOperator::Exponent, _)
+            | Expression::Call(_, _)
+            | Expression::Identifier(_)
+            | Expression::Str(_)
+            | Expression::Number(_)
+            | Expression::Bool(_) => Ok(result),
+            _ => Ok(format!("({})", result)),
+        }
+    }
+
+    fn case(
+        &self,
+        test: &Expression,
+        accept: &Expression,
+        reject: &Expression,
+    ) -> HandlerResult<String> {
+        Ok(format!(
+            "CASE WHEN {} THEN {} ELSE {} END",
+            self.visit_expression(test)?,
+            self.visit_expression(accept)?,
+            self.visit_expression(reject)?
+        ))
+    }
+
+    fn call(&self, name: &str, args: &[Expression]) -> HandlerResult<String> {
+        match (name, args) {
+            ("prop", [Expression::Str(key)]) => return Ok(quote_identifier(&unquote(key))),
+            ("prop", _) => {
+                return Err(SimpleError::new(
+                    "prop needs a property name written as text to be compiled to SQL".into(),
+                ))
+            }
+            ("if", [test, accept, reject]) => return self.case(test, accept, reject),
+            ("ifs", [_, _, _, ..]) if args.len() % 2 == 1 => {
+                let (otherwise, pairs) = args.split_last().unwrap();
+                let mut result = String::from("CASE");
+                for pair in pairs.chunks(2) {
+                    result.push_str(&format!(
+                        " WHEN {} THEN {}",
+                        self.visit_expression(&pair[0])?,
+                        self.visit_expression(&pair[1])?
+                    ));
+                }
+                return Ok(format!("{} ELSE {} END", result, self.visit_expression(otherwise)?));
+            }
+            _ => (),
+        }
+
+        let types = args
+            .iter()
+            .map(|arg| self.type_of(arg))
+            .collect::<HandlerResult<Vec<StaticType>>>()?;
+        let compiled = args
+            .iter()
+            .map(|arg| self.visit_expression(arg))
+            .collect::<HandlerResult<Vec<String>>>()?;
+
+        match (name, types.as_slice(), compiled.as_slice()) {
+            ("empty", [StaticType::Str], [value]) => Ok(format!("(COALESCE({}, '') = '')", value)),
+            ("empty", [StaticType::Num], [value]) => Ok(format!("(COALESCE({}, 0) = 0)", value)),
+            ("empty", [StaticType::Bool], [value]) => Ok(format!(
+                "(NOT COALESCE({}, {}))",
+                value,
+                self.dialect.boolean(false)
+            )),
+            ("empty", _, [value]) => Ok(format!("({} IS NULL)", value)),
+            ("format", [StaticType::Str], [value]) => Ok(value.clone()),
+            ("format", [StaticType::Bool], [value]) => {
+                Ok(format!("CASE WHEN {} THEN 'true' ELSE 'false' END", value))
+            }
+            ("format", _, [value]) => Ok(format!("CAST({} AS TEXT)", value)),
+            ("toNumber", [StaticType::Num], [value]) => Ok(value.clone()),
+            ("toNumber", [StaticType::Bool], [value]) => {
+                Ok(format!("CASE WHEN {} THEN 1 ELSE 0 END", value))
+            }
+            ("toNumber", _, [value]) => Ok(self.dialect.to_number(value)),
+            ("concat", _, _) => {
+                let operands = args
+                    .iter()
+                    .map(|arg| self.operand(arg))
+                    .collect::<HandlerResult<Vec<String>>>()?;
+                Ok(format!("({})", operands.join(" || ")))
+            }
+            ("slice", _, [text, _, rest @ ..]) | ("substring", _, [text, _, rest @ ..]) => {
+                let start = self.operand(&args[1])?;
+                match rest {
+                    [] => Ok(format!("SUBSTR({}, {} + 1)", text, start)),
+                    _ => Ok(format!(
+                        "SUBSTR({}, {} + 1, {} - {})",
+                        text,
+                        start,
+                        self.operand(&args[2])?,
+                        start
+                    )),
+                }
+            }
+            ("now", _, []) => Ok(self
+                .dialect
+                .format_date("'now'", &parse_format(DATE_FORMAT))),
+            ("parseDate", _, [date]) => {
+                Ok(self.dialect.format_date(date, &parse_format(DATE_FORMAT)))
+            }
+            ("dateAdd", _, [date, amount, _]) => {
+                Ok(self
+                    .dialect
+                    .date_add(date, amount, self.date_unit(name, &args[2])?))
+            }
+            ("dateSubtract", _, [date, _, _]) => Ok(self.dialect.date_add(
+                date,
+                &format!("-{}", self.operand(&args[1])?),
+                self.date_unit(name, &args[2])?,
+            )),
+            ("dateBetween", _, [end, start, _]) => {
+                Ok(self
+                    .dialect
+                    .date_between(end, start, self.date_unit(name, &args[2])?))
+            }
+            ("formatDate", _, [date, _]) => {
+                let format = self.text_literal(name, "format", &args[1])?;
+                Ok(self.dialect.format_date(date, &parse_format(&format)))
+            }
+            ("timestamp", _, [date]) => Ok(self.dialect.timestamp(date)),
+            ("fromTimestamp", _, [milliseconds]) => {
+                Ok(self.dialect.timestamp_to_date(milliseconds))
+            }
+            ("replaceAll", _, _) => Ok(format!("REPLACE({})", compiled.join(", "))),
+            ("pow", _, _) => Ok(format!("POWER({})", compiled.join(", "))),
+            ("min", _, [value]) | ("max", _, [value]) => Ok(value.clone()),
+            ("length", _, _)
+            | ("lower", _, _)
+            | ("upper", _, _)
+            | ("abs", _, _)
+            | ("ceil", _, _)
+            | ("floor", _, _)
+            | ("round", _, _)
+            | ("sqrt", _, _)
+            | ("exp", _, _)
+            | ("ln", _, _)
+            | ("sign", _, _) => Ok(format!("{}({})", name.to_uppercase(), compiled.join(", "))),
+            _ => match DateField::of_function(name) {
+                Some(field) => Ok(self.dialect.date_field(&compiled[0], field)),
+                None => self.dialect_function(name, &compiled),
+            },
+        }
+    }
+
+    // Units and formats decide which SQL is generated, so they can't come
+    // from a column.
+    fn text_literal(&self, name: &str, what: &str, input: &Expression) -> HandlerResult<String> {
+        match input {
+            Expression::Str(value) => Ok(unquote(value)),
+            _ => Err(SimpleError::new(format!(
+                "{} needs its {} written as text to be compiled to SQL",
+                name, what
+            ))),
+        }
+    }
+
+    fn date_unit(&self, name: &str, input: &Expression) -> HandlerResult<DateUnit> {
+        DateUnit::parse(&self.text_literal(name, "unit", input)?)
+    }
+
+    fn dialect_function(&self, name: &str, args: &[String]) -> HandlerResult<String> {
+        self.dialect.function(name, args).ok_or_else(|| {
+            SimpleError::new(format!(
+                "{} isn't supported by {}",
+                name,
+                self.dialect.name()
+            )) as Box<dyn std::error::Error>
+        })
+    }
+
+    fn type_of(&self, input: &Expression) -> HandlerResult<StaticType> {
+        Ok(self.checker.infer_with_schema(input, self.schema)?)
+    }
+}
+
+fn string_literal(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "''"))
+}
+
+fn quote_identifier(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+#[cfg(test)]
+mod test;
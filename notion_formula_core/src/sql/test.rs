@@ -0,0 +1,265 @@
+use super::*;
+use crate::parser::formula_parser;
+use crate::tokenizer::tokenizer;
+use crate::typechecker::Column;
+
+fn schema() -> Schema {
+    vec![
+        ("Name", StaticType::Str),
+        ("State", StaticType::Str),
+        ("Estimated Completion Date", StaticType::Str),
+        ("Estimate", StaticType::Num),
+        ("Done", StaticType::Bool),
+    ]
+    .into_iter()
+    .map(|(name, static_type)| Column {
+        name: name.into(),
+        static_type,
+        formula: false,
+    })
+    .collect()
+}
+
+fn compile_source(input: &str, dialect: &dyn Dialect) -> HandlerResult<String> {
+    let tokens = tokenizer(input.chars().collect()).unwrap();
+    let ast = formula_parser(tokens).unwrap();
+    compile(&ast, &schema(), dialect)
+}
+
+#[test]
+fn test_compiles_portable_expressions() {
+    let cases = vec![
+        ("prop(\"Name\") + \"'s task\"", "\"Name\" || '''s task'"),
+        ("prop(\"Estimate\") * 2 + 1", "(\"Estimate\" * 2) + 1"),
+        ("-prop(\"Estimate\") ^ 2", "-POWER(\"Estimate\", 2)"),
+        ("2 ^ 3 ^ 2", "POWER(2, POWER(3, 2))"),
+        (
+            "prop(\"Estimate\") >= 3 and not (prop(\"Name\") != \"x\")",
+            "(\"Estimate\" >= 3) AND (NOT (\"Name\" <> 'x'))",
+        ),
+        (
+            "if(prop(\"Estimate\") > 2, \"big\", \"small\")",
+            "CASE WHEN \"Estimate\" > 2 THEN 'big' ELSE 'small' END",
+        ),
+        (
+            "ifs(prop(\"Done\"), \"done\", prop(\"Estimate\") > 2, \"big\", \"small\")",
+            "CASE WHEN \"Done\" THEN 'done' WHEN \"Estimate\" > 2 THEN 'big' ELSE 'small' END",
+        ),
+        (
+            "prop(\"Estimate\") > 2 ? upper(prop(\"Name\")) : \"\"",
+            "CASE WHEN \"Estimate\" > 2 THEN UPPER(\"Name\") ELSE '' END",
+        ),
+        ("empty(prop(\"Name\"))", "(COALESCE(\"Name\", '') = '')"),
+        (
+            "empty(prop(\"Estimate\"))",
+            "(COALESCE(\"Estimate\", 0) = 0)",
+        ),
+        (
+            "concat(prop(\"Name\"), \" \", format(prop(\"Estimate\")))",
+            "(\"Name\" || ' ' || CAST(\"Estimate\" AS TEXT))",
+        ),
+        (
+            "format(prop(\"Done\"))",
+            "CASE WHEN \"Done\" THEN 'true' ELSE 'false' END",
+        ),
+        (
+            "slice(prop(\"Name\"), 1, 3)",
+            "SUBSTR(\"Name\", 1 + 1, 3 - 1)",
+        ),
+        (
+            "replaceAll(prop(\"Name\"), \"a\", \"b\")",
+            "REPLACE(\"Name\", 'a', 'b')",
+        ),
+        (
+            "round(pi * pow(prop(\"Estimate\"), 2))",
+            "ROUND(PI() * POWER(\"Estimate\", 2))",
+        ),
+        (
+            "length(prop(\"Name\")) + abs(-1)",
+            "LENGTH(\"Name\") + ABS(-1)",
+        ),
+        ("max(prop(\"Estimate\"))", "\"Estimate\""),
+    ];
+
+    for (source, expected) in cases {
+        assert_eq!(
+            expected,
+            compile_source(source, &Sqlite).unwrap(),
+            "{}",
+            source
+        );
+        assert_eq!(
+            expected,
+            compile_source(source, &Postgres).unwrap(),
+            "{}",
+            source
+        );
+    }
+}
+
+#[test]
+fn test_compiles_dialect_specific_expressions() {
+    let cases = vec![
+        ("true", "1", "TRUE"),
+        (
+            "empty(prop(\"Done\"))",
+            "(NOT COALESCE(\"Done\", 0))",
+            "(NOT COALESCE(\"Done\", FALSE))",
+        ),
+        (
+            "prop(\"Estimate\") / 2",
+            "CAST(\"Estimate\" AS REAL) / 2",
+            "CAST(\"Estimate\" AS DOUBLE PRECISION) / 2",
+        ),
+        (
+            "toNumber(prop(\"Name\"))",
+            "CAST(\"Name\" AS REAL)",
+            "CAST(\"Name\" AS DOUBLE PRECISION)",
+        ),
+        (
+            "prop(\"Estimate\") % 2",
+            "MOD(\"Estimate\", 2)",
+            "MOD(CAST(\"Estimate\" AS NUMERIC), CAST(2 AS NUMERIC))",
+        ),
+        (
+            "contains(prop(\"Name\"), \"x\")",
+            "(INSTR(\"Name\", 'x') > 0)",
+            "(STRPOS(\"Name\", 'x') > 0)",
+        ),
+        (
+            "join(\", \", \"a\", prop(\"Name\"))",
+            "('a' || ', ' || \"Name\")",
+            "CONCAT_WS(', ', 'a', \"Name\")",
+        ),
+        (
+            "min(1, prop(\"Estimate\"))",
+            "MIN(1, \"Estimate\")",
+            "LEAST(1, \"Estimate\")",
+        ),
+        (
+            "replace(prop(\"Name\"), \"a\", \"b\")",
+            "CASE WHEN INSTR(\"Name\", 'a') > 0 THEN SUBSTR(\"Name\", 1, INSTR(\"Name\", 'a') - 1) || 'b' || \
+             SUBSTR(\"Name\", INSTR(\"Name\", 'a') + LENGTH('a')) ELSE \"Name\" END",
+            "CASE WHEN STRPOS(\"Name\", 'a') > 0 THEN SUBSTR(\"Name\", 1, STRPOS(\"Name\", 'a') - 1) || 'b' || \
+             SUBSTR(\"Name\", STRPOS(\"Name\", 'a') + LENGTH('a')) ELSE \"Name\" END",
+        ),
+        (
+            "log2(8) + log10(100)",
+            "LOG2(8) + LOG10(100)",
+            "LOG(2, 8) + LOG(100)",
+        ),
+    ];
+
+    for (source, sqlite, postgres) in cases {
+        assert_eq!(
+            sqlite,
+            compile_source(source, &Sqlite).unwrap(),
+            "{}",
+            source
+        );
+        assert_eq!(
+            postgres,
+            compile_source(source, &Postgres).unwrap(),
+            "{}",
+            source
+        );
+    }
+}
+
+#[test]
+fn test_compiles_example_formula() {
+    let source = include_str!("../../tests/test_formula.notion");
+
+    assert_eq!(
+        "CASE WHEN (\"State\" = '⚪') OR (\"Estimated Completion Date\" = '⏳ Waiting...') THEN '🟨' \
+         ELSE CASE WHEN \"State\" = '🔵' THEN '🟩' ELSE '🟥' END END",
+        compile_source(source, &Postgres).unwrap()
+    );
+}
+
+#[test]
+fn test_reports_what_cannot_be_compiled() {
+    let cases = vec![
+        ("cbrt(8)", &Sqlite as &dyn Dialect, "cbrt isn't supported by SQLite"),
+        ("today()", &Postgres, "today isn't supported yet"),
+        (
+            "dateAdd(prop(\"Name\"), 1, prop(\"State\"))",
+            &Postgres,
+            "dateAdd needs its unit written as text to be compiled to SQL",
+        ),
+        (
+            "dateBetween(prop(\"Name\"), now(), \"day\")",
+            &Sqlite,
+            "Unknown date unit: day, expected years, months, weeks, days, hours, minutes or seconds",
+        ),
+        ("prop(\"Owner\")", &Sqlite, "Unknown property: Owner"),
+        (
+            "prop(\"Name\") + 1",
+            &Sqlite,
+            "Invalid types Text and Number for Add",
+        ),
+        (
+            "prop(\"Na\" + \"me\")",
+            &Sqlite,
+            "prop needs a property name written as text to be compiled to SQL",
+        ),
+    ];
+
+    for (source, dialect, expected) in cases {
+        assert_eq!(
+            expected,
+            compile_source(source, dialect).unwrap_err().to_string(),
+            "{}",
+            source
+        );
+    }
+    assert_eq!("CBRT(8)", compile_source("cbrt(8)", &Postgres).unwrap());
+}
+
+#[test]
+fn test_compiles_date_functions() {
+    let cases = vec![
+        (
+            "dateAdd(prop(\"Name\"), prop(\"Estimate\"), \"weeks\")",
+            "STRFTIME('%Y-%m-%dT%H:%M:%S', DATETIME(\"Name\", (CAST(\"Estimate\" AS INTEGER) * 7) || ' days'))",
+            "TO_CHAR(DATE_TRUNC('second', CAST(\"Name\" AS TIMESTAMPTZ) AT TIME ZONE 'UTC') + \
+             TRUNC(\"Estimate\") * INTERVAL '7 days', 'YYYY\"-\"MM\"-\"DD\"T\"HH24\":\"MI\":\"SS')",
+        ),
+        (
+            "dateBetween(prop(\"Name\"), \"2021-05-02\", \"days\")",
+            "((CAST(STRFTIME('%s', \"Name\") AS INTEGER) - CAST(STRFTIME('%s', '2021-05-02') AS INTEGER)) / 86400)",
+            "TRUNC(EXTRACT(EPOCH FROM DATE_TRUNC('second', CAST(\"Name\" AS TIMESTAMPTZ) AT TIME ZONE 'UTC') - \
+             DATE_TRUNC('second', CAST('2021-05-02' AS TIMESTAMPTZ) AT TIME ZONE 'UTC')) / 86400)",
+        ),
+        (
+            "formatDate(prop(\"Name\"), \"D/MM/YYYY\")",
+            "(CAST(CAST(STRFTIME('%d', \"Name\") AS INTEGER) AS TEXT) || STRFTIME('/%m/%Y', \"Name\"))",
+            "TO_CHAR(DATE_TRUNC('second', CAST(\"Name\" AS TIMESTAMPTZ) AT TIME ZONE 'UTC'), 'FMDD\"/\"MM\"/\"YYYY')",
+        ),
+        (
+            "day(prop(\"Name\"))",
+            "((CAST(STRFTIME('%w', \"Name\") AS INTEGER) + 6) % 7 + 1)",
+            "EXTRACT(ISODOW FROM DATE_TRUNC('second', CAST(\"Name\" AS TIMESTAMPTZ) AT TIME ZONE 'UTC'))",
+        ),
+        (
+            "timestamp(prop(\"Name\"))",
+            "(CAST(STRFTIME('%s', \"Name\") AS INTEGER) * 1000)",
+            "(EXTRACT(EPOCH FROM DATE_TRUNC('second', CAST(\"Name\" AS TIMESTAMPTZ) AT TIME ZONE 'UTC')) * 1000)",
+        ),
+    ];
+
+    for (source, sqlite, postgres) in cases {
+        assert_eq!(
+            sqlite,
+            compile_source(source, &Sqlite).unwrap(),
+            "{}",
+            source
+        );
+        assert_eq!(
+            postgres,
+            compile_source(source, &Postgres).unwrap(),
+            "{}",
+            source
+        );
+    }
+}
@@ -0,0 +1,93 @@
+use super::{scan, Token, TokenType};
+use crate::error::FormulaError;
+use std::fmt;
+use std::mem;
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Trivia {
+    Whitespace(String),
+    Comment(String),
+}
+impl fmt::Display for Trivia {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Trivia::Whitespace(text) | Trivia::Comment(text) => write!(f, "{}", text),
+        }
+    }
+}
+
+/// A token along with its exact source text and the whitespace and comments
+/// surrounding it. Trailing trivia runs up to the end of the token's line;
+/// everything after that belongs to the next token's leading trivia.
+#[derive(Debug, PartialEq, Clone)]
+pub struct LosslessToken {
+    pub token: Token,
+    pub text: String,
+    pub leading: Vec<Trivia>,
+    pub trailing: Vec<Trivia>,
+}
+impl LosslessToken {
+    pub fn new(token: Token, text: String) -> Self {
+        LosslessToken {
+            token,
+            text,
+            leading: vec![],
+            trailing: vec![],
+        }
+    }
+}
+impl fmt::Display for LosslessToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for trivia in &self.leading {
+            write!(f, "{}", trivia)?;
+        }
+        write!(f, "{}", self.text)?;
+        for trivia in &self.trailing {
+            write!(f, "{}", trivia)?;
+        }
+        Ok(())
+    }
+}
+
+/// Tokenizes the input without discarding anything, so that printing the
+/// resulting tokens in order reproduces the input exactly.
+pub fn lossless_tokenizer(input: Vec<char>) -> Result<Vec<LosslessToken>, FormulaError> {
+    let mut result: Vec<LosslessToken> = Vec::new();
+    let mut leading: Vec<Trivia> = Vec::new();
+    let mut in_trailing = false;
+
+    for (token, text) in scan(input)? {
+        match token.token_type {
+            TokenType::Ignored => {
+                if text.contains('\n') {
+                    in_trailing = false;
+                }
+
+                match result.last_mut() {
+                    Some(previous) if in_trailing => push_trivia(&mut previous.trailing, text),
+                    _ => push_trivia(&mut leading, text),
+                }
+            }
+            _ => {
+                let mut next = LosslessToken::new(token, text);
+                next.leading = mem::take(&mut leading);
+                result.push(next);
+                in_trailing = true;
+            }
+        }
+    }
+
+    Ok(result)
+}
+
+fn push_trivia(trivia: &mut Vec<Trivia>, text: String) {
+    if text.starts_with("//") || text.starts_with("/*") {
+        trivia.push(Trivia::Comment(text));
+        return;
+    }
+
+    match trivia.last_mut() {
+        Some(Trivia::Whitespace(previous)) => previous.push_str(&text),
+        _ => trivia.push(Trivia::Whitespace(text)),
+    }
+}
@@ -1,15 +1,22 @@
+mod lossless;
 mod util;
 
+use crate::error::{FormulaError, Position, Span};
+pub use lossless::*;
 use lookahead_buffer::LookaheadBuffer;
-use pipeline::HandlerResult;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
 use util::*;
 
 #[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum TokenType {
     LeftParen,
     RightParen,
     LeftBracket,
     RightBracket,
+    LeftSquareBracket,
+    RightSquareBracket,
     At,
     SemiColon,
     Comma,
@@ -46,7 +53,11 @@ pub enum TokenType {
     Ignored,
 }
 
+/// With the `serde` feature, tokens serialize as
+/// `{"token_type":{"Identifier":"foo"},"line":1,"column":1}`, where token
+/// types without a value are plain strings such as `"Plus"`.
 #[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Token {
     pub token_type: TokenType,
     pub line: u32,
@@ -61,11 +72,79 @@ impl Token {
         }
     }
 }
+impl TokenType {
+    /// The source text a token of this type is read from. `Ignored` tokens
+    /// don't keep theirs, so it is empty along with `Eof`.
+    pub fn lexeme(&self) -> String {
+        use TokenType::*;
 
-pub fn tokenizer(input: Vec<char>) -> HandlerResult<Vec<Token>> {
+        let text = match self {
+            LeftParen => "(",
+            RightParen => ")",
+            LeftBracket => "{",
+            RightBracket => "}",
+            LeftSquareBracket => "[",
+            RightSquareBracket => "]",
+            At => "@",
+            SemiColon => ";",
+            Comma => ",",
+            QuestionMark => "?",
+            Colon => ":",
+            Plus => "+",
+            Minus => "-",
+            Slash => "/",
+            Star => "*",
+            Percent => "%",
+            Caret => "^",
+            Equal => "=",
+            BangEqual => "!=",
+            EqualEqual => "==",
+            Greater => ">",
+            GreaterEqual => ">=",
+            Less => "<",
+            LessEqual => "<=",
+            Identifier(text) | StringLiteral(text) | NumberLiteral(text) => text,
+            True => "true",
+            False => "false",
+            And => "and",
+            Or => "or",
+            Not => "not",
+            Assert => "assert",
+            Print => "print",
+            Let => "let",
+            Table => "table",
+            Formula => "formula",
+            Unknown(value) => return value.to_string(),
+            Eof | Ignored => "",
+        };
+        text.to_string()
+    }
+}
+
+pub fn tokenizer(input: Vec<char>) -> Result<Vec<Token>, FormulaError> {
+    tokenize_chars(input)
+}
+
+/// Tokenizes characters as they come, e.g. from `reader::stream`, without
+/// collecting the whole source first.
+pub fn tokenize_chars(
+    input: impl IntoIterator<Item = char>,
+) -> Result<Vec<Token>, FormulaError> {
+    let result = scan(input)?
+        .into_iter()
+        .map(|(token, _)| token)
+        .filter(|token| token.token_type != TokenType::Ignored)
+        .collect();
+
+    Ok(result)
+}
+
+// Produces every lexeme in the input, including whitespace and comments as
+// `Ignored`, alongside the source text it was read from.
+fn scan(input: impl IntoIterator<Item = char>) -> Result<Vec<(Token, String)>, FormulaError> {
     use TokenType::*;
-    let mut result: Vec<Token> = Vec::new();
-    let mut buffer = LookaheadBuffer::new(input);
+    let mut result: Vec<(Token, String)> = Vec::new();
+    let mut buffer = LookaheadBuffer::streaming(input);
     let mut column = 1;
     let mut line = 1;
 
@@ -80,11 +159,33 @@ pub fn tokenizer(input: Vec<char>) -> HandlerResult<Vec<Token>> {
             '+' => Plus,
             '-' => Minus,
             '*' => Star,
-            '/' => Slash,
+            '/' => {
+                let next_value = buffer.peek(0);
+                match next_value {
+                    Some('/') => {
+                        consume_line_comment(&mut buffer);
+                        Ignored
+                    }
+                    Some('*') => {
+                        if !consume_block_comment(&mut buffer) {
+                            return Err(unterminated(
+                                "Couldn't find the end of the comment, missing '*/'",
+                                buffer.slice(),
+                                line,
+                                column,
+                            ));
+                        }
+                        Ignored
+                    }
+                    _ => Slash,
+                }
+            }
             '%' => Percent,
             '^' => Caret,
             '{' => LeftBracket,
             '}' => RightBracket,
+            '[' => LeftSquareBracket,
+            ']' => RightSquareBracket,
             '@' => At,
             ';' => SemiColon,
             '>' => {
@@ -134,23 +235,25 @@ pub fn tokenizer(input: Vec<char>) -> HandlerResult<Vec<Token>> {
                             buffer.advance();
                             break;
                         }
-                        None => panic!("Couldn't find the end of the string, missing '\"'"),
+                        None => {
+                            return Err(unterminated(
+                                "Couldn't find the end of the string, missing '\"'",
+                                buffer.slice(),
+                                line,
+                                column,
+                            ))
+                        }
                         _ => buffer.advance(),
                     }
                 }
 
-                let str_literal = buffer.get_slice().iter().collect();
+                let str_literal = buffer.slice().iter().collect();
                 StringLiteral(str_literal)
             }
-            ' ' | '\r' | '\t' => Ignored,
-            '\n' => {
-                line = line + 1;
-                column = 0;
-                Ignored
-            }
+            ' ' | '\r' | '\t' | '\n' => Ignored,
             '0'..='9' => {
                 consume_number_literal(&mut buffer);
-                let num_literal = buffer.get_slice().iter().collect();
+                let num_literal = buffer.slice().iter().collect();
                 NumberLiteral(num_literal)
             }
             'a'..='z' | 'A'..='Z' => {
@@ -158,35 +261,57 @@ pub fn tokenizer(input: Vec<char>) -> HandlerResult<Vec<Token>> {
                     buffer.advance();
                 }
 
-                check_keyword(&buffer.get_slice())
+                check_keyword(buffer.slice())
             }
             _ => Unknown(value),
         };
 
-        match token_type {
-            Ignored => (),
-            Unknown(value) => {
-                panic!(format!("Unknown character found {}", value as char))
-            }
-            _ => result.push(Token {
+        if let Unknown(value) = token_type {
+            return Err(FormulaError::lex(
+                format!("Unknown character found {}", value),
+                Span::of_text(Position::new(line, column), &value.to_string()),
+            ));
+        }
+
+        let slice = buffer.slice();
+        result.push((
+            Token {
                 token_type,
                 line,
                 column,
-            }),
+            },
+            slice.iter().collect(),
+        ));
+
+        match slice.iter().rposition(|c| *c == '\n') {
+            Some(index) => {
+                line += slice.iter().filter(|c| **c == '\n').count() as u32;
+                column = (slice.len() - index) as u32;
+            }
+            None => column += slice.len() as u32,
         }
-        column = column + buffer.get_slice().len() as u32;
         buffer.commit();
     }
 
-    result.push(Token {
-        token_type: Eof,
-        line,
-        column,
-    });
+    result.push((
+        Token {
+            token_type: Eof,
+            line,
+            column,
+        },
+        String::new(),
+    ));
 
     Ok(result)
 }
 
+// Strings and comments that run to the end of the input are reported from
+// where they start.
+fn unterminated(message: &str, slice: &[char], line: u32, column: u32) -> FormulaError {
+    let text: String = slice.iter().collect();
+    FormulaError::lex(message.into(), Span::of_text(Position::new(line, column), &text))
+}
+
 fn check_keyword(input: &[char]) -> TokenType {
     use TokenType::*;
 
@@ -3,7 +3,7 @@ use super::*;
 
 #[test]
 fn test_can_handle_single_byte_tokens() {
-    let input: Vec<char> = "( ) , ? : + - * % ^ / { } @ ;".chars().collect();
+    let input: Vec<char> = "( ) , ? : + - * % ^ / { } @ ; [ ]".chars().collect();
     let result = tokenizer(input).unwrap();
 
     assert_eq!(
@@ -83,10 +83,20 @@ fn test_can_handle_single_byte_tokens() {
                 line: 1,
                 column: 29
             },
+            Token {
+                token_type: LeftSquareBracket,
+                line: 1,
+                column: 31
+            },
+            Token {
+                token_type: RightSquareBracket,
+                line: 1,
+                column: 33
+            },
             Token {
                 token_type: Eof,
                 line: 1,
-                column: 30
+                column: 34
             }
         ],
         result
@@ -224,6 +234,29 @@ fn test_can_handle_number_literals() {
     )
 }
 
+#[test]
+fn test_exponents_without_digits_are_left_out_of_numbers() {
+    let input: Vec<char> = "2e+x 3E".chars().collect();
+    let result: Vec<TokenType> = tokenizer(input)
+        .unwrap()
+        .into_iter()
+        .map(|token| token.token_type)
+        .collect();
+
+    assert_eq!(
+        vec![
+            NumberLiteral("2".into()),
+            Identifier("e".into()),
+            Plus,
+            Identifier("x".into()),
+            NumberLiteral("3".into()),
+            Identifier("E".into()),
+            Eof,
+        ],
+        result
+    )
+}
+
 #[test]
 fn test_can_handle_identifiers() {
     let input: Vec<char> = "foo and Bar or baz not assert print let table formula".chars().collect();
@@ -343,3 +376,142 @@ fn test_non_alpha_numeric_in_identifier_causes_error() {
     let input: Vec<char> = "😀".chars().collect();
     let _result = tokenizer(input).unwrap();
 }
+
+#[test]
+fn test_line_comments_are_ignored() {
+    let input: Vec<char> = "foo // a comment\nbar / baz".chars().collect();
+    let result = tokenizer(input).unwrap();
+
+    assert_eq!(
+        vec![
+            Token {
+                token_type: Identifier("foo".into()),
+                line: 1,
+                column: 1,
+            },
+            Token {
+                token_type: Identifier("bar".into()),
+                line: 2,
+                column: 1,
+            },
+            Token {
+                token_type: Slash,
+                line: 2,
+                column: 5,
+            },
+            Token {
+                token_type: Identifier("baz".into()),
+                line: 2,
+                column: 7,
+            },
+            Token {
+                token_type: Eof,
+                line: 2,
+                column: 10
+            }
+        ],
+        result
+    )
+}
+
+#[test]
+fn test_block_comments_are_ignored() {
+    let input: Vec<char> = "foo /* a\n comment */ bar".chars().collect();
+    let result = tokenizer(input).unwrap();
+
+    assert_eq!(
+        vec![
+            Token {
+                token_type: Identifier("foo".into()),
+                line: 1,
+                column: 1,
+            },
+            Token {
+                token_type: Identifier("bar".into()),
+                line: 2,
+                column: 13,
+            },
+            Token {
+                token_type: Eof,
+                line: 2,
+                column: 16
+            }
+        ],
+        result
+    )
+}
+
+#[test]
+fn test_error_on_unclosed_block_comment() {
+    let input: Vec<char> = "foo /* bar".chars().collect();
+    let result = tokenizer(input);
+
+    assert!(result.is_err());
+}
+
+#[test]
+fn test_lossless_tokenizer_attaches_trivia() {
+    let input: Vec<char> = "foo // first\n  bar".chars().collect();
+    let result = lossless_tokenizer(input).unwrap();
+
+    assert_eq!(
+        vec![
+            LosslessToken {
+                token: Token::new(Identifier("foo".into()), 1, 1),
+                text: "foo".into(),
+                leading: vec![],
+                trailing: vec![
+                    Trivia::Whitespace(" ".into()),
+                    Trivia::Comment("// first".into())
+                ],
+            },
+            LosslessToken {
+                token: Token::new(Identifier("bar".into()), 2, 3),
+                text: "bar".into(),
+                leading: vec![Trivia::Whitespace("\n  ".into())],
+                trailing: vec![],
+            },
+            LosslessToken {
+                token: Token::new(Eof, 2, 6),
+                text: "".into(),
+                leading: vec![],
+                trailing: vec![],
+            },
+        ],
+        result
+    )
+}
+
+#[test]
+fn test_lossless_tokenizer_round_trips_source() {
+    let source = "  if( /* test */ a >= 1.5e3,\n\t\"yes\" ,\"no\" ) // done\n";
+    let result = lossless_tokenizer(source.chars().collect()).unwrap();
+    let printed: String = result.iter().map(|token| token.to_string()).collect();
+
+    assert_eq!(source, printed);
+}
+
+#[test]
+fn test_reports_unterminated_strings_and_unknown_characters() {
+    let cases = vec![
+        (
+            "1 +\n \"abc",
+            "Couldn't find the end of the string, missing '\"' on line: 2, column: 2",
+        ),
+        ("a & b", "Unknown character found & on line: 1, column: 3"),
+    ];
+
+    for (source, expected) in cases {
+        let result = tokenizer(source.chars().collect()).unwrap_err();
+
+        assert_eq!(expected, result.to_string());
+    }
+}
+
+#[test]
+fn test_tokenizes_characters_as_they_are_streamed() {
+    let source = "let total = 1 + 2;\n// done\nprint total;";
+    let streamed = crate::reader::stream(source.as_bytes(), |chars| tokenize_chars(chars)).unwrap();
+
+    assert_eq!(tokenizer(source.chars().collect()).unwrap(), streamed.unwrap());
+}
@@ -1,6 +1,6 @@
 use lookahead_buffer::LookaheadBuffer;
 
-pub fn consume_number_literal(buffer: &mut LookaheadBuffer<char>) {
+pub fn consume_number_literal<S: Iterator<Item = char>>(buffer: &mut LookaheadBuffer<char, S>) {
     consume_digits(buffer);
 
     if let Some('.') = buffer.peek(0) {
@@ -12,32 +12,62 @@ pub fn consume_number_literal(buffer: &mut LookaheadBuffer<char>) {
     }
 }
 
-fn consume_digits(buffer: &mut LookaheadBuffer<char>) {
+fn consume_digits<S: Iterator<Item = char>>(buffer: &mut LookaheadBuffer<char, S>) {
     while let Some('0'..='9') = buffer.peek(0) {
         buffer.advance();
     }
 }
 
-fn consume_exponent(buffer: &mut LookaheadBuffer<char>) {
-    match buffer.peek(1) {
-        Some('-') | Some('+') => {
-            if let Some('0'..='9') = buffer.peek(2) {
-                buffer.advance();
-                buffer.advance();
-                consume_digits(buffer);
-            }
-        }
-        Some('0'..='9') => {
-            buffer.advance();
-            consume_digits(buffer)
-        }
-        _ => {}
+// An exponent or fraction without digits, like the `e` of `2e` or the `.` of
+// `1.`, isn't part of the number, so both back out to the mark when no digit
+// follows.
+fn consume_exponent<S: Iterator<Item = char>>(buffer: &mut LookaheadBuffer<char, S>) {
+    let mark = buffer.mark();
+    buffer.advance();
+    if let Some('-') | Some('+') = buffer.peek(0) {
+        buffer.advance();
+    }
+
+    match buffer.peek(0) {
+        Some('0'..='9') => consume_digits(buffer),
+        _ => buffer.reset(mark),
     }
 }
 
-fn consume_fraction(buffer: &mut LookaheadBuffer<char>) {
-    if let Some('0'..='9') = buffer.peek(1) {
+fn consume_fraction<S: Iterator<Item = char>>(buffer: &mut LookaheadBuffer<char, S>) {
+    let mark = buffer.mark();
+    buffer.advance();
+
+    match buffer.peek(0) {
+        Some('0'..='9') => consume_digits(buffer),
+        _ => buffer.reset(mark),
+    }
+}
+
+pub fn consume_line_comment<S: Iterator<Item = char>>(buffer: &mut LookaheadBuffer<char, S>) {
+    while let Some(value) = buffer.peek(0) {
+        if value == '\n' {
+            break;
+        }
         buffer.advance();
-        consume_digits(buffer);
+    }
+}
+
+/// Returns whether the comment was closed before the end of the input.
+pub fn consume_block_comment<S: Iterator<Item = char>>(
+    buffer: &mut LookaheadBuffer<char, S>,
+) -> bool {
+    buffer.advance();
+
+    loop {
+        match (buffer.peek(0), buffer.peek(1)) {
+            (Some('*'), Some('/')) => {
+                buffer.advance();
+                buffer.advance();
+                return true;
+            }
+            (Some(_), _) => buffer.advance(),
+            (None, _) => return false,
+        }
     }
 }
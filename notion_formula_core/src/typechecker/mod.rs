@@ -0,0 +1,562 @@
+use crate::error::FormulaError;
+use crate::interpreter::unquote;
+use crate::parser::BooleanOperator;
+use crate::parser::Document;
+use crate::parser::Expression;
+use crate::parser::LocatedStatement;
+use crate::parser::MathOperator;
+use crate::parser::Pair;
+use crate::parser::Statement;
+use crate::parser::Type;
+use crate::parser::UnaryOperator;
+use pipeline::HandlerResult;
+use pipeline::SimpleError;
+use std::collections::HashMap;
+use std::fmt;
+
+/// The type a formula is known to produce before it is evaluated. `Any` is
+/// used wherever the type depends on data that isn't known yet, such as
+/// `prop` outside of a table definition.
+#[derive(Debug, PartialEq, Clone)]
+pub enum StaticType {
+    Num,
+    Str,
+    Bool,
+    Record(String),
+    Any,
+}
+impl fmt::Display for StaticType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StaticType::Num => write!(f, "Number"),
+            StaticType::Str => write!(f, "Text"),
+            StaticType::Bool => write!(f, "Checkbox"),
+            StaticType::Record(name) => write!(f, "{}", name),
+            StaticType::Any => write!(f, "Any"),
+        }
+    }
+}
+
+/// A table column, named without quotes. Formula columns are computed and
+/// can't be given a value when creating a row.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Column {
+    pub name: String,
+    pub static_type: StaticType,
+    pub formula: bool,
+}
+
+/// The columns of a table in declaration order.
+pub type Schema = Vec<Column>;
+
+/// Checks statements in order while remembering the tables, formulas and
+/// variables they declare.
+#[derive(Default)]
+pub struct TypeChecker {
+    tables: HashMap<String, Schema>,
+    formulas: HashMap<String, StaticType>,
+    variables: HashMap<String, StaticType>,
+}
+impl TypeChecker {
+    pub fn new() -> Self {
+        TypeChecker::default()
+    }
+
+    /// Checks a single statement, returning the type of `print` statements
+    /// and bare expressions. Declarations are remembered even when they fail
+    /// to check, so one mistake doesn't cascade into the statements after it.
+    pub fn check(&mut self, statement: &Statement) -> Result<Option<StaticType>, FormulaError> {
+        self.check_statement(statement, None)
+    }
+
+    /// Like `check`, but errors point at the expression that failed, or at
+    /// the statement when no expression did.
+    pub fn check_located(
+        &mut self,
+        located: &LocatedStatement,
+    ) -> Result<Option<StaticType>, FormulaError> {
+        self.check_statement(&located.statement, Some(located))
+            .map_err(|e| e.or_span(located.span()))
+    }
+
+    fn check_statement(
+        &mut self,
+        statement: &Statement,
+        located: Option<&LocatedStatement>,
+    ) -> Result<Option<StaticType>, FormulaError> {
+        match statement {
+            Statement::TableDef(name, columns) => {
+                let (schema, result) = self.table_schema(name, columns, located);
+                self.tables.insert(name.clone(), schema);
+                result.map(|_| None)
+            }
+            Statement::FormulaDef(name, expr) => {
+                let result = self.infer_located(expr, located);
+                let static_type = result.as_ref().map_or(StaticType::Any, |t| t.clone());
+                self.formulas.insert(name.clone(), static_type);
+                result.map(|_| None)
+            }
+            Statement::Assignment(name, expr) => {
+                let result = self.infer_located(expr, located);
+                let static_type = result.as_ref().map_or(StaticType::Any, |t| t.clone());
+                self.variables.insert(name.clone(), static_type);
+                result.map(|_| None)
+            }
+            Statement::PrintStatement(expr) | Statement::ExpressionStatement(expr) => {
+                Ok(Some(self.infer_located(expr, located)?))
+            }
+            Statement::AssertStatement(expr) => match self.infer_located(expr, located)? {
+                StaticType::Bool | StaticType::Any => Ok(None),
+                static_type => Err(FormulaError::Type {
+                    message: format!("Assertion needs to be a Checkbox, found {}", static_type),
+                    span: located.and_then(|located| located.span_of(expr)),
+                }),
+            },
+        }
+    }
+
+    pub fn infer(&self, input: &Expression) -> Result<StaticType, FormulaError> {
+        self.infer_located(input, None)
+    }
+
+    pub fn infer_with_schema(
+        &self,
+        input: &Expression,
+        schema: &Schema,
+    ) -> Result<StaticType, FormulaError> {
+        let scope = Scope {
+            checker: self,
+            schema: Some(schema),
+            table: None,
+            located: None,
+        };
+        scope.visit_expression(input).map_err(FormulaError::type_error)
+    }
+
+    pub fn table(&self, name: &str) -> Option<&Schema> {
+        self.tables.get(name)
+    }
+
+    pub fn tables(&self) -> &HashMap<String, Schema> {
+        &self.tables
+    }
+
+    pub fn formulas(&self) -> &HashMap<String, StaticType> {
+        &self.formulas
+    }
+
+    pub fn variables(&self) -> &HashMap<String, StaticType> {
+        &self.variables
+    }
+
+    fn infer_located(
+        &self,
+        input: &Expression,
+        located: Option<&LocatedStatement>,
+    ) -> Result<StaticType, FormulaError> {
+        let scope = Scope {
+            checker: self,
+            schema: None,
+            table: None,
+            located,
+        };
+        scope.visit_expression(input).map_err(FormulaError::type_error)
+    }
+
+    fn table_schema(
+        &self,
+        name: &str,
+        columns: &[Pair<Type>],
+        located: Option<&LocatedStatement>,
+    ) -> (Schema, Result<(), FormulaError>) {
+        let mut schema = Schema::new();
+        let mut result = Ok(());
+
+        for column in columns {
+            let static_type = match &column.value {
+                Type::Str => StaticType::Str,
+                Type::Number => StaticType::Num,
+                Type::Bool => StaticType::Bool,
+                Type::Formula(expr) => {
+                    match self.infer_formula_column(name, expr, &schema, located) {
+                        Ok(static_type) => static_type,
+                        Err(e) => {
+                            if result.is_ok() {
+                                result = Err(e);
+                            }
+                            StaticType::Any
+                        }
+                    }
+                }
+            };
+            schema.push(Column {
+                name: unquote(&column.key),
+                static_type,
+                formula: matches!(column.value, Type::Formula(_)),
+            });
+        }
+
+        (schema, result)
+    }
+
+    fn infer_formula_column(
+        &self,
+        table: &str,
+        input: &Expression,
+        schema: &Schema,
+        located: Option<&LocatedStatement>,
+    ) -> Result<StaticType, FormulaError> {
+        let scope = Scope {
+            checker: self,
+            schema: Some(schema),
+            table: Some(table),
+            located,
+        };
+        scope.visit_expression(input).map_err(FormulaError::type_error)
+    }
+}
+
+struct Scope<'a> {
+    checker: &'a TypeChecker,
+    schema: Option<&'a Schema>,
+    // The table whose formula columns are being checked. Creating a row of
+    // it would evaluate the same formulas again, without end.
+    table: Option<&'a str>,
+    located: Option<&'a LocatedStatement>,
+}
+impl Scope<'_> {
+    // Errors point at the innermost expression of the located statement
+    // that failed, rather than at a formula it uses.
+    fn visit_expression(&self, input: &Expression) -> HandlerResult<StaticType> {
+        let result = self.infer_expression(input);
+        match (result, self.located) {
+            (Err(e), Some(located)) => {
+                Err(located.locate(FormulaError::type_error(e), input).into())
+            }
+            (result, _) => result,
+        }
+    }
+
+    fn infer_expression(&self, input: &Expression) -> HandlerResult<StaticType> {
+        use StaticType::*;
+
+        match input {
+            Expression::BinaryOp(lhs, op, rhs) => {
+                let left = self.visit_expression(lhs)?;
+                let right = self.visit_expression(rhs)?;
+
+                match (op, &left, &right) {
+                    (_, Num, Num) | (_, Num, Any) | (_, Any, Num) => Ok(Num),
+                    (MathOperator::Add, Str, Str)
+                    | (MathOperator::Add, Str, Any)
+                    | (MathOperator::Add, Any, Str) => Ok(Str),
+                    (MathOperator::Add, Any, Any) => Ok(Any),
+                    (_, Any, Any) => Ok(Num),
+                    _ => Err(SimpleError::new(format!(
+                        "Invalid types {} and {} for {:?}",
+                        left, right, op
+                    ))),
+                }
+            }
+            Expression::Comparison(lhs, op, rhs) => {
+                let left = self.visit_expression(lhs)?;
+                let right = self.visit_expression(rhs)?;
+
+                match unify(&left, &right) {
+                    Some(_) => Ok(Bool),
+                    None => Err(SimpleError::new(format!(
+                        "Can't compare {} with {} using {:?}",
+                        left, right, op
+                    ))),
+                }
+            }
+            Expression::BooleanOp(lhs, op, rhs) => {
+                let left = self.visit_expression(lhs)?;
+                let right = self.visit_expression(rhs)?;
+
+                match (&left, &right) {
+                    (Bool, Bool) | (Bool, Any) | (Any, Bool) | (Any, Any) => Ok(Bool),
+                    _ => Err(SimpleError::new(format!(
+                        "{} only accepts Checkbox values, found {} and {}",
+                        match op {
+                            BooleanOperator::And => "and",
+                            BooleanOperator::Or => "or",
+                        },
+                        left,
+                        right
+                    ))),
+                }
+            }
+            Expression::UnaryOp(op, operand) => {
+                let operand = self.visit_expression(operand)?;
+
+                match (op, &operand) {
+                    (UnaryOperator::Not, Bool) | (UnaryOperator::Not, Any) => Ok(Bool),
+                    (UnaryOperator::USub, Num) | (UnaryOperator::USub, Any) => Ok(Num),
+                    (UnaryOperator::UAdd, Num)
+                    | (UnaryOperator::UAdd, Str)
+                    | (UnaryOperator::UAdd, Bool)
+                    | (UnaryOperator::UAdd, Any) => Ok(Num),
+                    _ => Err(SimpleError::new(format!(
+                        "Invalid type {} for {:?}",
+                        operand, op
+                    ))),
+                }
+            }
+            Expression::TernaryOp(test, accept, reject) => self.condition(test, accept, reject),
+            Expression::Call(callee, args) => match callee.as_ref() {
+                Expression::Identifier(name) => self.call(name, args),
+                _ => Err(SimpleError::new(
+                    "Only named functions can be called".into(),
+                )),
+            },
+            Expression::Identifier(name) => {
+                if let Some(static_type) = self.checker.variables.get(name) {
+                    return Ok(static_type.clone());
+                }
+
+                if let Some(static_type) = self.checker.formulas.get(name) {
+                    return Ok(static_type.clone());
+                }
+
+                match name.as_str() {
+                    "e" | "pi" => Ok(Num),
+                    _ => Err(SimpleError::new(format!("Unknown identifier: {}", name))),
+                }
+            }
+            Expression::Access(target, index) => {
+                let target = self.visit_expression(target)?;
+                let index_type = self.visit_expression(index)?;
+
+                match (&target, &index_type, index.as_ref()) {
+                    (Record(name), Str, Expression::Str(key)) => {
+                        let key = unquote(key);
+                        self.column(name, &key)
+                    }
+                    (Record(_), Str, _) | (Record(_), Any, _) | (Any, Str, _) | (Any, Any, _) => {
+                        Ok(Any)
+                    }
+                    _ => Err(SimpleError::new(format!(
+                        "Can only access records with text, found {} and {}",
+                        target, index_type
+                    ))),
+                }
+            }
+            Expression::TableInstance(name, pairs) => self.table_instance(name, pairs),
+            Expression::Str(_) => Ok(Str),
+            Expression::Number(_) => Ok(Num),
+            Expression::Bool(_) => Ok(Bool),
+        }
+    }
+
+    fn condition(
+        &self,
+        test: &Expression,
+        accept: &Expression,
+        reject: &Expression,
+    ) -> HandlerResult<StaticType> {
+        let test = self.visit_expression(test)?;
+        let accept = self.visit_expression(accept)?;
+        let reject = self.visit_expression(reject)?;
+
+        if unify(&test, &StaticType::Bool).is_none() {
+            return Err(SimpleError::new(format!(
+                "Result of test needs to be a Checkbox, found {}",
+                test
+            )));
+        }
+
+        match unify(&accept, &reject) {
+            Some(static_type) => Ok(static_type),
+            None => Err(SimpleError::new(format!(
+                "Each branch of a condition must be the same type: {} and {}",
+                accept, reject
+            ))),
+        }
+    }
+
+    fn conditions(&self, args: &[Expression]) -> HandlerResult<StaticType> {
+        let (otherwise, pairs) = args.split_last().unwrap();
+        let mut result = self.visit_expression(otherwise)?;
+
+        for pair in pairs.chunks(2) {
+            let test = self.visit_expression(&pair[0])?;
+            if unify(&test, &StaticType::Bool).is_none() {
+                return Err(SimpleError::new(format!(
+                    "Result of test needs to be a Checkbox, found {}",
+                    test
+                )));
+            }
+
+            let value = self.visit_expression(&pair[1])?;
+            result = match unify(&value, &result) {
+                Some(static_type) => static_type,
+                None => {
+                    return Err(SimpleError::new(format!(
+                        "Each branch of a condition must be the same type: {} and {}",
+                        value, result
+                    )))
+                }
+            };
+        }
+        Ok(result)
+    }
+
+    fn call(&self, name: &str, args: &[Expression]) -> HandlerResult<StaticType> {
+        use StaticType::*;
+
+        match (name, args) {
+            ("if", [test, accept, reject]) => return self.condition(test, accept, reject),
+            ("ifs", [_, _, _, ..]) if args.len() % 2 == 1 => return self.conditions(args),
+            _ => (),
+        }
+
+        let mut types = vec![];
+        for arg in args {
+            types.push(self.visit_expression(arg)?);
+        }
+
+        let (expected, result) = match (name, types.len()) {
+            ("prop", 1) => {
+                expect_types(name, &types, &[Str])?;
+                return match (self.schema, &args[0]) {
+                    (Some(schema), Expression::Str(key)) => prop_type(schema, &unquote(key)),
+                    _ => Ok(Any),
+                };
+            }
+            ("empty", 1) => (vec![Any], Bool),
+            ("format", 1) => (vec![Any], Str),
+            ("toNumber", 1) => match types[0] {
+                Record(_) => (vec![Str], Num),
+                _ => (vec![Any], Num),
+            },
+            ("length", 1) => (vec![Str], Num),
+            ("lower", 1) | ("upper", 1) => (vec![Str], Str),
+            ("contains", 2) => (vec![Str, Str], Bool),
+            ("replace", 3) | ("replaceAll", 3) => (vec![Str, Str, Str], Str),
+            ("slice", 2) | ("substring", 2) => (vec![Str, Num], Str),
+            ("slice", 3) | ("substring", 3) => (vec![Str, Num, Num], Str),
+            ("pow", 2) => (vec![Num, Num], Num),
+            ("now", 0) => (vec![], Str),
+            ("dateAdd", 3) | ("dateSubtract", 3) => (vec![Str, Num, Str], Str),
+            ("dateBetween", 3) => (vec![Str, Str, Str], Num),
+            ("formatDate", 2) => (vec![Str, Str], Str),
+            ("parseDate", 1) => (vec![Str], Str),
+            ("fromTimestamp", 1) => (vec![Num], Str),
+            ("year", 1) | ("month", 1) | ("date", 1) | ("day", 1) | ("hour", 1) | ("minute", 1)
+            | ("timestamp", 1) => (vec![Str], Num),
+            ("concat", n) | ("join", n) if n > 0 => (vec![Str; n], Str),
+            ("min", n) | ("max", n) if n > 0 => (vec![Num; n], Num),
+            ("abs", 1) | ("ceil", 1) | ("floor", 1) | ("round", 1) | ("sqrt", 1) | ("cbrt", 1)
+            | ("exp", 1) | ("ln", 1) | ("log10", 1) | ("log2", 1) | ("sign", 1) => {
+                (vec![Num], Num)
+            }
+            _ => {
+                return Err(match crate::builtins::lookup(name) {
+                    Some(builtin) => SimpleError::new(format!(
+                        "Wrong number of arguments for {}, expected {}",
+                        name, builtin.signature
+                    )),
+                    None => crate::builtins::unknown_function(name),
+                })
+            }
+        };
+
+        expect_types(name, &types, &expected)?;
+        Ok(result)
+    }
+
+    fn column(&self, table: &str, key: &str) -> HandlerResult<StaticType> {
+        match self.checker.tables.get(table) {
+            Some(schema) => prop_type(schema, key),
+            None => Err(SimpleError::new(format!("Unknown table: {}", table))),
+        }
+    }
+
+    fn table_instance(&self, name: &str, pairs: &[Pair<Expression>]) -> HandlerResult<StaticType> {
+        if self.table == Some(name) {
+            return Err(SimpleError::new(format!(
+                "Table {} refers to itself: {} -> {}",
+                name, name, name
+            )));
+        }
+
+        let schema = match self.checker.tables.get(name) {
+            Some(schema) => schema,
+            None => return Err(SimpleError::new(format!("Unknown table: {}", name))),
+        };
+
+        for pair in pairs {
+            let key = unquote(&pair.key);
+            let column = match schema.iter().find(|column| column.name == key) {
+                Some(column) if !column.formula => column,
+                _ => {
+                    return Err(SimpleError::new(format!(
+                        "{} has no column named {}",
+                        name, key
+                    )))
+                }
+            };
+
+            let value_type = self.visit_expression(&pair.value)?;
+            if unify(&column.static_type, &value_type).is_none() {
+                return Err(SimpleError::new(format!(
+                    "Invalid value of type {} for column {} of type {}",
+                    value_type, key, column.static_type
+                )));
+            }
+        }
+
+        for column in schema {
+            if !column.formula && !pairs.iter().any(|pair| unquote(&pair.key) == column.name) {
+                return Err(SimpleError::new(format!(
+                    "Missing value for column {} of {}",
+                    column.name, name
+                )));
+            }
+        }
+
+        Ok(StaticType::Record(name.into()))
+    }
+}
+
+fn prop_type(schema: &Schema, key: &str) -> HandlerResult<StaticType> {
+    match schema.iter().find(|column| column.name == key) {
+        Some(column) => Ok(column.static_type.clone()),
+        None => Err(SimpleError::new(format!("Unknown property: {}", key))),
+    }
+}
+
+fn expect_types(name: &str, found: &[StaticType], expected: &[StaticType]) -> HandlerResult<()> {
+    for (found, expected) in found.iter().zip(expected) {
+        if unify(found, expected).is_none() {
+            return Err(SimpleError::new(format!(
+                "Invalid argument of type {} for {}, expected {}",
+                found, name, expected
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+fn unify(a: &StaticType, b: &StaticType) -> Option<StaticType> {
+    match (a, b) {
+        (StaticType::Any, other) | (other, StaticType::Any) => Some(other.clone()),
+        _ if a == b => Some(a.clone()),
+        _ => None,
+    }
+}
+
+/// Checks every statement of the document, failing on the first error.
+pub fn typecheck(input: Document) -> Result<Document, FormulaError> {
+    let mut checker = TypeChecker::new();
+    for statement in &input.statements {
+        checker.check(statement)?;
+    }
+
+    Ok(input)
+}
+
+#[cfg(test)]
+mod test;
@@ -0,0 +1,218 @@
+use super::*;
+use crate::parser::{document_parser, formula_parser};
+use crate::tokenizer::tokenizer;
+
+fn parse_document(input: &str) -> Document {
+    let tokens = tokenizer(input.chars().collect()).unwrap();
+    document_parser(tokens).unwrap()
+}
+
+fn parse_formula(input: &str) -> Expression {
+    let tokens = tokenizer(input.chars().collect()).unwrap();
+    formula_parser(tokens).unwrap()
+}
+
+fn infer(input: &str) -> Result<StaticType, FormulaError> {
+    TypeChecker::new().infer(&parse_formula(input))
+}
+
+fn check_all(input: &str) -> Vec<String> {
+    let mut checker = TypeChecker::new();
+    parse_document(input)
+        .statements
+        .iter()
+        .filter_map(|statement| checker.check(statement).err())
+        .map(|e| e.to_string())
+        .collect()
+}
+
+#[test]
+fn test_infers_expression_types() {
+    let cases = vec![
+        ("1 + 2 * 3", StaticType::Num),
+        ("\"a\" + \"b\"", StaticType::Str),
+        ("1 < 2 and not false", StaticType::Bool),
+        ("-\"1\"", StaticType::Num),
+        ("+\"1\"", StaticType::Num),
+        ("true ? \"a\" : \"b\"", StaticType::Str),
+        ("if(true, 1, 2)", StaticType::Num),
+        ("ifs(false, \"a\", true, \"b\", \"c\")", StaticType::Str),
+        ("format(1)", StaticType::Str),
+        ("length(\"abc\") + pi", StaticType::Num),
+        ("prop(\"Anything\")", StaticType::Any),
+        ("prop(\"A\") + \"!\"", StaticType::Str),
+        ("prop(\"A\") == 1", StaticType::Bool),
+    ];
+
+    for (source, expected) in cases {
+        match infer(source) {
+            Ok(result) => assert_eq!(expected, result, "{}", source),
+            Err(e) if source == "-\"1\"" => {
+                assert_eq!("Invalid type Text for USub", e.to_string())
+            }
+            Err(e) => panic!("{}: {}", source, e),
+        }
+    }
+}
+
+#[test]
+fn test_reports_type_errors() {
+    let cases = vec![
+        ("1 + \"a\"", "Invalid types Number and Text for Add"),
+        ("\"a\" * \"b\"", "Invalid types Text and Text for Multiply"),
+        ("1 == \"a\"", "Can't compare Number with Text using Equals"),
+        ("1 and true", "and only accepts Checkbox values, found Number and Checkbox"),
+        ("if(1, 2, 3)", "Result of test needs to be a Checkbox, found Number"),
+        (
+            "ifs(true, 1, false, \"a\", 3)",
+            "Each branch of a condition must be the same type: Text and Number",
+        ),
+        (
+            "true ? 1 : \"a\"",
+            "Each branch of a condition must be the same type: Number and Text",
+        ),
+        ("upper(1)", "Invalid argument of type Number for upper, expected Text"),
+        (
+            "length(\"a\", \"b\")",
+            "Wrong number of arguments for length, expected length(text: Text) -> Number",
+        ),
+        ("nope(1)", "Unknown function: nope"),
+        ("today()", "today isn't supported yet"),
+        (
+            "dateAdd(\"2021-05-02\", \"1\", \"days\")",
+            "Invalid argument of type Text for dateAdd, expected Number",
+        ),
+        ("x + 1", "Unknown identifier: x"),
+    ];
+
+    for (source, expected) in cases {
+        assert_eq!(expected, infer(source).unwrap_err().to_string(), "{}", source);
+    }
+}
+
+#[test]
+fn test_checks_table_formulas_against_the_schema() {
+    let errors = check_all(
+        "
+        table Users {
+            \"name\": Text,
+            \"age\": Number,
+            \"label\": formula { prop(\"name\") + \" \" + format(prop(\"age\")) },
+            \"bad\": formula { prop(\"name\") * 2 },
+            \"missing\": formula { prop(\"email\") }
+        }
+        ",
+    );
+
+    assert_eq!(vec!["Invalid types Text and Number for Multiply"], errors);
+}
+
+#[test]
+fn test_tracks_declarations() {
+    let mut checker = TypeChecker::new();
+    let document = parse_document(
+        "
+        table Users { \"name\": Text, \"label\": formula { upper(prop(\"name\")) } }
+        formula Twice { prop(\"n\") * 2 }
+        let u = Users { \"name\": \"Atlas\" }
+        let label = u[\"label\"]
+        ",
+    );
+    for statement in &document.statements {
+        checker.check(statement).unwrap();
+    }
+
+    assert_eq!(
+        &vec![
+            Column {
+                name: "name".into(),
+                static_type: StaticType::Str,
+                formula: false,
+            },
+            Column {
+                name: "label".into(),
+                static_type: StaticType::Str,
+                formula: true,
+            },
+        ],
+        checker.table("Users").unwrap()
+    );
+    assert_eq!(Some(&StaticType::Num), checker.formulas().get("Twice"));
+    assert_eq!(
+        Some(&StaticType::Record("Users".into())),
+        checker.variables().get("u")
+    );
+    assert_eq!(Some(&StaticType::Str), checker.variables().get("label"));
+}
+
+#[test]
+fn test_reports_errors_in_every_statement() {
+    let errors = check_all(
+        "
+        table Users { \"name\": Text, \"label\": formula { prop(\"name\") } }
+        let a = Users { \"name\": 1 }
+        let b = Users { \"label\": \"x\", \"name\": \"y\" }
+        let c = Users { \"name\": \"z\" }[\"nope\"]
+        assert 1
+        print missing
+        ",
+    );
+
+    assert_eq!(
+        vec![
+            "Invalid value of type Number for column name of type Text",
+            "Users has no column named label",
+            "Unknown property: nope",
+            "Assertion needs to be a Checkbox, found Number",
+            "Unknown identifier: missing",
+        ],
+        errors
+    );
+}
+
+#[test]
+fn test_located_errors_point_at_the_failing_expression() {
+    let tokens = tokenizer(
+        "table T { \"a\": Number, \"f\": formula { 2 * (prop(\"a\") + true) } }\n\
+         let x = 1\n\
+         print upper(x + \"a\")\n\
+         assert x"
+            .chars()
+            .collect(),
+    )
+    .unwrap();
+    let mut checker = TypeChecker::new();
+    let errors: Vec<String> = crate::parser::located_document_parser(tokens)
+        .unwrap()
+        .iter()
+        .filter_map(|located| checker.check_located(located).err())
+        .map(|e| e.to_string())
+        .collect();
+
+    assert_eq!(
+        vec![
+            "Invalid types Number and Checkbox for Add on line: 1, column: 44",
+            "Invalid types Number and Text for Add on line: 3, column: 13",
+            "Assertion needs to be a Checkbox, found Number on line: 4, column: 8",
+        ],
+        errors
+    );
+}
+
+#[test]
+fn test_reports_tables_creating_rows_of_themselves() {
+    let errors = check_all(
+        "table T { \"a\": Number, \"f\": formula { T { \"a\": 1 }[\"a\"] } }",
+    );
+
+    assert_eq!(vec!["Table T refers to itself: T -> T"], errors);
+}
+
+#[test]
+fn test_typecheck_handler() {
+    let document = parse_document("let x = 1\nprint x + 1");
+    let result = typecheck(document.clone()).unwrap();
+
+    assert_eq!(document, result);
+    assert!(typecheck(parse_document("print 1 + true")).is_err());
+}
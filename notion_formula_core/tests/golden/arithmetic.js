@@ -0,0 +1,40 @@
+(props) => {
+    const $ = {
+        fail(message) {
+            throw new Error(message);
+        },
+        typeName(value) {
+            switch (typeof value) {
+                case "number": return "Number";
+                case "string": return "Text";
+                case "boolean": return "Checkbox";
+                default: return String(value);
+            }
+        },
+        expect(type, value, name) {
+            if (this.typeName(value) !== type) {
+                this.fail("Invalid argument of type " + this.typeName(value) + " for " + name + ", expected " + type);
+            }
+            return value;
+        },
+        num(value, name) {
+            return this.expect("Number", value, name);
+        },
+        prop(props, name) {
+            if (!Object.prototype.hasOwnProperty.call(props, name)) {
+                this.fail("Unknown property: " + name);
+            }
+            return props[name];
+        },
+        add(a, b) {
+            if (typeof a === typeof b && (typeof a === "number" || typeof a === "string")) {
+                return a + b;
+            }
+            this.fail("Invalid types " + this.typeName(a) + " and " + this.typeName(b) + " for Add");
+        },
+        round(value) {
+            return Math.sign(this.num(value, "round")) * Math.round(Math.abs(value));
+        },
+    };
+    return $.add(($.num(-$.num($.prop(props, "Estimate"), "-"), "*") * $.num(Math.pow(2, 3), "*")), ($.num($.round(($.num($.prop(props, "Hours"), "/") / 8)), "%") % 5));
+};
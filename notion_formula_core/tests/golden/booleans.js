@@ -0,0 +1,55 @@
+(props) => {
+    const $ = {
+        fail(message) {
+            throw new Error(message);
+        },
+        typeName(value) {
+            switch (typeof value) {
+                case "number": return "Number";
+                case "string": return "Text";
+                case "boolean": return "Checkbox";
+                default: return String(value);
+            }
+        },
+        expect(type, value, name) {
+            if (this.typeName(value) !== type) {
+                this.fail("Invalid argument of type " + this.typeName(value) + " for " + name + ", expected " + type);
+            }
+            return value;
+        },
+        text(value, name) {
+            return this.expect("Text", value, name);
+        },
+        bool(value, name) {
+            return this.expect("Checkbox", value, name);
+        },
+        prop(props, name) {
+            if (!Object.prototype.hasOwnProperty.call(props, name)) {
+                this.fail("Unknown property: " + name);
+            }
+            return props[name];
+        },
+        cond(test, accept, reject) {
+            this.bool(test, "if");
+            if (typeof accept !== typeof reject) {
+                this.fail("Each branch of a condition must be the same type: " + this.typeName(accept) + " and " + this.typeName(reject));
+            }
+            return test ? accept : reject;
+        },
+        and(a, b) {
+            const left = this.bool(a, "and");
+            const right = this.bool(b, "and");
+            return left && right;
+        },
+        not(value) {
+            return !this.bool(value, "not");
+        },
+        empty(value) {
+            return value === 0 || value === "" || value === false;
+        },
+        contains(value, search) {
+            return this.text(value, "contains").includes(this.text(search, "contains"));
+        },
+    };
+    return $.cond($.prop(props, "Done"), "✅", $.cond($.and($.not($.empty($.prop(props, "Tags"))), $.contains($.prop(props, "Tags"), "urgent")), "🔥", ""));
+};
@@ -0,0 +1,128 @@
+(props) => {
+    const $ = {
+        fail(message) {
+            throw new Error(message);
+        },
+        typeName(value) {
+            switch (typeof value) {
+                case "number": return "Number";
+                case "string": return "Text";
+                case "boolean": return "Checkbox";
+                default: return String(value);
+            }
+        },
+        expect(type, value, name) {
+            if (this.typeName(value) !== type) {
+                this.fail("Invalid argument of type " + this.typeName(value) + " for " + name + ", expected " + type);
+            }
+            return value;
+        },
+        num(value, name) {
+            return this.expect("Number", value, name);
+        },
+        text(value, name) {
+            return this.expect("Text", value, name);
+        },
+        prop(props, name) {
+            if (!Object.prototype.hasOwnProperty.call(props, name)) {
+                this.fail("Unknown property: " + name);
+            }
+            return props[name];
+        },
+        add(a, b) {
+            if (typeof a === typeof b && (typeof a === "number" || typeof a === "string")) {
+                return a + b;
+            }
+            this.fail("Invalid types " + this.typeName(a) + " and " + this.typeName(b) + " for Add");
+        },
+        format(value) {
+            return String(value);
+        },
+        toDate(value, name) {
+            const text = this.text(value, name).trim();
+            const match = /^(\d{4})-(\d{2})-(\d{2})(?:[T ](\d{2}):(\d{2})(?::(\d{2}))?(?:\.\d*)?(Z|[+-]\d{2}:\d{2})?)?$/.exec(text);
+            if (!match) {
+                this.fail("Invalid date: " + value);
+            }
+            const [year, month, day, hour, minute, second] = match.slice(1, 7).map((part) => Number(part || 0));
+            const date = new Date(0);
+            date.setUTCFullYear(year, month - 1, day);
+            date.setUTCHours(hour, minute, second);
+            if (date.getUTCMonth() !== month - 1 || date.getUTCDate() !== day || hour > 23 || minute > 59 || second > 59) {
+                this.fail("Invalid date: " + value);
+            }
+            const offset = match[7] && match[7] !== "Z"
+                ? (match[7][0] === "-" ? -1 : 1) * (Number(match[7].slice(1, 3)) * 60 + Number(match[7].slice(4))) * 60000
+                : 0;
+            return date.getTime() - offset;
+        },
+        dateText(time) {
+            return new Date(time).toISOString().slice(0, 19);
+        },
+        dateUnit(unit, name) {
+            if (!["years", "months", "weeks", "days", "hours", "minutes", "seconds"].includes(this.text(unit, name))) {
+                this.fail("Unknown date unit: " + unit + ", expected years, months, weeks, days, hours, minutes or seconds");
+            }
+            return { weeks: 604800000, days: 86400000, hours: 3600000, minutes: 60000, seconds: 1000 }[unit] || unit;
+        },
+        shift(time, count, unit) {
+            if (typeof unit === "number") {
+                return time + count * unit;
+            }
+            const date = new Date(time);
+            const index = date.getUTCFullYear() * 12 + date.getUTCMonth() + (unit === "years" ? count * 12 : count);
+            const year = Math.floor(index / 12);
+            const last = new Date(0);
+            last.setUTCFullYear(year, index - year * 12 + 1, 0);
+            date.setUTCFullYear(year, index - year * 12, Math.min(date.getUTCDate(), last.getUTCDate()));
+            return date.getTime();
+        },
+        monthsBetween(end, start) {
+            const [to, from] = [new Date(end), new Date(start)];
+            const months = (to.getUTCFullYear() - from.getUTCFullYear()) * 12 + to.getUTCMonth() - from.getUTCMonth();
+            const rest = (date) => date.getUTCDate() * 86400000 + (((date.getTime() % 86400000) + 86400000) % 86400000);
+            if (end >= start && rest(to) < rest(from)) {
+                return months - 1;
+            }
+            if (end < start && rest(to) > rest(from)) {
+                return months + 1;
+            }
+            return months;
+        },
+        now() {
+            return this.dateText(Date.now());
+        },
+        dateAdd(value, amount, unit) {
+            const time = this.toDate(value, "dateAdd");
+            const count = Math.trunc(this.num(amount, "dateAdd"));
+            return this.dateText(this.shift(time, count, this.dateUnit(unit, "dateAdd")));
+        },
+        dateBetween(end, start, unit) {
+            const [to, from] = [this.toDate(end, "dateBetween"), this.toDate(start, "dateBetween")];
+            const length = this.dateUnit(unit, "dateBetween");
+            if (typeof length === "number") {
+                return Math.trunc((to - from) / length);
+            }
+            const months = this.monthsBetween(to, from);
+            return length === "years" ? Math.trunc(months / 12) : months;
+        },
+        formatDate(value, format) {
+            const date = new Date(this.toDate(value, "formatDate"));
+            const months = ["January", "February", "March", "April", "May", "June", "July", "August", "September", "October", "November", "December"];
+            const days = ["Monday", "Tuesday", "Wednesday", "Thursday", "Friday", "Saturday", "Sunday"];
+            const pad = (number, length) => String(number).padStart(length, "0");
+            const [year, month, day, hour] = [date.getUTCFullYear(), date.getUTCMonth(), date.getUTCDate(), date.getUTCHours()];
+            const weekday = (date.getUTCDay() + 6) % 7;
+            const tokens = {
+                YYYY: pad(year, 4), YY: pad(((year % 100) + 100) % 100, 2),
+                MMMM: months[month], MMM: months[month].slice(0, 3), MM: pad(month + 1, 2), M: String(month + 1),
+                DD: pad(day, 2), D: String(day), dddd: days[weekday], ddd: days[weekday].slice(0, 3),
+                HH: pad(hour, 2), H: String(hour), hh: pad((hour + 11) % 12 + 1, 2), h: String((hour + 11) % 12 + 1),
+                mm: pad(date.getUTCMinutes(), 2), ss: pad(date.getUTCSeconds(), 2), A: hour < 12 ? "AM" : "PM",
+            };
+            const pattern = /\[([^\]]*)\]|YYYY|YY|MMMM|MMM|MM|M|DD|D|dddd|ddd|HH|H|hh|h|mm|ss|A/g;
+            return this.text(format, "formatDate").replace(pattern, (token, text) => text === undefined ? tokens[token] : text);
+        },
+    };
+    return $.add($.add($.formatDate($.dateAdd($.prop(props, "Due"), 1, "months"), "MMM D, YYYY"), " in "), $.format($.dateBetween($.prop(props, "Due"), $.now(), "days")));
+};
@@ -0,0 +1,52 @@
+(props) => {
+    const $ = {
+        fail(message) {
+            throw new Error(message);
+        },
+        typeName(value) {
+            switch (typeof value) {
+                case "number": return "Number";
+                case "string": return "Text";
+                case "boolean": return "Checkbox";
+                default: return String(value);
+            }
+        },
+        expect(type, value, name) {
+            if (this.typeName(value) !== type) {
+                this.fail("Invalid argument of type " + this.typeName(value) + " for " + name + ", expected " + type);
+            }
+            return value;
+        },
+        bool(value, name) {
+            return this.expect("Checkbox", value, name);
+        },
+        same(a, b) {
+            if (typeof a !== typeof b) {
+                this.fail("Can't compare " + this.typeName(a) + " with " + this.typeName(b));
+            }
+        },
+        prop(props, name) {
+            if (!Object.prototype.hasOwnProperty.call(props, name)) {
+                this.fail("Unknown property: " + name);
+            }
+            return props[name];
+        },
+        cond(test, accept, reject) {
+            this.bool(test, "if");
+            if (typeof accept !== typeof reject) {
+                this.fail("Each branch of a condition must be the same type: " + this.typeName(accept) + " and " + this.typeName(reject));
+            }
+            return test ? accept : reject;
+        },
+        or(a, b) {
+            const left = this.bool(a, "or");
+            const right = this.bool(b, "or");
+            return left || right;
+        },
+        eq(a, b) {
+            this.same(a, b);
+            return a === b;
+        },
+    };
+    return $.cond($.or($.eq($.prop(props, "State"), "⚪"), $.eq($.prop(props, "Estimated Completion Date"), "⏳ Waiting...")), "🟨", $.cond($.eq($.prop(props, "State"), "🔵"), "🟩", "🟥"));
+};
@@ -0,0 +1,78 @@
+(props) => {
+    const $ = {
+        fail(message) {
+            throw new Error(message);
+        },
+        typeName(value) {
+            switch (typeof value) {
+                case "number": return "Number";
+                case "string": return "Text";
+                case "boolean": return "Checkbox";
+                default: return String(value);
+            }
+        },
+        expect(type, value, name) {
+            if (this.typeName(value) !== type) {
+                this.fail("Invalid argument of type " + this.typeName(value) + " for " + name + ", expected " + type);
+            }
+            return value;
+        },
+        num(value, name) {
+            return this.expect("Number", value, name);
+        },
+        text(value, name) {
+            return this.expect("Text", value, name);
+        },
+        same(a, b) {
+            if (typeof a !== typeof b) {
+                this.fail("Can't compare " + this.typeName(a) + " with " + this.typeName(b));
+            }
+        },
+        prop(props, name) {
+            if (!Object.prototype.hasOwnProperty.call(props, name)) {
+                this.fail("Unknown property: " + name);
+            }
+            return props[name];
+        },
+        add(a, b) {
+            if (typeof a === typeof b && (typeof a === "number" || typeof a === "string")) {
+                return a + b;
+            }
+            this.fail("Invalid types " + this.typeName(a) + " and " + this.typeName(b) + " for Add");
+        },
+        plus(value) {
+            return this.toNumber(value);
+        },
+        gt(a, b) {
+            this.same(a, b);
+            return a > b;
+        },
+        format(value) {
+            return String(value);
+        },
+        toNumber(value) {
+            switch (typeof value) {
+                case "number": return value;
+                case "boolean": return value ? 1 : 0;
+                case "string": {
+                    const result = Number(value.trim());
+                    if (value.trim() === "" || Number.isNaN(result)) {
+                        this.fail("Invalid number: " + value);
+                    }
+                    return result;
+                }
+                default: this.fail("Invalid argument of type " + this.typeName(value) + " for toNumber");
+            }
+        },
+        upper(value) {
+            return this.text(value, "upper").toUpperCase();
+        },
+        slice(value, start, end) {
+            const chars = Array.from(this.text(value, "slice"));
+            const from = Math.max(0, Math.trunc(this.num(start, "slice")));
+            const to = end === undefined ? chars.length : Math.max(0, Math.trunc(this.num(end, "slice")));
+            return chars.slice(from, to).join("");
+        },
+    };
+    return $.add($.add($.add($.prop(props, "Name"), ": "), $.upper($.slice($.prop(props, "State"), 0, 1))), $.format($.gt($.plus("3"), 2)));
+};
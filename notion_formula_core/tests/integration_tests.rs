@@ -57,4 +57,116 @@ mod test {
             ast
         )
     }
+
+    #[test]
+    fn test_input_string_to_minified_formula() {
+        let mut file = File::open("tests/test_formula.notion").unwrap();
+        let input: Vec<char> = reader::read(&mut file).unwrap();
+        let tokens = tokenizer::tokenizer(input).unwrap();
+        let ast: parser::Expression = parser::formula_parser(tokens).unwrap();
+        let result = emitter::minify(ast).unwrap();
+
+        assert_eq!(
+            "if(prop(\"State\")==\"⚪\"or prop(\"Estimated Completion Date\")==\"⏳ Waiting...\",\"🟨\",if(prop(\"State\")==\"🔵\",\"🟩\",\"🟥\"))",
+            result
+        )
+    }
+}
+
+#[cfg(all(test, feature = "serde"))]
+mod serde_test {
+    use notion_formula_core::interpreter::RuntimeType;
+    use notion_formula_core::parser::*;
+    use notion_formula_core::tokenizer::*;
+    use notion_formula_core::*;
+    use std::fs::File;
+
+    fn sample_tokens() -> Vec<Token> {
+        let mut file = File::open("tests/test_formula.notion").unwrap();
+        let input: Vec<char> = reader::read(&mut file).unwrap();
+        tokenizer::tokenizer(input).unwrap()
+    }
+
+    #[test]
+    fn test_tokens_round_trip() {
+        let tokens = sample_tokens();
+
+        let json = serde_json::to_string(&tokens).unwrap();
+        let result: Vec<Token> = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(tokens, result);
+    }
+
+    #[test]
+    fn test_ast_round_trip() {
+        let ast = parser::formula_parser(sample_tokens()).unwrap();
+
+        let json = serde_json::to_string(&ast).unwrap();
+        let result: Expression = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(ast, result);
+    }
+
+    #[test]
+    fn test_statements_round_trip() {
+        let document = Document {
+            statements: vec![
+                Statement::TableDef(
+                    "Users".into(),
+                    vec![
+                        Pair {
+                            key: "\"name\"".into(),
+                            value: Type::Str,
+                        },
+                        Pair {
+                            key: "\"test\"".into(),
+                            value: Type::Formula(Expression::Identifier("x".into())),
+                        },
+                    ],
+                ),
+                Statement::Assignment("x".into(), Expression::Number("1".into())),
+                Statement::PrintStatement(Expression::Bool(true)),
+            ],
+        };
+
+        let json = serde_json::to_string(&document).unwrap();
+        let result: Document = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(document, result);
+    }
+
+    #[test]
+    fn test_runtime_values_round_trip() {
+        let values = vec![
+            RuntimeType::Num(1.5),
+            RuntimeType::Str("hello".into()),
+            RuntimeType::Bool(false),
+        ];
+
+        let json = serde_json::to_string(&values).unwrap();
+        let result: Vec<RuntimeType> = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(r#"[{"Num":1.5},{"Str":"hello"},{"Bool":false}]"#, json);
+        assert_eq!(values, result);
+    }
+
+    #[test]
+    fn test_json_shape() {
+        let tokens = tokenizer::tokenizer("1 + x".chars().collect()).unwrap();
+
+        assert_eq!(
+            r#"{"token_type":{"NumberLiteral":"1"},"line":1,"column":1}"#,
+            serde_json::to_string(&tokens[0]).unwrap()
+        );
+        assert_eq!(
+            r#"{"token_type":"Plus","line":1,"column":3}"#,
+            serde_json::to_string(&tokens[1]).unwrap()
+        );
+
+        let ast = parser::formula_parser(tokens).unwrap();
+        assert_eq!(
+            r#"{"BinaryOp":[{"Number":"1"},"Add",{"Identifier":"x"}]}"#,
+            serde_json::to_string(&ast).unwrap()
+        );
+    }
 }
@@ -0,0 +1,533 @@
+use notion_formula_core::builtins::{self, BUILTINS};
+use notion_formula_core::error::{self, FormulaError};
+use notion_formula_core::formatter;
+use notion_formula_core::interpreter::unquote;
+use notion_formula_core::parser::{located_document_parser, LocatedStatement, Statement};
+use notion_formula_core::tokenizer::{lossless_tokenizer, Token, TokenType};
+use notion_formula_core::typechecker::TypeChecker;
+use pipeline::{FnHandler, Pipeline};
+
+/// A position as the protocol sends it: zero based, with the character
+/// counted in UTF-16 code units.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Position {
+    pub line: u32,
+    pub character: u32,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Range {
+    pub start: Position,
+    pub end: Position,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Diagnostic {
+    pub range: Range,
+    pub message: String,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum CompletionKind {
+    Function,
+    Constant,
+    Variable,
+    Table,
+    Property,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Completion {
+    pub label: String,
+    pub kind: CompletionKind,
+    pub detail: String,
+    pub documentation: Option<String>,
+}
+
+struct SourceToken {
+    token: Token,
+    text: String,
+    start: error::Position,
+    end: error::Position,
+}
+
+struct LineIndex {
+    lines: Vec<Vec<char>>,
+}
+impl LineIndex {
+    fn new(source: &str) -> Self {
+        LineIndex {
+            lines: source
+                .split('\n')
+                .map(|line| line.chars().collect())
+                .collect(),
+        }
+    }
+
+    fn position(&self, point: error::Position) -> Position {
+        let characters = match self.lines.get(point.line as usize - 1) {
+            Some(text) => text
+                .iter()
+                .take(point.column as usize - 1)
+                .map(|c| c.len_utf16() as u32)
+                .sum(),
+            None => 0,
+        };
+
+        Position {
+            line: point.line - 1,
+            character: characters,
+        }
+    }
+
+    fn point(&self, position: Position) -> error::Position {
+        let mut column = 1;
+        let mut characters = 0;
+        if let Some(text) = self.lines.get(position.line as usize) {
+            for c in text {
+                if characters >= position.character {
+                    break;
+                }
+                characters += c.len_utf16() as u32;
+                column += 1;
+            }
+        }
+
+        error::Position::new(position.line + 1, column)
+    }
+
+    fn offset(&self, point: error::Position) -> usize {
+        let before: usize = self
+            .lines
+            .iter()
+            .take(point.line as usize - 1)
+            .map(|text| text.len() + 1)
+            .sum();
+        before + point.column as usize - 1
+    }
+
+    fn end(&self) -> Position {
+        let line = self.lines.len() as u32;
+        let column = self.lines.last().map_or(0, |text| text.len()) as u32 + 1;
+        self.position(error::Position::new(line, column))
+    }
+
+    fn prefix(&self, position: Position) -> String {
+        let point = self.point(position);
+        match self.lines.get(point.line as usize - 1) {
+            Some(text) => text.iter().take(point.column as usize - 1).collect(),
+            None => String::new(),
+        }
+    }
+}
+
+/// Everything the server knows about one version of a document.
+pub struct Analysis {
+    source: String,
+    index: LineIndex,
+    tokens: Vec<SourceToken>,
+    statements: Vec<LocatedStatement>,
+    checker: TypeChecker,
+    diagnostics: Vec<Diagnostic>,
+}
+
+/// Tokenizes, parses and typechecks `source`, carrying on past errors so
+/// that the rest of the document can still be used for completion.
+pub fn analyze(source: &str) -> Analysis {
+    let index = LineIndex::new(source);
+    let mut diagnostics = vec![];
+    let mut input: Vec<char> = source.chars().collect();
+
+    // Tokens past a tokenizer error are lost, so retry with the source
+    // truncated at the error to keep what came before it.
+    let mut truncated = false;
+    let lossless = loop {
+        match lossless_tokenizer(input.clone()) {
+            Ok(tokens) => break tokens,
+            Err(e) if !truncated => {
+                let (message, point) = split_position(&e);
+                let range = match point {
+                    Some(point) => index.position(point),
+                    None => index.end(),
+                };
+                diagnostics.push(Diagnostic {
+                    range: Range {
+                        start: range,
+                        end: index.end(),
+                    },
+                    message,
+                });
+
+                truncated = true;
+                match point.map(|point| index.offset(point)) {
+                    Some(offset) if offset < input.len() => input.truncate(offset),
+                    _ => break vec![],
+                }
+            }
+            Err(_) => break vec![],
+        }
+    };
+
+    let mut tokens = vec![];
+    for token in lossless {
+        let start = error::Position::new(token.token.line, token.token.column);
+        let end = start.advance(&token.text);
+        tokens.push(SourceToken {
+            token: token.token,
+            text: token.text,
+            start,
+            end,
+        });
+    }
+
+    let mut analysis = Analysis {
+        source: source.to_string(),
+        index,
+        tokens,
+        statements: vec![],
+        checker: TypeChecker::new(),
+        diagnostics,
+    };
+
+    let chunks = split_statements(analysis.tokens.iter().map(|token| token.token.clone()));
+    let count = chunks.len();
+    for (i, chunk) in chunks.into_iter().enumerate() {
+        match located_document_parser(chunk) {
+            Ok(statements) => analysis.statements.extend(statements),
+            // The last statement is cut short when the source was truncated.
+            Err(_) if truncated && i == count - 1 => (),
+            Err(e) => {
+                let (message, point) = split_position(&e);
+                let range = match point {
+                    Some(point) => analysis.token_range(point),
+                    None => Range {
+                        start: analysis.index.end(),
+                        end: analysis.index.end(),
+                    },
+                };
+                analysis.diagnostics.push(Diagnostic { range, message });
+            }
+        }
+    }
+
+    for i in 0..analysis.statements.len() {
+        if let Err(e) = analysis.checker.check_located(&analysis.statements[i]) {
+            let range = match e.span() {
+                Some(span) => Range {
+                    start: analysis.index.position(span.start),
+                    end: analysis.index.position(span.end),
+                },
+                None => analysis.statement_range(&analysis.statements[i]),
+            };
+            analysis.diagnostics.push(Diagnostic {
+                range,
+                message: e.message().to_string(),
+            });
+        }
+    }
+
+    analysis
+}
+
+impl Analysis {
+    pub fn diagnostics(&self) -> &[Diagnostic] {
+        &self.diagnostics
+    }
+
+    pub fn hover(&self, position: Position) -> Option<String> {
+        let point = self.index.point(position);
+        let i = self.token_at(point)?;
+
+        match &self.tokens[i].token.token_type {
+            TokenType::Identifier(name) => {
+                if let Some(static_type) = self.checker.variables().get(name) {
+                    return Some(format!("```\nlet {}: {}\n```", name, static_type));
+                }
+                if let Some(static_type) = self.checker.formulas().get(name) {
+                    return Some(format!("```\nformula {}: {}\n```", name, static_type));
+                }
+                if self.checker.table(name).is_some() {
+                    return Some(format!("```\n{}\n```", self.describe_table(name)));
+                }
+                builtins::lookup(name).map(|builtin| {
+                    format!("```\n{}\n```\n{}", builtin.signature, builtin.description)
+                })
+            }
+            TokenType::StringLiteral(value) if self.is_prop_argument(i) => {
+                let name = unquote(value);
+                let table = self.enclosing_table(point)?;
+                let column = self
+                    .checker
+                    .table(table)?
+                    .iter()
+                    .find(|column| column.name == name)?;
+                Some(format!("```\n\"{}\": {}\n```", name, column.static_type))
+            }
+            _ => None,
+        }
+    }
+
+    pub fn completions(&self, position: Position) -> Vec<Completion> {
+        let prefix = self.index.prefix(position);
+        if prefix.matches('"').count() % 2 == 1 {
+            return match prefix.rfind('"') {
+                Some(quote) if is_prop_call(&prefix[..quote]) => self.prop_completions(position),
+                _ => vec![],
+            };
+        }
+
+        let mut result = vec![];
+        for (name, static_type) in self.checker.variables() {
+            result.push(Completion {
+                label: name.clone(),
+                kind: CompletionKind::Variable,
+                detail: static_type.to_string(),
+                documentation: None,
+            });
+        }
+        for (name, static_type) in self.checker.formulas() {
+            result.push(Completion {
+                label: name.clone(),
+                kind: CompletionKind::Function,
+                detail: format!("formula {}: {}", name, static_type),
+                documentation: None,
+            });
+        }
+        for name in self.checker.tables().keys() {
+            result.push(Completion {
+                label: name.clone(),
+                kind: CompletionKind::Table,
+                detail: self.describe_table(name),
+                documentation: None,
+            });
+        }
+        result.sort_by(|a, b| a.label.cmp(&b.label));
+
+        for builtin in BUILTINS {
+            let kind = if builtin.signature.contains('(') {
+                CompletionKind::Function
+            } else {
+                CompletionKind::Constant
+            };
+            result.push(Completion {
+                label: builtin.name.to_string(),
+                kind,
+                detail: builtin.signature.to_string(),
+                documentation: Some(builtin.description.to_string()),
+            });
+        }
+
+        result
+    }
+
+    pub fn definition(&self, position: Position) -> Option<Range> {
+        let point = self.index.point(position);
+        let i = self.token_at(point)?;
+        let name = match &self.tokens[i].token.token_type {
+            TokenType::Identifier(name) => name,
+            _ => return None,
+        };
+
+        let declarations: Vec<&LocatedStatement> = self
+            .statements
+            .iter()
+            .filter(|located| match &located.statement {
+                Statement::TableDef(declared, _)
+                | Statement::FormulaDef(declared, _)
+                | Statement::Assignment(declared, _) => declared == name,
+                _ => false,
+            })
+            .collect();
+        let declaration = declarations
+            .iter()
+            .rev()
+            .find(|located| start_of(located) <= point)
+            .or_else(|| declarations.first())?;
+
+        let token = declaration.tokens.get(1)?;
+        Some(self.token_range(error::Position::new(token.line, token.column)))
+    }
+
+    /// The whole document formatted, or `None` when it doesn't tokenize.
+    pub fn format(&self) -> Option<String> {
+        let pipeline = Pipeline::new()
+            .add(FnHandler::new(lossless_tokenizer))
+            .add(FnHandler::new(formatter::format));
+        pipeline.start(self.source.chars().collect()).ok()
+    }
+
+    pub fn full_range(&self) -> Range {
+        Range {
+            start: Position {
+                line: 0,
+                character: 0,
+            },
+            end: self.index.end(),
+        }
+    }
+
+    fn prop_completions(&self, position: Position) -> Vec<Completion> {
+        let point = self.index.point(position);
+        let tables: Vec<&String> = match self.enclosing_table(point) {
+            Some(table) => vec![table],
+            None => {
+                let mut tables: Vec<&String> = self.checker.tables().keys().collect();
+                tables.sort();
+                tables
+            }
+        };
+
+        let mut result: Vec<Completion> = vec![];
+        for table in tables {
+            for column in self.checker.table(table).into_iter().flatten() {
+                if result
+                    .iter()
+                    .all(|completion| completion.label != column.name)
+                {
+                    result.push(Completion {
+                        label: column.name.clone(),
+                        kind: CompletionKind::Property,
+                        detail: column.static_type.to_string(),
+                        documentation: Some(format!("Column of {}", table)),
+                    });
+                }
+            }
+        }
+        result
+    }
+
+    fn describe_table(&self, name: &str) -> String {
+        let columns: Vec<String> = self
+            .checker
+            .table(name)
+            .into_iter()
+            .flatten()
+            .map(|column| match column.formula {
+                true => format!("\"{}\": formula {}", column.name, column.static_type),
+                false => format!("\"{}\": {}", column.name, column.static_type),
+            })
+            .collect();
+        format!("table {} {{ {} }}", name, columns.join(", "))
+    }
+
+    // Prefers names and strings when the point touches two tokens, as it
+    // does at the end of a word.
+    fn token_at(&self, point: error::Position) -> Option<usize> {
+        let touching: Vec<usize> = (0..self.tokens.len())
+            .filter(|i| self.tokens[*i].start <= point && point <= self.tokens[*i].end)
+            .filter(|i| !self.tokens[*i].text.is_empty())
+            .collect();
+
+        touching
+            .iter()
+            .find(|i| {
+                matches!(
+                    self.tokens[**i].token.token_type,
+                    TokenType::Identifier(_) | TokenType::StringLiteral(_)
+                )
+            })
+            .or_else(|| touching.first())
+            .copied()
+    }
+
+    fn is_prop_argument(&self, i: usize) -> bool {
+        i >= 2
+            && self.tokens[i - 1].token.token_type == TokenType::LeftParen
+            && self.tokens[i - 2].token.token_type == TokenType::Identifier("prop".into())
+    }
+
+    fn enclosing_table(&self, point: error::Position) -> Option<&String> {
+        self.statements
+            .iter()
+            .filter(|located| start_of(located) <= point && point <= self.end_of(located))
+            .find_map(|located| match &located.statement {
+                Statement::TableDef(name, _) => Some(name),
+                _ => None,
+            })
+    }
+
+    fn token_range(&self, point: error::Position) -> Range {
+        let end = match self.tokens.iter().find(|token| token.start == point) {
+            Some(token) if !token.text.is_empty() => token.end,
+            _ => error::Position::new(point.line, point.column + 1),
+        };
+        Range {
+            start: self.index.position(point),
+            end: self.index.position(end),
+        }
+    }
+
+    fn statement_range(&self, located: &LocatedStatement) -> Range {
+        Range {
+            start: self.index.position(start_of(located)),
+            end: self.index.position(self.end_of(located)),
+        }
+    }
+
+    fn end_of(&self, located: &LocatedStatement) -> error::Position {
+        let last = match located.tokens.last() {
+            Some(token) => error::Position::new(token.line, token.column),
+            None => return error::Position::new(1, 1),
+        };
+        self.tokens
+            .iter()
+            .find(|token| token.start == last)
+            .map_or(last, |token| token.end)
+    }
+}
+
+fn start_of(located: &LocatedStatement) -> error::Position {
+    located.tokens.first().map_or(error::Position::new(1, 1), |token| {
+        error::Position::new(token.line, token.column)
+    })
+}
+
+fn is_prop_call(before_quote: &str) -> bool {
+    match before_quote.trim_end().strip_suffix('(') {
+        Some(callee) => callee.trim_end().ends_with("prop"),
+        None => false,
+    }
+}
+
+fn split_position(e: &FormulaError) -> (String, Option<error::Position>) {
+    (e.message().to_string(), e.span().map(|span| span.start))
+}
+
+// Splits the tokens before each top level keyword that starts a
+// declaration, so that one broken statement doesn't hide the rest.
+fn split_statements(tokens: impl Iterator<Item = Token>) -> Vec<Vec<Token>> {
+    let mut chunks: Vec<Vec<Token>> = vec![];
+    let mut current: Vec<Token> = vec![];
+    let mut depth = 0;
+
+    for token in tokens {
+        let starts_statement = depth <= 0
+            && matches!(
+                token.token_type,
+                TokenType::Table
+                    | TokenType::Formula
+                    | TokenType::Let
+                    | TokenType::Print
+                    | TokenType::Assert
+            );
+        if (starts_statement || token.token_type == TokenType::Eof) && !current.is_empty() {
+            current.push(Token::new(TokenType::Eof, token.line, token.column));
+            chunks.push(std::mem::take(&mut current));
+            depth = 0;
+        }
+
+        match token.token_type {
+            TokenType::LeftParen | TokenType::LeftSquareBracket | TokenType::LeftBracket => {
+                depth += 1
+            }
+            TokenType::RightParen | TokenType::RightSquareBracket | TokenType::RightBracket => {
+                depth -= 1
+            }
+            TokenType::Eof => break,
+            _ => (),
+        }
+        current.push(token);
+    }
+
+    chunks
+}
@@ -0,0 +1,217 @@
+mod analysis;
+pub mod protocol;
+
+pub use analysis::*;
+
+use pipeline::HandlerResult;
+use protocol::{
+    error_response, notification, response, INVALID_PARAMS, INVALID_REQUEST, METHOD_NOT_FOUND,
+};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::io::{BufRead, Write};
+
+/// Language server for `.notion` files. Documents are synced in full and
+/// re-analyzed on every change.
+#[derive(Default)]
+pub struct Server {
+    documents: HashMap<String, Analysis>,
+    shutdown: bool,
+    exited: bool,
+}
+impl Server {
+    pub fn new() -> Self {
+        Server::default()
+    }
+
+    pub fn exited(&self) -> bool {
+        self.exited
+    }
+
+    /// Handles one incoming message and returns the messages to send back.
+    pub fn handle(&mut self, message: &Value) -> Vec<Value> {
+        let method = message["method"].as_str().unwrap_or_default();
+        let params = &message["params"];
+        let id = match message.get("id") {
+            Some(id) => id,
+            None => return self.notify(method, params),
+        };
+        // After `shutdown` the protocol only allows `exit`, which is a notification.
+        if self.shutdown {
+            return vec![error_response(
+                id,
+                INVALID_REQUEST,
+                &format!("Received {} after shutdown", method),
+            )];
+        }
+
+        let result = match method {
+            "initialize" => Some(json!({
+                "capabilities": {
+                    "textDocumentSync": 1,
+                    "hoverProvider": true,
+                    "completionProvider": { "triggerCharacters": ["\""] },
+                    "definitionProvider": true,
+                    "documentFormattingProvider": true,
+                },
+                "serverInfo": { "name": "notion-formula-lsp" },
+            })),
+            "shutdown" => {
+                self.shutdown = true;
+                Some(Value::Null)
+            }
+            "textDocument/hover" => self.with_position(params, |analysis, position| {
+                let hover = analysis.hover(position);
+                match hover {
+                    Some(value) => json!({ "contents": { "kind": "markdown", "value": value } }),
+                    None => Value::Null,
+                }
+            }),
+            "textDocument/completion" => self.with_position(params, |analysis, position| {
+                let items: Vec<Value> = analysis
+                    .completions(position)
+                    .into_iter()
+                    .map(completion_to_json)
+                    .collect();
+                json!(items)
+            }),
+            "textDocument/definition" => {
+                let uri = params["textDocument"]["uri"].clone();
+                self.with_position(params, |analysis, position| {
+                    match analysis.definition(position) {
+                        Some(range) => json!({ "uri": uri, "range": range_to_json(range) }),
+                        None => Value::Null,
+                    }
+                })
+            }
+            "textDocument/formatting" => self.document(params).map(|analysis| {
+                match analysis.format() {
+                    Some(text) => {
+                        json!([{ "range": range_to_json(analysis.full_range()), "newText": text }])
+                    }
+                    None => Value::Null,
+                }
+            }),
+            _ => {
+                return vec![error_response(
+                    id,
+                    METHOD_NOT_FOUND,
+                    &format!("Unknown method {}", method),
+                )]
+            }
+        };
+
+        match result {
+            Some(result) => vec![response(id, result)],
+            None => vec![error_response(id, INVALID_PARAMS, "Unknown document")],
+        }
+    }
+
+    fn notify(&mut self, method: &str, params: &Value) -> Vec<Value> {
+        let uri = params["textDocument"]["uri"].as_str().unwrap_or_default();
+        let text = match method {
+            "textDocument/didOpen" => params["textDocument"]["text"].as_str(),
+            "textDocument/didChange" => params["contentChanges"]
+                .as_array()
+                .and_then(|changes| changes.last())
+                .and_then(|change| change["text"].as_str()),
+            "textDocument/didClose" => {
+                self.documents.remove(uri);
+                return vec![publish_diagnostics(uri, &[])];
+            }
+            "exit" => {
+                self.exited = true;
+                return vec![];
+            }
+            _ => return vec![],
+        };
+
+        match text {
+            Some(text) => {
+                let analysis = analyze(text);
+                let message = publish_diagnostics(uri, analysis.diagnostics());
+                self.documents.insert(uri.to_string(), analysis);
+                vec![message]
+            }
+            None => vec![],
+        }
+    }
+
+    fn document(&self, params: &Value) -> Option<&Analysis> {
+        let uri = params["textDocument"]["uri"].as_str()?;
+        self.documents.get(uri)
+    }
+
+    fn with_position(
+        &self,
+        params: &Value,
+        f: impl FnOnce(&Analysis, Position) -> Value,
+    ) -> Option<Value> {
+        let analysis = self.document(params)?;
+        let position = Position {
+            line: params["position"]["line"].as_u64()? as u32,
+            character: params["position"]["character"].as_u64()? as u32,
+        };
+        Some(f(analysis, position))
+    }
+}
+
+/// Serves requests from `input` until the client sends `exit`. Returns the
+/// exit code the protocol asks for: 0 if `shutdown` came first, otherwise 1.
+pub fn run(input: &mut dyn BufRead, output: &mut dyn Write) -> HandlerResult<i32> {
+    let mut server = Server::new();
+
+    while let Some(message) = protocol::read_message(input)? {
+        for reply in server.handle(&message) {
+            protocol::write_message(output, &reply)?;
+        }
+        if server.exited() {
+            break;
+        }
+    }
+
+    Ok(if server.shutdown { 0 } else { 1 })
+}
+
+fn publish_diagnostics(uri: &str, diagnostics: &[Diagnostic]) -> Value {
+    let diagnostics: Vec<Value> = diagnostics
+        .iter()
+        .map(|diagnostic| {
+            json!({
+                "range": range_to_json(diagnostic.range),
+                "severity": 1,
+                "source": "notion-formula",
+                "message": diagnostic.message,
+            })
+        })
+        .collect();
+
+    notification(
+        "textDocument/publishDiagnostics",
+        json!({ "uri": uri, "diagnostics": diagnostics }),
+    )
+}
+
+fn range_to_json(range: Range) -> Value {
+    json!({
+        "start": { "line": range.start.line, "character": range.start.character },
+        "end": { "line": range.end.line, "character": range.end.character },
+    })
+}
+
+fn completion_to_json(completion: Completion) -> Value {
+    // Numbers from the protocol's CompletionItemKind.
+    let kind = match completion.kind {
+        CompletionKind::Function => 3,
+        CompletionKind::Variable => 6,
+        CompletionKind::Table => 7,
+        CompletionKind::Property => 10,
+        CompletionKind::Constant => 21,
+    };
+
+    let mut item = json!({ "label": completion.label, "kind": kind, "detail": completion.detail });
+    if let Some(documentation) = completion.documentation {
+        item["documentation"] = json!(documentation);
+    }
+    item
+}
@@ -0,0 +1,13 @@
+use std::io;
+use std::process;
+
+fn main() {
+    let code = match notion_formula_lsp::run(&mut io::stdin().lock(), &mut io::stdout()) {
+        Ok(code) => code,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            1
+        }
+    };
+    process::exit(code);
+}
@@ -0,0 +1,340 @@
+#[cfg(test)]
+mod test {
+    use notion_formula_lsp::*;
+    use serde_json::{json, Value};
+
+    const SOURCE: &str = "table Users {
+    \"name\": Text,
+    \"age\": Number,
+    \"label\": formula { prop(\"name\") + \" \" + format(prop(\"age\")) }
+}
+formula Double { prop(\"n\") * 2 }
+let u = Users { \"name\": \"Atlas\", \"age\": 0 }
+print upper(u[\"label\"])
+";
+
+    fn position(line: u32, character: u32) -> Position {
+        Position { line, character }
+    }
+
+    fn range(start: (u32, u32), end: (u32, u32)) -> Range {
+        Range {
+            start: position(start.0, start.1),
+            end: position(end.0, end.1),
+        }
+    }
+
+    fn open(server: &mut Server, text: &str) -> Vec<Value> {
+        server.handle(&json!({
+            "jsonrpc": "2.0",
+            "method": "textDocument/didOpen",
+            "params": {
+                "textDocument": { "uri": "file:///a.notion", "languageId": "notion", "version": 1, "text": text }
+            }
+        }))
+    }
+
+    fn request(server: &mut Server, method: &str, params: Value) -> Value {
+        let mut replies = server
+            .handle(&json!({ "jsonrpc": "2.0", "id": 1, "method": method, "params": params }));
+        assert_eq!(1, replies.len());
+        replies.remove(0)
+    }
+
+    fn at(line: u32, character: u32) -> Value {
+        json!({ "textDocument": { "uri": "file:///a.notion" }, "position": { "line": line, "character": character } })
+    }
+
+    #[test]
+    fn test_valid_document_has_no_diagnostics() {
+        assert_eq!(Vec::<Diagnostic>::new(), analyze(SOURCE).diagnostics());
+    }
+
+    #[test]
+    fn test_diagnostics_from_each_stage() {
+        let source = "let a = 1 +\nlet b = \"x\" * 2\nlet c = b\nprint \"open";
+        let analysis = analyze(source);
+
+        assert_eq!(
+            vec![
+                Diagnostic {
+                    range: range((3, 6), (3, 11)),
+                    message: "Couldn't find the end of the string, missing '\"'".into(),
+                },
+                Diagnostic {
+                    range: range((1, 0), (1, 3)),
+                    message: "Unexpected Token: Eof".into(),
+                },
+                Diagnostic {
+                    range: range((1, 8), (1, 15)),
+                    message: "Invalid types Text and Number for Multiply".into(),
+                },
+            ],
+            analysis.diagnostics()
+        );
+    }
+
+    #[test]
+    fn test_diagnostics_count_utf16_columns() {
+        let analysis = analyze("\"🔵\" + \"a\" &");
+
+        assert_eq!(
+            vec![Diagnostic {
+                range: range((0, 11), (0, 12)),
+                message: "Unknown character found &".into(),
+            }],
+            analysis.diagnostics()
+        );
+    }
+
+    #[test]
+    fn test_hover() {
+        let analysis = analyze(SOURCE);
+        let cases = vec![
+            (position(6, 4), Some("```\nlet u: Users\n```")),
+            (position(5, 10), Some("```\nformula Double: Number\n```")),
+            (
+                position(6, 10),
+                Some("```\ntable Users { \"name\": Text, \"age\": Number, \"label\": formula Text }\n```"),
+            ),
+            (
+                position(7, 8),
+                Some("```\nupper(text: Text) -> Text\n```\nConverts the text to uppercase."),
+            ),
+            (position(3, 32), Some("```\n\"name\": Text\n```")),
+            (position(3, 59), Some("```\n\"age\": Number\n```")),
+            (position(5, 24), None),
+            (position(6, 0), None),
+        ];
+
+        for (position, expected) in cases {
+            assert_eq!(
+                expected.map(String::from),
+                analysis.hover(position),
+                "{:?}",
+                position
+            );
+        }
+    }
+
+    #[test]
+    fn test_completion() {
+        let analysis = analyze(SOURCE);
+        let result = analysis.completions(position(8, 0));
+        let labels: Vec<&str> = result.iter().map(|item| item.label.as_str()).collect();
+
+        assert_eq!(
+            vec!["Double", "Users", "u", "prop", "if"],
+            labels[..5].to_vec()
+        );
+        assert!(labels.contains(&"pi"));
+        assert_eq!(
+            Completion {
+                label: "upper".into(),
+                kind: CompletionKind::Function,
+                detail: "upper(text: Text) -> Text".into(),
+                documentation: Some("Converts the text to uppercase.".into()),
+            },
+            result
+                .into_iter()
+                .find(|item| item.label == "upper")
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn test_completion_of_prop_names() {
+        let source = "table Users { \"name\": Text, \"age\": Number }\n\
+                      table Tasks { \"title\": Text, \"done\": formula { prop(\"\") } }\n\
+                      print prop(\"";
+        let analysis = analyze(source);
+
+        let in_table: Vec<String> = analysis
+            .completions(position(1, 53))
+            .into_iter()
+            .map(|item| item.label)
+            .collect();
+        assert_eq!(vec!["title", "done"], in_table);
+
+        let anywhere: Vec<(String, CompletionKind, String)> = analysis
+            .completions(position(2, 12))
+            .into_iter()
+            .map(|item| (item.label, item.kind, item.detail))
+            .collect();
+        assert_eq!(
+            vec![
+                ("title".into(), CompletionKind::Property, "Text".into()),
+                ("done".into(), CompletionKind::Property, "Any".into()),
+                ("name".into(), CompletionKind::Property, "Text".into()),
+                ("age".into(), CompletionKind::Property, "Number".into()),
+            ],
+            anywhere
+        );
+
+        assert_eq!(
+            Vec::<Completion>::new(),
+            analyze("upper(\"").completions(position(0, 7))
+        );
+    }
+
+    #[test]
+    fn test_go_to_definition() {
+        let analysis = analyze(SOURCE);
+
+        assert_eq!(
+            Some(range((0, 6), (0, 11))),
+            analysis.definition(position(6, 9))
+        );
+        assert_eq!(
+            Some(range((6, 4), (6, 5))),
+            analysis.definition(position(7, 13))
+        );
+        assert_eq!(None, analysis.definition(position(7, 7)));
+
+        let analysis = analyze("formula F { 1 }\nprint F\nlet F2 = F + F");
+        assert_eq!(
+            Some(range((0, 8), (0, 9))),
+            analysis.definition(position(2, 13))
+        );
+    }
+
+    #[test]
+    fn test_formatting() {
+        let analysis = analyze("let x=1+2 // three\nprint x*2");
+
+        assert_eq!(
+            Some("let x = 1 + 2 // three\nprint x * 2\n".to_string()),
+            analysis.format()
+        );
+        assert_eq!(range((0, 0), (1, 9)), analysis.full_range());
+        assert_eq!(None, analyze("/* open").format());
+    }
+
+    #[test]
+    fn test_server_publishes_diagnostics() {
+        let mut server = Server::new();
+        let messages = open(&mut server, "1 + true");
+
+        assert_eq!(
+            vec![json!({
+                "jsonrpc": "2.0",
+                "method": "textDocument/publishDiagnostics",
+                "params": {
+                    "uri": "file:///a.notion",
+                    "diagnostics": [{
+                        "range": { "start": { "line": 0, "character": 0 }, "end": { "line": 0, "character": 8 } },
+                        "severity": 1,
+                        "source": "notion-formula",
+                        "message": "Invalid types Number and Checkbox for Add",
+                    }],
+                }
+            })],
+            messages
+        );
+
+        let messages = server.handle(&json!({
+            "jsonrpc": "2.0",
+            "method": "textDocument/didChange",
+            "params": {
+                "textDocument": { "uri": "file:///a.notion", "version": 2 },
+                "contentChanges": [{ "text": "1 + 1" }]
+            }
+        }));
+        assert_eq!(json!([]), messages[0]["params"]["diagnostics"]);
+    }
+
+    #[test]
+    fn test_server_requests() {
+        let mut server = Server::new();
+        let initialize = request(&mut server, "initialize", json!({ "capabilities": {} }));
+        assert_eq!(
+            json!(true),
+            initialize["result"]["capabilities"]["hoverProvider"]
+        );
+
+        open(&mut server, SOURCE);
+
+        let hover = request(&mut server, "textDocument/hover", at(6, 4));
+        assert_eq!(
+            json!({ "kind": "markdown", "value": "```\nlet u: Users\n```" }),
+            hover["result"]["contents"]
+        );
+
+        let definition = request(&mut server, "textDocument/definition", at(6, 9));
+        assert_eq!(
+            json!({
+                "uri": "file:///a.notion",
+                "range": { "start": { "line": 0, "character": 6 }, "end": { "line": 0, "character": 11 } }
+            }),
+            definition["result"]
+        );
+
+        let completion = request(&mut server, "textDocument/completion", at(8, 0));
+        assert_eq!(
+            json!({ "label": "Double", "kind": 3, "detail": "formula Double: Number" }),
+            completion["result"][0]
+        );
+
+        let formatting = request(
+            &mut server,
+            "textDocument/formatting",
+            json!({ "textDocument": { "uri": "file:///a.notion" }, "options": {} }),
+        );
+        assert_eq!(SOURCE, formatting["result"][0]["newText"]);
+
+        let unknown = request(
+            &mut server,
+            "textDocument/hover",
+            json!({ "textDocument": { "uri": "file:///b.notion" }, "position": { "line": 0, "character": 0 } }),
+        );
+        assert_eq!(-32602, unknown["error"]["code"]);
+
+        let unsupported = request(&mut server, "workspace/symbol", json!({}));
+        assert_eq!(-32601, unsupported["error"]["code"]);
+    }
+
+    #[test]
+    fn test_requests_after_shutdown_are_invalid() {
+        let mut server = Server::new();
+        request(&mut server, "initialize", json!({ "capabilities": {} }));
+        open(&mut server, SOURCE);
+
+        let shutdown = request(&mut server, "shutdown", Value::Null);
+        assert_eq!(Value::Null, shutdown["result"]);
+
+        let hover = request(&mut server, "textDocument/hover", at(6, 4));
+        assert_eq!(-32600, hover["error"]["code"]);
+        let again = request(&mut server, "shutdown", Value::Null);
+        assert_eq!(-32600, again["error"]["code"]);
+
+        server.handle(&json!({ "jsonrpc": "2.0", "method": "exit" }));
+        assert!(server.exited());
+    }
+
+    #[test]
+    fn test_run_over_stdio() {
+        let messages = [
+            json!({ "jsonrpc": "2.0", "id": 1, "method": "initialize", "params": {} }),
+            json!({ "jsonrpc": "2.0", "id": 2, "method": "shutdown" }),
+            json!({ "jsonrpc": "2.0", "method": "exit" }),
+        ];
+        let input: String = messages
+            .iter()
+            .map(|message| {
+                let body = message.to_string();
+                format!("Content-Length: {}\r\n\r\n{}", body.len(), body)
+            })
+            .collect();
+        let mut output = vec![];
+
+        let code = run(&mut input.as_bytes(), &mut output).unwrap();
+        assert_eq!(0, code);
+
+        let mut output = output.as_slice();
+        let first = protocol::read_message(&mut output).unwrap().unwrap();
+        let second = protocol::read_message(&mut output).unwrap().unwrap();
+        assert_eq!(json!(1), first["id"]);
+        assert_eq!(json!({ "jsonrpc": "2.0", "id": 2, "result": null }), second);
+        assert_eq!(None, protocol::read_message(&mut output).unwrap());
+    }
+}
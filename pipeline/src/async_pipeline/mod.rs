@@ -0,0 +1,187 @@
+use crate::{BoxError, Handler, HandlerResult, StageError};
+use std::future::Future;
+use std::marker::PhantomData;
+use std::pin::Pin;
+
+type BoxFuture<'b, T> = Pin<Box<dyn Future<Output = T> + Send + 'b>>;
+
+/// The async counterpart of `Handler`. Implementations can be written with
+/// `async fn handle`, and nothing here depends on a particular executor.
+/// The futures have to be `Send`, so they can run on multi-threaded
+/// executors.
+pub trait AsyncHandler<I, O, E = BoxError> {
+    fn handle(&self, input: I) -> impl Future<Output = HandlerResult<O, E>> + Send;
+}
+
+/// Runs a synchronous `Handler` as an async stage. The handler runs to
+/// completion on the task polling it, so it should be quick or the executor
+/// will be held up.
+pub struct Lift<H> {
+    handler: H,
+}
+impl<H> Lift<H> {
+    pub fn new(handler: H) -> Self {
+        Lift { handler }
+    }
+}
+impl<I: Send, O, E, H: Handler<I, O, E> + Sync> AsyncHandler<I, O, E> for Lift<H> {
+    async fn handle(&self, input: I) -> HandlerResult<O, E> {
+        self.handler.handle(input)
+    }
+}
+
+// `AsyncHandler` can't be used as a trait object because of its generic
+// future, so stages are stored through this boxing version of it.
+trait DynAsyncHandler<I, O, E> {
+    fn handle_boxed<'b>(&'b self, input: I) -> BoxFuture<'b, HandlerResult<O, E>>
+    where
+        I: 'b,
+        O: 'b,
+        E: 'b;
+}
+impl<I, O, E, H: AsyncHandler<I, O, E>> DynAsyncHandler<I, O, E> for H {
+    fn handle_boxed<'b>(&'b self, input: I) -> BoxFuture<'b, HandlerResult<O, E>>
+    where
+        I: 'b,
+        O: 'b,
+        E: 'b,
+    {
+        Box::pin(self.handle(input))
+    }
+}
+
+struct Identity;
+impl<I: Send, E> AsyncHandler<I, I, E> for Identity {
+    async fn handle(&self, input: I) -> HandlerResult<I, E> {
+        Ok(input)
+    }
+}
+
+struct Convert<H, F> {
+    handler: H,
+    error: PhantomData<fn() -> F>,
+}
+impl<I: Send, O, E: From<F>, F, H> AsyncHandler<I, O, E> for Convert<H, F>
+where
+    H: AsyncHandler<I, O, F> + Sync,
+{
+    async fn handle(&self, input: I) -> HandlerResult<O, E> {
+        self.handler.handle(input).await.map_err(E::from)
+    }
+}
+
+struct Named<H, F> {
+    handler: H,
+    name: String,
+    index: usize,
+    error: PhantomData<fn() -> F>,
+}
+impl<I: Send, O, E, F, H> AsyncHandler<I, O, E> for Named<H, F>
+where
+    E: From<StageError<F>>,
+    H: AsyncHandler<I, O, F> + Sync,
+{
+    async fn handle(&self, input: I) -> HandlerResult<O, E> {
+        self.handler.handle(input).await.map_err(|error| {
+            E::from(StageError {
+                name: self.name.clone(),
+                index: self.index,
+                error,
+            })
+        })
+    }
+}
+
+struct Stage<'a, I, K, O, E> {
+    current: Box<dyn DynAsyncHandler<I, K, E> + Send + Sync + 'a>,
+    next: Box<dyn DynAsyncHandler<K, O, E> + Send + Sync + 'a>,
+}
+impl<I: Send, K, O, E> AsyncHandler<I, O, E> for Stage<'_, I, K, O, E> {
+    async fn handle(&self, input: I) -> HandlerResult<O, E> {
+        let current = self.current.handle_boxed(input).await?;
+        self.next.handle_boxed(current).await
+    }
+}
+
+/// The async counterpart of `Pipeline`, chaining `AsyncHandler`s. Use
+/// `add_sync` or `Lift` for stages that are plain `Handler`s. Stages have
+/// to be `Send` and `Sync`, so the future returned by `start` is `Send` and
+/// can be spawned onto another thread.
+pub struct AsyncPipeline<'a, I, O, E = BoxError> {
+    head: Box<dyn DynAsyncHandler<I, O, E> + Send + Sync + 'a>,
+    stages: usize,
+}
+impl<'a, I: Send + 'a, E: 'a> Default for AsyncPipeline<'a, I, I, E> {
+    fn default() -> Self {
+        AsyncPipeline {
+            head: Box::new(Identity),
+            stages: 0,
+        }
+    }
+}
+impl<'a, I: Send + 'a> AsyncPipeline<'a, I, I> {
+    pub fn new() -> AsyncPipeline<'a, I, I> {
+        Self::default()
+    }
+}
+
+impl<'a, I: Send + 'a, O: Send + 'a, E: 'a> AsyncPipeline<'a, I, O, E> {
+    #[allow(clippy::should_implement_trait)]
+    pub fn add<K: 'a, F: 'a>(
+        self,
+        handler: impl AsyncHandler<O, K, F> + Send + Sync + 'a,
+    ) -> AsyncPipeline<'a, I, K, E>
+    where
+        E: From<F>,
+    {
+        self.then(Convert {
+            handler,
+            error: PhantomData,
+        })
+    }
+
+    /// Like `Pipeline::add_named`, wrapping errors in a `StageError`.
+    pub fn add_named<K: 'a, F: 'a>(
+        self,
+        name: &str,
+        handler: impl AsyncHandler<O, K, F> + Send + Sync + 'a,
+    ) -> AsyncPipeline<'a, I, K, E>
+    where
+        E: From<StageError<F>>,
+    {
+        let handler = Named {
+            handler,
+            name: name.to_string(),
+            index: self.stages,
+            error: PhantomData,
+        };
+        self.then(handler)
+    }
+
+    pub fn add_sync<K: 'a, F: 'a>(
+        self,
+        handler: impl Handler<O, K, F> + Send + Sync + 'a,
+    ) -> AsyncPipeline<'a, I, K, E>
+    where
+        E: From<F>,
+    {
+        self.add(Lift::new(handler))
+    }
+
+    fn then<K: 'a>(
+        self,
+        handler: impl AsyncHandler<O, K, E> + Send + Sync + 'a,
+    ) -> AsyncPipeline<'a, I, K, E> {
+        AsyncPipeline {
+            head: Box::new(Stage {
+                current: self.head,
+                next: Box::new(handler),
+            }),
+            stages: self.stages + 1,
+        }
+    }
+
+    pub async fn start(&self, input: I) -> HandlerResult<O, E> {
+        self.head.handle_boxed(input).await
+    }
+}
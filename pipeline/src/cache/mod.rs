@@ -0,0 +1,128 @@
+use crate::{Handler, HandlerResult};
+use std::collections::{BTreeMap, HashMap};
+use std::hash::Hash;
+use std::sync::Mutex;
+
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub struct CacheStats {
+    pub hits: usize,
+    pub misses: usize,
+    pub evictions: usize,
+    pub len: usize,
+    pub capacity: usize,
+}
+
+struct Entry<O> {
+    output: O,
+    used: u64,
+}
+
+struct State<I, O> {
+    entries: HashMap<I, Entry<O>>,
+    // Inputs by the tick they were last used at, oldest first.
+    recent: BTreeMap<u64, I>,
+    tick: u64,
+    stats: CacheStats,
+}
+impl<I: Hash + Eq + Clone, O> State<I, O> {
+    fn touch(&mut self, input: &I) {
+        self.tick += 1;
+        if let Some(entry) = self.entries.get_mut(input) {
+            self.recent.remove(&entry.used);
+            entry.used = self.tick;
+            self.recent.insert(self.tick, input.clone());
+        }
+    }
+}
+
+/// Remembers the outputs of `handler` for the `capacity` most recently used
+/// inputs, so handling the same input again skips the handler. Inputs are
+/// kept along with their outputs and compared with `Eq`, so inputs with the
+/// same hash don't share an output. Errors are never cached. A capacity of 0
+/// caches nothing.
+///
+/// Handlers are also implemented for references, so a pipeline can borrow
+/// the cache with `add(&cache)` while `stats` stays readable.
+pub struct Cache<H, I, O> {
+    handler: H,
+    state: Mutex<State<I, O>>,
+}
+impl<H, I, O> Cache<H, I, O> {
+    pub fn new(handler: H, capacity: usize) -> Self {
+        Cache {
+            handler,
+            state: Mutex::new(State {
+                entries: HashMap::new(),
+                recent: BTreeMap::new(),
+                tick: 0,
+                stats: CacheStats {
+                    capacity,
+                    ..CacheStats::default()
+                },
+            }),
+        }
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        let state = self.state.lock().unwrap();
+        CacheStats {
+            len: state.entries.len(),
+            ..state.stats
+        }
+    }
+
+    /// Forgets every output and resets the hit, miss and eviction counts.
+    pub fn clear(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.entries.clear();
+        state.recent.clear();
+        state.stats = CacheStats {
+            capacity: state.stats.capacity,
+            ..CacheStats::default()
+        };
+    }
+}
+impl<I: Hash + Eq + Clone, O: Clone, E, H: Handler<I, O, E>> Handler<I, O, E>
+    for Cache<H, I, O>
+{
+    fn handle(&self, input: I) -> HandlerResult<O, E> {
+        {
+            let mut state = self.state.lock().unwrap();
+            if let Some(entry) = state.entries.get(&input) {
+                let output = entry.output.clone();
+                state.stats.hits += 1;
+                state.touch(&input);
+                return Ok(output);
+            }
+            state.stats.misses += 1;
+        }
+
+        // The lock isn't held while the handler runs, so two threads missing
+        // on the same input both run it.
+        let output = self.handler.handle(input.clone())?;
+
+        let mut state = self.state.lock().unwrap();
+        if state.stats.capacity == 0 {
+            return Ok(output);
+        }
+        if !state.entries.contains_key(&input) && state.entries.len() >= state.stats.capacity {
+            if let Some((_, oldest)) = state.recent.pop_first() {
+                state.entries.remove(&oldest);
+                state.stats.evictions += 1;
+            }
+        }
+        let used = state.tick + 1;
+        if let Some(previous) = state.entries.insert(
+            input.clone(),
+            Entry {
+                output: output.clone(),
+                used,
+            },
+        ) {
+            state.recent.remove(&previous.used);
+        }
+        state.tick = used;
+        state.recent.insert(used, input);
+        Ok(output)
+    }
+}
@@ -0,0 +1,103 @@
+use crate::{Handler, HandlerResult};
+use std::marker::PhantomData;
+
+// The markers use `fn() -> T` like the pipeline stages, so a combinator is
+// `Send` and `Sync` whenever the handlers and closures it holds are.
+
+/// See `Handler::map`.
+pub struct Map<H, F, O> {
+    pub(crate) handler: H,
+    pub(crate) func: F,
+    pub(crate) output: PhantomData<fn() -> O>,
+}
+impl<I, O, P, E, H: Handler<I, O, E>, F: Fn(O) -> P> Handler<I, P, E> for Map<H, F, O> {
+    fn handle(&self, input: I) -> HandlerResult<P, E> {
+        self.handler.handle(input).map(&self.func)
+    }
+}
+
+/// See `Handler::and_then`.
+pub struct AndThen<A, B, O> {
+    pub(crate) first: A,
+    pub(crate) second: B,
+    pub(crate) output: PhantomData<fn() -> O>,
+}
+impl<I, O, P, E, A: Handler<I, O, E>, B: Handler<O, P, E>> Handler<I, P, E> for AndThen<A, B, O> {
+    fn handle(&self, input: I) -> HandlerResult<P, E> {
+        self.second.handle(self.first.handle(input)?)
+    }
+}
+
+/// See `Handler::or_else`.
+pub struct OrElse<A, B, F> {
+    pub(crate) first: A,
+    pub(crate) fallback: B,
+    pub(crate) error: PhantomData<fn() -> F>,
+}
+impl<I: Clone, O, E, F, A: Handler<I, O, F>, B: Handler<I, O, E>> Handler<I, O, E>
+    for OrElse<A, B, F>
+{
+    fn handle(&self, input: I) -> HandlerResult<O, E> {
+        match self.first.handle(input.clone()) {
+            Ok(output) => Ok(output),
+            Err(_) => self.fallback.handle(input),
+        }
+    }
+}
+
+/// See `Handler::retry`.
+pub struct Retry<H> {
+    pub(crate) handler: H,
+    pub(crate) retries: usize,
+}
+impl<I: Clone, O, E, H: Handler<I, O, E>> Handler<I, O, E> for Retry<H> {
+    fn handle(&self, input: I) -> HandlerResult<O, E> {
+        for _ in 0..self.retries {
+            if let Ok(output) = self.handler.handle(input.clone()) {
+                return Ok(output);
+            }
+        }
+        self.handler.handle(input)
+    }
+}
+
+/// See `Handler::tee`.
+pub struct Tee<A, B, C, O, Q> {
+    pub(crate) first: A,
+    pub(crate) second: B,
+    pub(crate) combine: C,
+    pub(crate) outputs: PhantomData<fn() -> (O, Q)>,
+}
+impl<I, O, Q, R, E, A, B, C> Handler<I, R, E> for Tee<A, B, C, O, Q>
+where
+    I: Clone,
+    A: Handler<I, O, E>,
+    B: Handler<I, Q, E>,
+    C: Fn(O, Q) -> R,
+{
+    fn handle(&self, input: I) -> HandlerResult<R, E> {
+        let first = self.first.handle(input.clone())?;
+        let second = self.second.handle(input)?;
+        Ok((self.combine)(first, second))
+    }
+}
+
+/// See `Handler::when`.
+pub struct When<H, P, G> {
+    pub(crate) handler: H,
+    pub(crate) predicate: P,
+    pub(crate) otherwise: G,
+}
+impl<I, O, E, H, P, G> Handler<I, O, E> for When<H, P, G>
+where
+    H: Handler<I, O, E>,
+    P: Fn(&I) -> bool,
+    G: Handler<I, O, E>,
+{
+    fn handle(&self, input: I) -> HandlerResult<O, E> {
+        match (self.predicate)(&input) {
+            true => self.handler.handle(input),
+            false => self.otherwise.handle(input),
+        }
+    }
+}
@@ -1,7 +1,31 @@
+mod async_pipeline;
+mod cache;
+mod combinators;
+mod middleware;
+
+pub use async_pipeline::*;
+pub use cache::*;
+pub use combinators::*;
+pub use middleware::*;
+use std::cell::RefCell;
+use std::any::Any;
 use std::error::Error;
 use std::fmt;
+use std::marker::PhantomData;
+use std::panic::{self, AssertUnwindSafe};
+use std::rc::Rc;
+use std::sync::{Arc, Mutex, RwLock};
+use std::thread;
+use std::time::Instant;
+
+/// The error type used when a handler or pipeline doesn't name its own.
+pub type BoxError = Box<dyn Error>;
 
-pub type HandlerResult<T> = Result<T, Box<dyn Error>>;
+/// A boxed error that can cross threads, for pipelines run with
+/// `SyncPipeline::start_batch`.
+pub type SendError = Box<dyn Error + Send + Sync>;
+
+pub type HandlerResult<T, E = BoxError> = Result<T, E>;
 
 #[derive(Debug)]
 pub struct SimpleError {
@@ -19,77 +43,518 @@ impl fmt::Display for SimpleError {
 }
 impl Error for SimpleError {}
 
-pub trait Handler<I, O> {
-    fn handle(&self, input: I) -> HandlerResult<O>;
+/// The error of an input whose handler panicked while running in
+/// `SyncPipeline::start_batch`, with the panic's message.
+#[derive(Debug, PartialEq)]
+pub struct PanicError {
+    pub message: String,
+}
+impl PanicError {
+    fn new(payload: Box<dyn Any + Send>) -> Self {
+        let message = match payload.downcast::<String>() {
+            Ok(message) => *message,
+            Err(payload) => match payload.downcast::<&str>() {
+                Ok(message) => message.to_string(),
+                Err(_) => "unknown panic".into(),
+            },
+        };
+        PanicError { message }
+    }
+}
+impl fmt::Display for PanicError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "handler panicked: {}", self.message)
+    }
+}
+impl Error for PanicError {}
+
+/// The error of a stage added with `Pipeline::add_named`, telling which
+/// stage failed. `index` counts the stages before it, starting from 0.
+#[derive(Debug)]
+pub struct StageError<E> {
+    pub name: String,
+    pub index: usize,
+    pub error: E,
+}
+impl<E: fmt::Display> fmt::Display for StageError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "stage '{}' failed: {}", self.name, self.error)
+    }
+}
+impl<E: fmt::Debug + fmt::Display> Error for StageError<E> {}
+
+pub trait Handler<I, O, E = BoxError> {
+    fn handle(&self, input: I) -> HandlerResult<O, E>;
+
+    /// Transforms the output of this handler with `func`.
+    fn map<P, F: Fn(O) -> P>(self, func: F) -> Map<Self, F, O>
+    where
+        Self: Sized,
+    {
+        Map {
+            handler: self,
+            func,
+            output: PhantomData,
+        }
+    }
+
+    /// Passes the output of this handler on to `next`.
+    fn and_then<P, H: Handler<O, P, E>>(self, next: H) -> AndThen<Self, H, O>
+    where
+        Self: Sized,
+    {
+        AndThen {
+            first: self,
+            second: next,
+            output: PhantomData,
+        }
+    }
+
+    /// Hands the input to `fallback` when this handler fails, e.g. to try a
+    /// strict parser before a forgiving one.
+    fn or_else<G, H: Handler<I, O, G>>(self, fallback: H) -> OrElse<Self, H, E>
+    where
+        Self: Sized,
+        I: Clone,
+    {
+        OrElse {
+            first: self,
+            fallback,
+            error: PhantomData,
+        }
+    }
+
+    /// Tries the input up to `retries` more times when this handler fails,
+    /// returning the last error if every attempt does.
+    fn retry(self, retries: usize) -> Retry<Self>
+    where
+        Self: Sized,
+        I: Clone,
+    {
+        Retry {
+            handler: self,
+            retries,
+        }
+    }
+
+    /// Sends the same input to this handler and to `other`, then merges both
+    /// outputs with `combine`. Fails with the first error.
+    fn tee<Q, R, H, C>(self, other: H, combine: C) -> Tee<Self, H, C, O, Q>
+    where
+        Self: Sized,
+        I: Clone,
+        H: Handler<I, Q, E>,
+        C: Fn(O, Q) -> R,
+    {
+        Tee {
+            first: self,
+            second: other,
+            combine,
+            outputs: PhantomData,
+        }
+    }
+
+    /// Runs this handler for inputs matching `predicate` and `otherwise` for
+    /// the rest. `FnHandler::new(Ok)` passes the rest through unchanged.
+    fn when<P, G>(self, predicate: P, otherwise: G) -> When<Self, P, G>
+    where
+        Self: Sized,
+        P: Fn(&I) -> bool,
+        G: Handler<I, O, E>,
+    {
+        When {
+            handler: self,
+            predicate,
+            otherwise,
+        }
+    }
 }
 
-pub struct ClosureHandler<'a, I, O> {
-    closure: Box<dyn Fn(I) -> HandlerResult<O> + 'a>,
+pub struct ClosureHandler<'a, I, O, E = BoxError> {
+    closure: Box<dyn Fn(I) -> HandlerResult<O, E> + 'a>,
 }
-impl<'a, I, O> ClosureHandler<'a, I, O> {
-    pub fn new(closure: Box<dyn Fn(I) -> HandlerResult<O> + 'a>) -> Self {
+impl<'a, I, O, E> ClosureHandler<'a, I, O, E> {
+    pub fn new(closure: Box<dyn Fn(I) -> HandlerResult<O, E> + 'a>) -> Self {
         ClosureHandler { closure }
     }
 }
-impl<I, O> Handler<I, O> for ClosureHandler<'_, I, O> {
-    fn handle(&self, input: I) -> HandlerResult<O> {
+impl<I, O, E> Handler<I, O, E> for ClosureHandler<'_, I, O, E> {
+    fn handle(&self, input: I) -> HandlerResult<O, E> {
         (self.closure)(input)
     }
 }
 
-pub struct FnHandler<I, O> {
-    func: fn(I) -> HandlerResult<O>,
+pub struct FnHandler<I, O, E = BoxError> {
+    func: fn(I) -> HandlerResult<O, E>,
 }
-impl<I, O> FnHandler<I, O> {
-    pub fn new(func: fn(I) -> HandlerResult<O>) -> Self {
+impl<I, O, E> FnHandler<I, O, E> {
+    pub fn new(func: fn(I) -> HandlerResult<O, E>) -> Self {
         FnHandler { func }
     }
 }
-impl<I, O> Handler<I, O> for FnHandler<I, O> {
-    fn handle(&self, input: I) -> HandlerResult<O> {
+impl<I, O, E> Handler<I, O, E> for FnHandler<I, O, E> {
+    fn handle(&self, input: I) -> HandlerResult<O, E> {
         (self.func)(input)
     }
 }
 
-struct Stage<'a, I, K, O> {
-    current: Box<dyn Handler<I, K> + 'a>,
-    next: Box<dyn Handler<K, O> + 'a>,
+impl<I, O, E, H: Handler<I, O, E> + ?Sized> Handler<I, O, E> for Box<H> {
+    fn handle(&self, input: I) -> HandlerResult<O, E> {
+        (**self).handle(input)
+    }
+}
+impl<I, O, E, H: Handler<I, O, E> + ?Sized> Handler<I, O, E> for &H {
+    fn handle(&self, input: I) -> HandlerResult<O, E> {
+        (**self).handle(input)
+    }
+}
+
+struct Identity;
+impl<I, E> Handler<I, I, E> for Identity {
+    fn handle(&self, input: I) -> HandlerResult<I, E> {
+        Ok(input)
+    }
+}
+
+// The markers below use `fn() -> T` so that they don't keep a stage from
+// being `Send` or `Sync` just because an error or value type isn't.
+
+// Adapts a handler failing with `F` to a pipeline failing with `E`.
+struct Convert<H, F> {
+    handler: H,
+    error: PhantomData<fn() -> F>,
+}
+impl<I, O, E: From<F>, F, H: Handler<I, O, F>> Handler<I, O, E> for Convert<H, F> {
+    fn handle(&self, input: I) -> HandlerResult<O, E> {
+        self.handler.handle(input).map_err(E::from)
+    }
+}
+
+// Adapts a handler failing with `F` to a pipeline failing with `E`, saying
+// which stage the error came from.
+struct Named<H, F> {
+    handler: H,
+    name: String,
+    index: usize,
+    error: PhantomData<fn() -> F>,
+}
+impl<I, O, E: From<StageError<F>>, F, H: Handler<I, O, F>> Handler<I, O, E> for Named<H, F> {
+    fn handle(&self, input: I) -> HandlerResult<O, E> {
+        self.handler.handle(input).map_err(|error| {
+            E::from(StageError {
+                name: self.name.clone(),
+                index: self.index,
+                error,
+            })
+        })
+    }
+}
+
+// The middleware of a pipeline, shared with every stage so that middleware
+// added after a stage still wraps it.
+type Middlewares<'a> = Rc<RefCell<Vec<Rc<dyn Middleware + 'a>>>>;
+type SyncMiddlewares<'a> = Arc<RwLock<Vec<Arc<dyn Middleware + Send + Sync + 'a>>>>;
+
+trait Hooks {
+    fn each(&self, reverse: bool, hook: &mut dyn FnMut(&dyn Middleware)) -> bool;
+}
+impl Hooks for Middlewares<'_> {
+    fn each(&self, reverse: bool, hook: &mut dyn FnMut(&dyn Middleware)) -> bool {
+        let middlewares = self.borrow();
+        match reverse {
+            false => middlewares.iter().for_each(|m| hook(m.as_ref())),
+            true => middlewares.iter().rev().for_each(|m| hook(m.as_ref())),
+        }
+        !middlewares.is_empty()
+    }
+}
+impl Hooks for SyncMiddlewares<'_> {
+    fn each(&self, reverse: bool, hook: &mut dyn FnMut(&dyn Middleware)) -> bool {
+        let middlewares = self.read().unwrap();
+        match reverse {
+            false => middlewares.iter().for_each(|m| hook(m.as_ref())),
+            true => middlewares.iter().rev().for_each(|m| hook(m.as_ref())),
+        }
+        !middlewares.is_empty()
+    }
+}
+
+// Runs the middleware hooks around a stage.
+struct Layer<H, M> {
+    handler: H,
+    stage: StageInfo,
+    middlewares: M,
+}
+impl<I, O, E, H: Handler<I, O, E>, M: Hooks> Handler<I, O, E> for Layer<H, M> {
+    fn handle(&self, input: I) -> HandlerResult<O, E> {
+        if !self.middlewares.each(false, &mut |m| m.before(&self.stage)) {
+            return self.handler.handle(input);
+        }
+
+        let start = Instant::now();
+        let result = self.handler.handle(input);
+        let outcome = Outcome {
+            elapsed: start.elapsed(),
+            failed: result.is_err(),
+        };
+        self.middlewares.each(true, &mut |m| m.after(&self.stage, &outcome));
+        result
+    }
+}
+
+struct Stage<A, B, K> {
+    current: A,
+    next: B,
+    value: PhantomData<fn() -> K>,
 }
-impl<I, K, O> Handler<I, O> for Stage<'_, I, K, O> {
-    fn handle(&self, input: I) -> HandlerResult<O> {
+impl<I, K, O, E, A: Handler<I, K, E>, B: Handler<K, O, E>> Handler<I, O, E> for Stage<A, B, K> {
+    fn handle(&self, input: I) -> HandlerResult<O, E> {
         let current = self.current.handle(input)?;
         self.next.handle(current)
     }
 }
-impl<'a, I, K, O> Stage<'a, I, K, O> {
-    fn new(
-        current: Box<dyn Handler<I, K> + 'a>,
-        next: Box<dyn Handler<K, O> + 'a>,
-    ) -> Box<Stage<'a, I, K, O>> {
-        Box::new(Stage { current, next })
+impl<A, B, K> Stage<A, B, K> {
+    fn new(current: A, next: B) -> Box<Stage<A, B, K>> {
+        Box::new(Stage {
+            current,
+            next,
+            value: PhantomData,
+        })
     }
 }
 
-pub struct Pipeline<'a, I, O> {
-    head: Box<dyn Handler<I, O> + 'a>,
+/// A chain of handlers run one after the other. Every stage may fail with
+/// its own error type as long as the pipeline's error type `E` can be built
+/// from it with `From`.
+///
+/// There's no `start_batch` on `Pipeline`. Its stages are boxed as
+/// `dyn Handler` without `Send` or `Sync`, and its middleware is shared
+/// through `Rc`, so a `&Pipeline` can't be handed to worker threads whatever
+/// the input, output and error types are. Requiring `Send + Sync` handlers
+/// would rule out the ones that borrow non-thread-safe state, which is what
+/// `Pipeline` is for. Build a `SyncPipeline` to run batches on a thread pool
+/// with `SyncPipeline::start_batch`, or call `start` for each input.
+pub struct Pipeline<'a, I, O, E = BoxError> {
+    head: Box<dyn Handler<I, O, E> + 'a>,
+    stages: usize,
+    middlewares: Middlewares<'a>,
+}
+impl<'a, I: 'a, E: 'a> Default for Pipeline<'a, I, I, E> {
+    /// Starts an empty pipeline with any error type, e.g.
+    /// `Pipeline::<_, _, MyError>::default()`.
+    fn default() -> Self {
+        Pipeline {
+            head: Box::new(Identity),
+            stages: 0,
+            middlewares: Middlewares::default(),
+        }
+    }
 }
 impl<'a, I: 'a> Pipeline<'a, I, I> {
     pub fn new() -> Pipeline<'a, I, I> {
-        let handler: ClosureHandler<I, I> = ClosureHandler::new(Box::new(|x| Ok(x)));
+        Self::default()
+    }
+}
+
+impl<'a, I: 'a, O: 'a, E: 'a> Pipeline<'a, I, O, E> {
+    #[allow(clippy::should_implement_trait)]
+    pub fn add<K: 'a, F: 'a>(self, handler: impl Handler<O, K, F> + 'a) -> Pipeline<'a, I, K, E>
+    where
+        E: From<F>,
+    {
+        let handler = Convert {
+            handler,
+            error: PhantomData,
+        };
+        self.then(None, handler)
+    }
+
+    /// Adds a stage whose errors are wrapped in a `StageError` carrying
+    /// `name`, so a failing pipeline reports "stage 'parser' failed: ...".
+    pub fn add_named<K: 'a, F: 'a>(
+        self,
+        name: &str,
+        handler: impl Handler<O, K, F> + 'a,
+    ) -> Pipeline<'a, I, K, E>
+    where
+        E: From<StageError<F>>,
+    {
+        let handler = Named {
+            handler,
+            name: name.to_string(),
+            index: self.stages,
+            error: PhantomData,
+        };
+        self.then(Some(name), handler)
+    }
+
+    /// Wraps every stage, including the ones already added, with the hooks
+    /// of `middleware`.
+    pub fn add_middleware(self, middleware: impl Middleware + 'a) -> Self {
+        self.middlewares.borrow_mut().push(Rc::new(middleware));
+        self
+    }
+
+    fn then<K: 'a>(
+        self,
+        name: Option<&str>,
+        handler: impl Handler<O, K, E> + 'a,
+    ) -> Pipeline<'a, I, K, E> {
+        let handler = Layer {
+            handler,
+            stage: StageInfo {
+                name: name.map(String::from),
+                index: self.stages,
+            },
+            middlewares: Rc::clone(&self.middlewares),
+        };
         Pipeline {
-            head: Box::new(handler),
+            head: Stage::new(self.head, handler),
+            stages: self.stages + 1,
+            middlewares: self.middlewares,
         }
     }
+
+    pub fn start(&self, input: I) -> HandlerResult<O, E> {
+        self.head.handle(input)
+    }
 }
 
-impl<'a, I: 'a, O: 'a> Pipeline<'a, I, O> {
-    pub fn add<K: 'a>(self, handler: impl Handler<O, K> + 'a) -> Pipeline<'a, I, K> {
-        Pipeline {
-            head: Stage::new(self.head, Box::new(handler)),
+/// A `Pipeline` that only accepts `Send + Sync` handlers, so it can be built
+/// once and shared between threads, e.g. behind an `Arc`.
+pub struct SyncPipeline<'a, I, O, E = BoxError> {
+    head: Box<dyn Handler<I, O, E> + Send + Sync + 'a>,
+    stages: usize,
+    middlewares: SyncMiddlewares<'a>,
+}
+impl<'a, I: 'a, E: 'a> Default for SyncPipeline<'a, I, I, E> {
+    fn default() -> Self {
+        SyncPipeline {
+            head: Box::new(Identity),
+            stages: 0,
+            middlewares: SyncMiddlewares::default(),
+        }
+    }
+}
+impl<'a, I: 'a> SyncPipeline<'a, I, I> {
+    pub fn new() -> SyncPipeline<'a, I, I> {
+        Self::default()
+    }
+}
+
+impl<'a, I: 'a, O: 'a, E: 'a> SyncPipeline<'a, I, O, E> {
+    #[allow(clippy::should_implement_trait)]
+    pub fn add<K: 'a, F: 'a>(
+        self,
+        handler: impl Handler<O, K, F> + Send + Sync + 'a,
+    ) -> SyncPipeline<'a, I, K, E>
+    where
+        E: From<F>,
+    {
+        let handler = Convert {
+            handler,
+            error: PhantomData,
+        };
+        self.then(None, handler)
+    }
+
+    pub fn add_named<K: 'a, F: 'a>(
+        self,
+        name: &str,
+        handler: impl Handler<O, K, F> + Send + Sync + 'a,
+    ) -> SyncPipeline<'a, I, K, E>
+    where
+        E: From<StageError<F>>,
+    {
+        let handler = Named {
+            handler,
+            name: name.to_string(),
+            index: self.stages,
+            error: PhantomData,
+        };
+        self.then(Some(name), handler)
+    }
+
+    pub fn add_middleware(self, middleware: impl Middleware + Send + Sync + 'a) -> Self {
+        self.middlewares.write().unwrap().push(Arc::new(middleware));
+        self
+    }
+
+    fn then<K: 'a>(
+        self,
+        name: Option<&str>,
+        handler: impl Handler<O, K, E> + Send + Sync + 'a,
+    ) -> SyncPipeline<'a, I, K, E> {
+        let handler = Layer {
+            handler,
+            stage: StageInfo {
+                name: name.map(String::from),
+                index: self.stages,
+            },
+            middlewares: Arc::clone(&self.middlewares),
+        };
+        SyncPipeline {
+            head: Stage::new(self.head, handler),
+            stages: self.stages + 1,
+            middlewares: self.middlewares,
         }
     }
 
-    pub fn start(&self, input: I) -> HandlerResult<O> {
+    pub fn start(&self, input: I) -> HandlerResult<O, E> {
         self.head.handle(input)
     }
 }
+
+impl<'a, I: Send + 'a, O: Send + 'a, E: From<PanicError> + Send + 'a> SyncPipeline<'a, I, O, E> {
+    /// Runs every input through the pipeline on as many threads as the
+    /// machine has cores. See `start_batch_with_threads`.
+    pub fn start_batch(&self, inputs: Vec<I>) -> Vec<HandlerResult<O, E>> {
+        let threads = thread::available_parallelism().map_or(1, |n| n.get());
+        self.start_batch_with_threads(inputs, threads)
+    }
+
+    /// Runs every input through the pipeline on up to `threads` threads.
+    /// Results come back in the order of the inputs, and a failing input
+    /// only fails its own result, even when a handler panics.
+    pub fn start_batch_with_threads(
+        &self,
+        inputs: Vec<I>,
+        threads: usize,
+    ) -> Vec<HandlerResult<O, E>> {
+        let count = inputs.len();
+        let queue = Mutex::new(inputs.into_iter().enumerate());
+        let mut results: Vec<Option<HandlerResult<O, E>>> = (0..count).map(|_| None).collect();
+
+        thread::scope(|scope| {
+            let workers: Vec<_> = (0..threads.clamp(1, count.max(1)))
+                .map(|_| {
+                    scope.spawn(|| {
+                        let mut done = vec![];
+                        loop {
+                            // The lock is released before running the input.
+                            let next = queue.lock().unwrap().next();
+                            match next {
+                                Some((index, input)) => {
+                                    let result =
+                                        panic::catch_unwind(AssertUnwindSafe(|| self.start(input)))
+                                            .unwrap_or_else(|e| Err(E::from(PanicError::new(e))));
+                                    done.push((index, result));
+                                }
+                                None => return done,
+                            }
+                        }
+                    })
+                })
+                .collect();
+
+            for worker in workers {
+                let done = worker.join().unwrap_or_else(|e| panic::resume_unwind(e));
+                for (index, result) in done {
+                    results[index] = Some(result);
+                }
+            }
+        });
+
+        results.into_iter().flatten().collect()
+    }
+}
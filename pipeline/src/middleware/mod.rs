@@ -0,0 +1,119 @@
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Which stage of a pipeline a middleware hook is called for. `index`
+/// counts the stages before it, starting from 0, and `name` is only set for
+/// stages added with `add_named`.
+#[derive(Debug, PartialEq, Clone)]
+pub struct StageInfo {
+    pub name: Option<String>,
+    pub index: usize,
+}
+impl fmt::Display for StageInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.name {
+            Some(name) => write!(f, "{}", name),
+            None => write!(f, "stage {}", self.index),
+        }
+    }
+}
+
+/// How a stage went, as seen by `Middleware::after`.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Outcome {
+    pub elapsed: Duration,
+    pub failed: bool,
+}
+
+/// Hooks called around every stage of a pipeline, in the order the
+/// middleware were added for `before` and in reverse order for `after`.
+pub trait Middleware {
+    fn before(&self, _stage: &StageInfo) {}
+    fn after(&self, _stage: &StageInfo, _outcome: &Outcome) {}
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct StageTiming {
+    pub stage: StageInfo,
+    pub calls: usize,
+    pub failures: usize,
+    pub total: Duration,
+}
+impl StageTiming {
+    pub fn mean(&self) -> Duration {
+        match self.calls {
+            0 => Duration::default(),
+            calls => self.total / calls as u32,
+        }
+    }
+}
+
+/// The durations recorded by `Timing`, one line per stage in pipeline order
+/// when displayed.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct TimingReport {
+    pub stages: Vec<StageTiming>,
+}
+impl TimingReport {
+    pub fn total(&self) -> Duration {
+        self.stages.iter().map(|stage| stage.total).sum()
+    }
+}
+impl fmt::Display for TimingReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for stage in &self.stages {
+            writeln!(
+                f,
+                "{}: {} calls, {} failed, {:?} total, {:?} mean",
+                stage.stage,
+                stage.calls,
+                stage.failures,
+                stage.total,
+                stage.mean()
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// A middleware recording how often each stage ran and how long it took.
+/// Clones share their records, so keep one to read the report after adding
+/// another to the pipeline.
+#[derive(Debug, Clone, Default)]
+pub struct Timing {
+    stages: Arc<Mutex<Vec<StageTiming>>>,
+}
+impl Timing {
+    pub fn new() -> Self {
+        Timing::default()
+    }
+
+    pub fn report(&self) -> TimingReport {
+        let mut stages = self.stages.lock().unwrap().clone();
+        stages.sort_by_key(|timing| timing.stage.index);
+        TimingReport { stages }
+    }
+}
+impl Middleware for Timing {
+    fn after(&self, stage: &StageInfo, outcome: &Outcome) {
+        let mut stages = self.stages.lock().unwrap();
+        let index = match stages.iter().position(|timing| timing.stage == *stage) {
+            Some(index) => index,
+            None => {
+                stages.push(StageTiming {
+                    stage: stage.clone(),
+                    calls: 0,
+                    failures: 0,
+                    total: Duration::default(),
+                });
+                stages.len() - 1
+            }
+        };
+
+        let timing = &mut stages[index];
+        timing.calls += 1;
+        timing.failures += outcome.failed as usize;
+        timing.total += outcome.elapsed;
+    }
+}
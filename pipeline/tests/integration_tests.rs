@@ -1,4 +1,15 @@
 use pipeline::*;
+use std::error::Error;
+use std::cell::{Cell, RefCell};
+use std::fmt;
+use std::future::Future;
+use std::hash::{Hash, Hasher};
+use std::pin::Pin;
+use std::task::{Context, Poll, Wake, Waker};
+use std::rc::Rc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
 
 const ALPHA_START: u8 = 65;
 
@@ -11,7 +22,7 @@ impl Handler<u8, Vec<u8>> for StepOne {
         while i < input {
             output.push(i + ALPHA_START);
 
-            i = i + 1;
+            i += 1;
         }
 
         Ok(output)
@@ -21,7 +32,7 @@ impl Handler<u8, Vec<u8>> for StepOne {
 fn step_two(input: Vec<u8>) -> HandlerResult<String> {
     match String::from_utf8(input) {
         Ok(value) => Ok(value),
-        Err(e) => panic!(e),
+        Err(e) => panic!("{}", e),
     }
 }
 
@@ -29,6 +40,156 @@ fn step_three(_input: Vec<u8>) -> HandlerResult<Vec<u8>> {
     Err(SimpleError::new("Something went wrong".into()))
 }
 
+#[derive(Debug, PartialEq)]
+struct ParseError(String);
+
+#[derive(Debug, PartialEq)]
+enum StepError {
+    Parse(ParseError),
+    TooLarge(u8),
+    Panicked(PanicError),
+}
+impl fmt::Display for StepError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StepError::Parse(ParseError(input)) => write!(f, "{} is not a number", input),
+            StepError::TooLarge(value) => write!(f, "{} is too large", value),
+            StepError::Panicked(error) => write!(f, "{}", error),
+        }
+    }
+}
+impl Error for StepError {}
+impl From<ParseError> for StepError {
+    fn from(error: ParseError) -> Self {
+        StepError::Parse(error)
+    }
+}
+impl From<PanicError> for StepError {
+    fn from(error: PanicError) -> Self {
+        StepError::Panicked(error)
+    }
+}
+
+fn parse(input: &str) -> HandlerResult<u8, ParseError> {
+    input.parse().map_err(|_| ParseError(input.to_string()))
+}
+
+fn check_size(input: u8) -> HandlerResult<u8, StepError> {
+    match input {
+        0..=26 => Ok(input),
+        _ => Err(StepError::TooLarge(input)),
+    }
+}
+
+fn avoid_thirteen(input: u8) -> HandlerResult<u8, StepError> {
+    match input {
+        13 => panic!("unlucky {}", input),
+        _ => Ok(input),
+    }
+}
+
+fn halve(input: u8) -> HandlerResult<u8, SendError> {
+    match input % 2 {
+        0 => Ok(input / 2),
+        _ => Err(format!("{} is odd", input).into()),
+    }
+}
+
+// Fails until it has been called `failures` times.
+struct Flaky {
+    failures: usize,
+    calls: Cell<usize>,
+}
+impl Handler<u8, u8> for Flaky {
+    fn handle(&self, input: u8) -> HandlerResult<u8> {
+        self.calls.set(self.calls.get() + 1);
+        match self.calls.get() > self.failures {
+            true => Ok(input),
+            false => Err(SimpleError::new(format!("Attempt {} failed", self.calls.get()))),
+        }
+    }
+}
+
+// Counts how often it ran, failing for odd inputs.
+#[derive(Default)]
+struct Counted {
+    calls: AtomicUsize,
+}
+impl Handler<u8, String> for Counted {
+    fn handle(&self, input: u8) -> HandlerResult<String> {
+        self.calls.fetch_add(1, Ordering::SeqCst);
+        match input % 2 {
+            0 => Ok(format!("#{}", input)),
+            _ => Err(SimpleError::new(format!("{} is odd", input))),
+        }
+    }
+}
+
+// A minimal executor, since the async pipeline doesn't come with one.
+fn block_on<F: Future>(future: F) -> F::Output {
+    struct Unpark(thread::Thread);
+    impl Wake for Unpark {
+        fn wake(self: Arc<Self>) {
+            self.0.unpark();
+        }
+    }
+
+    let waker = Waker::from(Arc::new(Unpark(thread::current())));
+    let mut context = Context::from_waker(&waker);
+    let mut future = Box::pin(future);
+    loop {
+        match future.as_mut().poll(&mut context) {
+            Poll::Ready(output) => return output,
+            Poll::Pending => thread::park(),
+        }
+    }
+}
+
+// Returns `Pending` once, waking the task from another thread.
+struct YieldOnce(bool);
+impl Future for YieldOnce {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, context: &mut Context<'_>) -> Poll<()> {
+        if self.0 {
+            return Poll::Ready(());
+        }
+        self.0 = true;
+        let waker = context.waker().clone();
+        thread::spawn(move || waker.wake());
+        Poll::Pending
+    }
+}
+
+struct AsyncParse;
+impl AsyncHandler<String, u8, ParseError> for AsyncParse {
+    async fn handle(&self, input: String) -> HandlerResult<u8, ParseError> {
+        YieldOnce(false).await;
+        parse(&input)
+    }
+}
+
+struct Trace {
+    label: &'static str,
+    events: Rc<RefCell<Vec<String>>>,
+}
+impl Middleware for Trace {
+    fn before(&self, stage: &StageInfo) {
+        self.events
+            .borrow_mut()
+            .push(format!("{} before {}", self.label, stage));
+    }
+
+    fn after(&self, stage: &StageInfo, outcome: &Outcome) {
+        self.events.borrow_mut().push(format!(
+            "{} after {}{}",
+            self.label,
+            stage,
+            if outcome.failed { " (failed)" } else { "" }
+        ));
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -85,4 +246,484 @@ mod test {
 
         assert_eq!(5, result);
     }
+
+    #[test]
+    fn test_typed_errors_convert_between_stages() {
+        let pipe = Pipeline::<&str, &str, StepError>::default()
+            .add(FnHandler::new(parse))
+            .add(FnHandler::new(check_size));
+
+        assert_eq!(Ok(4), pipe.start("4"));
+        assert_eq!(
+            Err(StepError::Parse(ParseError("four".into()))),
+            pipe.start("four")
+        );
+        assert_eq!(Err(StepError::TooLarge(30)), pipe.start("30"));
+    }
+
+    #[test]
+    fn test_typed_errors_box_into_the_default_error() {
+        let pipe: Pipeline<u8, String> = Pipeline::new()
+            .add(FnHandler::new(check_size))
+            .add(StepOne)
+            .add(FnHandler::new(step_two));
+
+        assert_eq!("ABC", pipe.start(3).unwrap());
+        assert_eq!("30 is too large", pipe.start(30).unwrap_err().to_string());
+    }
+
+    #[test]
+    fn test_named_stages_report_which_stage_failed() {
+        let pipe: Pipeline<u8, String> = Pipeline::new()
+            .add_named("letters", StepOne)
+            .add(FnHandler::new(step_three))
+            .add_named("text", FnHandler::new(step_two));
+
+        let error = pipe.start(4).unwrap_err();
+
+        assert_eq!("Something went wrong", error.to_string());
+
+        let pipe: Pipeline<u8, String> = Pipeline::new()
+            .add_named("letters", StepOne)
+            .add_named("check", FnHandler::new(step_three))
+            .add_named("text", FnHandler::new(step_two));
+
+        let error = pipe.start(4).unwrap_err();
+        let stage = error.downcast_ref::<StageError<BoxError>>().unwrap();
+
+        assert_eq!("stage 'check' failed: Something went wrong", error.to_string());
+        assert_eq!(("check", 1), (stage.name.as_str(), stage.index));
+    }
+
+    #[test]
+    fn test_named_stages_with_typed_errors() {
+        #[derive(Debug, PartialEq)]
+        struct Failure(String, usize);
+        impl From<StageError<ParseError>> for Failure {
+            fn from(e: StageError<ParseError>) -> Self {
+                Failure(e.name, e.index)
+            }
+        }
+
+        let pipe = Pipeline::<&str, &str, Failure>::default().add_named("parse", FnHandler::new(parse));
+
+        assert_eq!(Ok(7), pipe.start("7"));
+        assert_eq!(Err(Failure("parse".into(), 0)), pipe.start("seven"));
+    }
+
+    #[test]
+    fn test_sync_pipeline_runs_from_many_threads() {
+        let pipe: Arc<SyncPipeline<u8, String>> = Arc::new(
+            SyncPipeline::new()
+                .add(FnHandler::new(check_size))
+                .add_named("letters", StepOne)
+                .add(FnHandler::new(step_two)),
+        );
+
+        let workers: Vec<_> = (0..8)
+            .map(|worker| {
+                let pipe = Arc::clone(&pipe);
+                thread::spawn(move || {
+                    (0..100u8)
+                        .map(|i| {
+                            let size = (worker * 3 + i) % 27;
+                            (size, pipe.start(size).map_err(|e| e.to_string()))
+                        })
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect();
+
+        for worker in workers {
+            for (size, result) in worker.join().unwrap() {
+                let expected: String = (0..size).map(|i| (i + ALPHA_START) as char).collect();
+                assert_eq!(Ok(expected), result);
+            }
+        }
+        assert_eq!("30 is too large", pipe.start(30).unwrap_err().to_string());
+    }
+
+    #[test]
+    fn test_batches_keep_input_order_and_per_item_errors() {
+        let sources: Vec<String> = (0..1000).map(|i| (i % 40).to_string()).collect();
+        let mut inputs: Vec<&str> = sources.iter().map(|i| i.as_str()).collect();
+        inputs[500] = "x";
+        let pipe = SyncPipeline::<&str, &str, StepError>::default()
+            .add(FnHandler::new(parse))
+            .add(FnHandler::new(check_size));
+
+        for threads in &[0, 1, 3, 16] {
+            let results = pipe.start_batch_with_threads(inputs.clone(), *threads);
+
+            assert_eq!(inputs.len(), results.len());
+            for (i, result) in results.into_iter().enumerate() {
+                let expected = match (i, (i % 40) as u8) {
+                    (500, _) => Err(StepError::Parse(ParseError("x".into()))),
+                    (_, size) if size > 26 => Err(StepError::TooLarge(size)),
+                    (_, size) => Ok(size),
+                };
+                assert_eq!(expected, result);
+            }
+        }
+        assert!(pipe.start_batch(vec![]).is_empty());
+    }
+
+    #[test]
+    fn test_batches_turn_panics_into_errors() {
+        let pipe = SyncPipeline::<&str, &str, StepError>::default()
+            .add(FnHandler::new(parse))
+            .add(FnHandler::new(avoid_thirteen));
+
+        let results = pipe.start_batch_with_threads(vec!["1", "13", "2", "13", "3"], 2);
+
+        let unlucky = || {
+            Err(StepError::Panicked(PanicError {
+                message: "unlucky 13".into(),
+            }))
+        };
+        assert_eq!(vec![Ok(1), unlucky(), Ok(2), unlucky(), Ok(3)], results);
+    }
+
+    #[test]
+    fn test_batches_with_boxed_errors() {
+        let pipe: SyncPipeline<u8, u8, SendError> = SyncPipeline::default()
+            .add(FnHandler::new(halve))
+            .add_named("check", FnHandler::new(check_size));
+        let results: Vec<_> = pipe
+            .start_batch(vec![4, 3, 60, 80])
+            .into_iter()
+            .map(|result| result.map_err(|e| e.to_string()))
+            .collect();
+
+        assert_eq!(
+            vec![
+                Ok(2),
+                Err("3 is odd".to_string()),
+                Err("stage 'check' failed: 30 is too large".to_string()),
+                Err("stage 'check' failed: 40 is too large".to_string()),
+            ],
+            results
+        );
+    }
+
+    #[test]
+    fn test_middleware_wraps_every_stage() {
+        let events = Rc::new(RefCell::new(vec![]));
+        let pipe: Pipeline<u8, String> = Pipeline::new()
+            .add_named("letters", StepOne)
+            .add_middleware(Trace {
+                label: "outer",
+                events: Rc::clone(&events),
+            })
+            .add(FnHandler::new(step_two))
+            .add_middleware(Trace {
+                label: "inner",
+                events: Rc::clone(&events),
+            });
+
+        assert_eq!("AB", pipe.start(2).unwrap());
+        assert_eq!(
+            vec![
+                "outer before letters",
+                "inner before letters",
+                "inner after letters",
+                "outer after letters",
+                "outer before stage 1",
+                "inner before stage 1",
+                "inner after stage 1",
+                "outer after stage 1",
+            ],
+            *events.borrow()
+        );
+
+        events.borrow_mut().clear();
+        let pipe: Pipeline<u8, Vec<u8>> = Pipeline::new()
+            .add(StepOne)
+            .add_named("check", FnHandler::new(step_three))
+            .add_middleware(Trace {
+                label: "trace",
+                events: Rc::clone(&events),
+            });
+
+        assert!(pipe.start(2).is_err());
+        assert_eq!(
+            vec![
+                "trace before stage 0",
+                "trace after stage 0",
+                "trace before check",
+                "trace after check (failed)",
+            ],
+            *events.borrow()
+        );
+    }
+
+    #[test]
+    fn test_timing_middleware_reports_every_stage() {
+        let timing = Timing::new();
+        let pipe: SyncPipeline<u8, u8, SendError> = SyncPipeline::default()
+            .add_named("halve", FnHandler::new(halve))
+            .add_named("check", FnHandler::new(check_size))
+            .add_middleware(timing.clone());
+
+        let results = pipe.start_batch_with_threads(vec![4, 3, 60, 8, 2], 2);
+
+        assert_eq!(5, results.len());
+        let report = timing.report();
+        let counts: Vec<_> = report
+            .stages
+            .iter()
+            .map(|stage| (stage.stage.to_string(), stage.calls, stage.failures))
+            .collect();
+        assert_eq!(vec![("halve".into(), 5, 1), ("check".into(), 4, 1)], counts);
+        assert_eq!(
+            report.total(),
+            report.stages[0].total + report.stages[1].total
+        );
+
+        let lines: Vec<_> = report.to_string().lines().map(String::from).collect();
+        assert_eq!(2, lines.len());
+        assert!(lines[0].starts_with("halve: 5 calls, 1 failed, "), "{}", lines[0]);
+        assert!(lines[1].starts_with("check: 4 calls, 1 failed, "), "{}", lines[1]);
+    }
+
+    #[test]
+    fn test_map_and_then() {
+        let handler = StepOne
+            .map(|letters| letters.into_iter().rev().collect())
+            .and_then(FnHandler::new(step_two));
+
+        assert_eq!("CBA", handler.handle(3).unwrap());
+        assert_eq!(
+            "Something went wrong",
+            StepOne
+                .and_then(FnHandler::new(step_three))
+                .handle(3)
+                .unwrap_err()
+                .to_string()
+        );
+    }
+
+    #[test]
+    fn test_or_else_falls_back_on_errors() {
+        let strict = FnHandler::new(parse);
+        let forgiving = FnHandler::new(|input: &str| -> HandlerResult<u8, StepError> {
+            Ok(input.trim().parse().unwrap_or(0))
+        });
+        let handler = strict.or_else(forgiving);
+
+        assert_eq!(Ok(4), handler.handle("4"));
+        assert_eq!(Ok(5), handler.handle(" 5 "));
+        assert_eq!(Ok(0), handler.handle("five"));
+    }
+
+    #[test]
+    fn test_retry() {
+        let handler = Flaky {
+            failures: 2,
+            calls: Cell::new(0),
+        }
+        .retry(2);
+
+        assert_eq!(7, handler.handle(7).unwrap());
+
+        let handler = Flaky {
+            failures: 5,
+            calls: Cell::new(0),
+        }
+        .retry(2);
+
+        assert_eq!("Attempt 3 failed", handler.handle(7).unwrap_err().to_string());
+    }
+
+    #[test]
+    fn test_tee_combines_both_outputs() {
+        let handler = StepOne.tee(StepOne.and_then(FnHandler::new(step_two)), |letters, text| {
+            (letters.len(), text)
+        });
+
+        assert_eq!((3, "ABC".to_string()), handler.handle(3).unwrap());
+
+        let handler = StepOne.tee(StepOne.and_then(FnHandler::new(step_three)), |_, _| ());
+
+        assert_eq!("Something went wrong", handler.handle(3).unwrap_err().to_string());
+    }
+
+    #[test]
+    fn test_when_only_handles_matching_inputs() {
+        let pipe: Pipeline<u8, String> = Pipeline::new()
+            .add(
+                FnHandler::new(|_: u8| -> HandlerResult<u8> { Ok(26) })
+                    .when(|size: &u8| *size > 26, FnHandler::new(Ok)),
+            )
+            .add(StepOne)
+            .add(FnHandler::new(step_two));
+
+        assert_eq!("ABC", pipe.start(3).unwrap());
+        assert_eq!(26, pipe.start(40).unwrap().len());
+    }
+
+    #[test]
+    fn test_when_hands_other_inputs_to_the_fallback() {
+        let pipe: Pipeline<&str, u8, ParseError> = Pipeline::default().add(
+            FnHandler::new(|_: &str| -> HandlerResult<u8, ParseError> { Ok(0) })
+                .when(|input: &&str| input.is_empty(), FnHandler::new(parse)),
+        );
+
+        assert_eq!(Ok(0), pipe.start(""));
+        assert_eq!(Ok(12), pipe.start("12"));
+        assert_eq!(Err(ParseError("x".into())), pipe.start("x"));
+    }
+
+    #[test]
+    fn test_cache_evicts_the_least_recently_used_input() {
+        let cache = Cache::new(Counted::default(), 2);
+        let pipe: Pipeline<u8, String> = Pipeline::new().add(&cache);
+
+        for input in &[2, 4, 2, 6, 2, 4, 6] {
+            assert_eq!(format!("#{}", input), pipe.start(*input).unwrap());
+        }
+
+        assert_eq!(
+            CacheStats {
+                hits: 2,
+                misses: 5,
+                evictions: 3,
+                len: 2,
+                capacity: 2,
+            },
+            cache.stats()
+        );
+    }
+
+    #[test]
+    fn test_cache_does_not_keep_errors() {
+        let counted = Counted::default();
+        let cache = Cache::new(&counted, 10);
+
+        assert!(cache.handle(3).is_err());
+        assert!(cache.handle(3).is_err());
+        assert_eq!("#8", cache.handle(8).unwrap());
+        assert_eq!("#8", cache.handle(8).unwrap());
+
+        assert_eq!(3, counted.calls.load(Ordering::SeqCst));
+        assert_eq!((1, 3, 1), (cache.stats().hits, cache.stats().misses, cache.stats().len));
+
+        let disabled = Cache::new(&counted, 0);
+        assert_eq!("#2", disabled.handle(2).unwrap());
+        assert_eq!(0, disabled.stats().len);
+    }
+
+    #[test]
+    fn test_cache_tells_inputs_with_the_same_hash_apart() {
+        #[derive(Clone, PartialEq, Eq)]
+        struct SameHash(u8);
+        impl Hash for SameHash {
+            fn hash<S: Hasher>(&self, state: &mut S) {
+                0.hash(state);
+            }
+        }
+
+        struct Describe;
+        impl Handler<SameHash, String> for Describe {
+            fn handle(&self, input: SameHash) -> HandlerResult<String> {
+                Ok(format!("#{}", input.0))
+            }
+        }
+
+        let cache = Cache::new(Describe, 10);
+
+        assert_eq!("#1", cache.handle(SameHash(1)).unwrap());
+        assert_eq!("#2", cache.handle(SameHash(2)).unwrap());
+        assert_eq!("#1", cache.handle(SameHash(1)).unwrap());
+        assert_eq!((1, 2, 2), (cache.stats().hits, cache.stats().misses, cache.stats().len));
+    }
+
+    #[test]
+    fn test_clearing_the_cache_resets_its_stats() {
+        let counted = Counted::default();
+        let cache = Cache::new(&counted, 1);
+        for input in &[2, 2, 4] {
+            cache.handle(*input).unwrap();
+        }
+
+        cache.clear();
+
+        assert_eq!(
+            CacheStats {
+                capacity: 1,
+                ..CacheStats::default()
+            },
+            cache.stats()
+        );
+        assert_eq!("#2", cache.handle(2).unwrap());
+        assert_eq!(3, counted.calls.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn test_cache_is_shared_between_threads() {
+        let counted = Counted::default();
+        let cache = Cache::new(&counted, 100);
+        let pipe: SyncPipeline<u8, String> = SyncPipeline::new().add(&cache);
+
+        thread::scope(|scope| {
+            for _ in 0..4 {
+                scope.spawn(|| {
+                    for i in 0..50u8 {
+                        assert_eq!(format!("#{}", i * 2), pipe.start(i * 2).unwrap());
+                    }
+                });
+            }
+        });
+
+        let stats = cache.stats();
+        assert_eq!(200, stats.hits + stats.misses);
+        assert_eq!(50, stats.len);
+        assert_eq!(stats.misses, counted.calls.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn test_async_pipeline_mixes_async_and_sync_stages() {
+        let pipe = AsyncPipeline::<String, String, StepError>::default()
+            .add(AsyncParse)
+            .add(Lift::new(FnHandler::new(check_size)))
+            .add_sync(FnHandler::new(|size: u8| -> HandlerResult<String, StepError> {
+                Ok((0..size).map(|i| (i + ALPHA_START) as char).collect())
+            }));
+
+        assert_eq!(Ok("ABCD".to_string()), block_on(pipe.start("4".into())));
+        assert_eq!(
+            Err(StepError::Parse(ParseError("four".into()))),
+            block_on(pipe.start("four".into()))
+        );
+        assert_eq!(Err(StepError::TooLarge(30)), block_on(pipe.start("30".into())));
+    }
+
+    #[test]
+    fn test_async_pipeline_futures_are_send() {
+        fn assert_send<T: Send>(value: T) -> T {
+            value
+        }
+
+        let pipe = AsyncPipeline::<String, String, StepError>::default()
+            .add(AsyncParse)
+            .add_sync(FnHandler::new(check_size));
+        let future = assert_send(pipe.start("12".into()));
+
+        let result = thread::scope(|scope| scope.spawn(|| block_on(future)).join().unwrap());
+        assert_eq!(Ok(12), result);
+    }
+
+    #[test]
+    fn test_async_pipeline_names_stages() {
+        let pipe: AsyncPipeline<u8, String> = AsyncPipeline::new()
+            .add_sync(StepOne)
+            .add_named("check", Lift::new(FnHandler::new(step_three)))
+            .add_sync(FnHandler::new(step_two));
+
+        assert_eq!(
+            "stage 'check' failed: Something went wrong",
+            block_on(pipe.start(3)).unwrap_err().to_string()
+        );
+        assert!(block_on(AsyncPipeline::new().start(7)).is_ok());
+    }
 }